@@ -434,7 +434,14 @@ where
     let file_name = attribute.structured_value::<_, NtfsFileName>(&mut info.fs)?;
 
     println!("{:34}\"{}\"", "Name:", file_name.name().to_string_lossy());
-    println!("{:34}{:?}", "Namespace:", file_name.namespace());
+    match file_name.namespace() {
+        Some(namespace) => println!("{:34}{:?}", "Namespace:", namespace),
+        None => println!(
+            "{:34}Unknown ({})",
+            "Namespace:",
+            file_name.namespace_value()
+        ),
+    }
     println!(
         "{:34}{:#x}",
         "Parent Directory Record Number:",
@@ -0,0 +1,228 @@
+// Copyright 2021-2023 Colin Finck <colin@reactos.org>
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+use alloc::vec::Vec;
+use binrw::io::{Read, Seek};
+
+use crate::error::{NtfsError, Result};
+use crate::file::NtfsFile;
+use crate::ntfs::Ntfs;
+use crate::path_lookup::NtfsPathLookup;
+use crate::upcase_table::NtfsCaseSensitivity;
+
+/// The artifacts located by a default [`NtfsArtifactLocator`].
+pub const DEFAULT_NTFS_ARTIFACTS: &[NtfsArtifact<'static>] = &[
+    NtfsArtifact::new("SAM", "Windows/System32/config/SAM"),
+    NtfsArtifact::new("SECURITY", "Windows/System32/config/SECURITY"),
+    NtfsArtifact::new("SOFTWARE", "Windows/System32/config/SOFTWARE"),
+    NtfsArtifact::new("SYSTEM", "Windows/System32/config/SYSTEM"),
+    NtfsArtifact::new("DEFAULT", "Windows/System32/config/DEFAULT"),
+    NtfsArtifact::new("Amcache", "Windows/AppCompat/Programs/Amcache.hve"),
+    NtfsArtifact::new("RecycleBin", "$Recycle.Bin"),
+    NtfsArtifact::new(
+        "ApplicationEventLog",
+        "Windows/System32/winevt/Logs/Application.evtx",
+    ),
+    NtfsArtifact::new(
+        "SecurityEventLog",
+        "Windows/System32/winevt/Logs/Security.evtx",
+    ),
+    NtfsArtifact::new("SystemEventLog", "Windows/System32/winevt/Logs/System.evtx"),
+];
+
+/// A forensic artifact identified by a unique name and located at a path relative to the root directory.
+///
+/// Paths are resolved like in [`Ntfs::file_by_path`], so both `\` and `/` are accepted as path separators.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct NtfsArtifact<'a> {
+    name: &'a str,
+    path: &'a str,
+}
+
+impl<'a> NtfsArtifact<'a> {
+    /// Creates a new [`NtfsArtifact`] with the given name and path.
+    pub const fn new(name: &'a str, path: &'a str) -> Self {
+        Self { name, path }
+    }
+
+    /// Returns the name of this artifact.
+    pub fn name(&self) -> &'a str {
+        self.name
+    }
+
+    /// Returns the path of this artifact, relative to the root directory.
+    pub fn path(&self) -> &'a str {
+        self.path
+    }
+}
+
+/// Resolves a list of well-known forensic artifacts (like registry hives, event logs, and the Recycle Bin)
+/// to [`NtfsFile`]s with a single call to [`NtfsArtifactLocator::locate`].
+///
+/// This is a builder: Start with [`NtfsArtifactLocator::new`] to get the [`DEFAULT_NTFS_ARTIFACTS`]
+/// (or with [`NtfsArtifactLocator::empty`]) and chain calls to add, override, or remove artifacts.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct NtfsArtifactLocator<'a> {
+    artifacts: Vec<NtfsArtifact<'a>>,
+}
+
+impl<'a> NtfsArtifactLocator<'a> {
+    /// Creates a new [`NtfsArtifactLocator`] for the [`DEFAULT_NTFS_ARTIFACTS`].
+    pub fn new() -> Self {
+        Self {
+            artifacts: DEFAULT_NTFS_ARTIFACTS.to_vec(),
+        }
+    }
+
+    /// Returns all artifacts of this locator in the order they are located.
+    pub fn artifacts(&self) -> &[NtfsArtifact<'a>] {
+        &self.artifacts
+    }
+
+    /// Creates a new [`NtfsArtifactLocator`] without any artifacts.
+    pub fn empty() -> Self {
+        Self {
+            artifacts: Vec::new(),
+        }
+    }
+
+    /// Resolves all artifacts of this locator and returns an [`NtfsLocatedArtifact`] for each of them,
+    /// in the same order.
+    ///
+    /// Artifacts are looked up case-insensitively like on Windows, and through damaged directory indexes
+    /// as configured via [`NtfsOptions::with_damaged_index_policy`].
+    /// Errors are reported per artifact, so an unreadable artifact doesn't prevent locating the others.
+    ///
+    /// # Panics
+    ///
+    /// Panics if [`read_upcase_table`][Ntfs::read_upcase_table] had not been called.
+    ///
+    /// [`NtfsOptions::with_damaged_index_policy`]: crate::NtfsOptions::with_damaged_index_policy
+    pub fn locate<'n, T>(&self, ntfs: &'n Ntfs, fs: &mut T) -> Vec<NtfsLocatedArtifact<'n, 'a>>
+    where
+        T: Read + Seek,
+    {
+        self.artifacts
+            .iter()
+            .map(|&artifact| {
+                let result = ntfs
+                    .lookup_path(fs, artifact.path(), NtfsCaseSensitivity::Insensitive)
+                    .transpose();
+
+                NtfsLocatedArtifact { artifact, result }
+            })
+            .collect()
+    }
+
+    /// Adds the given artifact, replacing any existing artifact of the same name in place.
+    pub fn with_artifact(mut self, artifact: NtfsArtifact<'a>) -> Self {
+        match self
+            .artifacts
+            .iter_mut()
+            .find(|existing| existing.name() == artifact.name())
+        {
+            Some(existing) => *existing = artifact,
+            None => self.artifacts.push(artifact),
+        }
+
+        self
+    }
+
+    /// Removes the artifact of the given name (if any).
+    pub fn without_artifact(mut self, name: &str) -> Self {
+        self.artifacts.retain(|artifact| artifact.name() != name);
+        self
+    }
+}
+
+impl<'a> Default for NtfsArtifactLocator<'a> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Result of locating a single [`NtfsArtifact`], as returned by [`NtfsArtifactLocator::locate`].
+#[derive(Debug)]
+pub struct NtfsLocatedArtifact<'n, 'a> {
+    artifact: NtfsArtifact<'a>,
+    result: Result<Option<NtfsPathLookup<'n>>>,
+}
+
+impl<'n, 'a> NtfsLocatedArtifact<'n, 'a> {
+    /// Returns the [`NtfsArtifact`] that has been looked up.
+    pub fn artifact(&self) -> NtfsArtifact<'a> {
+        self.artifact
+    }
+
+    /// Returns the error that occurred while locating the artifact (if any).
+    pub fn error(&self) -> Option<&NtfsError> {
+        self.result.as_ref().err()
+    }
+
+    /// Returns the [`NtfsFile`] of the artifact, or `None` if it doesn't exist or couldn't be read.
+    pub fn file(&self) -> Option<&NtfsFile<'n>> {
+        self.result
+            .as_ref()
+            .ok()?
+            .as_ref()
+            .map(NtfsPathLookup::file)
+    }
+
+    /// Returns whether the artifact has been found by scanning the Master File Table because a directory
+    /// index along its path was damaged (see [`NtfsPathLookup::found_by_mft_scan`]).
+    pub fn found_by_mft_scan(&self) -> bool {
+        matches!(&self.result, Ok(Some(lookup)) if lookup.found_by_mft_scan())
+    }
+
+    /// Returns the [`NtfsFile`] of the artifact, `None` if it doesn't exist, or the error that
+    /// occurred while locating it.
+    pub fn into_result(self) -> Result<Option<NtfsFile<'n>>> {
+        self.result
+            .map(|lookup| lookup.map(NtfsPathLookup::into_file))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_artifact_locator() {
+        let mut testfs1 = crate::helpers::tests::testfs1();
+        let mut ntfs = Ntfs::new(&mut testfs1).unwrap();
+        ntfs.read_upcase_table(&mut testfs1).unwrap();
+
+        let locator = NtfsArtifactLocator::new()
+            .without_artifact("Amcache")
+            .with_artifact(NtfsArtifact::new("SYSTEM", "MANY_SUBDIRS/123"))
+            .with_artifact(NtfsArtifact::new("Custom", "file-with-12345"));
+        assert_eq!(locator.artifacts().len(), DEFAULT_NTFS_ARTIFACTS.len());
+        assert_eq!(locator.artifacts()[3].path(), "MANY_SUBDIRS/123");
+
+        // testfs1 is no Windows system volume, so only the overridden artifacts exist.
+        let located = locator.locate(&ntfs, &mut testfs1);
+        assert_eq!(located.len(), locator.artifacts().len());
+
+        for located_artifact in &located {
+            let name = located_artifact.artifact().name();
+            assert!(located_artifact.error().is_none());
+            assert!(!located_artifact.found_by_mft_scan());
+            assert_eq!(
+                located_artifact.file().is_some(),
+                name == "SYSTEM" || name == "Custom",
+                "{}",
+                name
+            );
+        }
+
+        let file = located
+            .into_iter()
+            .last()
+            .unwrap()
+            .into_result()
+            .unwrap()
+            .unwrap();
+        let file_name = file.name(&mut testfs1, None, None).unwrap().unwrap();
+        assert_eq!(file_name.name(), "file-with-12345");
+    }
+}
@@ -0,0 +1,613 @@
+// Copyright 2021-2023 Colin Finck <colin@reactos.org>
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+use core::future::Future;
+use core::pin::Pin;
+
+use alloc::boxed::Box;
+use alloc::collections::{BTreeMap, BTreeSet, VecDeque};
+use alloc::vec;
+use alloc::vec::Vec;
+use binrw::io;
+use binrw::io::{Read, Seek, SeekFrom};
+
+use crate::error::{NtfsError, Result};
+
+/// Default size of a single cached block, in bytes.
+const DEFAULT_BLOCK_SIZE: u32 = 4096;
+
+/// Default number of cached blocks.
+const DEFAULT_CAPACITY: usize = 1024;
+
+/// Number of consecutive missing blocks fetched from the backend for a single random access.
+/// Sequential misses double this count up to the capacity of the cache.
+const INITIAL_READAHEAD_BLOCKS: usize = 8;
+
+/// Boxed future returned by the functions of [`NtfsAsyncBackend`].
+pub type NtfsAsyncFuture<'a, T> = Pin<Box<dyn Future<Output = T> + Send + 'a>>;
+
+/// Trait implemented by asynchronous filesystem readers to be used with [`NtfsAsyncReader`].
+///
+/// This crate does not depend on a specific async runtime.
+/// Instead, you implement this trait for a thin wrapper around the `AsyncRead + AsyncSeek` reader of your runtime.
+/// For tokio, this looks like:
+///
+/// ```ignore
+/// struct TokioBackend(tokio::fs::File);
+///
+/// impl NtfsAsyncBackend for TokioBackend {
+///     fn size<'a>(&'a mut self) -> NtfsAsyncFuture<'a, std::io::Result<u64>> {
+///         Box::pin(async move { self.0.seek(std::io::SeekFrom::End(0)).await })
+///     }
+///
+///     fn read_at<'a>(
+///         &'a mut self,
+///         position: u64,
+///         buf: &'a mut [u8],
+///     ) -> NtfsAsyncFuture<'a, std::io::Result<usize>> {
+///         Box::pin(async move {
+///             self.0.seek(std::io::SeekFrom::Start(position)).await?;
+///             self.0.read(buf).await
+///         })
+///     }
+/// }
+/// ```
+pub trait NtfsAsyncBackend: Send {
+    /// Returns the size of the NTFS partition, in bytes.
+    ///
+    /// This is only called when an operation seeks relative to the end of the partition.
+    /// Return an error (e.g. of kind `Unsupported`) if the size is unknown.
+    fn size<'a>(&'a mut self) -> NtfsAsyncFuture<'a, io::Result<u64>>;
+
+    /// Reads bytes starting at the absolute byte `position` of the NTFS partition into `buf`.
+    ///
+    /// Returns the number of bytes read, which is only less than `buf.len()` at the end of the partition.
+    fn read_at<'a>(
+        &'a mut self,
+        position: u64,
+        buf: &'a mut [u8],
+    ) -> NtfsAsyncFuture<'a, io::Result<usize>>;
+}
+
+/// Filesystem reader to run the parsers of this crate over an [`NtfsAsyncBackend`].
+///
+/// All parsers of this crate operate on a synchronous [`Read`] + [`Seek`] reader.
+/// `NtfsAsyncReader` implements both traits, but only serves data from a bounded cache of blocks.
+/// [`NtfsAsyncReader::run`] and [`NtfsAsyncReader::prefetch`] run any synchronous operation of this crate
+/// over that cache, asynchronously fetch every block the operation misses from the backend,
+/// and repeat the operation until it gets along with cached data only.
+/// This way, the entire parse pipeline runs without ever blocking on I/O:
+///
+/// ```ignore
+/// let mut fs = NtfsAsyncReader::new(backend);
+/// let mut ntfs = fs.run(Ntfs::new).await?;
+/// fs.run(|fs| ntfs.read_upcase_table(fs)).await?;
+/// let file = fs.run(|fs| ntfs.file(fs, 5)).await?;
+///
+/// // Stateful operations like iterators are first run on a copy and then for real.
+/// let index = fs.run(|fs| file.directory_index(fs)).await?;
+/// let mut entries = index.entries();
+/// loop {
+///     fs.prefetch(|fs| {
+///         let _ = entries.clone().next(fs);
+///     }).await?;
+///     let entry = match entries.next(&mut fs) {
+///         Some(entry) => entry?,
+///         None => break,
+///     };
+///     // ...
+/// }
+/// ```
+///
+/// # Cost
+///
+/// Every cache miss aborts the operation, fetches the missing block (and possibly some of the following ones),
+/// and restarts the operation from the beginning.
+/// Consecutive misses of sequential blocks double the number of blocks fetched at once, so that an operation
+/// covering `n` blocks is restarted about `log2(n)` times.
+/// Operations on small structures (like looking up a File Record or an index entry) are usually restarted only
+/// a handful of times.
+///
+/// The cache never holds more than `capacity` blocks.
+/// Blocks are evicted in the order they were fetched, even while an operation runs.
+/// If a single operation needs more blocks than fit into the cache, it fails with
+/// [`NtfsError::AsyncCacheTooSmall`] instead of growing the cache.
+/// Split large reads of attribute values into chunks that fit into the cache.
+#[derive(Debug)]
+pub struct NtfsAsyncReader<B> {
+    backend: B,
+    block_size: u32,
+    capacity: usize,
+    blocks: BTreeMap<u64, Vec<u8>>,
+    insertion_order: VecDeque<u64>,
+    size: Option<u64>,
+    stream_position: u64,
+    missing: Option<MissingData>,
+    operation_blocks: BTreeSet<u64>,
+    readahead_blocks: usize,
+    next_sequential_block: Option<u64>,
+}
+
+/// Data that the current operation needs, but which has not been fetched from the backend yet.
+#[derive(Clone, Copy, Debug)]
+enum MissingData {
+    Block(u64),
+    Size,
+}
+
+impl<B> NtfsAsyncReader<B>
+where
+    B: NtfsAsyncBackend,
+{
+    /// Creates a new `NtfsAsyncReader` that caches up to 1024 blocks of 4096 bytes each.
+    pub fn new(backend: B) -> Self {
+        Self::with_block_size(backend, DEFAULT_BLOCK_SIZE, DEFAULT_CAPACITY)
+    }
+
+    /// Creates a new `NtfsAsyncReader` that caches up to `capacity` blocks of `block_size` bytes each.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `block_size` is zero.
+    pub fn with_block_size(backend: B, block_size: u32, capacity: usize) -> Self {
+        assert!(block_size > 0, "The block size must not be zero");
+
+        Self {
+            backend,
+            block_size,
+            capacity: capacity.max(1),
+            blocks: BTreeMap::new(),
+            insertion_order: VecDeque::new(),
+            size: None,
+            stream_position: 0,
+            missing: None,
+            operation_blocks: BTreeSet::new(),
+            readahead_blocks: INITIAL_READAHEAD_BLOCKS,
+            next_sequential_block: None,
+        }
+    }
+
+    /// Returns a reference to the backend.
+    pub fn backend(&self) -> &B {
+        &self.backend
+    }
+
+    /// Returns a mutable reference to the backend.
+    pub fn backend_mut(&mut self) -> &mut B {
+        &mut self.backend
+    }
+
+    /// Returns the number of currently cached blocks, which never exceeds the capacity.
+    pub fn cached_blocks(&self) -> usize {
+        self.blocks.len()
+    }
+
+    /// Drops all cached blocks.
+    pub fn clear(&mut self) {
+        self.blocks.clear();
+        self.insertion_order.clear();
+    }
+
+    /// Consumes this `NtfsAsyncReader` and returns the backend.
+    pub fn into_backend(self) -> B {
+        self.backend
+    }
+
+    /// Runs the synchronous operation `f` over the cached data, fetching all blocks it needs from the backend.
+    ///
+    /// `f` is called repeatedly until it doesn't miss any block, and the result of that last call is returned.
+    /// Hence, `f` must be free of side effects that break when it is repeated.
+    /// This holds for all functions of this crate that don't take `&mut self`, as well as for
+    /// [`Ntfs::read_upcase_table`].
+    /// Use [`NtfsAsyncReader::prefetch`] for everything else, including iterators and attribute value readers.
+    ///
+    /// See the [`NtfsAsyncReader`] documentation for the cost of this approach.
+    ///
+    /// [`Ntfs::read_upcase_table`]: crate::Ntfs::read_upcase_table
+    pub async fn run<F, O>(&mut self, mut f: F) -> Result<O>
+    where
+        F: FnMut(&mut Self) -> Result<O>,
+    {
+        self.operation_blocks.clear();
+
+        loop {
+            self.missing = None;
+            let result = f(self);
+
+            match self.missing.take() {
+                Some(missing) => self.fetch(missing).await?,
+                None => return result,
+            }
+        }
+    }
+
+    /// Like [`NtfsAsyncReader::run`], but discards the result of `probe`.
+    ///
+    /// This prefetches all blocks needed by an operation that mutates its state, like advancing an iterator
+    /// or reading from an attribute value.
+    /// `probe` performs the operation on a copy of that state.
+    /// Afterwards, perform the real operation directly on this `NtfsAsyncReader`, which is then guaranteed to not
+    /// miss any block.
+    pub async fn prefetch<F>(&mut self, mut probe: F) -> Result<()>
+    where
+        F: FnMut(&mut Self),
+    {
+        self.run(|fs| {
+            probe(fs);
+            Ok(())
+        })
+        .await
+    }
+
+    async fn fetch(&mut self, missing: MissingData) -> Result<()> {
+        match missing {
+            MissingData::Block(block_number) => self.fetch_blocks(block_number).await,
+            MissingData::Size => {
+                self.size = Some(self.backend.size().await?);
+                Ok(())
+            }
+        }
+    }
+
+    async fn fetch_blocks(&mut self, first_block_number: u64) -> Result<()> {
+        // A block that has been fetched for this operation before has been evicted again,
+        // so the operation needs more blocks than the cache can hold.
+        if !self.operation_blocks.insert(first_block_number) {
+            return Err(NtfsError::AsyncCacheTooSmall {
+                capacity: self.capacity,
+            });
+        }
+
+        if self.next_sequential_block == Some(first_block_number) {
+            self.readahead_blocks = self.readahead_blocks.saturating_mul(2);
+        } else {
+            self.readahead_blocks = INITIAL_READAHEAD_BLOCKS;
+        }
+
+        // Read ahead as many consecutive blocks as possible, which are not yet cached,
+        // but never evict blocks fetched for this operation to make room for them.
+        let max_block_count = self
+            .readahead_blocks
+            .min(
+                self.capacity
+                    .saturating_sub(self.operation_blocks.len() - 1),
+            )
+            .max(1);
+        let mut block_count = 1;
+        while block_count < max_block_count
+            && !self
+                .blocks
+                .contains_key(&(first_block_number + block_count as u64))
+        {
+            block_count += 1;
+        }
+
+        let position = first_block_number
+            .checked_mul(self.block_size as u64)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "invalid position"))?;
+        let mut data = vec![0u8; block_count * self.block_size as usize];
+        let mut filled = 0;
+
+        while filled < data.len() {
+            match self
+                .backend
+                .read_at(position + filled as u64, &mut data[filled..])
+                .await
+            {
+                Ok(0) => break,
+                Ok(n) => filled += n,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(NtfsError::Io(e)),
+            }
+        }
+
+        data.truncate(filled);
+
+        // Always insert the first block, even if it's empty (because we are at the end of the partition).
+        // Otherwise, we would try to fetch it over and over again.
+        let mut chunks = data.chunks(self.block_size as usize);
+        let first_chunk = chunks.next().unwrap_or(&[]);
+        self.insert_block(first_block_number, first_chunk.to_vec());
+
+        let mut next_block_number = first_block_number + 1;
+        for chunk in chunks {
+            self.operation_blocks.insert(next_block_number);
+            self.insert_block(next_block_number, chunk.to_vec());
+            next_block_number += 1;
+        }
+
+        self.next_sequential_block = Some(next_block_number);
+
+        Ok(())
+    }
+
+    fn insert_block(&mut self, block_number: u64, data: Vec<u8>) {
+        if let Some(block) = self.blocks.get_mut(&block_number) {
+            *block = data;
+            return;
+        }
+
+        while self.blocks.len() >= self.capacity {
+            match self.insertion_order.pop_front() {
+                Some(evicted_block_number) => {
+                    self.blocks.remove(&evicted_block_number);
+                }
+                None => break,
+            }
+        }
+
+        self.blocks.insert(block_number, data);
+        self.insertion_order.push_back(block_number);
+    }
+
+    fn would_block(&mut self, missing: MissingData) -> io::Error {
+        // Remember the missing data to let `run` fetch it.
+        self.missing = Some(missing);
+        io::Error::new(io::ErrorKind::WouldBlock, "data has not been fetched yet")
+    }
+}
+
+impl<B> Read for NtfsAsyncReader<B>
+where
+    B: NtfsAsyncBackend,
+{
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let mut bytes_read = 0;
+
+        while bytes_read < buf.len() {
+            let block_number = self.stream_position / self.block_size as u64;
+            let offset_in_block = (self.stream_position % self.block_size as u64) as usize;
+
+            let block = match self.blocks.get(&block_number) {
+                Some(block) => block,
+                None => return Err(self.would_block(MissingData::Block(block_number))),
+            };
+
+            if offset_in_block >= block.len() {
+                // We have reached the end of the partition.
+                break;
+            }
+
+            let bytes_to_copy = usize::min(block.len() - offset_in_block, buf.len() - bytes_read);
+            buf[bytes_read..bytes_read + bytes_to_copy]
+                .copy_from_slice(&block[offset_in_block..offset_in_block + bytes_to_copy]);
+
+            bytes_read += bytes_to_copy;
+            self.stream_position += bytes_to_copy as u64;
+
+            if offset_in_block + bytes_to_copy < self.block_size as usize {
+                // Either the buffer is full or this is an incomplete block at the end of the partition.
+                break;
+            }
+        }
+
+        Ok(bytes_read)
+    }
+}
+
+impl<B> Seek for NtfsAsyncReader<B>
+where
+    B: NtfsAsyncBackend,
+{
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let (base, n) = match pos {
+            SeekFrom::Start(n) => {
+                self.stream_position = n;
+                return Ok(n);
+            }
+            SeekFrom::Current(n) => (self.stream_position, n),
+            SeekFrom::End(n) => match self.size {
+                Some(size) => (size, n),
+                None => return Err(self.would_block(MissingData::Size)),
+            },
+        };
+
+        let new_position = if n >= 0 {
+            base.checked_add(n as u64)
+        } else {
+            base.checked_sub(n.wrapping_neg() as u64)
+        };
+
+        match new_position {
+            Some(n) => {
+                self.stream_position = n;
+                Ok(self.stream_position)
+            }
+            None => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "invalid seek to a negative or overflowing position",
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::task::{Context, Poll, Wake};
+
+    use super::*;
+    use crate::indexes::NtfsFileNameIndex;
+    use crate::ntfs::Ntfs;
+    use crate::traits::NtfsReadSeek;
+
+    /// Backend over an in-memory image that counts the reads.
+    struct MemoryBackend {
+        data: Vec<u8>,
+        reads: usize,
+    }
+
+    impl NtfsAsyncBackend for MemoryBackend {
+        fn size<'a>(&'a mut self) -> NtfsAsyncFuture<'a, io::Result<u64>> {
+            Box::pin(async move { Ok(self.data.len() as u64) })
+        }
+
+        fn read_at<'a>(
+            &'a mut self,
+            position: u64,
+            buf: &'a mut [u8],
+        ) -> NtfsAsyncFuture<'a, io::Result<usize>> {
+            Box::pin(async move {
+                self.reads += 1;
+                let start = usize::min(position as usize, self.data.len());
+                let end = usize::min(start + buf.len(), self.data.len());
+                buf[..end - start].copy_from_slice(&self.data[start..end]);
+                Ok(end - start)
+            })
+        }
+    }
+
+    struct NoopWaker;
+
+    impl Wake for NoopWaker {
+        fn wake(self: Arc<Self>) {}
+    }
+
+    /// Minimal executor for futures that never wait for anything but our `MemoryBackend`.
+    fn block_on<F: Future>(future: F) -> F::Output {
+        let waker = Arc::new(NoopWaker).into();
+        let mut context = Context::from_waker(&waker);
+        let mut future = Box::pin(future);
+
+        loop {
+            if let Poll::Ready(output) = future.as_mut().poll(&mut context) {
+                return output;
+            }
+        }
+    }
+
+    fn testfs1_async(capacity: usize) -> NtfsAsyncReader<MemoryBackend> {
+        let data = crate::helpers::tests::testfs1().into_inner();
+        NtfsAsyncReader::with_block_size(MemoryBackend { data, reads: 0 }, 4096, capacity)
+    }
+
+    #[test]
+    fn test_async_futures_are_send() {
+        fn assert_send<T: Send>(_: &T) {}
+
+        let mut fs = testfs1_async(DEFAULT_CAPACITY);
+        assert_send(&fs.run(Ntfs::new));
+
+        let ntfs = block_on(fs.run(Ntfs::new)).unwrap();
+        let root_dir = block_on(fs.run(|fs| ntfs.root_directory(fs))).unwrap();
+        let root_dir_index = block_on(fs.run(|fs| root_dir.directory_index(fs))).unwrap();
+        let iter = root_dir_index.entries();
+        assert_send(&fs.prefetch(|fs| {
+            let _ = iter.clone().next(fs);
+        }));
+    }
+
+    #[test]
+    fn test_async_pipeline() {
+        block_on(async {
+            let mut fs = testfs1_async(DEFAULT_CAPACITY);
+            let mut ntfs = fs.run(Ntfs::new).await.unwrap();
+            assert_eq!(ntfs.cluster_size(), 512);
+            fs.run(|fs| ntfs.read_upcase_table(fs)).await.unwrap();
+
+            // Find a file and read its data.
+            let root_dir = fs.run(|fs| ntfs.root_directory(fs)).await.unwrap();
+            let root_dir_index = fs.run(|fs| root_dir.directory_index(fs)).await.unwrap();
+            let mut finder = root_dir_index.finder();
+            fs.prefetch(|fs| {
+                let _ = NtfsFileNameIndex::find(&mut finder, &ntfs, fs, "1000-bytes-file");
+            })
+            .await
+            .unwrap();
+            let entry = NtfsFileNameIndex::find(&mut finder, &ntfs, &mut fs, "1000-bytes-file")
+                .unwrap()
+                .unwrap();
+            let file = fs.run(|fs| entry.to_file(&ntfs, fs)).await.unwrap();
+            let data_item = fs.run(|fs| file.data(fs, "").transpose()).await.unwrap();
+            let data_item = data_item.unwrap();
+            let data_attribute = data_item.to_attribute().unwrap();
+            let mut data_value = fs.run(|fs| data_attribute.value(fs)).await.unwrap();
+
+            let mut buf = [0u8; 1000];
+            fs.prefetch(|fs| {
+                let _ = data_value.clone().read_exact(fs, &mut buf);
+            })
+            .await
+            .unwrap();
+            data_value.read_exact(&mut fs, &mut buf).unwrap();
+            assert!(buf.chunks(5).all(|chunk| chunk == b"12345"));
+
+            // Iterate over a large directory.
+            let subdir = fs
+                .run(|fs| {
+                    ntfs.file_by_path(fs, "many_subdirs", Default::default())
+                        .unwrap()
+                })
+                .await
+                .unwrap();
+            let subdir_index = fs.run(|fs| subdir.directory_index(fs)).await.unwrap();
+            let mut iter = subdir_index.entries();
+            let mut count = 0;
+
+            loop {
+                fs.prefetch(|fs| {
+                    let _ = iter.clone().next(fs);
+                })
+                .await
+                .unwrap();
+                let entry = match iter.next(&mut fs) {
+                    Some(entry) => entry.unwrap(),
+                    None => break,
+                };
+                let file_name = entry.key().unwrap().unwrap();
+                assert!(file_name
+                    .name()
+                    .to_string_lossy()
+                    .starts_with(char::is_numeric));
+                count += 1;
+            }
+
+            assert_eq!(count, 512);
+            assert!(fs.cached_blocks() <= DEFAULT_CAPACITY);
+        });
+    }
+
+    #[test]
+    fn test_async_bounded_cache() {
+        block_on(async {
+            let mut fs = testfs1_async(64);
+
+            // Seeking from the end asks the backend for the partition size.
+            let len = fs.run(|fs| Ok(fs.seek(SeekFrom::End(0))?)).await.unwrap();
+            assert_eq!(len, 2096640 + 512);
+
+            // A sequential read of 48 blocks fits into the cache and only needs a few reads
+            // thanks to the growing readahead.
+            let mut buf = vec![0u8; 48 * 4096];
+            fs.run(|fs| {
+                fs.seek(SeekFrom::Start(0))?;
+                Ok(fs.read_exact(&mut buf)?)
+            })
+            .await
+            .unwrap();
+            assert_eq!(&buf[3..7], b"NTFS");
+            assert!(fs.backend().reads <= 4, "{}", fs.backend().reads);
+            assert!(fs.cached_blocks() <= 64);
+
+            // A read of more blocks than the cache holds fails instead of growing the cache.
+            let mut buf = vec![0u8; 100 * 4096];
+            let result = fs
+                .run(|fs| {
+                    fs.seek(SeekFrom::Start(0))?;
+                    Ok(fs.read_exact(&mut buf)?)
+                })
+                .await;
+            assert!(matches!(
+                result,
+                Err(NtfsError::AsyncCacheTooSmall { capacity: 64 })
+            ));
+            assert!(fs.cached_blocks() <= 64);
+
+            // Later operations evict the blocks of earlier ones.
+            let ntfs = fs.run(Ntfs::new).await.unwrap();
+            let file = fs.run(|fs| ntfs.file(fs, 255)).await.unwrap();
+            assert_eq!(file.file_record_number(), 255);
+            assert!(fs.cached_blocks() <= 64);
+        });
+    }
+}
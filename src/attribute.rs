@@ -5,6 +5,7 @@ use core::iter::FusedIterator;
 use core::ops::Range;
 use core::{fmt, mem};
 
+use alloc::vec::Vec;
 use binrw::io::{Read, Seek};
 use bitflags::bitflags;
 use byteorder::{ByteOrder, LittleEndian};
@@ -14,11 +15,14 @@ use nt_string::u16strle::U16StrLe;
 use strum_macros::Display;
 
 use crate::attribute_value::{
-    NtfsAttributeListNonResidentAttributeValue, NtfsAttributeValue, NtfsNonResidentAttributeValue,
-    NtfsResidentAttributeValue,
+    AttributeListConnectedEntries, NtfsAttributeListNonResidentAttributeValue, NtfsAttributeValue,
+    NtfsNonResidentAttributeValue, NtfsResidentAttributeValue,
 };
 use crate::error::{NtfsError, Result};
+use crate::extents::NtfsExtentMap;
 use crate::file::NtfsFile;
+use crate::options::NtfsUnsupportedPolicy;
+use crate::piecewise_hash::{NtfsPieceHash, NtfsPieceHasher};
 use crate::structured_values::{
     NtfsAttributeList, NtfsAttributeListEntries, NtfsStructuredValue,
     NtfsStructuredValueFromResidentAttributeValue,
@@ -201,6 +205,49 @@ impl<'n, 'f> NtfsAttribute<'n, 'f> {
         Ok(attribute)
     }
 
+    /// Returns the allocated size of the value of this NTFS Attribute, in bytes.
+    ///
+    /// For a non-resident attribute, this is the space allocated in clusters on the filesystem.
+    /// If its value is split over connected attributes of an Attribute List, the allocated sizes
+    /// of all connected attributes are summed up, which requires reading their File Records.
+    ///
+    /// For a resident attribute, this is the same as [`NtfsAttribute::value_length`].
+    pub fn allocated_size<T>(&self, fs: &mut T) -> Result<u64>
+    where
+        T: Read + Seek,
+    {
+        if let Some(list_entries) = self.list_entries {
+            let mut connected_entries = AttributeListConnectedEntries::new(
+                list_entries.clone(),
+                self.instance(),
+                self.raw_ty(),
+            );
+            let mut allocated_size = 0u64;
+
+            while let Some(entry) = connected_entries.next(fs) {
+                let entry = entry?;
+                let file = entry.to_file(self.file.ntfs(), fs)?;
+                let attribute = entry.to_attribute(&file)?;
+
+                // Connected attributes must always be non-resident. Verify that.
+                if attribute.is_resident() {
+                    return Err(NtfsError::UnexpectedResidentAttribute {
+                        position: attribute.position(),
+                    });
+                }
+
+                allocated_size = allocated_size
+                    .saturating_add(attribute.non_resident_value_fragment_allocated_size());
+            }
+
+            Ok(allocated_size)
+        } else if self.is_resident() {
+            Ok(self.resident_value_length() as u64)
+        } else {
+            Ok(self.non_resident_value_allocated_size())
+        }
+    }
+
     /// Returns the length of this NTFS Attribute, in bytes.
     ///
     /// This denotes the length of the attribute structure on disk.
@@ -224,6 +271,23 @@ impl<'n, 'f> NtfsAttribute<'n, 'f> {
         Ok(())
     }
 
+    /// Returns the sizes and on-disk cluster ranges of the value of this NTFS Attribute as an
+    /// [`NtfsExtentMap`].
+    ///
+    /// This also collects the Data Runs of all connected attributes if the value is split up via
+    /// an Attribute List.
+    pub fn extent_map<T>(&self, fs: &mut T) -> Result<NtfsExtentMap>
+    where
+        T: Read + Seek,
+    {
+        NtfsExtentMap::new(self.file.ntfs(), self, fs)
+    }
+
+    /// Returns the [`NtfsFile`] whose File Record holds this attribute.
+    pub(crate) fn file(&self) -> &'f NtfsFile<'n> {
+        self.file
+    }
+
     /// Returns flags set for this attribute as specified by [`NtfsAttributeFlags`].
     pub fn flags(&self) -> NtfsAttributeFlags {
         let start = self.offset + offset_of!(NtfsAttributeHeader, flags);
@@ -246,6 +310,10 @@ impl<'n, 'f> NtfsAttribute<'n, 'f> {
         is_non_resident == 0
     }
 
+    pub(crate) fn list_entries(&self) -> Option<&'f NtfsAttributeListEntries<'n, 'f>> {
+        self.list_entries
+    }
+
     /// Gets the name of this NTFS Attribute (if any) and returns it wrapped in a [`U16StrLe`].
     ///
     /// Note that most NTFS attributes have no name and are distinguished by their types.
@@ -292,21 +360,55 @@ impl<'n, 'f> NtfsAttribute<'n, 'f> {
     }
 
     pub(crate) fn non_resident_value_data_and_position(&self) -> Result<(&'f [u8], NtfsPosition)> {
+        let range = self.non_resident_value_data_runs_range()?;
+        let position = self.file.position() + range.start;
+        Ok((&self.file.record_data()[range], position))
+    }
+
+    /// Returns the byte range of the Data Runs of the non-resident value within the File Record.
+    pub(crate) fn non_resident_value_data_runs_range(&self) -> Result<Range<usize>> {
         debug_assert!(!self.is_resident());
         let start = self.offset + self.non_resident_value_data_runs_offset() as usize;
         let end = self.offset + self.attribute_length() as usize;
-        let position = self.file.position() + start;
-        let data = &self.file.record_data().get(start..end).ok_or(
-            NtfsError::InvalidNonResidentValueDataRange {
-                position,
+
+        if end > self.file.record_data().len() || start > end {
+            return Err(NtfsError::InvalidNonResidentValueDataRange {
+                position: self.file.position() + start,
                 range: start..end,
                 size: self.file.record_data().len(),
-            },
-        )?;
-        Ok((data, position))
+            });
+        }
+
+        Ok(start..end)
+    }
+
+    pub(crate) fn non_resident_value_allocated_size(&self) -> u64 {
+        debug_assert!(!self.is_resident());
+        let start = self.offset + offset_of!(NtfsNonResidentAttributeHeader, allocated_size);
+        LittleEndian::read_u64(&self.file.record_data()[start..])
+    }
+
+    /// Returns the allocated size of just the clusters referenced by this attribute, in bytes.
+    ///
+    /// Contrary to [`NtfsAttribute::non_resident_value_allocated_size`], this also works for connected attributes,
+    /// where only the first attribute reports the allocated size for all of them.
+    pub(crate) fn non_resident_value_fragment_allocated_size(&self) -> u64 {
+        debug_assert!(!self.is_resident());
+        let record_data = self.file.record_data();
+        let start = self.offset + offset_of!(NtfsNonResidentAttributeHeader, lowest_vcn);
+        let lowest_vcn = LittleEndian::read_i64(&record_data[start..]);
+        let start = self.offset + offset_of!(NtfsNonResidentAttributeHeader, highest_vcn);
+        let highest_vcn = LittleEndian::read_i64(&record_data[start..]);
+
+        // `highest_vcn` may be -1 for zero-length values.
+        let cluster_count = highest_vcn
+            .saturating_sub(lowest_vcn)
+            .saturating_add(1)
+            .max(0) as u64;
+        cluster_count.saturating_mul(self.file.ntfs().cluster_size() as u64)
     }
 
-    fn non_resident_value_data_size(&self) -> u64 {
+    pub(crate) fn non_resident_value_data_size(&self) -> u64 {
         debug_assert!(!self.is_resident());
         let start = self.offset + offset_of!(NtfsNonResidentAttributeHeader, data_size);
         LittleEndian::read_u64(&self.file.record_data()[start..])
@@ -318,15 +420,87 @@ impl<'n, 'f> NtfsAttribute<'n, 'f> {
         LittleEndian::read_u16(&self.file.record_data()[start..])
     }
 
+    pub(crate) fn non_resident_value_highest_vcn(&self) -> Vcn {
+        debug_assert!(!self.is_resident());
+        let start = self.offset + offset_of!(NtfsNonResidentAttributeHeader, highest_vcn);
+        Vcn::from(LittleEndian::read_i64(&self.file.record_data()[start..]))
+    }
+
+    pub(crate) fn non_resident_value_initialized_size(&self) -> u64 {
+        debug_assert!(!self.is_resident());
+        let start = self.offset + offset_of!(NtfsNonResidentAttributeHeader, initialized_size);
+        LittleEndian::read_u64(&self.file.record_data()[start..])
+    }
+
+    pub(crate) fn non_resident_value_lowest_vcn(&self) -> Vcn {
+        debug_assert!(!self.is_resident());
+        let start = self.offset + offset_of!(NtfsNonResidentAttributeHeader, lowest_vcn);
+        Vcn::from(LittleEndian::read_i64(&self.file.record_data()[start..]))
+    }
+
     pub(crate) fn offset(&self) -> usize {
         self.offset
     }
 
+    /// Hashes the entire value of this NTFS Attribute in pieces of `piece_size` bytes and returns
+    /// the hash value of every piece.
+    ///
+    /// See [`NtfsAttributeValue::piecewise_hashes`] for details.
+    /// In addition, this returns [`NtfsError::UnsupportedCompressedAttribute`] for a compressed
+    /// attribute instead of hashing its compressed clusters.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `piece_size` is zero.
+    pub fn piecewise_hashes<T, H>(
+        &self,
+        fs: &mut T,
+        piece_size: u64,
+        hasher: &mut H,
+    ) -> Result<Vec<NtfsPieceHash<H::Digest>>>
+    where
+        T: Read + Seek,
+        H: NtfsPieceHasher + ?Sized,
+    {
+        if self.flags().contains(NtfsAttributeFlags::COMPRESSED) {
+            return Err(NtfsError::UnsupportedCompressedAttribute {
+                position: self.position(),
+            });
+        }
+
+        self.value(fs)?.piecewise_hashes(fs, piece_size, hasher)
+    }
+
     /// Returns the absolute position of this NTFS Attribute within the filesystem, in bytes.
     pub fn position(&self) -> NtfsPosition {
         self.file.position() + self.offset
     }
 
+    /// Reads the entire value of this NTFS Attribute in chunks of `chunk_size` bytes, passes every
+    /// chunk to `f`, and returns the number of bytes read.
+    ///
+    /// See [`NtfsAttributeValue::read_all_chunked`] for details.
+    /// In addition, this returns [`NtfsError::UnsupportedCompressedAttribute`] for a compressed
+    /// attribute instead of passing its compressed clusters.
+    /// Encrypted attributes are passed as stored on the filesystem (i.e. encrypted).
+    ///
+    /// # Panics
+    ///
+    /// Panics if `chunk_size` is zero.
+    pub fn read_all_chunked<T, F>(&self, fs: &mut T, chunk_size: usize, f: F) -> Result<u64>
+    where
+        T: Read + Seek,
+        F: FnMut(&[u8]),
+    {
+        if self.flags().contains(NtfsAttributeFlags::COMPRESSED) {
+            return Err(NtfsError::UnsupportedCompressedAttribute {
+                position: self.position(),
+            });
+        }
+
+        self.value(fs)?.read_all_chunked(fs, chunk_size, f)
+    }
+
     /// Attempts to parse the value data as the given resident structured value type and returns that.
     ///
     /// This is a fast path for attributes that are always resident.
@@ -356,11 +530,20 @@ impl<'n, 'f> NtfsAttribute<'n, 'f> {
         debug_assert!(self.is_resident());
         self.validate_resident_value_sizes()?;
 
+        let data = &self.file.record_data()[self.resident_value_range()?];
+        let position = self.position() + self.resident_value_offset();
+
+        Ok(NtfsResidentAttributeValue::new(data, position))
+    }
+
+    /// Returns the byte range of the resident value data within the File Record.
+    pub(crate) fn resident_value_range(&self) -> Result<Range<usize>> {
+        debug_assert!(self.is_resident());
+        self.validate_resident_value_sizes()?;
+
         let start = self.offset + self.resident_value_offset() as usize;
         let end = start + self.resident_value_length() as usize;
-        let data = &self.file.record_data()[start..end];
-
-        Ok(NtfsResidentAttributeValue::new(data, self.position()))
+        Ok(start..end)
     }
 
     fn resident_value_length(&self) -> u32 {
@@ -390,11 +573,15 @@ impl<'n, 'f> NtfsAttribute<'n, 'f> {
         S::from_attribute_value(fs, value)
     }
 
+    pub(crate) fn raw_ty(&self) -> u32 {
+        let start = self.offset + offset_of!(NtfsAttributeHeader, ty);
+        LittleEndian::read_u32(&self.file.record_data()[start..])
+    }
+
     /// Returns the type of this NTFS Attribute, or [`NtfsError::UnsupportedAttributeType`]
     /// if it's an unknown type.
     pub fn ty(&self) -> Result<NtfsAttributeType> {
-        let start = self.offset + offset_of!(NtfsAttributeHeader, ty);
-        let ty = LittleEndian::read_u32(&self.file.record_data()[start..]);
+        let ty = self.raw_ty();
 
         NtfsAttributeType::n(ty).ok_or(NtfsError::UnsupportedAttributeType {
             position: self.position(),
@@ -402,6 +589,20 @@ impl<'n, 'f> NtfsAttribute<'n, 'f> {
         })
     }
 
+    /// Returns whether this NTFS Attribute is of the given type.
+    ///
+    /// Attributes of unknown types never match, but return [`NtfsError::UnsupportedAttributeType`]
+    /// under [`NtfsUnsupportedPolicy::Error`].
+    pub(crate) fn is_ty(&self, ty: NtfsAttributeType) -> Result<bool> {
+        match self.ty() {
+            Ok(own_ty) => Ok(own_ty == ty),
+            Err(e) => match self.file.ntfs().options().attribute_type_policy() {
+                NtfsUnsupportedPolicy::Error => Err(e),
+                NtfsUnsupportedPolicy::Skip | NtfsUnsupportedPolicy::YieldUnknown => Ok(false),
+            },
+        }
+    }
+
     fn validate_attribute_length(&self) -> Result<()> {
         let start = self.offset;
         let end = self.file.record_data().len();
@@ -510,7 +711,7 @@ impl<'n, 'f> NtfsAttribute<'n, 'f> {
                 fs,
                 list_entries.clone(),
                 self.instance(),
-                self.ty()?,
+                self.raw_ty(),
                 data_size,
             )?;
             Ok(NtfsAttributeValue::AttributeListNonResident(value))
@@ -524,6 +725,9 @@ impl<'n, 'f> NtfsAttribute<'n, 'f> {
     }
 
     /// Returns the length of the value data of this NTFS Attribute, in bytes.
+    ///
+    /// If the value is split over connected attributes of an Attribute List, this is the total length
+    /// reported by the first connected attribute.
     pub fn value_length(&self) -> u64 {
         if self.is_resident() {
             self.resident_value_length() as u64
@@ -547,7 +751,8 @@ impl<'n, 'f> NtfsAttribute<'n, 'f> {
 pub struct NtfsAttributes<'n, 'f> {
     raw_iter: NtfsAttributesRaw<'n, 'f>,
     list_entries: Option<NtfsAttributeListEntries<'n, 'f>>,
-    list_skip_info: Option<(u16, NtfsAttributeType)>,
+    list_skip_info: Option<(u16, u32)>,
+    raw_skip_info: Vec<(u16, u32)>,
 }
 
 impl<'n, 'f> NtfsAttributes<'n, 'f> {
@@ -556,6 +761,7 @@ impl<'n, 'f> NtfsAttributes<'n, 'f> {
             raw_iter: NtfsAttributesRaw::new(file),
             list_entries: None,
             list_skip_info: None,
+            raw_skip_info: Vec::new(),
         }
     }
 
@@ -591,23 +797,66 @@ impl<'n, 'f> NtfsAttributes<'n, 'f> {
                     };
                     let entry_instance = entry.instance();
                     let entry_record_number = entry.base_file_reference().file_record_number();
-                    let entry_ty = iter_try!(entry.ty());
+                    let entry_ty = entry.raw_ty();
 
-                    // Ignore all Attribute List entries that just repeat attributes of the raw iterator.
-                    if entry_record_number == self.raw_iter.file.file_record_number() {
-                        continue;
+                    if let Err(e) = entry.ty() {
+                        match self.raw_iter.file.ntfs().options().attribute_type_policy() {
+                            NtfsUnsupportedPolicy::Error => return Some(Err(e)),
+                            NtfsUnsupportedPolicy::Skip => continue,
+                            NtfsUnsupportedPolicy::YieldUnknown => (),
+                        }
                     }
 
+                    let is_base_entry =
+                        entry_record_number == self.raw_iter.file.file_record_number();
+
                     // Ignore all Attribute List entries that are connected attributes of a previous one.
                     if let Some((skip_instance, skip_ty)) = self.list_skip_info {
                         if entry_instance == skip_instance && entry_ty == skip_ty {
+                            if is_base_entry {
+                                // The raw iterator must not return this connected attribute on its own.
+                                self.raw_skip_info.push((entry_instance, entry_ty));
+                            }
+
                             continue;
                         }
                     }
 
-                    // We found an attribute that we want to return.
                     self.list_skip_info = None;
 
+                    if is_base_entry {
+                        // Ignore all Attribute List entries that just repeat attributes of the raw iterator.
+                        // The only exception is an attribute whose value is continued by connected attributes:
+                        // We return it here (and skip it in the raw iterator), so that its value and sizes
+                        // cover all connected attributes.
+                        let mut next_entries = attribute_list_entries.clone();
+                        let is_continued = match next_entries.next(fs) {
+                            Some(Ok(next_entry)) => {
+                                next_entry.instance() == entry_instance
+                                    && next_entry.raw_ty() == entry_ty
+                            }
+                            Some(Err(e)) => return Some(Err(e)),
+                            None => false,
+                        };
+
+                        if !is_continued {
+                            continue;
+                        }
+
+                        let entry_attribute = iter_try!(entry.to_attribute(self.raw_iter.file));
+                        self.list_skip_info = Some((entry_instance, entry_ty));
+                        self.raw_skip_info.push((entry_instance, entry_ty));
+
+                        let item = NtfsAttributeItem {
+                            attribute_file: self.raw_iter.file,
+                            attribute_value_file: None,
+                            attribute_offset: entry_attribute.offset(),
+                            list_entries: Some(attribute_list_entries_clone),
+                        };
+                        return Some(Ok(item));
+                    }
+
+                    // We found an attribute that we want to return.
                     let ntfs = self.raw_iter.file.ntfs();
                     let entry_file = iter_try!(entry.to_file(ntfs, fs));
                     let entry_attribute = iter_try!(entry.to_attribute(&entry_file));
@@ -635,6 +884,21 @@ impl<'n, 'f> NtfsAttributes<'n, 'f> {
                     iter_try!(attribute.structured_value::<T, NtfsAttributeList>(fs));
                 self.list_entries = Some(attribute_list.entries());
             } else {
+                // Skip all attributes that have already been returned as part of connected attributes.
+                if self
+                    .raw_skip_info
+                    .contains(&(attribute.instance(), attribute.raw_ty()))
+                {
+                    continue;
+                }
+
+                if attribute.ty().is_err()
+                    && self.raw_iter.file.ntfs().options().attribute_type_policy()
+                        == NtfsUnsupportedPolicy::Skip
+                {
+                    continue;
+                }
+
                 let item = NtfsAttributeItem {
                     attribute_file: self.raw_iter.file,
                     attribute_value_file: None,
@@ -771,11 +1035,41 @@ impl<'n, 'f> Iterator for NtfsAttributesRaw<'n, 'f> {
 
 impl<'n, 'f> FusedIterator for NtfsAttributesRaw<'n, 'f> {}
 
+/// Writes the highest VCN and the sizes of a non-resident value into the header of the attribute
+/// at byte offset `offset` of the File Record data `record_data`.
+pub(crate) fn write_non_resident_value_sizes(
+    record_data: &mut [u8],
+    offset: usize,
+    highest_vcn: Vcn,
+    allocated_size: u64,
+    data_size: u64,
+    initialized_size: u64,
+) {
+    let start = offset + offset_of!(NtfsNonResidentAttributeHeader, highest_vcn);
+    LittleEndian::write_i64(&mut record_data[start..], highest_vcn.value());
+    let start = offset + offset_of!(NtfsNonResidentAttributeHeader, allocated_size);
+    LittleEndian::write_u64(&mut record_data[start..], allocated_size);
+    let start = offset + offset_of!(NtfsNonResidentAttributeHeader, data_size);
+    LittleEndian::write_u64(&mut record_data[start..], data_size);
+    let start = offset + offset_of!(NtfsNonResidentAttributeHeader, initialized_size);
+    LittleEndian::write_u64(&mut record_data[start..], initialized_size);
+}
+
 #[cfg(test)]
 mod tests {
+    use alloc::vec::Vec;
+    use byteorder::{ByteOrder, LittleEndian};
+    use memoffset::offset_of;
+
+    use super::{
+        write_non_resident_value_sizes, NtfsAttributeType, NtfsNonResidentAttributeHeader,
+        NtfsResidentAttributeHeader,
+    };
+    use crate::file::{KnownNtfsFileRecordNumber, NtfsFileFlags};
     use crate::indexes::NtfsFileNameIndex;
     use crate::ntfs::Ntfs;
     use crate::traits::NtfsReadSeek;
+    use crate::types::Vcn;
 
     #[test]
     fn test_empty_data_attribute() {
@@ -804,4 +1098,209 @@ mod tests {
         let bytes_read = data_attribute_value.read(&mut testfs1, &mut buf).unwrap();
         assert_eq!(bytes_read, 0);
     }
+
+    #[test]
+    fn test_allocated_size() {
+        let mut testfs1 = crate::helpers::tests::testfs1();
+        let ntfs = Ntfs::new(&mut testfs1).unwrap();
+
+        // The non-resident $DATA attribute of $MFT must have allocated exactly the clusters of its Data Runs.
+        let mft = ntfs
+            .file(&mut testfs1, KnownNtfsFileRecordNumber::MFT as u64)
+            .unwrap();
+        let data_attribute_item = mft.data(&mut testfs1, "").unwrap().unwrap();
+        let data_attribute = data_attribute_item.to_attribute().unwrap();
+        assert!(!data_attribute.is_resident());
+
+        let allocated_size = data_attribute.allocated_size(&mut testfs1).unwrap();
+        assert_eq!(allocated_size % ntfs.cluster_size() as u64, 0);
+        assert!(allocated_size >= data_attribute.value_length());
+        assert_eq!(
+            allocated_size,
+            data_attribute.non_resident_value_fragment_allocated_size()
+        );
+
+        // Resident attributes have no allocation apart from their value.
+        let info_attribute_item = mft
+            .attributes()
+            .attach(&mut testfs1)
+            .find(|item| {
+                let attribute = item.as_ref().unwrap().to_attribute().unwrap();
+                attribute.ty().unwrap() == NtfsAttributeType::StandardInformation
+            })
+            .unwrap()
+            .unwrap();
+        let info_attribute = info_attribute_item.to_attribute().unwrap();
+        assert_eq!(
+            info_attribute.allocated_size(&mut testfs1).unwrap(),
+            info_attribute.value_length()
+        );
+    }
+
+    #[test]
+    fn test_allocated_size_attribute_list() {
+        let mut testfs1 = crate::helpers::tests::testfs1();
+        let mut ntfs = Ntfs::new(&mut testfs1).unwrap();
+        ntfs.read_upcase_table(&mut testfs1).unwrap();
+        let cluster_size = ntfs.cluster_size() as u64;
+
+        // testfs1 has no file with an Attribute List.
+        // Therefore, split the 2-cluster $DATA attribute of "1000-bytes-file" into two connected attributes:
+        // The first one stays in the base File Record, the second one goes to the unused File Record 27,
+        // and a new $ATTRIBUTE_LIST in the base File Record references both of them.
+        let mut file = ntfs
+            .file_by_path(&mut testfs1, "1000-bytes-file", Default::default())
+            .unwrap()
+            .unwrap();
+        let file_record_number = file.file_record_number();
+        let base_reference = file_record_number | (file.sequence_number() as u64) << 48;
+
+        let mut extension = ntfs.file(&mut testfs1, 27).unwrap();
+        assert!(!extension.flags().contains(NtfsFileFlags::IN_USE));
+        let extension_reference = 27 | (extension.sequence_number() as u64) << 48;
+
+        // Collect the Attribute List entries and the offsets we need.
+        let mut entries = Vec::new();
+        let mut list_offset = 0;
+        let mut data_range = 0..0;
+        let mut data_instance = 0;
+
+        for attribute in file.attributes_raw() {
+            let attribute = attribute.unwrap();
+            let ty = attribute.ty().unwrap();
+            let end = attribute.offset + attribute.attribute_length() as usize;
+            entries.push((
+                attribute.raw_ty(),
+                0i64,
+                base_reference,
+                attribute.instance(),
+            ));
+
+            if ty == NtfsAttributeType::StandardInformation {
+                list_offset = end;
+            } else if ty == NtfsAttributeType::Data {
+                assert!(!attribute.is_resident());
+                assert_eq!(
+                    attribute.non_resident_value_allocated_size(),
+                    2 * cluster_size
+                );
+                data_range = attribute.offset..end;
+                data_instance = attribute.instance();
+            }
+        }
+
+        entries.push((
+            NtfsAttributeType::Data as u32,
+            1,
+            extension_reference,
+            data_instance,
+        ));
+
+        // Build the second connected attribute from the first one, mapping the second cluster.
+        let mut fragment = file.record_data()[data_range.clone()].to_vec();
+        let data_runs_offset = LittleEndian::read_u16(
+            &fragment[offset_of!(NtfsNonResidentAttributeHeader, data_runs_offset)..],
+        ) as usize;
+
+        // The single Data Run has a 1-byte cluster count (2) and a 2-byte LCN.
+        assert_eq!(
+            &fragment[data_runs_offset..data_runs_offset + 2],
+            &[0x21, 2]
+        );
+        let lcn = LittleEndian::read_u16(&fragment[data_runs_offset + 2..]);
+
+        let start = offset_of!(NtfsNonResidentAttributeHeader, lowest_vcn);
+        LittleEndian::write_i64(&mut fragment[start..], 1);
+        write_non_resident_value_sizes(&mut fragment, 0, Vcn::from(1), 0, 0, 0);
+        fragment[data_runs_offset + 1] = 1;
+        LittleEndian::write_u16(&mut fragment[data_runs_offset + 2..], lcn + 1);
+
+        // Shorten the first connected attribute to the first cluster, keeping the sizes of the entire value.
+        let record_data = file.record_data_mut();
+        write_non_resident_value_sizes(
+            record_data,
+            data_range.start,
+            Vcn::from(0),
+            2 * cluster_size,
+            1000,
+            1000,
+        );
+        record_data[data_range.start + data_runs_offset + 1] = 1;
+
+        // Build the resident $ATTRIBUTE_LIST attribute with 32-byte entries.
+        // Its value follows the resident attribute header and 1 byte of padding.
+        let value_offset = core::mem::size_of::<NtfsResidentAttributeHeader>() + 1;
+        let list_instance = LittleEndian::read_u16(&record_data[0x28..]);
+        let mut list = vec![0u8; value_offset + entries.len() * 32];
+        let list_length = list.len();
+        LittleEndian::write_u32(&mut list[0..], NtfsAttributeType::AttributeList as u32);
+        LittleEndian::write_u32(&mut list[4..], list_length as u32);
+        LittleEndian::write_u16(&mut list[10..], value_offset as u16);
+        LittleEndian::write_u16(&mut list[14..], list_instance);
+        LittleEndian::write_u32(&mut list[16..], (list_length - value_offset) as u32);
+        LittleEndian::write_u16(&mut list[20..], value_offset as u16);
+
+        for (entry, (ty, lowest_vcn, reference, instance)) in
+            list[value_offset..].chunks_exact_mut(32).zip(entries)
+        {
+            LittleEndian::write_u32(&mut entry[0..], ty);
+            LittleEndian::write_u16(&mut entry[4..], 32);
+            entry[7] = 0x1a;
+            LittleEndian::write_i64(&mut entry[8..], lowest_vcn);
+            LittleEndian::write_u64(&mut entry[16..], reference);
+            LittleEndian::write_u16(&mut entry[24..], instance);
+        }
+
+        // Insert it after $STANDARD_INFORMATION and update the File Record header
+        // (used size at 0x18, next attribute instance at 0x28).
+        let data_size = LittleEndian::read_u32(&record_data[0x18..]) as usize;
+        record_data.copy_within(list_offset..data_size, list_offset + list_length);
+        record_data[list_offset..list_offset + list_length].copy_from_slice(&list);
+        LittleEndian::write_u32(&mut record_data[0x18..], (data_size + list_length) as u32);
+        LittleEndian::write_u16(&mut record_data[0x28..], list_instance + 1);
+        file.write_record(&mut testfs1).unwrap();
+
+        // Turn File Record 27 into an extension File Record holding the second connected attribute
+        // (flags at 0x16, base File Record at 0x20).
+        let record_data = extension.record_data_mut();
+        let first_attribute_offset = LittleEndian::read_u16(&record_data[0x14..]) as usize;
+        let end = first_attribute_offset + fragment.len();
+        record_data[first_attribute_offset..end].copy_from_slice(&fragment);
+        LittleEndian::write_u32(&mut record_data[end..], 0xffff_ffff);
+        LittleEndian::write_u32(&mut record_data[0x18..], (end + 8) as u32);
+        LittleEndian::write_u16(&mut record_data[0x16..], NtfsFileFlags::IN_USE.bits());
+        LittleEndian::write_u64(&mut record_data[0x20..], base_reference);
+        LittleEndian::write_u16(&mut record_data[0x28..], data_instance + 1);
+        extension.write_record(&mut testfs1).unwrap();
+
+        // The value must now be reported once, with the sizes of the entire value.
+        let file = ntfs.file(&mut testfs1, file_record_number).unwrap();
+        let data_items = file
+            .attributes()
+            .attach(&mut testfs1)
+            .map(Result::unwrap)
+            .filter(|item| item.to_attribute().unwrap().ty().unwrap() == NtfsAttributeType::Data)
+            .count();
+        assert_eq!(data_items, 1);
+
+        let data_attribute_item = file.data(&mut testfs1, "").unwrap().unwrap();
+        let data_attribute = data_attribute_item.to_attribute().unwrap();
+        assert_eq!(data_attribute.value_length(), 1000);
+        assert_eq!(
+            data_attribute.non_resident_value_fragment_allocated_size(),
+            cluster_size
+        );
+        assert_eq!(
+            data_attribute.allocated_size(&mut testfs1).unwrap(),
+            2 * cluster_size
+        );
+
+        // Reading the value crosses from the first into the second connected attribute.
+        let mut data_attribute_value = data_attribute.value(&mut testfs1).unwrap();
+        let mut buf = [0u8; 1000];
+        data_attribute_value
+            .read_exact(&mut testfs1, &mut buf)
+            .unwrap();
+        assert!(buf.chunks(5).all(|chunk| chunk == b"12345"));
+    }
 }
@@ -9,10 +9,11 @@
 use binrw::io::{Read, Seek, SeekFrom};
 
 use super::{DataRunsState, NtfsDataRuns, StreamState};
-use crate::attribute::{NtfsAttribute, NtfsAttributeType};
+use crate::attribute::NtfsAttribute;
 use crate::error::{NtfsError, Result};
 use crate::file::NtfsFile;
 use crate::ntfs::Ntfs;
+use crate::options::NtfsUnbackedDataPolicy;
 use crate::structured_values::{NtfsAttributeListEntries, NtfsAttributeListEntry};
 use crate::traits::NtfsReadSeek;
 use crate::types::NtfsPosition;
@@ -44,7 +45,7 @@ impl<'n, 'f> NtfsAttributeListNonResidentAttributeValue<'n, 'f> {
         fs: &mut T,
         attribute_list_entries: NtfsAttributeListEntries<'n, 'f>,
         instance: u16,
-        ty: NtfsAttributeType,
+        ty: u32,
         data_size: u64,
     ) -> Result<Self>
     where
@@ -67,6 +68,37 @@ impl<'n, 'f> NtfsAttributeListNonResidentAttributeValue<'n, 'f> {
         Ok(value)
     }
 
+    /// Returns the number of bytes of the value that are actually provided by the Data Runs of all
+    /// connected attributes (including "sparse" Data Runs).
+    ///
+    /// See [`NtfsNonResidentAttributeValue::backed_len`] for details.
+    ///
+    /// [`NtfsNonResidentAttributeValue::backed_len`]: crate::attribute_value::NtfsNonResidentAttributeValue::backed_len
+    pub fn backed_len<T>(&self, fs: &mut T) -> Result<u64>
+    where
+        T: Read + Seek,
+    {
+        let mut connected_entries = AttributeListConnectedEntries::new(
+            self.initial_attribute_list_entries.clone(),
+            self.connected_entries.instance,
+            self.connected_entries.ty,
+        );
+        let mut backed_len = 0u64;
+
+        while let Some(entry) = connected_entries.next(fs) {
+            let entry = entry?;
+            let file = entry.to_file(self.ntfs, fs)?;
+            let attribute = entry.to_attribute(&file)?;
+            let (data, position) = attribute.non_resident_value_data_and_position()?;
+
+            for data_run in NtfsDataRuns::new(self.ntfs, data, position) {
+                backed_len = backed_len.saturating_add(data_run?.allocated_size());
+            }
+        }
+
+        Ok(backed_len.min(self.len()))
+    }
+
     /// Returns the absolute current data seek position within the filesystem, in bytes.
     /// This may be `None` if:
     ///   * The current seek position is outside the valid range, or
@@ -220,6 +252,37 @@ impl<'n, 'f> NtfsReadSeek for NtfsAttributeListNonResidentAttributeValue<'n, 'f>
             }
         }
 
+        if self.stream_state.hit_unbacked_data(buf, bytes_read) {
+            match self.ntfs.options().unbacked_data_policy() {
+                NtfsUnbackedDataPolicy::Error => {
+                    // Return what we have read so far first, just like any short read.
+                    if bytes_read == 0 {
+                        let position = match &self.attribute_state {
+                            Some(attribute_state) => {
+                                NtfsAttribute::new(
+                                    &attribute_state.file,
+                                    attribute_state.attribute_offset,
+                                    None,
+                                )?
+                                .non_resident_value_data_and_position()?
+                                .1
+                            }
+                            None => NtfsPosition::none(),
+                        };
+
+                        return Err(NtfsError::AttributeValueNotBacked {
+                            position,
+                            data_size: self.len(),
+                            backed_size: self.backed_len(fs)?,
+                        });
+                    }
+                }
+                NtfsUnbackedDataPolicy::ZeroFill => {
+                    self.stream_state.read_zeros(buf, &mut bytes_read)
+                }
+            }
+        }
+
         Ok(bytes_read)
     }
 
@@ -281,18 +344,19 @@ impl<'n, 'f> NtfsReadSeek for NtfsAttributeListNonResidentAttributeValue<'n, 'f>
     }
 }
 
+/// Iterator over the Attribute List entries of all attributes connected to the first one.
 #[derive(Clone, Debug)]
-struct AttributeListConnectedEntries<'n, 'f> {
+pub(crate) struct AttributeListConnectedEntries<'n, 'f> {
     attribute_list_entries: Option<NtfsAttributeListEntries<'n, 'f>>,
     instance: u16,
-    ty: NtfsAttributeType,
+    ty: u32,
 }
 
 impl<'n, 'f> AttributeListConnectedEntries<'n, 'f> {
-    fn new(
+    pub(crate) fn new(
         attribute_list_entries: NtfsAttributeListEntries<'n, 'f>,
         instance: u16,
-        ty: NtfsAttributeType,
+        ty: u32,
     ) -> Self {
         Self {
             attribute_list_entries: Some(attribute_list_entries),
@@ -301,14 +365,14 @@ impl<'n, 'f> AttributeListConnectedEntries<'n, 'f> {
         }
     }
 
-    fn next<T>(&mut self, fs: &mut T) -> Option<Result<NtfsAttributeListEntry>>
+    pub(crate) fn next<T>(&mut self, fs: &mut T) -> Option<Result<NtfsAttributeListEntry>>
     where
         T: Read + Seek,
     {
         let attribute_list_entries = self.attribute_list_entries.as_mut()?;
 
         let entry = iter_try!(attribute_list_entries.next(fs)?);
-        if entry.instance() == self.instance && iter_try!(entry.ty()) == self.ty {
+        if entry.instance() == self.instance && entry.raw_ty() == self.ty {
             Some(Ok(entry))
         } else {
             self.attribute_list_entries = None;
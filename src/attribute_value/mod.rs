@@ -11,13 +11,20 @@ pub use attribute_list_non_resident::*;
 pub use non_resident::*;
 pub use resident::*;
 
+use alloc::vec;
+use alloc::vec::Vec;
 use binrw::io;
-use binrw::io::{Read, Seek, SeekFrom};
+use binrw::io::{Read, Seek, SeekFrom, Write};
 
 use crate::error::{NtfsError, Result};
+use crate::piecewise_hash::{piecewise_hashes, NtfsPieceHash, NtfsPieceHasher};
+use crate::progress::{report_progress, NtfsProgressSink, NtfsProgressUnit};
 use crate::traits::NtfsReadSeek;
 use crate::types::NtfsPosition;
 
+/// Size of the buffer used for copying attribute values in chunks.
+pub(crate) const COPY_BUFFER_SIZE: usize = 64 * 1024;
+
 /// Reader that abstracts over all attribute value types, returned by [`NtfsAttribute::value`].
 ///
 /// [`NtfsAttribute::value`]: crate::NtfsAttribute::value
@@ -42,6 +49,62 @@ impl<'n, 'f> NtfsAttributeValue<'n, 'f> {
         NtfsAttributeValueAttached::new(fs, self)
     }
 
+    /// Returns the number of bytes of the value that are actually provided by the filesystem.
+    ///
+    /// For a resident value, this is always [`NtfsAttributeValue::len`].
+    /// For a non-resident value, this is the part covered by its Data Runs, which is less than
+    /// [`NtfsAttributeValue::len`] if a corrupted attribute declares a larger data size than its
+    /// Data Runs cover.
+    /// Reading past this length is handled according to [`NtfsOptions::unbacked_data_policy`].
+    ///
+    /// [`NtfsOptions::unbacked_data_policy`]: crate::NtfsOptions::unbacked_data_policy
+    pub fn backed_len<T>(&self, fs: &mut T) -> Result<u64>
+    where
+        T: Read + Seek,
+    {
+        match self {
+            Self::Resident(inner) => Ok(inner.len()),
+            Self::NonResident(inner) => inner.backed_len(),
+            Self::AttributeListNonResident(inner) => inner.backed_len(fs),
+        }
+    }
+
+    /// Reads the attribute value from the current seek position to the end, writes it to `w`,
+    /// and returns the number of bytes copied.
+    ///
+    /// The number of bytes copied is reported to an [`NtfsProgressSink`] after every chunk, which may
+    /// also cancel the operation.
+    /// This is useful for values spanning several gigabytes.
+    pub fn copy_to_with_progress<T, W, P>(
+        &mut self,
+        fs: &mut T,
+        w: &mut W,
+        progress: &mut P,
+    ) -> Result<u64>
+    where
+        T: Read + Seek,
+        W: Write,
+        P: NtfsProgressSink + ?Sized,
+    {
+        let mut buf = vec![0u8; COPY_BUFFER_SIZE];
+        let mut bytes_copied = 0u64;
+        let total = Some(self.len().saturating_sub(self.stream_position()));
+
+        loop {
+            report_progress(progress, NtfsProgressUnit::Bytes, bytes_copied, total)?;
+
+            let bytes_read = self.read(fs, &mut buf)?;
+            if bytes_read == 0 {
+                break;
+            }
+
+            w.write_all(&buf[..bytes_read])?;
+            bytes_copied += bytes_read as u64;
+        }
+
+        Ok(bytes_copied)
+    }
+
     /// Returns the absolute current data seek position within the filesystem, in bytes.
     /// This may be `None` if:
     ///   * The current seek position is outside the valid range, or
@@ -68,6 +131,76 @@ impl<'n, 'f> NtfsAttributeValue<'n, 'f> {
             Self::AttributeListNonResident(inner) => inner.len(),
         }
     }
+
+    /// Hashes the entire attribute value in pieces of `piece_size` bytes (e.g. 1 MiB) and returns
+    /// the hash value of every piece.
+    ///
+    /// Piece offsets refer to the logical content, so the value is read from its beginning,
+    /// regardless of the current seek position.
+    /// Sparse Data Runs are hashed as zeros, which makes the result independent of how the data is
+    /// stored on the filesystem.
+    /// An empty value has no pieces.
+    ///
+    /// The value is read via [`NtfsAttributeValue::read_all_chunked`].
+    /// If that fails in the middle of a piece, `hasher` is reset before the error is returned, so it
+    /// can be reused right away.
+    ///
+    /// Note that this crate doesn't decompress values, so use [`NtfsAttribute::piecewise_hashes`]
+    /// if the value may come from a compressed attribute.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `piece_size` is zero.
+    ///
+    /// [`NtfsAttribute::piecewise_hashes`]: crate::NtfsAttribute::piecewise_hashes
+    pub fn piecewise_hashes<T, H>(
+        &mut self,
+        fs: &mut T,
+        piece_size: u64,
+        hasher: &mut H,
+    ) -> Result<Vec<NtfsPieceHash<H::Digest>>>
+    where
+        T: Read + Seek,
+        H: NtfsPieceHasher + ?Sized,
+    {
+        piecewise_hashes(self, fs, piece_size, hasher)
+    }
+
+    /// Reads the attribute value from the current seek position to the end in chunks of `chunk_size`
+    /// bytes, passes every chunk to `f`, and returns the number of bytes read.
+    ///
+    /// Every chunk is exactly `chunk_size` bytes long, except for the last one, which may be shorter.
+    /// Chunks are filled across Data Run boundaries, and sparse Data Runs are passed as zeros.
+    /// This is the building block for hashing entire data streams without writing a read loop.
+    ///
+    /// Note that this crate doesn't decompress values, so use [`NtfsAttribute::read_all_chunked`]
+    /// if the value may come from a compressed attribute.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `chunk_size` is zero.
+    ///
+    /// [`NtfsAttribute::read_all_chunked`]: crate::NtfsAttribute::read_all_chunked
+    pub fn read_all_chunked<T, F>(&mut self, fs: &mut T, chunk_size: usize, mut f: F) -> Result<u64>
+    where
+        T: Read + Seek,
+        F: FnMut(&[u8]),
+    {
+        assert!(chunk_size > 0, "chunk_size must not be zero");
+
+        let remaining = self.len().saturating_sub(self.stream_position());
+        let mut buf = vec![0u8; (chunk_size as u64).min(remaining) as usize];
+        let mut bytes_read = 0u64;
+
+        while bytes_read < remaining {
+            let chunk_length = (remaining - bytes_read).min(buf.len() as u64) as usize;
+            self.read_exact(fs, &mut buf[..chunk_length])?;
+            f(&buf[..chunk_length]);
+            bytes_read += chunk_length as u64;
+        }
+
+        Ok(bytes_read)
+    }
 }
 
 impl<'n, 'f> NtfsReadSeek for NtfsAttributeValue<'n, 'f> {
@@ -16,9 +16,13 @@ use binrw::BinRead;
 use super::seek_contiguous;
 use crate::error::{NtfsError, Result};
 use crate::ntfs::Ntfs;
+use crate::options::NtfsUnbackedDataPolicy;
 use crate::traits::NtfsReadSeek;
 use crate::types::{Lcn, NtfsPosition, Vcn};
 
+#[cfg(feature = "serde")]
+use serde::ser::{Serialize, SerializeStruct, Serializer};
+
 /// Reader for a non-resident attribute value (whose data is in a cluster range outside the File Record).
 #[derive(Clone, Debug)]
 pub struct NtfsNonResidentAttributeValue<'n, 'f> {
@@ -68,6 +72,24 @@ impl<'n, 'f> NtfsNonResidentAttributeValue<'n, 'f> {
         NtfsNonResidentAttributeValueAttached::new(fs, self)
     }
 
+    /// Returns the number of bytes of the value that are actually provided by its Data Runs
+    /// (including "sparse" Data Runs).
+    ///
+    /// This is less than [`NtfsNonResidentAttributeValue::len`] if a corrupted attribute declares a larger
+    /// data size than its Data Runs cover.
+    /// Reading past this length is handled according to [`NtfsOptions::unbacked_data_policy`].
+    ///
+    /// [`NtfsOptions::unbacked_data_policy`]: crate::NtfsOptions::unbacked_data_policy
+    pub fn backed_len(&self) -> Result<u64> {
+        let mut backed_len = 0u64;
+
+        for data_run in self.data_runs() {
+            backed_len = backed_len.saturating_add(data_run?.allocated_size());
+        }
+
+        Ok(backed_len.min(self.len()))
+    }
+
     /// Returns the absolute current data seek position within the filesystem, in bytes.
     /// This may be `None` if:
     ///   * The current seek position is outside the valid range, or
@@ -143,6 +165,24 @@ impl<'n, 'f> NtfsReadSeek for NtfsNonResidentAttributeValue<'n, 'f> {
             }
         }
 
+        if self.stream_state.hit_unbacked_data(buf, bytes_read) {
+            match self.ntfs.options().unbacked_data_policy() {
+                NtfsUnbackedDataPolicy::Error => {
+                    // Return what we have read so far first, just like any short read.
+                    if bytes_read == 0 {
+                        return Err(NtfsError::AttributeValueNotBacked {
+                            position: self.position,
+                            data_size: self.len(),
+                            backed_size: self.backed_len()?,
+                        });
+                    }
+                }
+                NtfsUnbackedDataPolicy::ZeroFill => {
+                    self.stream_state.read_zeros(buf, &mut bytes_read)
+                }
+            }
+        }
+
         Ok(bytes_read)
     }
 
@@ -484,6 +524,21 @@ impl NtfsDataRun {
     }
 }
 
+/// Serializes the start position (`None` for a "sparse" Data Run) and allocated size of the Data Run.
+#[cfg(feature = "serde")]
+#[cfg_attr(docsrs, doc(cfg(feature = "serde")))]
+impl Serialize for NtfsDataRun {
+    fn serialize<S>(&self, serializer: S) -> core::result::Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut state = serializer.serialize_struct("NtfsDataRun", 2)?;
+        state.serialize_field("position", &self.position)?;
+        state.serialize_field("allocated_size", &self.allocated_size)?;
+        state.end()
+    }
+}
+
 impl NtfsReadSeek for NtfsDataRun {
     fn read<T>(&mut self, fs: &mut T, buf: &mut [u8]) -> Result<usize>
     where
@@ -619,6 +674,12 @@ impl StreamState {
         )))
     }
 
+    /// Returns whether a read into `buf` has stopped after the last Data Run, but before the data size
+    /// declared by the attribute.
+    pub(crate) fn hit_unbacked_data(&self, buf: &[u8], bytes_read: usize) -> bool {
+        bytes_read < buf.len() && self.stream_position < self.data_size
+    }
+
     /// Returns whether we read some bytes.
     pub(crate) fn read_data_run<T>(
         &mut self,
@@ -663,6 +724,17 @@ impl StreamState {
         Ok(true)
     }
 
+    /// Fills the rest of `buf` with zeros, up to the (used) data size of the entire value.
+    pub(crate) fn read_zeros(&mut self, buf: &mut [u8], bytes_read: &mut usize) {
+        let remaining_data_size = self.data_size.saturating_sub(self.stream_position);
+        let start = *bytes_read;
+        let end = start + usize::min(buf.len() - start, remaining_data_size as usize);
+
+        buf[start..end].fill(0);
+        *bytes_read = end;
+        self.stream_position += (end - start) as u64;
+    }
+
     /// Returns whether we have reached the final seek position within this Data Run and can therefore stop seeking.
     ///
     /// In all other cases, the caller should move to the next Data Run and seek again.
@@ -725,9 +797,13 @@ impl StreamState {
 mod tests {
     use binrw::io::SeekFrom;
 
+    use super::NtfsNonResidentAttributeValue;
+    use crate::error::NtfsError;
     use crate::indexes::NtfsFileNameIndex;
     use crate::ntfs::Ntfs;
+    use crate::options::{NtfsOptions, NtfsUnbackedDataPolicy};
     use crate::traits::NtfsReadSeek;
+    use crate::upcase_table::NtfsCaseSensitivity;
 
     #[test]
     fn test_read_and_seek() {
@@ -854,5 +930,93 @@ mod tests {
         assert_eq!(buf[..5], [b'1', b'2', b'3', b'4', b'5']);
         assert_eq!(buf[5..500000], [0u8].repeat(499995));
         assert_eq!(buf[500000..500005], [b'1', b'1', b'1', b'1', b'1']);
+
+        // Reading it in chunks yields the same data, with full chunks across the sparse Data Run.
+        let mut chunks = Vec::new();
+        let bytes_read = data_attribute
+            .read_all_chunked(&mut testfs1, 65536, |chunk| chunks.push(chunk.to_vec()))
+            .unwrap();
+        assert_eq!(bytes_read, 500005);
+        assert_eq!(chunks.len(), 8);
+        assert!(chunks[..7].iter().all(|chunk| chunk.len() == 65536));
+        assert_eq!(chunks.concat(), buf);
+
+        // A partially read value is read from its current position.
+        let mut data_attribute_value = data_attribute.value(&mut testfs1).unwrap();
+        data_attribute_value
+            .seek(&mut testfs1, SeekFrom::Start(500000))
+            .unwrap();
+        let mut tail = Vec::new();
+        let bytes_read = data_attribute_value
+            .read_all_chunked(&mut testfs1, 2, |chunk| tail.extend_from_slice(chunk))
+            .unwrap();
+        assert_eq!(bytes_read, 5);
+        assert_eq!(tail, b"11111");
+    }
+
+    #[test]
+    fn test_unbacked_data() {
+        for policy in [
+            NtfsUnbackedDataPolicy::Error,
+            NtfsUnbackedDataPolicy::ZeroFill,
+        ] {
+            let mut testfs1 = crate::helpers::tests::testfs1();
+            let options = NtfsOptions::new().with_unbacked_data_policy(policy);
+            let mut ntfs = Ntfs::new_with_options(&mut testfs1, options).unwrap();
+            ntfs.read_upcase_table(&mut testfs1).unwrap();
+            let file = ntfs
+                .file_by_path(
+                    &mut testfs1,
+                    "1000-bytes-file",
+                    NtfsCaseSensitivity::Insensitive,
+                )
+                .unwrap()
+                .unwrap();
+            let data_item = file.data(&mut testfs1, "").unwrap().unwrap();
+            let data_attribute = data_item.to_attribute().unwrap();
+            let (data, position) = data_attribute
+                .non_resident_value_data_and_position()
+                .unwrap();
+
+            // The single Data Run provides 1024 bytes. Pretend that the attribute declares 5000 bytes.
+            let healthy_value =
+                NtfsNonResidentAttributeValue::new(&ntfs, data, position, 1000).unwrap();
+            assert_eq!(healthy_value.backed_len().unwrap(), 1000);
+
+            let mut value =
+                NtfsNonResidentAttributeValue::new(&ntfs, data, position, 5000).unwrap();
+            assert_eq!(value.len(), 5000);
+            assert_eq!(value.backed_len().unwrap(), 1024);
+
+            let mut buf = vec![0xCCu8; 5000];
+            let bytes_read = value.read(&mut testfs1, &mut buf).unwrap();
+
+            match policy {
+                NtfsUnbackedDataPolicy::Error => {
+                    // The backed part is returned first, and the next read fails.
+                    assert_eq!(bytes_read, 1024);
+                    assert!(matches!(
+                        value.read(&mut testfs1, &mut buf[1024..]),
+                        Err(NtfsError::AttributeValueNotBacked {
+                            data_size: 5000,
+                            backed_size: 1024,
+                            ..
+                        })
+                    ));
+
+                    // This also applies after seeking past the last Data Run.
+                    value.seek(&mut testfs1, SeekFrom::Start(2000)).unwrap();
+                    assert!(matches!(
+                        value.read(&mut testfs1, &mut buf),
+                        Err(NtfsError::AttributeValueNotBacked { .. })
+                    ));
+                }
+                NtfsUnbackedDataPolicy::ZeroFill => {
+                    assert_eq!(bytes_read, 5000);
+                    assert!(buf[1024..].iter().all(|byte| *byte == 0));
+                    assert_eq!(value.read(&mut testfs1, &mut buf).unwrap(), 0);
+                }
+            }
+        }
     }
 }
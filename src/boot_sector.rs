@@ -3,12 +3,24 @@
 
 use core::ops::RangeInclusive;
 
-use binrw::BinRead;
+use binrw::io::{Read, Seek, SeekFrom};
+use binrw::{BinRead, BinReaderExt};
 use memoffset::offset_of;
 
 use crate::error::{NtfsError, Result};
 use crate::types::{Lcn, NtfsPosition};
 
+/// Byte offset of [`BiosParameterBlock`]'s `total_sectors` field within the boot sector.
+const TOTAL_SECTORS_OFFSET: usize = 0x28;
+
+/// Minimum sector size supported by Windows.
+/// NTFS-3G also supports 256-byte sectors, but I haven't seen them anywhere.
+const MIN_SECTOR_SIZE: u16 = 512;
+
+/// Maximum sector size currently supported by Windows.
+/// Tested with Arsenal Image Mounter (https://github.com/ColinFinck/ntfs/issues/14).
+const MAX_SECTOR_SIZE: u16 = 4096;
+
 // Sources:
 // - https://en.wikipedia.org/wiki/NTFS#Partition_Boot_Sector_(VBR)
 // - https://en.wikipedia.org/wiki/BIOS_parameter_block#NTFS
@@ -44,27 +56,9 @@ pub(crate) struct BiosParameterBlock {
 impl BiosParameterBlock {
     /// Returns the size of a single cluster, in bytes.
     pub(crate) fn cluster_size(&self) -> Result<u32> {
-        /// The cluster size cannot go lower than a single sector.
-        const MIN_CLUSTER_SIZE: u32 = 512;
-
-        /// The maximum cluster size supported by Windows is 2 MiB.
-        /// Source: https://en.wikipedia.org/wiki/NTFS
-        const MAX_CLUSTER_SIZE: u32 = 2097152;
-
-        const CLUSTER_SIZE_RANGE: RangeInclusive<u32> = MIN_CLUSTER_SIZE..=MAX_CLUSTER_SIZE;
-
-        // `sectors_per_cluster` and `sector_size` both check for powers of two.
-        // Don't need to do that a third time here.
-        let cluster_size = self.sectors_per_cluster()? as u32 * self.sector_size()? as u32;
-        if !CLUSTER_SIZE_RANGE.contains(&cluster_size) {
-            return Err(NtfsError::UnsupportedClusterSize {
-                min: MIN_CLUSTER_SIZE,
-                max: MAX_CLUSTER_SIZE,
-                actual: cluster_size,
-            });
-        }
-
-        Ok(cluster_size)
+        // `sectors_per_cluster` and `sector_size` both check for powers of two and valid ranges.
+        // Their product is at most 4096 * 4096 bytes, so it always fits into a `u32`.
+        Ok(self.sector_size()? as u32 * self.sectors_per_cluster()? as u32)
     }
 
     pub(crate) fn file_record_size(&self) -> Result<u32> {
@@ -80,54 +74,11 @@ impl BiosParameterBlock {
         }
     }
 
-    /// Source: https://en.wikipedia.org/wiki/NTFS#Partition_Boot_Sector_(VBR)
     fn record_size(&self, size_info: i8) -> Result<u32> {
-        // The usual exponent of `BiosParameterBlock::file_record_size_info` is 10 (2^10 = 1024 bytes).
-        // For index records, it's usually 12 (2^12 = 4096 bytes).
-
-        /// Exponents < 10 have never been seen and are denied to guarantee that every record header
-        /// fits into a record.
-        const MIN_EXPONENT: u32 = 10;
-
-        /// Exponents > 12 have neither been seen and are denied to prevent allocating too large buffers.
-        const MAX_EXPONENT: u32 = 12;
-
-        const EXPONENT_RANGE: RangeInclusive<u32> = MIN_EXPONENT..=MAX_EXPONENT;
-
-        let cluster_size = self.cluster_size()?;
-
-        if size_info > 0 {
-            // The size field denotes a cluster count.
-            cluster_size
-                .checked_mul(size_info as u32)
-                .ok_or(NtfsError::InvalidRecordSizeInfo {
-                    size_info,
-                    cluster_size,
-                })
-        } else {
-            // The size field denotes a binary exponent after negation.
-            let exponent = u32::from(size_info.unsigned_abs());
-
-            if !EXPONENT_RANGE.contains(&exponent) {
-                return Err(NtfsError::InvalidRecordSizeInfo {
-                    size_info,
-                    cluster_size,
-                });
-            }
-
-            Ok(1 << exponent)
-        }
+        record_size(size_info, self.cluster_size()?)
     }
 
     pub(crate) fn sector_size(&self) -> Result<u16> {
-        /// This is the minimum supported by Windows.
-        /// NTFS-3G also supports 256-byte sectors, but I haven't seen them anywhere.
-        const MIN_SECTOR_SIZE: u16 = 512;
-
-        /// This is the maximum currently supported by Windows.
-        /// Tested with Arsenal Image Mounter (https://github.com/ColinFinck/ntfs/issues/14).
-        const MAX_SECTOR_SIZE: u16 = 4096;
-
         const SECTOR_SIZE_RANGE: RangeInclusive<u16> = MIN_SECTOR_SIZE..=MAX_SECTOR_SIZE;
 
         if !SECTOR_SIZE_RANGE.contains(&self.sector_size) || !self.sector_size.is_power_of_two() {
@@ -202,12 +153,13 @@ impl BootSector {
         &self.bpb
     }
 
-    pub(crate) fn validate(&self) -> Result<()> {
+    /// Validates this boot sector, which has been read from byte position `position`.
+    pub(crate) fn validate(&self, position: u64) -> Result<()> {
         // Validate the infamous [0x55, 0xAA] signature at the end of the boot sector.
         let expected_signature = &[0x55, 0xAA];
         if &self.signature != expected_signature {
             return Err(NtfsError::InvalidTwoByteSignature {
-                position: NtfsPosition::new(offset_of!(BootSector, signature) as u64),
+                position: NtfsPosition::new(position + offset_of!(BootSector, signature) as u64),
                 expected: expected_signature,
                 actual: self.signature,
             });
@@ -216,3 +168,306 @@ impl BootSector {
         Ok(())
     }
 }
+
+/// Geometry of an NTFS volume, as parsed from the BIOS Parameter Block of its boot sector.
+///
+/// Every NTFS volume has a primary boot sector in its first sector and a backup boot sector in the
+/// last sector of its partition.
+/// If the primary boot sector fails validation, [`Ntfs::new`] falls back to the backup boot sector.
+/// Use [`NtfsBootSector::is_backup`] to find out which one has been used.
+///
+/// Returned by [`Ntfs::boot_sector`].
+///
+/// [`Ntfs::boot_sector`]: crate::Ntfs::boot_sector
+/// [`Ntfs::new`]: crate::Ntfs::new
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct NtfsBootSector {
+    position: u64,
+    is_backup: bool,
+    sector_size: u16,
+    cluster_size: u32,
+    total_sectors: u64,
+    size: u64,
+    mft_lcn: Lcn,
+    mft_mirror_lcn: Lcn,
+    file_record_size: u32,
+    index_record_size_info: i8,
+    serial_number: u64,
+}
+
+impl NtfsBootSector {
+    /// Reads and validates the primary boot sector and falls back to the backup boot sector
+    /// in the last sector of the partition if that fails.
+    ///
+    /// If both fail, the error of the primary boot sector is returned.
+    pub(crate) fn read<T>(fs: &mut T) -> Result<Self>
+    where
+        T: Read + Seek,
+    {
+        let primary_error = match Self::read_at(fs, 0, false) {
+            Ok(boot_sector) => return Ok(boot_sector),
+            Err(e) => e,
+        };
+
+        // The size of the backup boot sector depends on the sector size, which is unknown without a
+        // valid primary boot sector. Try all supported sector sizes and accept the backup only if
+        // it agrees on its own sector size.
+        let partition_size = fs.seek(SeekFrom::End(0))?;
+        let mut sector_size = MIN_SECTOR_SIZE;
+
+        while sector_size <= MAX_SECTOR_SIZE {
+            if let Some(position) = partition_size.checked_sub(sector_size as u64) {
+                if position > 0 {
+                    if let Ok(boot_sector) = Self::read_at(fs, position, true) {
+                        if boot_sector.sector_size == sector_size {
+                            return Ok(boot_sector);
+                        }
+                    }
+                }
+            }
+
+            sector_size *= 2;
+        }
+
+        Err(primary_error)
+    }
+
+    fn read_at<T>(fs: &mut T, position: u64, is_backup: bool) -> Result<Self>
+    where
+        T: Read + Seek,
+    {
+        fs.seek(SeekFrom::Start(position))?;
+        let boot_sector = fs.read_le::<BootSector>()?;
+        boot_sector.validate(position)?;
+
+        let bpb = boot_sector.bpb();
+        let sector_size = bpb.sector_size()?;
+        let cluster_size = bpb.cluster_size()?;
+        let total_sectors = bpb.total_sectors();
+        let size = total_sectors
+            .checked_mul(sector_size as u64)
+            .ok_or(NtfsError::TotalSectorsTooBig { total_sectors })?;
+        let mft_lcn = bpb.mft_lcn()?;
+        let file_record_size = bpb.file_record_size()?;
+
+        Ok(Self {
+            position,
+            is_backup,
+            sector_size,
+            cluster_size,
+            total_sectors,
+            size,
+            mft_lcn,
+            mft_mirror_lcn: bpb.mft_mirror_lcn,
+            file_record_size,
+            index_record_size_info: bpb.index_record_size_info,
+            serial_number: bpb.serial_number(),
+        })
+    }
+
+    /// Returns the size of a single cluster, in bytes.
+    pub fn cluster_size(&self) -> u32 {
+        self.cluster_size
+    }
+
+    /// Returns the size of a single File Record, in bytes.
+    pub fn file_record_size(&self) -> u32 {
+        self.file_record_size
+    }
+
+    /// Returns the default size of a single Index Record, in bytes.
+    ///
+    /// Every index stores its actual Index Record size in its $INDEX_ROOT attribute, which is why
+    /// this size isn't validated when opening the filesystem.
+    pub fn index_record_size(&self) -> Result<u32> {
+        record_size(self.index_record_size_info, self.cluster_size)
+    }
+
+    /// Returns `true` if this is the backup boot sector, because the primary boot sector has failed
+    /// validation.
+    pub fn is_backup(&self) -> bool {
+        self.is_backup
+    }
+
+    /// Returns the Logical Cluster Number (LCN) of the beginning of the Master File Table (MFT).
+    pub fn mft_lcn(&self) -> Lcn {
+        self.mft_lcn
+    }
+
+    /// Returns the Logical Cluster Number (LCN) of the beginning of the $MFTMirr file, which mirrors
+    /// the first File Records of the Master File Table.
+    pub fn mft_mirror_lcn(&self) -> Lcn {
+        self.mft_mirror_lcn
+    }
+
+    /// Returns the absolute byte position of this boot sector.
+    pub(crate) fn position(&self) -> u64 {
+        self.position
+    }
+
+    /// Returns the size of a single sector, in bytes.
+    pub fn sector_size(&self) -> u16 {
+        self.sector_size
+    }
+
+    /// Returns the serial number of the NTFS volume.
+    pub fn serial_number(&self) -> u64 {
+        self.serial_number
+    }
+
+    pub(crate) fn set_total_sectors(&mut self, total_sectors: u64) {
+        self.total_sectors = total_sectors;
+        self.size = total_sectors * self.sector_size as u64;
+    }
+
+    /// Returns the size of the NTFS volume, in bytes.
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+
+    /// Returns the number of sectors of the NTFS volume.
+    pub fn total_sectors(&self) -> u64 {
+        self.total_sectors
+    }
+}
+
+/// Returns the size of a File Record or Index Record from its size field in the BIOS Parameter Block.
+///
+/// Source: https://en.wikipedia.org/wiki/NTFS#Partition_Boot_Sector_(VBR)
+fn record_size(size_info: i8, cluster_size: u32) -> Result<u32> {
+    // The usual exponent of `BiosParameterBlock::file_record_size_info` is 10 (2^10 = 1024 bytes).
+    // For index records, it's usually 12 (2^12 = 4096 bytes).
+
+    /// Exponents < 10 have never been seen and are denied to guarantee that every record header
+    /// fits into a record.
+    const MIN_EXPONENT: u32 = 10;
+
+    /// Exponents > 12 have neither been seen and are denied to prevent allocating too large buffers.
+    const MAX_EXPONENT: u32 = 12;
+
+    const EXPONENT_RANGE: RangeInclusive<u32> = MIN_EXPONENT..=MAX_EXPONENT;
+
+    if size_info > 0 {
+        // The size field denotes a cluster count.
+        cluster_size
+            .checked_mul(size_info as u32)
+            .ok_or(NtfsError::InvalidRecordSizeInfo {
+                size_info,
+                cluster_size,
+            })
+    } else {
+        // The size field denotes a binary exponent after negation.
+        let exponent = u32::from(size_info.unsigned_abs());
+
+        if !EXPONENT_RANGE.contains(&exponent) {
+            return Err(NtfsError::InvalidRecordSizeInfo {
+                size_info,
+                cluster_size,
+            });
+        }
+
+        Ok(1 << exponent)
+    }
+}
+
+/// Writes a new total sector count into the raw bytes of a boot sector.
+pub(crate) fn write_total_sectors(boot_sector: &mut [u8], total_sectors: u64) {
+    boot_sector[TOTAL_SECTORS_OFFSET..TOTAL_SECTORS_OFFSET + 8]
+        .copy_from_slice(&total_sectors.to_le_bytes());
+}
+
+#[cfg(test)]
+mod tests {
+    use binrw::io::{Cursor, Read};
+    use binrw::BinReaderExt;
+
+    use super::*;
+    use crate::ntfs::Ntfs;
+
+    fn boot_sector_with(sector_size: u16, sectors_per_cluster: u8) -> BootSector {
+        let mut data = [0u8; 512];
+        crate::helpers::tests::testfs1()
+            .read_exact(&mut data)
+            .unwrap();
+
+        // The BIOS Parameter Block follows the jump instruction and the OEM name.
+        let start = 11;
+        data[start..start + 2].copy_from_slice(&sector_size.to_le_bytes());
+        data[start + 2] = sectors_per_cluster;
+
+        let boot_sector = Cursor::new(data).read_le::<BootSector>().unwrap();
+        boot_sector.validate(0).unwrap();
+        boot_sector
+    }
+
+    #[test]
+    fn test_sector_and_cluster_sizes() {
+        let boot_sector = boot_sector_with(4096, 1);
+        let bpb = boot_sector.bpb();
+        assert_eq!(bpb.sector_size().unwrap(), 4096);
+        assert_eq!(bpb.cluster_size().unwrap(), 4096);
+
+        // 2^-9 = 512 sectors per cluster give 2 MiB clusters.
+        let boot_sector = boot_sector_with(4096, (-9i8) as u8);
+        assert_eq!(boot_sector.bpb().cluster_size().unwrap(), 2097152);
+
+        // 2^-12 = 4096 sectors per cluster are representable, even if Windows doesn't create such clusters.
+        let boot_sector = boot_sector_with(4096, (-12i8) as u8);
+        assert_eq!(boot_sector.bpb().cluster_size().unwrap(), 16777216);
+
+        // Larger exponents are rejected.
+        let boot_sector = boot_sector_with(512, (-13i8) as u8);
+        assert!(matches!(
+            boot_sector.bpb().cluster_size(),
+            Err(NtfsError::InvalidSectorsPerCluster { .. })
+        ));
+
+        // Sector sizes need to be powers of two.
+        let boot_sector = boot_sector_with(3072, 1);
+        assert!(matches!(
+            boot_sector.bpb().sector_size(),
+            Err(NtfsError::UnsupportedSectorSize { .. })
+        ));
+    }
+
+    #[test]
+    fn test_backup_boot_sector() {
+        let mut testfs1 = crate::helpers::tests::testfs1();
+        let ntfs = Ntfs::new(&mut testfs1).unwrap();
+        let boot_sector = *ntfs.boot_sector().unwrap();
+        assert!(!boot_sector.is_backup());
+        assert_eq!(boot_sector.sector_size(), 512);
+        assert_eq!(boot_sector.cluster_size(), 512);
+        assert_eq!(boot_sector.total_sectors(), 4095);
+        assert_eq!(boot_sector.size(), 4095 * 512);
+        assert_eq!(boot_sector.file_record_size(), 1024);
+        assert_eq!(boot_sector.index_record_size().unwrap(), 4096);
+        assert!(boot_sector.mft_lcn().value() > 0);
+        assert!(boot_sector.mft_mirror_lcn().value() > 0);
+        assert_eq!(boot_sector.serial_number(), ntfs.serial_number());
+
+        // Break the signature of the primary boot sector.
+        // The signature occupies the last 2 bytes of the 512-byte boot sector structure.
+        let signature_position = 510;
+        testfs1.get_mut()[signature_position] = 0;
+
+        let ntfs = Ntfs::new(&mut testfs1).unwrap();
+        let backup_boot_sector = *ntfs.boot_sector().unwrap();
+        assert!(backup_boot_sector.is_backup());
+        assert_eq!(backup_boot_sector.position(), 4095 * 512);
+        assert_eq!(
+            backup_boot_sector.total_sectors(),
+            boot_sector.total_sectors()
+        );
+        assert_eq!(backup_boot_sector.mft_lcn(), boot_sector.mft_lcn());
+        ntfs.root_directory(&mut testfs1).unwrap();
+
+        // If the backup boot sector is broken as well, the error of the primary one is returned.
+        let len = testfs1.get_ref().len();
+        testfs1.get_mut()[len - 512 + signature_position] = 0;
+        assert!(matches!(
+            Ntfs::new(&mut testfs1),
+            Err(NtfsError::InvalidTwoByteSignature { .. })
+        ));
+    }
+}
@@ -0,0 +1,549 @@
+// Copyright 2021-2023 Colin Finck <colin@reactos.org>
+// SPDX-License-Identifier: MIT OR Apache-2.0
+//
+//! Structural consistency checks of an NTFS volume, comparable to an offline `chkdsk` without repairs.
+//!
+//! Start with [`Ntfs::check`] to get an [`NtfsCheckReport`].
+//!
+//! [`Ntfs::check`]: crate::Ntfs::check
+
+use alloc::vec;
+use alloc::vec::Vec;
+use binrw::io::{Read, Seek};
+use displaydoc::Display;
+
+use crate::attribute::{NtfsAttribute, NtfsAttributeFlags, NtfsAttributeType};
+use crate::error::{NtfsError, Result};
+use crate::file::{KnownNtfsFileRecordNumber, NtfsFile, NtfsFileFlags};
+use crate::ntfs::Ntfs;
+use crate::progress::{report_progress, NtfsProgressSink, NtfsProgressUnit};
+use crate::traits::NtfsReadSeek;
+use crate::types::{Lcn, NtfsPosition};
+
+/// A single inconsistency found by [`Ntfs::check`].
+///
+/// [`Ntfs::check`]: crate::Ntfs::check
+#[derive(Debug, Display)]
+#[non_exhaustive]
+pub enum NtfsCheckFinding {
+    /// The Data Run at byte position {position:#x} of File Record {file_record_number} covers {cluster_count} clusters from LCN {lcn:#x}, but not all of them are allocated in $Bitmap
+    DataRunNotAllocated {
+        file_record_number: u64,
+        position: NtfsPosition,
+        lcn: Lcn,
+        cluster_count: u64,
+    },
+    /// The Data Run at byte position {position:#x} of File Record {file_record_number} covers {cluster_count} clusters from LCN {lcn:#x}, which exceeds the {total_clusters} clusters of the volume
+    DataRunOutsideVolume {
+        file_record_number: u64,
+        position: NtfsPosition,
+        lcn: Lcn,
+        cluster_count: u64,
+        total_clusters: u64,
+    },
+    /// The Data Runs of the NTFS Attribute at byte position {position:#x} of File Record {file_record_number} cover {actual} bytes, but the attribute header indicates {expected} bytes
+    DataRunsSizeMismatch {
+        file_record_number: u64,
+        position: NtfsPosition,
+        expected: u64,
+        actual: u64,
+    },
+    /// File Record {file_record_number} is flagged as in use, but marked as free in the MFT bitmap
+    FreeInMftBitmap { file_record_number: u64 },
+    /// The directory index of File Record {directory_file_record_number} has an entry for File Record {file_record_number}, which is not in use
+    IndexEntryNotInUse {
+        directory_file_record_number: u64,
+        file_record_number: u64,
+    },
+    /// The directory index of File Record {directory_file_record_number} references File Record {file_record_number} with sequence number {expected}, but the File Record has sequence number {actual}
+    IndexEntryStaleFileReference {
+        directory_file_record_number: u64,
+        file_record_number: u64,
+        expected: u16,
+        actual: u16,
+    },
+    /// The NTFS Attribute at byte position {position:#x} of File Record {file_record_number} has inconsistent sizes (allocated size {allocated_size}, data size {data_size}, initialized size {initialized_size})
+    InconsistentAttributeSizes {
+        file_record_number: u64,
+        position: NtfsPosition,
+        allocated_size: u64,
+        data_size: u64,
+        initialized_size: u64,
+    },
+    /// An NTFS Attribute of File Record {file_record_number} cannot be read: {error}
+    UnreadableAttribute {
+        file_record_number: u64,
+        error: NtfsError,
+    },
+    /// File Record {file_record_number} is in use, but cannot be read: {error}
+    UnreadableFileRecord {
+        file_record_number: u64,
+        error: NtfsError,
+    },
+    /// The directory index of File Record {file_record_number} cannot be read: {error}
+    UnreadableIndex {
+        file_record_number: u64,
+        error: NtfsError,
+    },
+    /// File Record {file_record_number} is flagged as unused, but marked as in use in the MFT bitmap
+    UnusedInMftBitmap { file_record_number: u64 },
+}
+
+/// Result of running [`Ntfs::check`] over an NTFS volume.
+///
+/// Inconsistencies don't stop the check, but are collected as [`NtfsCheckFinding`]s.
+/// Only errors that prevent any further checking (like an unreadable $MFT or $Bitmap) are returned
+/// as an [`NtfsError`] by [`Ntfs::check`].
+///
+/// [`Ntfs::check`]: crate::Ntfs::check
+#[derive(Debug)]
+pub struct NtfsCheckReport {
+    findings: Vec<NtfsCheckFinding>,
+    file_records_checked: u64,
+    index_entries_checked: u64,
+}
+
+impl NtfsCheckReport {
+    pub(crate) fn new<T, P>(ntfs: &Ntfs, fs: &mut T, progress: &mut P) -> Result<Self>
+    where
+        T: Read + Seek,
+        P: NtfsProgressSink + ?Sized,
+    {
+        let mut checker = Checker::new(ntfs, fs)?;
+        let directories = checker.check_file_records(fs, progress)?;
+
+        for directory_file_record_number in directories {
+            checker.check_directory(fs, directory_file_record_number, progress)?;
+        }
+
+        Ok(checker.report)
+    }
+
+    /// Returns the number of File Records in use that have been checked.
+    pub fn file_records_checked(&self) -> u64 {
+        self.file_records_checked
+    }
+
+    /// Returns all inconsistencies found, in the order they were encountered.
+    pub fn findings(&self) -> &[NtfsCheckFinding] {
+        &self.findings
+    }
+
+    /// Returns the number of directory Index Entries that have been checked.
+    pub fn index_entries_checked(&self) -> u64 {
+        self.index_entries_checked
+    }
+
+    /// Returns whether no inconsistencies have been found.
+    pub fn is_consistent(&self) -> bool {
+        self.findings.is_empty()
+    }
+}
+
+struct Checker<'n> {
+    ntfs: &'n Ntfs,
+    report: NtfsCheckReport,
+    /// Contents of the $DATA attribute of the $Bitmap file, with a bit set for every allocated cluster.
+    volume_bitmap: Vec<u8>,
+    /// Contents of the $BITMAP attribute of the $MFT file, with a bit set for every File Record in use.
+    mft_bitmap: Vec<u8>,
+    /// Sequence numbers of all base File Records in use, indexed by File Record Number.
+    sequence_numbers: Vec<Option<u16>>,
+}
+
+impl<'n> Checker<'n> {
+    fn new<T>(ntfs: &'n Ntfs, fs: &mut T) -> Result<Self>
+    where
+        T: Read + Seek,
+    {
+        let bitmap_file = ntfs.file(fs, KnownNtfsFileRecordNumber::Bitmap as u64)?;
+        let data_item = bitmap_file
+            .data(fs, "")
+            .ok_or(NtfsError::AttributeNotFound {
+                position: bitmap_file.position(),
+                ty: NtfsAttributeType::Data,
+            })??;
+        let volume_bitmap = read_attribute_value(&data_item.to_attribute()?, fs)?;
+
+        let mft_file = ntfs.file(fs, KnownNtfsFileRecordNumber::MFT as u64)?;
+        let mft_bitmap = read_attribute_value(&find_mft_bitmap(&mft_file)?, fs)?;
+
+        let report = NtfsCheckReport {
+            findings: Vec::new(),
+            file_records_checked: 0,
+            index_entries_checked: 0,
+        };
+
+        Ok(Self {
+            ntfs,
+            report,
+            volume_bitmap,
+            mft_bitmap,
+            sequence_numbers: Vec::new(),
+        })
+    }
+
+    fn check_attribute(&mut self, file_record_number: u64, attribute: &NtfsAttribute) {
+        if attribute.is_resident() {
+            if let Err(error) = attribute.resident_value_range() {
+                self.add(NtfsCheckFinding::UnreadableAttribute {
+                    file_record_number,
+                    error,
+                });
+            }
+
+            return;
+        }
+
+        let cluster_size = self.ntfs.cluster_size() as u64;
+
+        // Only the first attribute of connected attributes has valid sizes.
+        if attribute.non_resident_value_lowest_vcn().value() == 0 {
+            let allocated_size = attribute.non_resident_value_allocated_size();
+            let data_size = attribute.non_resident_value_data_size();
+            let initialized_size = attribute.non_resident_value_initialized_size();
+
+            // Compressed and sparse values may be larger than their allocated size.
+            let may_exceed_allocation = attribute
+                .flags()
+                .intersects(NtfsAttributeFlags::COMPRESSED | NtfsAttributeFlags::SPARSE);
+
+            if allocated_size % cluster_size != 0
+                || initialized_size > data_size
+                || (data_size > allocated_size && !may_exceed_allocation)
+            {
+                self.add(NtfsCheckFinding::InconsistentAttributeSizes {
+                    file_record_number,
+                    position: attribute.position(),
+                    allocated_size,
+                    data_size,
+                    initialized_size,
+                });
+            }
+        }
+
+        let value = match attribute.non_resident_value() {
+            Ok(value) => value,
+            Err(error) => {
+                self.add(NtfsCheckFinding::UnreadableAttribute {
+                    file_record_number,
+                    error,
+                });
+                return;
+            }
+        };
+
+        let total_clusters = self.ntfs.size() / cluster_size;
+        let mut data_runs = value.data_runs();
+        let mut data_runs_size = 0u64;
+
+        loop {
+            let position = data_runs.position();
+            let data_run = match data_runs.next() {
+                Some(Ok(data_run)) => data_run,
+                Some(Err(error)) => {
+                    self.add(NtfsCheckFinding::UnreadableAttribute {
+                        file_record_number,
+                        error,
+                    });
+                    return;
+                }
+                None => break,
+            };
+
+            data_runs_size = data_runs_size.saturating_add(data_run.allocated_size());
+
+            // Sparse Data Runs don't occupy any clusters.
+            let data_position = match data_run.data_position().value() {
+                Some(data_position) => data_position.get(),
+                None => continue,
+            };
+            let lcn = data_position / cluster_size;
+            let cluster_count = data_run.allocated_size() / cluster_size;
+
+            if lcn.saturating_add(cluster_count) > total_clusters {
+                self.add(NtfsCheckFinding::DataRunOutsideVolume {
+                    file_record_number,
+                    position,
+                    lcn: Lcn::from(lcn),
+                    cluster_count,
+                    total_clusters,
+                });
+            } else if !(lcn..lcn + cluster_count).all(|lcn| bit_is_set(&self.volume_bitmap, lcn)) {
+                self.add(NtfsCheckFinding::DataRunNotAllocated {
+                    file_record_number,
+                    position,
+                    lcn: Lcn::from(lcn),
+                    cluster_count,
+                });
+            }
+        }
+
+        let expected = attribute.non_resident_value_fragment_allocated_size();
+        if data_runs_size != expected {
+            self.add(NtfsCheckFinding::DataRunsSizeMismatch {
+                file_record_number,
+                position: attribute.position(),
+                expected,
+                actual: data_runs_size,
+            });
+        }
+    }
+
+    fn check_directory<T, P>(
+        &mut self,
+        fs: &mut T,
+        directory_file_record_number: u64,
+        progress: &mut P,
+    ) -> Result<()>
+    where
+        T: Read + Seek,
+        P: NtfsProgressSink + ?Sized,
+    {
+        let ntfs = self.ntfs;
+        let directory = match ntfs.file(fs, directory_file_record_number) {
+            Ok(directory) => directory,
+            Err(error) => {
+                self.add(NtfsCheckFinding::UnreadableFileRecord {
+                    file_record_number: directory_file_record_number,
+                    error,
+                });
+                return Ok(());
+            }
+        };
+
+        let index = match directory.directory_index(fs) {
+            Ok(index) => index,
+            Err(error) => {
+                self.add(NtfsCheckFinding::UnreadableIndex {
+                    file_record_number: directory_file_record_number,
+                    error,
+                });
+                return Ok(());
+            }
+        };
+
+        let mut entries = index.entries();
+
+        while let Some(entry) = entries.next(fs) {
+            let entry = match entry {
+                Ok(entry) => entry,
+                Err(error) => {
+                    self.add(NtfsCheckFinding::UnreadableIndex {
+                        file_record_number: directory_file_record_number,
+                        error,
+                    });
+                    return Ok(());
+                }
+            };
+
+            // The last entry of every node has no key and references no file.
+            if entry.key().is_none() {
+                continue;
+            }
+
+            report_progress(
+                progress,
+                NtfsProgressUnit::IndexEntries,
+                self.report.index_entries_checked,
+                None,
+            )?;
+            self.report.index_entries_checked += 1;
+
+            let file_reference = entry.file_reference();
+            let file_record_number = file_reference.file_record_number();
+            let sequence_number = self
+                .sequence_numbers
+                .get(file_record_number as usize)
+                .copied()
+                .flatten();
+
+            match sequence_number {
+                None => self.add(NtfsCheckFinding::IndexEntryNotInUse {
+                    directory_file_record_number,
+                    file_record_number,
+                }),
+                // A sequence number of zero in the reference is not checked (see `NtfsFileReference::to_file`).
+                Some(actual)
+                    if file_reference.sequence_number() != 0
+                        && file_reference.sequence_number() != actual =>
+                {
+                    self.add(NtfsCheckFinding::IndexEntryStaleFileReference {
+                        directory_file_record_number,
+                        file_record_number,
+                        expected: file_reference.sequence_number(),
+                        actual,
+                    })
+                }
+                Some(_) => (),
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Checks all File Records in use and returns the File Record Numbers of all directories.
+    fn check_file_records<T, P>(&mut self, fs: &mut T, progress: &mut P) -> Result<Vec<u64>>
+    where
+        T: Read + Seek,
+        P: NtfsProgressSink + ?Sized,
+    {
+        let ntfs = self.ntfs;
+        let file_record_count = ntfs.file_record_count(fs)?;
+        let mut directories = Vec::new();
+        self.sequence_numbers = vec![None; file_record_count as usize];
+
+        for file_record_number in 0..file_record_count {
+            report_progress(
+                progress,
+                NtfsProgressUnit::FileRecords,
+                file_record_number,
+                Some(file_record_count),
+            )?;
+
+            let in_mft_bitmap = bit_is_set(&self.mft_bitmap, file_record_number);
+
+            let file = match ntfs.file(fs, file_record_number) {
+                Ok(file) => file,
+                // Unused File Records may never have been initialized.
+                Err(NtfsError::InvalidFileSignature { .. }) if !in_mft_bitmap => continue,
+                Err(error) => {
+                    self.add(NtfsCheckFinding::UnreadableFileRecord {
+                        file_record_number,
+                        error,
+                    });
+                    continue;
+                }
+            };
+
+            let in_use = file.flags().contains(NtfsFileFlags::IN_USE);
+            if in_use && !in_mft_bitmap {
+                self.add(NtfsCheckFinding::FreeInMftBitmap { file_record_number });
+            } else if !in_use && in_mft_bitmap {
+                self.add(NtfsCheckFinding::UnusedInMftBitmap { file_record_number });
+            }
+
+            if !in_use {
+                continue;
+            }
+
+            self.report.file_records_checked += 1;
+
+            // Extension File Records are checked for their attributes, but can't be referenced by directories.
+            if file.base_file_record().file_record_number() == 0 {
+                self.sequence_numbers[file_record_number as usize] = Some(file.sequence_number());
+
+                if file.is_directory() {
+                    directories.push(file_record_number);
+                }
+            }
+
+            for attribute in file.attributes_raw() {
+                match attribute {
+                    Ok(attribute) => self.check_attribute(file_record_number, &attribute),
+                    Err(error) => {
+                        // The attributes behind a broken one can't be found.
+                        self.add(NtfsCheckFinding::UnreadableAttribute {
+                            file_record_number,
+                            error,
+                        });
+                        break;
+                    }
+                }
+            }
+        }
+
+        Ok(directories)
+    }
+
+    fn add(&mut self, finding: NtfsCheckFinding) {
+        self.report.findings.push(finding);
+    }
+}
+
+fn bit_is_set(bitmap: &[u8], index: u64) -> bool {
+    match bitmap.get((index / 8) as usize) {
+        Some(byte) => byte & (1 << (index % 8)) != 0,
+        None => false,
+    }
+}
+
+fn find_mft_bitmap<'n, 'f>(mft_file: &'f NtfsFile<'n>) -> Result<NtfsAttribute<'n, 'f>> {
+    for attribute in mft_file.attributes_raw() {
+        let attribute = attribute?;
+
+        if attribute.ty()? == NtfsAttributeType::Bitmap && attribute.name_length() == 0 {
+            return Ok(attribute);
+        }
+    }
+
+    Err(NtfsError::AttributeNotFound {
+        position: mft_file.position(),
+        ty: NtfsAttributeType::Bitmap,
+    })
+}
+
+fn read_attribute_value<T>(attribute: &NtfsAttribute, fs: &mut T) -> Result<Vec<u8>>
+where
+    T: Read + Seek,
+{
+    let mut value = attribute.value(fs)?;
+    let mut data = vec![0u8; value.len() as usize];
+    value.read_exact(fs, &mut data)?;
+    Ok(data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::indexes::NtfsFileNameIndex;
+
+    #[test]
+    fn test_check() {
+        let mut testfs1 = crate::helpers::tests::testfs1();
+        let mut ntfs = Ntfs::new(&mut testfs1).unwrap();
+        ntfs.read_upcase_table(&mut testfs1).unwrap();
+
+        let report = ntfs.check(&mut testfs1).unwrap();
+        assert!(report.is_consistent(), "{:?}", report.findings());
+        assert_eq!(report.file_records_checked(), 536);
+        assert_eq!(report.index_entries_checked(), 532);
+
+        // Mark the clusters of "1000-bytes-file" as free in $Bitmap.
+        let root_dir = ntfs.root_directory(&mut testfs1).unwrap();
+        let root_dir_index = root_dir.directory_index(&mut testfs1).unwrap();
+        let mut root_dir_finder = root_dir_index.finder();
+        let entry =
+            NtfsFileNameIndex::find(&mut root_dir_finder, &ntfs, &mut testfs1, "1000-bytes-file")
+                .unwrap()
+                .unwrap();
+        let file = entry.to_file(&ntfs, &mut testfs1).unwrap();
+        let data_item = file.data(&mut testfs1, "").unwrap().unwrap();
+        let data_attribute = data_item.to_attribute().unwrap();
+        let data_value = data_attribute.non_resident_value().unwrap();
+        let data_run = data_value.data_runs().next().unwrap().unwrap();
+        let lcn = data_run.data_position().value().unwrap().get() / ntfs.cluster_size() as u64;
+
+        let bitmap_file = ntfs
+            .file(&mut testfs1, KnownNtfsFileRecordNumber::Bitmap as u64)
+            .unwrap();
+        let bitmap_item = bitmap_file.data(&mut testfs1, "").unwrap().unwrap();
+        let bitmap_attribute = bitmap_item.to_attribute().unwrap();
+        let mut bitmap_value = bitmap_attribute.value(&mut testfs1).unwrap();
+        bitmap_value
+            .seek(&mut testfs1, binrw::io::SeekFrom::Start(lcn / 8))
+            .unwrap();
+        let byte_position = bitmap_value.data_position().value().unwrap().get() as usize;
+        testfs1.get_mut()[byte_position] &= !(1 << (lcn % 8));
+
+        let report = ntfs.check(&mut testfs1).unwrap();
+        assert!(matches!(
+            report.findings(),
+            [NtfsCheckFinding::DataRunNotAllocated {
+                file_record_number,
+                lcn: finding_lcn,
+                ..
+            }] if *file_record_number == file.file_record_number() && finding_lcn.value() == lcn
+        ));
+    }
+}
@@ -0,0 +1,493 @@
+// Copyright 2021-2023 Colin Finck <colin@reactos.org>
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+use core::mem;
+
+use alloc::vec;
+use alloc::vec::Vec;
+use binrw::io::{Read, Seek, Write};
+use byteorder::{ByteOrder, LittleEndian};
+use nt_string::u16strle::U16StrLe;
+
+use crate::attribute::{NtfsAttribute, NtfsAttributeType};
+use crate::attribute_value::NtfsDataRuns;
+use crate::error::{NtfsError, Result};
+use crate::file::NtfsFileFlags;
+use crate::ntfs::Ntfs;
+use crate::progress::{report_progress, NtfsProgressSink, NtfsProgressUnit};
+use crate::types::{Lcn, NtfsPosition, Vcn};
+
+/// Signature of a cluster owner index written by [`NtfsClusterOwnerIndex::write_to`].
+const INDEX_SIGNATURE: &[u8] = b"LCNO";
+
+/// The file, attribute, and cluster within the attribute value that occupy a cluster, as returned by
+/// [`Ntfs::find_owner_of_lcn`] and [`NtfsClusterOwnerIndex::find`].
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct NtfsClusterOwner {
+    file_record_number: u64,
+    ty: NtfsAttributeType,
+    name: Vec<u8>,
+    vcn: Vcn,
+}
+
+impl NtfsClusterOwner {
+    /// Returns the File Record Number of the file owning the cluster.
+    ///
+    /// If the attribute is stored in an extension File Record, this is the number of the base File Record.
+    pub fn file_record_number(&self) -> u64 {
+        self.file_record_number
+    }
+
+    /// Gets the name of the owning attribute (the stream name for $DATA attributes) and returns it
+    /// wrapped in a [`U16StrLe`].
+    ///
+    /// This is empty for unnamed attributes (like the main data stream of a file).
+    pub fn name(&self) -> U16StrLe<'_> {
+        U16StrLe(&self.name)
+    }
+
+    /// Returns the type of the owning attribute.
+    pub fn ty(&self) -> NtfsAttributeType {
+        self.ty
+    }
+
+    /// Returns the Virtual Cluster Number (VCN) of the cluster within the owning attribute value.
+    pub fn vcn(&self) -> Vcn {
+        self.vcn
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+struct OwnerAttribute {
+    file_record_number: u64,
+    ty: NtfsAttributeType,
+    name: Vec<u8>,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+struct OwnedRun {
+    lcn: u64,
+    cluster_count: u64,
+    vcn: i64,
+    attribute: usize,
+}
+
+/// Reverse mapping from Logical Cluster Numbers (LCNs) to the files owning them.
+///
+/// Building this index requires reading the entire Master File Table, but every lookup afterwards
+/// is only a binary search in memory.
+/// Keep the index around if you need to look up multiple clusters, and use
+/// [`NtfsClusterOwnerIndex::write_to`] and [`NtfsClusterOwnerIndex::read_from`] to persist it
+/// between runs.
+///
+/// Build it via [`Ntfs::cluster_owner_index`].
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct NtfsClusterOwnerIndex {
+    attributes: Vec<OwnerAttribute>,
+    runs: Vec<OwnedRun>,
+}
+
+impl NtfsClusterOwnerIndex {
+    pub(crate) fn new<T, P>(ntfs: &Ntfs, fs: &mut T, progress: &mut P) -> Result<Self>
+    where
+        T: Read + Seek,
+        P: NtfsProgressSink + ?Sized,
+    {
+        let file_record_count = ntfs.file_record_count(fs)?;
+        let mut index = Self {
+            attributes: Vec::new(),
+            runs: Vec::new(),
+        };
+
+        for file_record_number in 0..file_record_count {
+            report_progress(
+                progress,
+                NtfsProgressUnit::FileRecords,
+                file_record_number,
+                Some(file_record_count),
+            )?;
+
+            // File Records that cannot be parsed (like unused ones that have never been initialized)
+            // don't own any clusters we could know about.
+            let file = match ntfs.file(fs, file_record_number) {
+                Ok(file) => file,
+                Err(NtfsError::Io(e)) => return Err(NtfsError::Io(e)),
+                Err(_) => continue,
+            };
+
+            if !file.flags().contains(NtfsFileFlags::IN_USE) {
+                continue;
+            }
+
+            let base_file_record_number = match file.base_file_record().file_record_number() {
+                0 => file_record_number,
+                base_file_record_number => base_file_record_number,
+            };
+
+            // The attributes behind a broken one can't be found.
+            for attribute in file.attributes_raw().map_while(|attribute| attribute.ok()) {
+                index.add_attribute(ntfs, base_file_record_number, &attribute);
+            }
+        }
+
+        index.runs.sort_unstable_by_key(|run| run.lcn);
+        index.remove_overlaps();
+        Ok(index)
+    }
+
+    fn add_attribute(&mut self, ntfs: &Ntfs, file_record_number: u64, attribute: &NtfsAttribute) {
+        if attribute.is_resident() {
+            return;
+        }
+
+        let (ty, name) = match (attribute.ty(), attribute.name()) {
+            (Ok(ty), Ok(name)) => (ty, name.0.to_vec()),
+            _ => return,
+        };
+
+        let (data, position) = match attribute.non_resident_value_data_and_position() {
+            Ok(data_and_position) => data_and_position,
+            Err(_) => return,
+        };
+
+        let cluster_size = ntfs.cluster_size() as u64;
+        let attribute_index = self.attributes.len();
+        let mut vcn = attribute.non_resident_value_lowest_vcn().value();
+
+        for data_run in NtfsDataRuns::new(ntfs, data, position).map_while(|run| run.ok()) {
+            let cluster_count = data_run.allocated_size() / cluster_size;
+
+            if let Some(data_position) = data_run.data_position().value() {
+                self.runs.push(OwnedRun {
+                    lcn: data_position.get() / cluster_size,
+                    cluster_count,
+                    vcn,
+                    attribute: attribute_index,
+                });
+            }
+
+            vcn = vcn.saturating_add(cluster_count as i64);
+        }
+
+        if self.runs.last().map(|run| run.attribute) == Some(attribute_index) {
+            self.attributes.push(OwnerAttribute {
+                file_record_number,
+                ty,
+                name,
+            });
+        }
+    }
+
+    /// Cuts all clusters from the sorted runs that are already owned by a previous run,
+    /// so that every cluster has a single owner for [`NtfsClusterOwnerIndex::find`].
+    fn remove_overlaps(&mut self) {
+        let mut end = 0u64;
+
+        for mut run in mem::take(&mut self.runs) {
+            let run_end = run.lcn.saturating_add(run.cluster_count);
+            let overlap = end.saturating_sub(run.lcn).min(run.cluster_count);
+
+            run.lcn += overlap;
+            run.cluster_count -= overlap;
+            run.vcn = run.vcn.saturating_add(overlap as i64);
+            end = end.max(run_end);
+
+            if run.cluster_count > 0 {
+                self.runs.push(run);
+            }
+        }
+    }
+
+    /// Returns the [`NtfsClusterOwner`] of the given cluster, or `None` if no file owns it.
+    ///
+    /// Clusters may be owned by multiple files on a corrupted filesystem, in which case the index only
+    /// keeps one of the owners.
+    pub fn find(&self, lcn: Lcn) -> Option<NtfsClusterOwner> {
+        let lcn = lcn.value();
+
+        // Find the last run starting at or before the given LCN.
+        let run_index = self
+            .runs
+            .partition_point(|run| run.lcn <= lcn)
+            .checked_sub(1)?;
+        let run = self.runs[run_index];
+        let offset = lcn - run.lcn;
+        if offset >= run.cluster_count {
+            return None;
+        }
+
+        let attribute = &self.attributes[run.attribute];
+
+        Some(NtfsClusterOwner {
+            file_record_number: attribute.file_record_number,
+            ty: attribute.ty,
+            name: attribute.name.clone(),
+            vcn: Vcn::from(run.vcn.saturating_add(offset as i64)),
+        })
+    }
+
+    /// Returns the LCN of the cluster at `vcn` of the unnamed attribute of type `ty` of the given file,
+    /// or `None` if that cluster is not part of this index.
+    pub(crate) fn find_lcn(
+        &self,
+        file_record_number: u64,
+        ty: NtfsAttributeType,
+        vcn: Vcn,
+    ) -> Option<Lcn> {
+        self.runs.iter().find_map(|run| {
+            let attribute = &self.attributes[run.attribute];
+            let offset = vcn.value().checked_sub(run.vcn)?;
+
+            if attribute.file_record_number == file_record_number
+                && attribute.ty == ty
+                && attribute.name.is_empty()
+                && offset >= 0
+                && (offset as u64) < run.cluster_count
+            {
+                Some(Lcn::from(run.lcn.saturating_add(offset as u64)))
+            } else {
+                None
+            }
+        })
+    }
+
+    /// Returns `true` if no cluster is owned by any file.
+    pub fn is_empty(&self) -> bool {
+        self.runs.is_empty()
+    }
+
+    /// Returns the number of continuous cluster ranges in this index.
+    pub fn len(&self) -> usize {
+        self.runs.len()
+    }
+
+    /// Reads an index previously written by [`NtfsClusterOwnerIndex::write_to`].
+    ///
+    /// The runs are checked to be sorted, non-overlapping, and within the range of cluster numbers,
+    /// otherwise [`NtfsError::InvalidClusterOwnerIndex`] is returned.
+    /// Note that the index is not validated against the filesystem.
+    /// It is up to the caller to only use it for the same, unmodified volume.
+    pub fn read_from<R>(r: &mut R) -> Result<Self>
+    where
+        R: Read,
+    {
+        let mut signature = [0u8; 4];
+        r.read_exact(&mut signature)?;
+        if signature != INDEX_SIGNATURE {
+            return Err(NtfsError::InvalidClusterOwnerIndex);
+        }
+
+        let mut buf = [0u8; 8];
+        r.read_exact(&mut buf)?;
+        let attribute_count = LittleEndian::read_u64(&buf);
+        let mut attributes = Vec::new();
+
+        for _ in 0..attribute_count {
+            let mut header = [0u8; 14];
+            r.read_exact(&mut header)?;
+
+            let file_record_number = LittleEndian::read_u64(&header[0..]);
+            let raw_ty = LittleEndian::read_u32(&header[8..]);
+            let ty = NtfsAttributeType::n(raw_ty).ok_or(NtfsError::UnsupportedAttributeType {
+                position: NtfsPosition::none(),
+                actual: raw_ty,
+            })?;
+
+            let mut name = vec![0u8; LittleEndian::read_u16(&header[12..]) as usize];
+            r.read_exact(&mut name)?;
+
+            attributes.push(OwnerAttribute {
+                file_record_number,
+                ty,
+                name,
+            });
+        }
+
+        r.read_exact(&mut buf)?;
+        let run_count = LittleEndian::read_u64(&buf);
+        let mut runs = Vec::new();
+        let mut end = 0u64;
+
+        for _ in 0..run_count {
+            let mut run = [0u8; 32];
+            r.read_exact(&mut run)?;
+
+            let lcn = LittleEndian::read_u64(&run[0..]);
+            let cluster_count = LittleEndian::read_u64(&run[8..]);
+            let vcn = LittleEndian::read_i64(&run[16..]);
+            let attribute = LittleEndian::read_u64(&run[24..]) as usize;
+            if attribute >= attributes.len() {
+                return Err(NtfsError::InvalidClusterOwnerIndex);
+            }
+
+            // `NtfsClusterOwnerIndex::find` relies on a binary search over sorted and disjoint runs.
+            if lcn < end {
+                return Err(NtfsError::InvalidClusterOwnerIndex);
+            }
+
+            end = lcn
+                .checked_add(cluster_count)
+                .ok_or(NtfsError::InvalidClusterOwnerIndex)?;
+            i64::try_from(cluster_count)
+                .ok()
+                .and_then(|cluster_count| vcn.checked_add(cluster_count))
+                .ok_or(NtfsError::InvalidClusterOwnerIndex)?;
+
+            runs.push(OwnedRun {
+                lcn,
+                cluster_count,
+                vcn,
+                attribute,
+            });
+        }
+
+        Ok(Self { attributes, runs })
+    }
+
+    /// Writes this index to `w` in a compact binary format, to be read back by
+    /// [`NtfsClusterOwnerIndex::read_from`].
+    pub fn write_to<W>(&self, w: &mut W) -> Result<()>
+    where
+        W: Write,
+    {
+        w.write_all(INDEX_SIGNATURE)?;
+        w.write_all(&(self.attributes.len() as u64).to_le_bytes())?;
+
+        for attribute in &self.attributes {
+            w.write_all(&attribute.file_record_number.to_le_bytes())?;
+            w.write_all(&(attribute.ty as u32).to_le_bytes())?;
+            w.write_all(&(attribute.name.len() as u16).to_le_bytes())?;
+            w.write_all(&attribute.name)?;
+        }
+
+        w.write_all(&(self.runs.len() as u64).to_le_bytes())?;
+
+        for run in &self.runs {
+            w.write_all(&run.lcn.to_le_bytes())?;
+            w.write_all(&run.cluster_count.to_le_bytes())?;
+            w.write_all(&run.vcn.to_le_bytes())?;
+            w.write_all(&(run.attribute as u64).to_le_bytes())?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::file::KnownNtfsFileRecordNumber;
+    use crate::progress::NtfsNoProgress;
+    use crate::upcase_table::NtfsCaseSensitivity;
+
+    #[test]
+    fn test_cluster_owner_index() {
+        let mut testfs1 = crate::helpers::tests::testfs1();
+        let mut ntfs = Ntfs::new(&mut testfs1).unwrap();
+        ntfs.read_upcase_table(&mut testfs1).unwrap();
+
+        let file = ntfs
+            .file_by_path(
+                &mut testfs1,
+                "1000-bytes-file",
+                NtfsCaseSensitivity::Insensitive,
+            )
+            .unwrap()
+            .unwrap();
+        let data_item = file.data(&mut testfs1, "").unwrap().unwrap();
+        let data_attribute = data_item.to_attribute().unwrap();
+        let extent_map = data_attribute.extent_map(&mut testfs1).unwrap();
+        let extent = extent_map.extents()[0];
+        let lcn = extent.lcn().unwrap();
+
+        // Both clusters of the file's data are owned by its unnamed $DATA attribute.
+        let index = ntfs.cluster_owner_index(&mut testfs1).unwrap();
+        for i in 0..extent.cluster_count() {
+            let owner = index.find(Lcn::from(lcn.value() + i)).unwrap();
+            assert_eq!(owner.file_record_number(), file.file_record_number());
+            assert_eq!(owner.ty(), NtfsAttributeType::Data);
+            assert_eq!(owner.name(), "");
+            assert_eq!(owner.vcn(), Vcn::from(i as i64));
+        }
+
+        let owner = ntfs.find_owner_of_lcn(&mut testfs1, lcn).unwrap().unwrap();
+        assert_eq!(owner.file_record_number(), file.file_record_number());
+
+        // The first cluster of the MFT is owned by the $MFT file, and the unused end of the volume
+        // by nobody.
+        let mft_lcn = ntfs.mft_position().value().unwrap().get() / ntfs.cluster_size() as u64;
+        let owner = index.find(Lcn::from(mft_lcn)).unwrap();
+        assert_eq!(
+            owner.file_record_number(),
+            KnownNtfsFileRecordNumber::MFT as u64
+        );
+        assert!(index.find(Lcn::from(4000)).is_none());
+
+        // The index survives a round trip through its binary format.
+        let mut data = Vec::new();
+        index.write_to(&mut data).unwrap();
+        let read_index = NtfsClusterOwnerIndex::read_from(&mut data.as_slice()).unwrap();
+        assert_eq!(read_index, index);
+
+        data[0] = b'X';
+        assert!(matches!(
+            NtfsClusterOwnerIndex::read_from(&mut data.as_slice()),
+            Err(NtfsError::InvalidClusterOwnerIndex)
+        ));
+
+        let index = NtfsClusterOwnerIndex::new(&ntfs, &mut testfs1, &mut NtfsNoProgress).unwrap();
+        assert_eq!(index.len(), read_index.len());
+    }
+
+    #[test]
+    fn test_cluster_owner_index_runs() {
+        let run = |lcn, cluster_count, vcn| OwnedRun {
+            lcn,
+            cluster_count,
+            vcn,
+            attribute: 0,
+        };
+        let mut index = NtfsClusterOwnerIndex {
+            attributes: vec![OwnerAttribute {
+                file_record_number: 64,
+                ty: NtfsAttributeType::Data,
+                name: Vec::new(),
+            }],
+            runs: vec![
+                run(10, 10, 0),
+                run(12, 2, 100),
+                run(15, 10, 200),
+                run(30, 5, 300),
+            ],
+        };
+
+        // Clusters owned twice on a corrupted filesystem are only kept for the first owner.
+        index.remove_overlaps();
+        assert_eq!(
+            index.runs,
+            [run(10, 10, 0), run(20, 5, 205), run(30, 5, 300)]
+        );
+        assert_eq!(index.find(Lcn::from(22)).unwrap().vcn(), Vcn::from(207));
+        assert!(index.find(Lcn::from(25)).is_none());
+
+        // Unsorted, overlapping, and overflowing runs are rejected when reading an index.
+        for runs in [
+            vec![run(30, 5, 300), run(10, 10, 0)],
+            vec![run(10, 10, 0), run(19, 1, 100)],
+            vec![run(u64::MAX, 2, 0)],
+            vec![run(10, 10, i64::MAX)],
+        ] {
+            let mut data = Vec::new();
+            NtfsClusterOwnerIndex {
+                attributes: index.attributes.clone(),
+                runs,
+            }
+            .write_to(&mut data)
+            .unwrap();
+            assert!(matches!(
+                NtfsClusterOwnerIndex::read_from(&mut data.as_slice()),
+                Err(NtfsError::InvalidClusterOwnerIndex)
+            ));
+        }
+    }
+}
@@ -0,0 +1,170 @@
+// Copyright 2021-2023 Colin Finck <colin@reactos.org>
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+use core::cmp::Ordering;
+
+use byteorder::{ByteOrder, LittleEndian};
+use enumn::N;
+use nt_string::u16strle::U16StrLe;
+
+use crate::ntfs::Ntfs;
+use crate::upcase_table::UpcaseOrd;
+
+/// Byte offset of the name length within a $FILE_NAME key.
+const FILE_NAME_NAME_LENGTH_OFFSET: usize = 64;
+
+/// Byte offset of the name within a $FILE_NAME key.
+const FILE_NAME_NAME_OFFSET: usize = 66;
+
+/// Sort order of the keys of an NTFS index, as declared by [`NtfsIndexRoot::collation_rule`].
+///
+/// Reference: <https://flatcap.github.io/linux-ntfs/ntfs/attributes/index_root.html>
+///
+/// [`NtfsIndexRoot::collation_rule`]: crate::structured_values::NtfsIndexRoot::collation_rule
+#[derive(Clone, Copy, Debug, Eq, Hash, N, PartialEq)]
+#[repr(u32)]
+pub enum NtfsCollationRule {
+    /// Keys are compared byte by byte.
+    Binary = 0x00,
+    /// Keys are $FILE_NAME structures, whose names are compared case-insensitively first
+    /// and binary second, so that names differing only in case (in case-sensitive directories)
+    /// have a defined order.
+    /// This is used by all directory indexes.
+    FileName = 0x01,
+    /// Keys are UTF-16 strings, which are compared case-insensitively.
+    UnicodeString = 0x02,
+    /// Keys are single little-endian 32-bit values, like in the $SII index of the $Secure file.
+    Ulong = 0x10,
+    /// Keys are Security Identifiers (SIDs), like in the $O index of the $Quota file.
+    Sid = 0x11,
+    /// Keys are the hash of a Security Descriptor followed by its security ID, like in the $SDH index
+    /// of the $Secure file.
+    SecurityHash = 0x12,
+    /// Keys are sequences of little-endian 32-bit values, like in the $O index of the $ObjId file.
+    Ulongs = 0x13,
+}
+
+impl NtfsCollationRule {
+    /// Compares the raw index keys `a` and `b` according to this collation rule.
+    ///
+    /// Keys that are too short for their collation rule are compared as far as possible, with
+    /// a shorter key sorting before a longer one.
+    ///
+    /// # Panics
+    ///
+    /// Panics for [`NtfsCollationRule::FileName`] and [`NtfsCollationRule::UnicodeString`] if
+    /// [`read_upcase_table`][Ntfs::read_upcase_table] had not been called on the passed [`Ntfs`] object.
+    pub fn compare(&self, ntfs: &Ntfs, a: &[u8], b: &[u8]) -> Ordering {
+        match self {
+            Self::Binary => a.cmp(b),
+            Self::FileName => {
+                // Like `NtfsFileNameIndex::find_with_case_sensitivity` with `NtfsCaseSensitivity::Sensitive`.
+                let (a, b) = (U16StrLe(file_name(a)), U16StrLe(file_name(b)));
+                a.upcase_cmp(ntfs, &b)
+                    .then_with(|| a.u16_iter().cmp(b.u16_iter()))
+            }
+            Self::UnicodeString => U16StrLe(a).upcase_cmp(ntfs, &U16StrLe(b)),
+            Self::Ulong => {
+                let a_value = a.get(..4).map(LittleEndian::read_u32);
+                let b_value = b.get(..4).map(LittleEndian::read_u32);
+                a_value.cmp(&b_value)
+            }
+            Self::Sid | Self::SecurityHash | Self::Ulongs => {
+                let a_values = a.chunks(4).map(ulong_from_chunk);
+                let b_values = b.chunks(4).map(ulong_from_chunk);
+                a_values.cmp(b_values)
+            }
+        }
+    }
+}
+
+/// Returns the name bytes of the $FILE_NAME structure `key`.
+fn file_name(key: &[u8]) -> &[u8] {
+    let name_length = key
+        .get(FILE_NAME_NAME_LENGTH_OFFSET)
+        .map_or(0, |name_length| *name_length as usize * 2);
+    let name = key.get(FILE_NAME_NAME_OFFSET..).unwrap_or_default();
+
+    &name[..name_length.min(name.len())]
+}
+
+/// Reads a little-endian 32-bit value from a chunk of up to 4 bytes, padding a shorter chunk
+/// with zeros.
+fn ulong_from_chunk(chunk: &[u8]) -> u32 {
+    let mut bytes = [0u8; 4];
+    bytes[..chunk.len()].copy_from_slice(chunk);
+    u32::from_le_bytes(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec;
+    use alloc::vec::Vec;
+
+    use super::*;
+
+    fn file_name_key(name: &str) -> Vec<u8> {
+        let mut key = vec![0u8; FILE_NAME_NAME_OFFSET];
+        key[FILE_NAME_NAME_LENGTH_OFFSET] = name.encode_utf16().count() as u8;
+        key.extend(name.encode_utf16().flat_map(u16::to_le_bytes));
+        key
+    }
+
+    #[test]
+    fn test_collation_rules() {
+        let mut testfs1 = crate::helpers::tests::testfs1();
+        let mut ntfs = Ntfs::new(&mut testfs1).unwrap();
+        ntfs.read_upcase_table(&mut testfs1).unwrap();
+
+        assert_eq!(
+            NtfsCollationRule::Binary.compare(&ntfs, b"ab", b"b"),
+            Ordering::Less
+        );
+        assert_eq!(
+            NtfsCollationRule::FileName.compare(
+                &ntfs,
+                &file_name_key("FILE"),
+                &file_name_key("file")
+            ),
+            Ordering::Less
+        );
+        assert_eq!(
+            NtfsCollationRule::FileName.compare(
+                &ntfs,
+                &file_name_key("file"),
+                &file_name_key("file")
+            ),
+            Ordering::Equal
+        );
+        assert_eq!(
+            NtfsCollationRule::FileName.compare(&ntfs, &file_name_key("a"), &file_name_key("B")),
+            Ordering::Less
+        );
+
+        // 0x100 is bigger than 0x02, even though its first byte is smaller.
+        let ulong = |value: u32| value.to_le_bytes();
+        assert_eq!(
+            NtfsCollationRule::Ulong.compare(&ntfs, &ulong(0x100), &ulong(0x02)),
+            Ordering::Greater
+        );
+        assert_eq!(
+            NtfsCollationRule::Binary.compare(&ntfs, &ulong(0x100), &ulong(0x02)),
+            Ordering::Less
+        );
+
+        let ulongs = |values: &[u32]| {
+            values
+                .iter()
+                .flat_map(|x| x.to_le_bytes())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            NtfsCollationRule::SecurityHash.compare(&ntfs, &ulongs(&[1, 0x100]), &ulongs(&[1, 2])),
+            Ordering::Greater
+        );
+        assert_eq!(
+            NtfsCollationRule::Ulongs.compare(&ntfs, &ulongs(&[1]), &ulongs(&[1, 0])),
+            Ordering::Less
+        );
+    }
+}
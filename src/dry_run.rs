@@ -0,0 +1,675 @@
+// Copyright 2021-2023 Colin Finck <colin@reactos.org>
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+use alloc::collections::BTreeMap;
+use alloc::vec;
+use alloc::vec::Vec;
+
+use binrw::io;
+use binrw::io::{Read, Seek, SeekFrom, Write};
+
+use crate::attribute::NtfsAttributeType;
+use crate::cluster_owners::NtfsClusterOwnerIndex;
+use crate::error::Result;
+use crate::file::KnownNtfsFileRecordNumber;
+use crate::ntfs::Ntfs;
+use crate::types::{Lcn, Vcn};
+
+/// The on-disk structure affected by an [`NtfsDryRunChange`].
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum NtfsDryRunTarget {
+    /// The boot sector at the start of the volume or its backup copy in the sector behind the end of
+    /// the volume.
+    BootSector {
+        /// Whether this is the backup boot sector.
+        is_backup: bool,
+    },
+    /// A File Record of the Master File Table.
+    FileRecord {
+        /// File Record Number of the changed File Record.
+        file_record_number: u64,
+    },
+    /// Bits of $Bitmap, which tracks the allocation state of all clusters.
+    Bitmap {
+        /// Logical Cluster Number (LCN) of the first cluster whose allocation bit is covered.
+        first_lcn: Lcn,
+        /// Number of clusters whose allocation bits are covered (8 per changed byte).
+        cluster_count: u64,
+    },
+    /// Any other clusters owned by an attribute, like an Index Record of a directory
+    /// (in an $INDEX_ALLOCATION attribute).
+    Attribute {
+        /// File Record Number of the file owning the attribute.
+        file_record_number: u64,
+        /// Type of the attribute.
+        ty: NtfsAttributeType,
+        /// Virtual Cluster Number (VCN) of the first changed cluster within the attribute value.
+        vcn: Vcn,
+    },
+    /// Clusters inside the volume that are not owned by any file.
+    Unowned,
+    /// Bytes behind the end of the volume and its backup boot sector.
+    OutsideVolume,
+}
+
+/// A continuous range of bytes that an operation would write, returned by [`NtfsDryRun::changes`].
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct NtfsDryRunChange {
+    position: u64,
+    length: u64,
+    target: NtfsDryRunTarget,
+    allocation_changed: bool,
+}
+
+impl NtfsDryRunChange {
+    /// Returns whether the simulated writes allocated or freed the cluster of this change in $Bitmap.
+    ///
+    /// The target of every change is determined from the cluster owners before the operation
+    /// (see [`NtfsDryRun::changes`]).
+    /// If this returns `true`, the operation itself changed the ownership of the cluster, so the target
+    /// is outdated: For example, clusters that the operation has newly allocated are reported as
+    /// [`NtfsDryRunTarget::Unowned`].
+    pub fn allocation_changed(&self) -> bool {
+        self.allocation_changed
+    }
+
+    /// Returns the number of changed bytes.
+    pub fn length(&self) -> u64 {
+        self.length
+    }
+
+    /// Returns the absolute byte position of the first changed byte within the filesystem.
+    pub fn position(&self) -> u64 {
+        self.position
+    }
+
+    /// Returns the on-disk structure affected by this change.
+    pub fn target(&self) -> NtfsDryRunTarget {
+        self.target
+    }
+}
+
+/// Simulation session for previewing and testing write operations without touching the filesystem.
+///
+/// All functions of this crate that write to the filesystem take a reader implementing [`Write`].
+/// `NtfsDryRun` wraps a reader (which doesn't need to implement [`Write`] itself) and keeps every write
+/// in memory instead.
+/// Subsequent reads see the written data, so an operation behaves exactly as if the writes had hit
+/// the filesystem, and even a sequence of operations can be simulated:
+///
+/// ```ignore
+/// let owners = ntfs.cluster_owner_index(&mut fs).unwrap();
+///
+/// let mut dry_run = NtfsDryRun::new(&mut fs);
+/// file.write_standard_information(&mut dry_run, &info).unwrap();
+///
+/// for change in dry_run.changes(&ntfs, &owners).unwrap() {
+///     println!("{:?}", change);
+/// }
+/// ```
+///
+/// Use [`NtfsDryRun::changed_ranges`] for the raw data that would have been written.
+/// Writes behind the end of the wrapped reader extend it, with any gap reading as zeros.
+///
+/// Only the writes are kept in memory, but operations taking `&mut Ntfs` (like [`Ntfs::resize`])
+/// also update that [`Ntfs`] object as if the writes had hit the filesystem.
+/// Discard such an object after the simulation and create a new one from the real filesystem.
+/// To actually apply the changes afterwards, run the operation again on the real filesystem.
+#[derive(Clone, Debug)]
+pub struct NtfsDryRun<T> {
+    inner: T,
+    /// Written data, as non-overlapping and non-adjacent ranges keyed by their start position.
+    writes: BTreeMap<u64, Vec<u8>>,
+    stream_position: u64,
+}
+
+impl<T> NtfsDryRun<T>
+where
+    T: Read + Seek,
+{
+    /// Creates a new `NtfsDryRun` without any writes.
+    pub fn new(inner: T) -> Self {
+        Self {
+            inner,
+            writes: BTreeMap::new(),
+            stream_position: 0,
+        }
+    }
+
+    /// Returns the total number of bytes that have been written.
+    ///
+    /// Bytes written multiple times are only counted once.
+    pub fn changed_bytes(&self) -> u64 {
+        self.writes.values().map(|data| data.len() as u64).sum()
+    }
+
+    /// Returns an iterator over all written byte ranges as pairs of their absolute byte position and data,
+    /// in ascending order of their positions.
+    ///
+    /// Overlapping and adjacent writes are merged into a single range.
+    pub fn changed_ranges(&self) -> impl Iterator<Item = (u64, &[u8])> + '_ {
+        self.writes
+            .iter()
+            .map(|(position, data)| (*position, data.as_slice()))
+    }
+
+    /// Classifies all written bytes by the on-disk structure that they affect and returns the changes
+    /// in ascending order of their positions.
+    ///
+    /// `owners` must have been built from the filesystem before the simulated operation
+    /// (see [`Ntfs::cluster_owner_index`]).
+    /// Hence, the targets reflect the cluster ownership before the operation.
+    /// Changes to clusters that the operation has allocated or freed are flagged via
+    /// [`NtfsDryRunChange::allocation_changed`].
+    /// Changes are split at cluster boundaries, and at File Record boundaries for the Master File Table.
+    /// Adjacent parts of the same structure are merged again.
+    pub fn changes(
+        &mut self,
+        ntfs: &Ntfs,
+        owners: &NtfsClusterOwnerIndex,
+    ) -> Result<Vec<NtfsDryRunChange>> {
+        let cluster_size = ntfs.cluster_size() as u64;
+        let file_record_size = ntfs.file_record_size() as u64;
+        let sector_size = ntfs.sector_size() as u64;
+        let mut changes = Vec::<NtfsDryRunChange>::new();
+
+        let ranges = self
+            .writes
+            .iter()
+            .map(|(position, data)| (*position, data.len() as u64))
+            .collect::<Vec<_>>();
+
+        for (position, length) in ranges {
+            let end = position + length;
+            let mut part_position = position;
+
+            while part_position < end {
+                let cluster_end = (part_position / cluster_size + 1) * cluster_size;
+                let mut part_end = end.min(cluster_end);
+
+                let target = if part_position < sector_size {
+                    part_end = end.min(sector_size);
+                    NtfsDryRunTarget::BootSector { is_backup: false }
+                } else if part_position >= ntfs.size() {
+                    let backup_end = ntfs.size() + sector_size;
+                    if part_position < backup_end {
+                        part_end = end.min(backup_end);
+                        NtfsDryRunTarget::BootSector { is_backup: true }
+                    } else {
+                        part_end = end;
+                        NtfsDryRunTarget::OutsideVolume
+                    }
+                } else {
+                    let lcn = Lcn::from(part_position / cluster_size);
+                    match owners.find(lcn) {
+                        Some(owner) => {
+                            // Byte offset of this part within the attribute value.
+                            let offset = owner.vcn().value() as u64 * cluster_size
+                                + part_position % cluster_size;
+
+                            if owner.file_record_number() == KnownNtfsFileRecordNumber::MFT as u64
+                                && owner.ty() == NtfsAttributeType::Data
+                            {
+                                let record_end = (offset / file_record_size + 1) * file_record_size;
+                                part_end = part_end.min(part_position + (record_end - offset));
+                                NtfsDryRunTarget::FileRecord {
+                                    file_record_number: offset / file_record_size,
+                                }
+                            } else if owner.file_record_number()
+                                == KnownNtfsFileRecordNumber::Bitmap as u64
+                                && owner.ty() == NtfsAttributeType::Data
+                            {
+                                NtfsDryRunTarget::Bitmap {
+                                    first_lcn: Lcn::from(offset * 8),
+                                    cluster_count: (part_end - part_position) * 8,
+                                }
+                            } else {
+                                NtfsDryRunTarget::Attribute {
+                                    file_record_number: owner.file_record_number(),
+                                    ty: owner.ty(),
+                                    vcn: owner.vcn(),
+                                }
+                            }
+                        }
+                        None => NtfsDryRunTarget::Unowned,
+                    }
+                };
+
+                let allocation_changed = part_position < ntfs.size()
+                    && self.allocation_changed(
+                        ntfs,
+                        owners,
+                        Lcn::from(part_position / cluster_size),
+                    )?;
+
+                let change = NtfsDryRunChange {
+                    position: part_position,
+                    length: part_end - part_position,
+                    target,
+                    allocation_changed,
+                };
+
+                match changes.last_mut() {
+                    Some(last)
+                        if last.position + last.length == change.position
+                            && last.allocation_changed == change.allocation_changed =>
+                    {
+                        if let Some(target) = merge_targets(last.target, change.target) {
+                            last.length += change.length;
+                            last.target = target;
+                        } else {
+                            changes.push(change);
+                        }
+                    }
+                    _ => changes.push(change),
+                }
+
+                part_position = part_end;
+            }
+        }
+
+        Ok(changes)
+    }
+
+    /// Returns whether the writes have changed the $Bitmap bit of the cluster `lcn`.
+    fn allocation_changed(
+        &mut self,
+        ntfs: &Ntfs,
+        owners: &NtfsClusterOwnerIndex,
+        lcn: Lcn,
+    ) -> io::Result<bool> {
+        let cluster_size = ntfs.cluster_size() as u64;
+        let bitmap_offset = lcn.value() / 8;
+        let bitmap_lcn = match owners.find_lcn(
+            KnownNtfsFileRecordNumber::Bitmap as u64,
+            NtfsAttributeType::Data,
+            Vcn::from((bitmap_offset / cluster_size) as i64),
+        ) {
+            Some(bitmap_lcn) => bitmap_lcn,
+            None => return Ok(false),
+        };
+
+        let position = bitmap_lcn.value() * cluster_size + bitmap_offset % cluster_size;
+        let byte = match self.written_byte(position) {
+            Some(byte) => byte,
+            None => return Ok(false),
+        };
+
+        // Bytes behind the end of the inner reader have never been allocated.
+        let mut buf = [0u8];
+        self.inner.seek(SeekFrom::Start(position))?;
+        let original_byte = if self.inner.read(&mut buf)? == 1 {
+            buf[0]
+        } else {
+            0
+        };
+
+        Ok((byte ^ original_byte) & (1 << (lcn.value() % 8)) != 0)
+    }
+
+    /// Discards all writes.
+    pub fn clear(&mut self) {
+        self.writes.clear();
+    }
+
+    /// Returns a reference to the underlying reader.
+    pub fn get_ref(&self) -> &T {
+        &self.inner
+    }
+
+    /// Returns a mutable reference to the underlying reader.
+    ///
+    /// Any data read through it doesn't contain the writes.
+    pub fn get_mut(&mut self) -> &mut T {
+        &mut self.inner
+    }
+
+    /// Returns the underlying reader and discards all writes.
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+
+    /// Returns `true` if nothing has been written.
+    pub fn is_empty(&self) -> bool {
+        self.writes.is_empty()
+    }
+
+    /// Returns the byte written at the given absolute position, or `None` if it hasn't been written.
+    fn written_byte(&self, position: u64) -> Option<u8> {
+        let (start, data) = self.writes.range(..=position).next_back()?;
+        data.get((position - start) as usize).copied()
+    }
+
+    /// Returns the position behind the last written byte, or zero if nothing has been written.
+    fn writes_end(&self) -> u64 {
+        self.writes
+            .iter()
+            .next_back()
+            .map_or(0, |(start, data)| start + data.len() as u64)
+    }
+
+    fn insert_write(&mut self, position: u64, data: &[u8]) {
+        let end = position + data.len() as u64;
+
+        // Collect all existing ranges that overlap or touch the new one.
+        let touching = self
+            .writes
+            .range(..=end)
+            .rev()
+            .take_while(|(start, existing)| *start + existing.len() as u64 >= position)
+            .map(|(start, existing)| (*start, existing.len() as u64))
+            .collect::<Vec<_>>();
+
+        let merged_start = touching
+            .iter()
+            .map(|(start, _)| *start)
+            .fold(position, u64::min);
+        let merged_end = touching
+            .iter()
+            .map(|(start, length)| start + length)
+            .fold(end, u64::max);
+
+        let mut merged = vec![0u8; (merged_end - merged_start) as usize];
+        for (start, _) in touching {
+            let existing = self.writes.remove(&start).unwrap();
+            let offset = (start - merged_start) as usize;
+            merged[offset..offset + existing.len()].copy_from_slice(&existing);
+        }
+
+        let offset = (position - merged_start) as usize;
+        merged[offset..offset + data.len()].copy_from_slice(data);
+        self.writes.insert(merged_start, merged);
+    }
+}
+
+impl<T> Read for NtfsDryRun<T>
+where
+    T: Read + Seek,
+{
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.inner.seek(SeekFrom::Start(self.stream_position))?;
+        let mut bytes_read = self.inner.read(buf)?;
+        let start = self.stream_position;
+
+        // Writes may extend the filesystem beyond the end of the inner reader.
+        // Any gap up to them reads as zeros.
+        if bytes_read == 0 && !buf.is_empty() {
+            bytes_read = (self.writes_end().saturating_sub(start)).min(buf.len() as u64) as usize;
+            buf[..bytes_read].fill(0);
+        }
+
+        let end = start + bytes_read as u64;
+
+        // Apply all writes overlapping the read range.
+        for (write_start, data) in self.writes.range(..end).rev() {
+            let write_end = write_start + data.len() as u64;
+            if write_end <= start {
+                break;
+            }
+
+            let overlap_start = start.max(*write_start);
+            let overlap_end = end.min(write_end);
+            buf[(overlap_start - start) as usize..(overlap_end - start) as usize].copy_from_slice(
+                &data[(overlap_start - write_start) as usize..(overlap_end - write_start) as usize],
+            );
+        }
+
+        self.stream_position = end;
+        Ok(bytes_read)
+    }
+}
+
+impl<T> Seek for NtfsDryRun<T>
+where
+    T: Read + Seek,
+{
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let new_position = match pos {
+            SeekFrom::Start(n) => Some(n),
+            SeekFrom::End(n) => {
+                let end = self.inner.seek(SeekFrom::End(0))?.max(self.writes_end());
+                if n >= 0 {
+                    end.checked_add(n as u64)
+                } else {
+                    end.checked_sub(n.wrapping_neg() as u64)
+                }
+            }
+            SeekFrom::Current(n) => {
+                if n >= 0 {
+                    self.stream_position.checked_add(n as u64)
+                } else {
+                    self.stream_position.checked_sub(n.wrapping_neg() as u64)
+                }
+            }
+        };
+
+        match new_position {
+            Some(n) => {
+                self.stream_position = n;
+                Ok(self.stream_position)
+            }
+            None => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "invalid seek to a negative or overflowing position",
+            )),
+        }
+    }
+}
+
+impl<T> Write for NtfsDryRun<T>
+where
+    T: Read + Seek,
+{
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.insert_write(self.stream_position, buf);
+        self.stream_position += buf.len() as u64;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Returns the merged target if `next` directly continues `last`.
+fn merge_targets(last: NtfsDryRunTarget, next: NtfsDryRunTarget) -> Option<NtfsDryRunTarget> {
+    match (last, next) {
+        (
+            NtfsDryRunTarget::FileRecord {
+                file_record_number: a,
+            },
+            NtfsDryRunTarget::FileRecord {
+                file_record_number: b,
+            },
+        ) if a == b => Some(last),
+        (
+            NtfsDryRunTarget::Bitmap {
+                first_lcn,
+                cluster_count,
+            },
+            NtfsDryRunTarget::Bitmap {
+                first_lcn: next_first_lcn,
+                cluster_count: next_cluster_count,
+            },
+        ) if first_lcn.value() + cluster_count == next_first_lcn.value() => {
+            Some(NtfsDryRunTarget::Bitmap {
+                first_lcn,
+                cluster_count: cluster_count + next_cluster_count,
+            })
+        }
+        (
+            NtfsDryRunTarget::Attribute {
+                file_record_number: a,
+                ty: a_ty,
+                ..
+            },
+            NtfsDryRunTarget::Attribute {
+                file_record_number: b,
+                ty: b_ty,
+                ..
+            },
+        ) if a == b && a_ty == b_ty => Some(last),
+        (
+            NtfsDryRunTarget::BootSector { is_backup: a },
+            NtfsDryRunTarget::BootSector { is_backup: b },
+        ) if a == b => Some(last),
+        (NtfsDryRunTarget::Unowned, NtfsDryRunTarget::Unowned)
+        | (NtfsDryRunTarget::OutsideVolume, NtfsDryRunTarget::OutsideVolume) => Some(last),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::file::KnownNtfsFileRecordNumber;
+    use crate::time::NtfsTime;
+
+    #[test]
+    fn test_dry_run() {
+        let mut testfs1 = crate::helpers::tests::testfs1();
+        let data = testfs1.get_ref().clone();
+        let mut ntfs = Ntfs::new(&mut testfs1).unwrap();
+        let owners = ntfs.cluster_owner_index(&mut testfs1).unwrap();
+
+        // Simulate a resize followed by a write to a File Record.
+        let mut dry_run = NtfsDryRun::new(&mut testfs1);
+        ntfs.resize(&mut dry_run, 3546).unwrap();
+
+        let mut file = ntfs
+            .file(
+                &mut dry_run,
+                KnownNtfsFileRecordNumber::RootDirectory as u64,
+            )
+            .unwrap();
+        let time = NtfsTime::from(132_000_000_000_000_000);
+        let mut info = file.info().unwrap();
+        info.set_modification_time(time);
+        file.write_standard_information(&mut dry_run, &info)
+            .unwrap();
+
+        // Subsequent reads see the writes.
+        assert_eq!(ntfs.boot_sector().unwrap().total_sectors(), 3546);
+        let file = ntfs
+            .file(
+                &mut dry_run,
+                KnownNtfsFileRecordNumber::RootDirectory as u64,
+            )
+            .unwrap();
+        assert_eq!(file.info().unwrap().modification_time(), time);
+
+        let file_record =
+            |file_record_number: u64| NtfsDryRunTarget::FileRecord { file_record_number };
+        let changes = dry_run.changes(&ntfs, &owners).unwrap();
+        assert!(!changes.iter().any(|change| change.allocation_changed()));
+        let targets = changes
+            .iter()
+            .map(|change| change.target())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            targets,
+            [
+                NtfsDryRunTarget::BootSector { is_backup: false },
+                file_record(KnownNtfsFileRecordNumber::RootDirectory as u64),
+                file_record(KnownNtfsFileRecordNumber::Bitmap as u64),
+                file_record(KnownNtfsFileRecordNumber::BadClus as u64),
+                NtfsDryRunTarget::Bitmap {
+                    first_lcn: Lcn::from(0),
+                    cluster_count: 3584,
+                },
+                NtfsDryRunTarget::BootSector { is_backup: true },
+            ]
+        );
+
+        // Nothing has been written to the filesystem.
+        let changed_bytes = dry_run.changed_bytes();
+        assert!(changed_bytes > 0);
+        assert_eq!(
+            changed_bytes,
+            dry_run
+                .changed_ranges()
+                .map(|(_, d)| d.len() as u64)
+                .sum::<u64>()
+        );
+        dry_run.clear();
+        assert!(dry_run.is_empty());
+
+        // Freeing the first cluster of "1000-bytes-file" in $Bitmap flags the changes to that cluster.
+        let lcn = 2567;
+        let bitmap_lcn = owners
+            .find_lcn(
+                KnownNtfsFileRecordNumber::Bitmap as u64,
+                NtfsAttributeType::Data,
+                Vcn::from(0),
+            )
+            .unwrap();
+        let bitmap_position = bitmap_lcn.value() * 512 + lcn / 8;
+        let mut byte = [0u8];
+        dry_run.seek(SeekFrom::Start(bitmap_position)).unwrap();
+        dry_run.read_exact(&mut byte).unwrap();
+        byte[0] &= !(1 << (lcn % 8));
+        dry_run.seek(SeekFrom::Start(bitmap_position)).unwrap();
+        dry_run.write_all(&byte).unwrap();
+        dry_run.seek(SeekFrom::Start(lcn * 512)).unwrap();
+        dry_run.write_all(&[0; 512]).unwrap();
+
+        let changes = dry_run.changes(&ntfs, &owners).unwrap();
+        assert_eq!(changes.len(), 2);
+        assert!(!changes[0].allocation_changed());
+        assert!(changes[1].allocation_changed());
+        assert_eq!(
+            changes[1].target(),
+            NtfsDryRunTarget::Attribute {
+                file_record_number: 66,
+                ty: NtfsAttributeType::Data,
+                vcn: Vcn::from(0),
+            }
+        );
+        dry_run.clear();
+        assert_eq!(*testfs1.get_ref(), data);
+    }
+
+    #[test]
+    fn test_dry_run_overlapping_writes() {
+        let testfs1 = crate::helpers::tests::testfs1();
+        let data = testfs1.get_ref().clone();
+        let mut dry_run = NtfsDryRun::new(testfs1);
+
+        dry_run.seek(SeekFrom::Start(100)).unwrap();
+        dry_run.write_all(&[1; 10]).unwrap();
+        dry_run.seek(SeekFrom::Start(120)).unwrap();
+        dry_run.write_all(&[2; 10]).unwrap();
+        dry_run.seek(SeekFrom::Current(-20)).unwrap();
+        dry_run.write_all(&[3; 10]).unwrap();
+
+        // Adjacent writes are merged.
+        let ranges = dry_run.changed_ranges().collect::<Vec<_>>();
+        assert_eq!(ranges.len(), 1);
+        assert_eq!(ranges[0].0, 100);
+        assert_eq!(ranges[0].1.len(), 30);
+        assert_eq!(dry_run.changed_bytes(), 30);
+
+        let mut buf = [0u8; 40];
+        dry_run.seek(SeekFrom::Start(95)).unwrap();
+        dry_run.read_exact(&mut buf).unwrap();
+        assert_eq!(&buf[..5], &data[95..100]);
+        assert_eq!(&buf[5..15], &[1; 10]);
+        assert_eq!(&buf[15..25], &[3; 10]);
+        assert_eq!(&buf[25..35], &[2; 10]);
+        assert_eq!(&buf[35..], &data[130..135]);
+
+        // Writes behind the end of the reader extend it.
+        let end = data.len() as u64;
+        dry_run.seek(SeekFrom::Start(end + 10)).unwrap();
+        dry_run.write_all(&[4; 10]).unwrap();
+        assert_eq!(dry_run.seek(SeekFrom::End(0)).unwrap(), end + 20);
+        dry_run.seek(SeekFrom::Start(end - 5)).unwrap();
+        let mut buf = Vec::new();
+        dry_run.read_to_end(&mut buf).unwrap();
+        assert_eq!(&buf[..5], &data[data.len() - 5..]);
+        assert_eq!(&buf[5..15], &[0; 10]);
+        assert_eq!(&buf[15..], &[4; 10]);
+
+        assert_eq!(*dry_run.into_inner().get_ref(), data);
+    }
+}
@@ -0,0 +1,461 @@
+// Copyright 2021-2023 Colin Finck <colin@reactos.org>
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use binrw::io::{Read, Seek};
+
+use crate::attribute::NtfsAttributeFlags;
+use crate::error::{NtfsError, Result};
+use crate::file::{NtfsFile, NtfsFileFlags};
+use crate::indexes::NtfsFileNameIndex;
+use crate::ntfs::Ntfs;
+use crate::progress::{report_progress, NtfsProgressSink, NtfsProgressUnit};
+use crate::structured_values::NtfsFileName;
+
+/// Where a duplicated copy of the file sizes is stored, returned by [`NtfsSizeMismatch::location`].
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum NtfsSizeLocation {
+    /// A $FILE_NAME attribute in the File Record of the file itself.
+    FileNameAttribute,
+    /// The entry for the file in the index of its parent directory, whose key is a copy of a
+    /// $FILE_NAME attribute.
+    DirectoryEntry {
+        /// File Record Number of the parent directory.
+        directory_file_record_number: u64,
+    },
+}
+
+/// A duplicated copy of the file sizes that doesn't match the sizes of the unnamed $DATA attribute.
+///
+/// NTFS stores the data size and allocated size of a file in every $FILE_NAME attribute and in every
+/// directory entry of the file.
+/// Windows updates these copies lazily, so a mismatch is not necessarily corruption:
+/// The $FILE_NAME attributes usually keep the sizes from the time the name was created, and the
+/// directory entry of the $MFT file is never updated.
+/// But a mismatching directory entry of a regular file is a cheap indicator of stale or
+/// tampered-with metadata, like a file whose content has been replaced without going through the
+/// filesystem driver.
+///
+/// Allocated sizes are only compared for non-resident values that are neither compressed nor sparse.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct NtfsSizeMismatch {
+    file_record_number: u64,
+    location: NtfsSizeLocation,
+    name: String,
+    recorded_data_size: u64,
+    recorded_allocated_size: u64,
+    actual_data_size: u64,
+    actual_allocated_size: u64,
+}
+
+impl NtfsSizeMismatch {
+    /// Returns the allocated size of the unnamed $DATA attribute, in bytes.
+    pub fn actual_allocated_size(&self) -> u64 {
+        self.actual_allocated_size
+    }
+
+    /// Returns the data size of the unnamed $DATA attribute, in bytes.
+    pub fn actual_data_size(&self) -> u64 {
+        self.actual_data_size
+    }
+
+    /// Returns the File Record Number of the file.
+    pub fn file_record_number(&self) -> u64 {
+        self.file_record_number
+    }
+
+    /// Returns where the mismatching copy of the sizes is stored.
+    pub fn location(&self) -> NtfsSizeLocation {
+        self.location
+    }
+
+    /// Returns the file name of the mismatching copy.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the allocated size stored in the mismatching copy, in bytes.
+    pub fn recorded_allocated_size(&self) -> u64 {
+        self.recorded_allocated_size
+    }
+
+    /// Returns the data size stored in the mismatching copy, in bytes.
+    pub fn recorded_data_size(&self) -> u64 {
+        self.recorded_data_size
+    }
+}
+
+/// Result of comparing the duplicated file sizes of all files via [`Ntfs::size_mismatches`].
+///
+/// Files that cannot be read don't stop the comparison, but are collected along with their errors.
+/// Only errors that prevent reading the Master File Table at all are returned as an [`NtfsError`]
+/// by [`Ntfs::size_mismatches`].
+#[derive(Debug)]
+pub struct NtfsSizeMismatchReport {
+    mismatches: Vec<NtfsSizeMismatch>,
+    errors: Vec<(u64, NtfsError)>,
+}
+
+impl NtfsSizeMismatchReport {
+    /// Returns the File Record Number and the error of every file whose sizes could not be compared,
+    /// in the order they were encountered.
+    pub fn errors(&self) -> &[(u64, NtfsError)] {
+        &self.errors
+    }
+
+    /// Returns all mismatches found.
+    pub fn mismatches(&self) -> &[NtfsSizeMismatch] {
+        &self.mismatches
+    }
+}
+
+/// Sizes of the unnamed $DATA attribute of a file.
+struct ActualSizes {
+    data_size: u64,
+    allocated_size: u64,
+    /// Resident values have no cluster allocation, and Windows doesn't agree on what to store as their
+    /// allocated size.
+    /// For compressed and sparse values, Windows stores the number of bytes actually allocated instead.
+    compare_allocated_size: bool,
+}
+
+impl ActualSizes {
+    fn new<T>(file: &NtfsFile, fs: &mut T) -> Option<Result<Self>>
+    where
+        T: Read + Seek,
+    {
+        let item = iter_try!(file.data(fs, "")?);
+        let attribute = iter_try!(item.to_attribute());
+        let allocated_size = iter_try!(attribute.allocated_size(fs));
+
+        let compare_allocated_size = !attribute.is_resident()
+            && !attribute
+                .flags()
+                .intersects(NtfsAttributeFlags::COMPRESSED | NtfsAttributeFlags::SPARSE);
+
+        Some(Ok(Self {
+            data_size: attribute.value_length(),
+            allocated_size,
+            compare_allocated_size,
+        }))
+    }
+
+    fn mismatch(
+        &self,
+        file: &NtfsFile,
+        location: NtfsSizeLocation,
+        file_name: &NtfsFileName,
+    ) -> Option<NtfsSizeMismatch> {
+        let allocated_size_matches =
+            !self.compare_allocated_size || file_name.allocated_size() == self.allocated_size;
+
+        if file_name.data_size() == self.data_size && allocated_size_matches {
+            return None;
+        }
+
+        Some(NtfsSizeMismatch {
+            file_record_number: file.file_record_number(),
+            location,
+            name: file_name.name().to_string_lossy(),
+            recorded_data_size: file_name.data_size(),
+            recorded_allocated_size: file_name.allocated_size(),
+            actual_data_size: self.data_size,
+            actual_allocated_size: self.allocated_size,
+        })
+    }
+}
+
+/// Compares the sizes in all $FILE_NAME attributes of `file` and in all of its directory entries
+/// with the sizes of its unnamed $DATA attribute.
+pub(crate) fn size_mismatches<T>(file: &NtfsFile, fs: &mut T) -> Result<Vec<NtfsSizeMismatch>>
+where
+    T: Read + Seek,
+{
+    let mut mismatches = Vec::new();
+
+    // Directories have no unnamed $DATA attribute and always store zero sizes.
+    if file.is_directory() {
+        return Ok(mismatches);
+    }
+
+    let actual_sizes = match ActualSizes::new(file, fs) {
+        Some(actual_sizes) => actual_sizes?,
+        None => return Ok(mismatches),
+    };
+
+    let ntfs = file.ntfs();
+    let mut names = file.names();
+
+    while let Some(file_name) = names.next(fs) {
+        let file_name = file_name?;
+        mismatches.extend(actual_sizes.mismatch(
+            file,
+            NtfsSizeLocation::FileNameAttribute,
+            &file_name,
+        ));
+
+        // Look up the directory entry belonging to this name.
+        let directory_file_record_number =
+            file_name.parent_directory_reference().file_record_number();
+        let directory = ntfs.file(fs, directory_file_record_number)?;
+        let index = match directory.directory_index(fs) {
+            Ok(index) => index,
+            Err(NtfsError::AttributeNotFound { .. }) => continue,
+            Err(e) => return Err(e),
+        };
+        let mut finder = index.finder();
+
+        // A case-sensitive directory may have other entries that only differ in case.
+        let case_sensitivity = directory.case_sensitivity()?;
+        let name = file_name.name().u16_iter().collect::<Vec<u16>>();
+        let entry = match NtfsFileNameIndex::find_u16_with_case_sensitivity(
+            &mut finder,
+            ntfs,
+            fs,
+            &name,
+            case_sensitivity,
+        ) {
+            Some(entry) => entry?,
+            None => continue,
+        };
+
+        if entry.file_reference().file_record_number() != file.file_record_number() {
+            continue;
+        }
+
+        if let Some(entry_file_name) = entry.key() {
+            mismatches.extend(actual_sizes.mismatch(
+                file,
+                NtfsSizeLocation::DirectoryEntry {
+                    directory_file_record_number,
+                },
+                &entry_file_name?,
+            ));
+        }
+    }
+
+    Ok(mismatches)
+}
+
+/// Runs [`size_mismatches`] for all files in use of the filesystem.
+pub(crate) fn volume_size_mismatches<T, P>(
+    ntfs: &Ntfs,
+    fs: &mut T,
+    progress: &mut P,
+) -> Result<NtfsSizeMismatchReport>
+where
+    T: Read + Seek,
+    P: NtfsProgressSink + ?Sized,
+{
+    let file_record_count = ntfs.file_record_count(fs)?;
+    let mut report = NtfsSizeMismatchReport {
+        mismatches: Vec::new(),
+        errors: Vec::new(),
+    };
+
+    for file_record_number in 0..file_record_count {
+        report_progress(
+            progress,
+            NtfsProgressUnit::FileRecords,
+            file_record_number,
+            Some(file_record_count),
+        )?;
+
+        let file = match ntfs.file(fs, file_record_number) {
+            Ok(file) => file,
+            // Unused File Records may never have been initialized.
+            Err(NtfsError::InvalidFileSignature { .. }) => continue,
+            Err(e) => {
+                report.errors.push((file_record_number, e));
+                continue;
+            }
+        };
+
+        // Extension File Records are covered by their base File Record.
+        if !file.flags().contains(NtfsFileFlags::IN_USE)
+            || file.base_file_record().file_record_number() != 0
+        {
+            continue;
+        }
+
+        match size_mismatches(&file, fs) {
+            Ok(mismatches) => report.mismatches.extend(mismatches),
+            Err(e) => report.errors.push((file_record_number, e)),
+        }
+    }
+
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::attribute::NtfsAttributeType;
+    use crate::file::KnownNtfsFileRecordNumber;
+    use crate::upcase_table::NtfsCaseSensitivity;
+
+    fn directory_entry_mismatches(mismatches: &[NtfsSizeMismatch]) -> Vec<(u64, &str)> {
+        mismatches
+            .iter()
+            .filter(|mismatch| {
+                matches!(mismatch.location(), NtfsSizeLocation::DirectoryEntry { .. })
+            })
+            .map(|mismatch| (mismatch.file_record_number(), mismatch.name()))
+            .collect()
+    }
+
+    #[test]
+    fn test_size_mismatches() {
+        let mut testfs1 = crate::helpers::tests::testfs1();
+        let mut ntfs = Ntfs::new(&mut testfs1).unwrap();
+        ntfs.read_upcase_table(&mut testfs1).unwrap();
+
+        // The $FILE_NAME attributes of testfs1 still have the sizes at creation time, and only the
+        // directory entry of $MFT is stale.
+        let report = ntfs.size_mismatches(&mut testfs1).unwrap();
+        assert!(report.errors().is_empty());
+        let mismatches = report.mismatches();
+        assert_eq!(
+            directory_entry_mismatches(mismatches),
+            [(KnownNtfsFileRecordNumber::MFT as u64, "$MFT")]
+        );
+        assert!(mismatches
+            .iter()
+            .any(|mismatch| mismatch.name() == "1000-bytes-file"
+                && mismatch.location() == NtfsSizeLocation::FileNameAttribute
+                && mismatch.recorded_data_size() == 0
+                && mismatch.actual_data_size() == 1000));
+
+        // Tamper with the data size in the directory entry of "1000-bytes-file".
+        let data_size_position = {
+            let root_dir = ntfs.root_directory(&mut testfs1).unwrap();
+            let index = root_dir.directory_index(&mut testfs1).unwrap();
+            let mut finder = index.finder();
+            let entry =
+                NtfsFileNameIndex::find(&mut finder, &ntfs, &mut testfs1, "1000-bytes-file")
+                    .unwrap()
+                    .unwrap();
+            assert_eq!(entry.key().unwrap().unwrap().data_size(), 1000);
+
+            // The key (a $FILE_NAME structure) follows the 16-byte Index Entry header,
+            // and the data size is at byte offset 48 of it.
+            entry.position().value().unwrap().get() as usize + 16 + 48
+        };
+        testfs1.get_mut()[data_size_position..][..8].copy_from_slice(&999u64.to_le_bytes());
+
+        let file = ntfs
+            .file_by_path(
+                &mut testfs1,
+                "1000-bytes-file",
+                NtfsCaseSensitivity::Insensitive,
+            )
+            .unwrap()
+            .unwrap();
+        let mismatches = file.size_mismatches(&mut testfs1).unwrap();
+        let mismatch = mismatches
+            .iter()
+            .find(|mismatch| {
+                mismatch.location()
+                    == NtfsSizeLocation::DirectoryEntry {
+                        directory_file_record_number: KnownNtfsFileRecordNumber::RootDirectory
+                            as u64,
+                    }
+            })
+            .unwrap();
+        assert_eq!(mismatch.recorded_data_size(), 999);
+        assert_eq!(mismatch.actual_data_size(), 1000);
+        assert_eq!(mismatch.recorded_allocated_size(), 1024);
+        assert_eq!(mismatch.actual_allocated_size(), 1024);
+
+        // A File Record that cannot be read is reported, but doesn't stop the comparison.
+        let position = file.position().value().unwrap().get() as usize;
+        testfs1.get_mut()[position + 510..][..2].copy_from_slice(&[0xde, 0xad]);
+
+        let report = ntfs.size_mismatches(&mut testfs1).unwrap();
+        assert_eq!(report.errors().len(), 1);
+        assert_eq!(report.errors()[0].0, file.file_record_number());
+        assert!(matches!(
+            report.errors()[0].1,
+            NtfsError::UpdateSequenceNumberMismatch { .. }
+        ));
+        assert_eq!(
+            directory_entry_mismatches(report.mismatches()),
+            [(KnownNtfsFileRecordNumber::MFT as u64, "$MFT")]
+        );
+    }
+
+    #[test]
+    fn test_size_mismatches_case_sensitive_directory() {
+        let mut testfs3 = crate::helpers::tests::testfs3();
+        let mut ntfs = Ntfs::new(&mut testfs3).unwrap();
+        ntfs.read_upcase_table(&mut testfs3).unwrap();
+
+        let mut directory = ntfs
+            .file_by_path(
+                &mut testfs3,
+                "case-sensitive-dir",
+                NtfsCaseSensitivity::Insensitive,
+            )
+            .unwrap()
+            .unwrap();
+
+        // Find the data size in the directory entry of "file", which sorts after "FILE".
+        // The directory is small enough to only have an Index Root in its File Record.
+        let data_size_offset = {
+            let index = directory.directory_index(&mut testfs3).unwrap();
+            let mut finder = index.finder();
+            let name = "file".encode_utf16().collect::<Vec<u16>>();
+            let entry = NtfsFileNameIndex::find_u16_with_case_sensitivity(
+                &mut finder,
+                &ntfs,
+                &mut testfs3,
+                &name,
+                NtfsCaseSensitivity::Sensitive,
+            )
+            .unwrap()
+            .unwrap();
+            assert_eq!(entry.key().unwrap().unwrap().data_size(), 10);
+
+            let entry_position = entry.position().value().unwrap().get();
+            let directory_position = directory.position().value().unwrap().get();
+            (entry_position - directory_position) as usize + 16 + 48
+        };
+
+        // Mark the directory case-sensitive, just like `fsutil file setCaseSensitiveInfo`,
+        // and tamper with the data size.
+        let value_range = directory
+            .find_resident_attribute(NtfsAttributeType::StandardInformation, None, None)
+            .unwrap()
+            .resident_value_range()
+            .unwrap();
+        let record_data = directory.record_data_mut();
+        record_data[value_range.start + 0x28] |= 0x1;
+        record_data[data_size_offset..][..8].copy_from_slice(&999u64.to_le_bytes());
+        directory.write_record(&mut testfs3).unwrap();
+        assert_eq!(
+            directory.case_sensitivity().unwrap(),
+            NtfsCaseSensitivity::Sensitive
+        );
+
+        // The mismatch is found in the entry of "file", and not attributed to "FILE".
+        let report = ntfs.size_mismatches(&mut testfs3).unwrap();
+        assert!(report.errors().is_empty());
+        let mismatch = report
+            .mismatches()
+            .iter()
+            .find(|mismatch| {
+                mismatch.location()
+                    == NtfsSizeLocation::DirectoryEntry {
+                        directory_file_record_number: directory.file_record_number(),
+                    }
+            })
+            .unwrap();
+        assert_eq!(mismatch.name(), "file");
+        assert_eq!(mismatch.recorded_data_size(), 999);
+        assert_eq!(mismatch.actual_data_size(), 10);
+        assert!(!directory_entry_mismatches(report.mismatches())
+            .iter()
+            .any(|(_, name)| *name == "FILE"));
+    }
+}
@@ -16,6 +16,8 @@ pub type Result<T, E = NtfsError> = core::result::Result<T, E>;
 #[derive(Debug, Display)]
 #[non_exhaustive]
 pub enum NtfsError {
+    /// The operation needs more blocks than the {capacity} blocks that the NtfsAsyncReader may cache
+    AsyncCacheTooSmall { capacity: usize },
     /// The NTFS file at byte position {position:#x} has no attribute of type {ty:?}, but it was expected
     AttributeNotFound {
         position: NtfsPosition,
@@ -27,8 +29,26 @@ pub enum NtfsError {
         expected: NtfsAttributeType,
         actual: NtfsAttributeType,
     },
+    /// The non-resident attribute value with Data Runs at byte position {position:#x} has a data size of {data_size} bytes, but its Data Runs only provide {backed_size} bytes
+    AttributeValueNotBacked {
+        position: NtfsPosition,
+        data_size: u64,
+        backed_size: u64,
+    },
     /// The given buffer should have at least {expected} bytes, but it only has {actual} bytes
     BufferTooSmall { expected: usize, actual: usize },
+    /// The operation has been cancelled by its NtfsProgressSink
+    Cancelled,
+    /// The data of the cluster at LCN {lcn} is unavailable, because only a standalone $MFT file has been opened
+    ClusterDataUnavailable { lcn: Lcn },
+    /// The range of {count} clusters starting at LCN {lcn} exceeds the {total_clusters} clusters of the filesystem
+    ClusterRangeOutOfBounds {
+        lcn: Lcn,
+        count: u64,
+        total_clusters: u64,
+    },
+    /// The Index Entry has {actual} bytes, but it must not exceed {max} bytes
+    IndexEntryTooBig { actual: usize, max: usize },
     /// The NTFS Attribute at byte position {position:#x} has a length of {expected} bytes, but only {actual} bytes are left in the record
     InvalidAttributeLength {
         position: NtfsPosition,
@@ -47,6 +67,8 @@ pub enum NtfsError {
         expected: u16,
         actual: u32,
     },
+    /// The cluster owner index data is invalid or has been written by an incompatible version
+    InvalidClusterOwnerIndex,
     /// The NTFS Data Run header at byte position {position:#x} indicates a maximum byte count of {expected}, but {actual} is the limit
     InvalidByteCountInDataRunHeader {
         position: NtfsPosition,
@@ -90,6 +112,18 @@ pub enum NtfsError {
         range: Range<usize>,
         size: u16,
     },
+    /// The NTFS Index Entry at byte position {position:#x} has a data field of {actual} bytes, but {expected} bytes were expected
+    InvalidIndexEntryDataSize {
+        position: NtfsPosition,
+        expected: usize,
+        actual: usize,
+    },
+    /// The NTFS Index Entry at byte position {position:#x} has a key of {actual} bytes, but {expected} bytes were expected
+    InvalidIndexEntryKeySize {
+        position: NtfsPosition,
+        expected: usize,
+        actual: usize,
+    },
     /// The NTFS Index Entry at byte position {position:#x} reports a size of {expected} bytes, but it only has {actual} bytes
     InvalidIndexEntrySize {
         position: NtfsPosition,
@@ -120,6 +154,8 @@ pub enum NtfsError {
         expected: u32,
         actual: u32,
     },
+    /// The WSL symlink reparse point at byte position {position:#x} has an unsupported version or a target that is not valid UTF-8
+    InvalidLxSymlinkData { position: NtfsPosition },
     /// The MFT LCN in the BIOS Parameter Block of the NTFS filesystem is invalid.
     InvalidMftLcn,
     /// The NTFS Non Resident Value Data at byte position {position:#x} references a data field in the range {range:?}, but the entry only has a size of {size} bytes
@@ -143,6 +179,8 @@ pub enum NtfsError {
     },
     /// A record size field in the BIOS Parameter Block denotes {size_info}, which is invalid considering the cluster size of {cluster_size} bytes
     InvalidRecordSizeInfo { size_info: i8, cluster_size: u32 },
+    /// The $Recycle.Bin $I file has {actual} bytes, but at least {expected} bytes were expected
+    InvalidRecycleBinInfoSize { expected: usize, actual: usize },
     /// The sectors per cluster field in the BIOS Parameter Block denotes {sectors_per_cluster:#04x}, which is invalid
     InvalidSectorsPerCluster { sectors_per_cluster: u8 },
     /// The NTFS structured value at byte position {position:#x} of type {ty:?} has {actual} bytes where {expected} bytes were expected
@@ -187,6 +225,24 @@ pub enum NtfsError {
     MissingIndexAllocation { position: NtfsPosition },
     /// The NTFS file at byte position {position:#x} is not a directory
     NotADirectory { position: NtfsPosition },
+    /// The reparse point data has {actual} bytes, but it must not exceed {max} bytes
+    ReparseDataTooBig { actual: usize, max: usize },
+    /// Resizing the volume requires {expected} bytes of $Bitmap, but only {actual} bytes are allocated for it
+    ResizeBitmapTooSmall { expected: u64, actual: u64 },
+    /// The volume cannot be shrunk, because the cluster at LCN {lcn} is still in use
+    ResizeClusterInUse { lcn: Lcn },
+    /// The Data Runs of the resized NTFS Attribute at byte position {position:#x} need {expected} bytes, but only {actual} bytes are available
+    ResizeDataRunsTooBig {
+        position: NtfsPosition,
+        expected: usize,
+        actual: usize,
+    },
+    /// The file reference to File Record {file_record_number} expects sequence number {expected}, but the File Record has sequence number {actual}
+    StaleFileReference {
+        file_record_number: u64,
+        expected: u16,
+        actual: u16,
+    },
     /// The total sector count is too big to be multiplied by the sector size
     TotalSectorsTooBig { total_sectors: u64 },
     /// The NTFS Attribute at byte position {position:#x} should not belong to an Attribute List, but it does
@@ -197,10 +253,18 @@ pub enum NtfsError {
     UnexpectedResidentAttribute { position: NtfsPosition },
     /// The type of the NTFS Attribute at byte position {position:#x} is {actual:#010x}, which is not supported
     UnsupportedAttributeType { position: NtfsPosition, actual: u32 },
-    /// The cluster size is {actual} bytes, but it needs to be between {min} and {max}
+    /// The cluster size derived from the standalone $MFT file is {actual} bytes, but it needs to be between {min} and {max}
     UnsupportedClusterSize { min: u32, max: u32, actual: u32 },
+    /// The Index Root at byte position {position:#x} declares collation rule {actual:#x}, which is not supported
+    UnsupportedCollationRule { position: NtfsPosition, actual: u32 },
+    /// The NTFS Attribute at byte position {position:#x} is compressed, which is not supported
+    UnsupportedCompressedAttribute { position: NtfsPosition },
     /// The namespace of the NTFS file name starting at byte position {position:#x} is {actual}, which is not supported
     UnsupportedFileNamespace { position: NtfsPosition, actual: u8 },
+    /// The File Record size is {actual} bytes, but it needs to be a power of two between {min} and {max}
+    UnsupportedFileRecordSize { min: u32, max: u32, actual: u32 },
+    /// The $Recycle.Bin $I file has version {actual}, which is not supported
+    UnsupportedRecycleBinInfoVersion { actual: u64 },
     /// The sector size is {actual} bytes, but it needs to be between {min} and {max}
     UnsupportedSectorSize { min: u16, max: u16, actual: u16 },
     /// The Update Sequence Array (USA) of the record at byte position {position:#x} has entries for {array_count} blocks of 512 bytes, but the record is only {record_size} bytes long
@@ -227,13 +291,201 @@ pub enum NtfsError {
     VcnTooBig { vcn: Vcn },
 }
 
+impl NtfsError {
+    /// Returns a numeric code that uniquely identifies the variant of this error.
+    ///
+    /// Codes are stable across releases and never reused, so they can be logged, stored, or matched
+    /// programmatically (even in `no_std` environments without [`Display`] output).
+    /// New variants receive new codes.
+    pub fn code(&self) -> u32 {
+        match self {
+            Self::AttributeNotFound { .. } => 1,
+            Self::AttributeOfDifferentType { .. } => 2,
+            Self::BufferTooSmall { .. } => 3,
+            Self::Cancelled => 4,
+            Self::ClusterDataUnavailable { .. } => 5,
+            Self::InvalidAttributeLength { .. } => 6,
+            Self::InvalidAttributeNameLength { .. } => 7,
+            Self::InvalidAttributeNameOffset { .. } => 8,
+            Self::InvalidByteCountInDataRunHeader { .. } => 9,
+            Self::InvalidClusterCountInDataRunHeader { .. } => 10,
+            Self::InvalidFileAllocatedSize { .. } => 12,
+            Self::InvalidFileRecordNumber { .. } => 13,
+            Self::InvalidFileSignature { .. } => 14,
+            Self::InvalidFileUsedSize { .. } => 15,
+            Self::InvalidIndexAllocatedSize { .. } => 16,
+            Self::InvalidIndexEntryDataRange { .. } => 17,
+            Self::InvalidIndexEntryDataSize { .. } => 18,
+            Self::InvalidIndexEntryKeySize { .. } => 19,
+            Self::InvalidIndexEntrySize { .. } => 20,
+            Self::InvalidIndexRootEntriesOffset { .. } => 21,
+            Self::InvalidIndexRootUsedSize { .. } => 22,
+            Self::InvalidIndexSignature { .. } => 23,
+            Self::InvalidIndexUsedSize { .. } => 24,
+            Self::InvalidLxSymlinkData { .. } => 25,
+            Self::InvalidMftLcn => 26,
+            Self::InvalidNonResidentValueDataRange { .. } => 27,
+            Self::InvalidResidentAttributeValueLength { .. } => 28,
+            Self::InvalidResidentAttributeValueOffset { .. } => 29,
+            Self::InvalidRecordSizeInfo { .. } => 30,
+            Self::InvalidRecycleBinInfoSize { .. } => 31,
+            Self::InvalidSectorsPerCluster { .. } => 32,
+            Self::InvalidStructuredValueSize { .. } => 33,
+            Self::InvalidTime => 34,
+            Self::InvalidTwoByteSignature { .. } => 35,
+            Self::InvalidUpcaseTableSize { .. } => 36,
+            Self::InvalidUpdateSequenceCount { .. } => 37,
+            Self::InvalidUpdateSequenceNumberRange { .. } => 38,
+            Self::InvalidVcnInDataRunHeader { .. } => 39,
+            Self::Io(_) => 40,
+            Self::LcnTooBig { .. } => 41,
+            Self::MissingIndexAllocation { .. } => 42,
+            Self::NotADirectory { .. } => 43,
+            Self::ReparseDataTooBig { .. } => 44,
+            Self::ResizeBitmapTooSmall { .. } => 45,
+            Self::ResizeClusterInUse { .. } => 46,
+            Self::ResizeDataRunsTooBig { .. } => 47,
+            Self::StaleFileReference { .. } => 48,
+            Self::TotalSectorsTooBig { .. } => 49,
+            Self::UnexpectedAttributeListAttribute { .. } => 50,
+            Self::UnexpectedNonResidentAttribute { .. } => 51,
+            Self::UnexpectedResidentAttribute { .. } => 52,
+            Self::UnsupportedAttributeType { .. } => 53,
+            Self::UnsupportedClusterSize { .. } => 54,
+            Self::UnsupportedFileNamespace { .. } => 55,
+            Self::UnsupportedFileRecordSize { .. } => 56,
+            Self::UnsupportedRecycleBinInfoVersion { .. } => 57,
+            Self::UnsupportedSectorSize { .. } => 58,
+            Self::UpdateSequenceArrayExceedsRecordSize { .. } => 59,
+            Self::UpdateSequenceNumberMismatch { .. } => 60,
+            Self::VcnMismatchInIndexAllocation { .. } => 61,
+            Self::VcnOutOfBoundsInIndexAllocation { .. } => 62,
+            Self::VcnTooBig { .. } => 63,
+            Self::InvalidClusterOwnerIndex => 64,
+            Self::UnsupportedCollationRule { .. } => 65,
+            Self::UnsupportedCompressedAttribute { .. } => 66,
+            Self::ClusterRangeOutOfBounds { .. } => 67,
+            Self::AttributeValueNotBacked { .. } => 68,
+            Self::AsyncCacheTooSmall { .. } => 69,
+            Self::IndexEntryTooBig { .. } => 70,
+        }
+    }
+
+    /// Returns the [`NtfsErrorKind`] category of this error.
+    pub fn kind(&self) -> NtfsErrorKind {
+        match self {
+            Self::Cancelled => NtfsErrorKind::Cancelled,
+            Self::AttributeValueNotBacked { .. }
+            | Self::InvalidAttributeLength { .. }
+            | Self::InvalidAttributeNameLength { .. }
+            | Self::InvalidAttributeNameOffset { .. }
+            | Self::InvalidByteCountInDataRunHeader { .. }
+            | Self::InvalidClusterCountInDataRunHeader { .. }
+            | Self::InvalidClusterOwnerIndex
+            | Self::InvalidFileAllocatedSize { .. }
+            | Self::InvalidFileSignature { .. }
+            | Self::InvalidFileUsedSize { .. }
+            | Self::InvalidIndexAllocatedSize { .. }
+            | Self::InvalidIndexEntryDataRange { .. }
+            | Self::InvalidIndexEntryDataSize { .. }
+            | Self::InvalidIndexEntryKeySize { .. }
+            | Self::InvalidIndexEntrySize { .. }
+            | Self::InvalidIndexRootEntriesOffset { .. }
+            | Self::InvalidIndexRootUsedSize { .. }
+            | Self::InvalidIndexSignature { .. }
+            | Self::InvalidIndexUsedSize { .. }
+            | Self::InvalidLxSymlinkData { .. }
+            | Self::InvalidMftLcn
+            | Self::InvalidNonResidentValueDataRange { .. }
+            | Self::InvalidResidentAttributeValueLength { .. }
+            | Self::InvalidResidentAttributeValueOffset { .. }
+            | Self::InvalidRecordSizeInfo { .. }
+            | Self::InvalidRecycleBinInfoSize { .. }
+            | Self::InvalidSectorsPerCluster { .. }
+            | Self::InvalidStructuredValueSize { .. }
+            | Self::InvalidTwoByteSignature { .. }
+            | Self::InvalidUpcaseTableSize { .. }
+            | Self::InvalidUpdateSequenceCount { .. }
+            | Self::InvalidUpdateSequenceNumberRange { .. }
+            | Self::InvalidVcnInDataRunHeader { .. }
+            | Self::LcnTooBig { .. }
+            | Self::MissingIndexAllocation { .. }
+            | Self::TotalSectorsTooBig { .. }
+            | Self::UnexpectedAttributeListAttribute { .. }
+            | Self::UnexpectedNonResidentAttribute { .. }
+            | Self::UnexpectedResidentAttribute { .. }
+            | Self::UpdateSequenceArrayExceedsRecordSize { .. }
+            | Self::UpdateSequenceNumberMismatch { .. }
+            | Self::VcnMismatchInIndexAllocation { .. }
+            | Self::VcnOutOfBoundsInIndexAllocation { .. }
+            | Self::VcnTooBig { .. } => NtfsErrorKind::Corrupted,
+            Self::AsyncCacheTooSmall { .. }
+            | Self::AttributeOfDifferentType { .. }
+            | Self::BufferTooSmall { .. }
+            | Self::ClusterRangeOutOfBounds { .. }
+            | Self::IndexEntryTooBig { .. }
+            | Self::InvalidFileRecordNumber { .. }
+            | Self::InvalidTime
+            | Self::NotADirectory { .. }
+            | Self::ReparseDataTooBig { .. }
+            | Self::ResizeBitmapTooSmall { .. }
+            | Self::ResizeClusterInUse { .. }
+            | Self::ResizeDataRunsTooBig { .. } => NtfsErrorKind::InvalidInput,
+            Self::Io(_) => NtfsErrorKind::Io,
+            Self::AttributeNotFound { .. } | Self::StaleFileReference { .. } => {
+                NtfsErrorKind::NotFound
+            }
+            Self::ClusterDataUnavailable { .. }
+            | Self::UnsupportedAttributeType { .. }
+            | Self::UnsupportedClusterSize { .. }
+            | Self::UnsupportedCollationRule { .. }
+            | Self::UnsupportedCompressedAttribute { .. }
+            | Self::UnsupportedFileNamespace { .. }
+            | Self::UnsupportedFileRecordSize { .. }
+            | Self::UnsupportedRecycleBinInfoVersion { .. }
+            | Self::UnsupportedSectorSize { .. } => NtfsErrorKind::Unsupported,
+        }
+    }
+}
+
+/// Category of an [`NtfsError`], as returned by [`NtfsError::kind`].
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[non_exhaustive]
+pub enum NtfsErrorKind {
+    /// The operation has been cancelled by the caller (see [`NtfsError::Cancelled`]).
+    Cancelled,
+    /// An on-disk structure is inconsistent or damaged.
+    Corrupted,
+    /// An argument passed by the caller is invalid for the requested operation.
+    InvalidInput,
+    /// The filesystem reader returned an I/O error (see [`NtfsError::Io`]).
+    Io,
+    /// A requested item doesn't exist (anymore).
+    NotFound,
+    /// The filesystem uses a feature or value that this crate doesn't support (yet).
+    Unsupported,
+}
+
+impl NtfsErrorKind {
+    fn to_io_error_kind(self) -> binrw::io::ErrorKind {
+        match self {
+            Self::Corrupted => binrw::io::ErrorKind::InvalidData,
+            Self::InvalidInput => binrw::io::ErrorKind::InvalidInput,
+            Self::NotFound => binrw::io::ErrorKind::NotFound,
+            // `io::ErrorKind::Interrupted` is not used for cancellation, because callers retry on it.
+            Self::Cancelled | Self::Io | Self::Unsupported => binrw::io::ErrorKind::Other,
+        }
+    }
+}
+
 impl From<binrw::error::Error> for NtfsError {
     fn from(error: binrw::error::Error) -> Self {
-        if let binrw::error::Error::Io(io_error) = error {
-            Self::Io(io_error)
-        } else {
+        match error {
+            binrw::error::Error::Io(io_error) => Self::Io(io_error),
+            // Errors inside derived parsers come wrapped with information about the failing field.
+            binrw::error::Error::Backtrace(backtrace) => Self::from(*backtrace.error),
             // We don't use any binrw attributes that result in other errors.
-            unreachable!("Got a binrw error of unexpected type: {:?}", error);
+            error => unreachable!("Got a binrw error of unexpected type: {:?}", error),
         }
     }
 }
@@ -251,11 +503,66 @@ impl From<NtfsError> for binrw::io::Error {
         if let NtfsError::Io(io_error) = error {
             io_error
         } else {
-            binrw::io::Error::new(binrw::io::ErrorKind::Other, error)
+            binrw::io::Error::new(error.kind().to_io_error_kind(), error)
         }
     }
 }
 
 #[cfg(feature = "std")]
 #[cfg_attr(docsrs, doc(cfg(feature = "std")))]
-impl std::error::Error for NtfsError {}
+impl std::error::Error for NtfsError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io(io_error) => Some(io_error),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_error_kind() {
+        let error = NtfsError::AttributeNotFound {
+            position: NtfsPosition::new(0x1000),
+            ty: NtfsAttributeType::Data,
+        };
+        assert_eq!(error.kind(), NtfsErrorKind::NotFound);
+        assert_eq!(error.code(), 1);
+        assert_eq!(
+            binrw::io::Error::from(error).kind(),
+            binrw::io::ErrorKind::NotFound
+        );
+
+        let error = NtfsError::InvalidFileSignature {
+            position: NtfsPosition::new(0x1000),
+            expected: b"FILE",
+            actual: *b"BAAD",
+        };
+        assert_eq!(error.kind(), NtfsErrorKind::Corrupted);
+        assert_eq!(
+            binrw::io::Error::from(error).kind(),
+            binrw::io::ErrorKind::InvalidData
+        );
+
+        assert_eq!(NtfsError::Cancelled.kind(), NtfsErrorKind::Cancelled);
+        assert_ne!(NtfsError::Cancelled.code(), NtfsError::InvalidMftLcn.code());
+
+        // I/O errors are passed through.
+        let error = NtfsError::from(binrw::io::Error::new(
+            binrw::io::ErrorKind::UnexpectedEof,
+            "failed to fill whole buffer",
+        ));
+        assert_eq!(error.kind(), NtfsErrorKind::Io);
+
+        #[cfg(feature = "std")]
+        assert!(std::error::Error::source(&error).is_some());
+
+        assert_eq!(
+            binrw::io::Error::from(error).kind(),
+            binrw::io::ErrorKind::UnexpectedEof
+        );
+    }
+}
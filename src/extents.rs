@@ -0,0 +1,320 @@
+// Copyright 2021-2023 Colin Finck <colin@reactos.org>
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+use alloc::vec::Vec;
+use binrw::io::{Read, Seek};
+
+use crate::attribute::NtfsAttribute;
+use crate::attribute_value::{AttributeListConnectedEntries, NtfsDataRuns};
+use crate::error::{NtfsError, Result};
+use crate::ntfs::Ntfs;
+use crate::types::{Lcn, Vcn};
+
+#[cfg(feature = "serde")]
+use serde::ser::{Serialize, SerializeStruct, Serializer};
+
+/// A well-known file in the root directory of a Windows system volume that holds the contents of
+/// physical memory.
+///
+/// These files are usually several gigabytes large and are the first ones to be located by memory
+/// forensics workflows.
+/// Get their [`NtfsExtentMap`] via [`Ntfs::memory_file_extents`].
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum NtfsMemoryFile {
+    /// "hiberfil.sys", which receives the contents of physical memory on hibernation
+    /// (and for Fast Startup since Windows 8).
+    Hiberfil,
+    /// "pagefile.sys", the main paging file.
+    Pagefile,
+    /// "swapfile.sys", which is used for swapping out entire modern (UWP) apps since Windows 8.
+    Swapfile,
+}
+
+impl NtfsMemoryFile {
+    /// Returns the name of this file in the root directory.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Hiberfil => "hiberfil.sys",
+            Self::Pagefile => "pagefile.sys",
+            Self::Swapfile => "swapfile.sys",
+        }
+    }
+}
+
+/// A continuous cluster range of a non-resident attribute value, as part of an [`NtfsExtentMap`].
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct NtfsExtent {
+    vcn: Vcn,
+    lcn: Option<Lcn>,
+    cluster_count: u64,
+}
+
+impl NtfsExtent {
+    /// Returns the number of clusters of this extent.
+    pub fn cluster_count(&self) -> u64 {
+        self.cluster_count
+    }
+
+    /// Returns `true` if this extent is "sparse", i.e. it has no clusters allocated on disk and reads as zeros.
+    pub fn is_sparse(&self) -> bool {
+        self.lcn.is_none()
+    }
+
+    /// Returns the Logical Cluster Number (LCN) of the first cluster of this extent on disk.
+    ///
+    /// This is `None` for a "sparse" extent.
+    pub fn lcn(&self) -> Option<Lcn> {
+        self.lcn
+    }
+
+    /// Returns the Virtual Cluster Number (VCN) of the first cluster of this extent within the
+    /// attribute value.
+    pub fn vcn(&self) -> Vcn {
+        self.vcn
+    }
+}
+
+#[cfg(feature = "serde")]
+#[cfg_attr(docsrs, doc(cfg(feature = "serde")))]
+impl Serialize for NtfsExtent {
+    fn serialize<S>(&self, serializer: S) -> core::result::Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut state = serializer.serialize_struct("NtfsExtent", 3)?;
+        state.serialize_field("vcn", &self.vcn)?;
+        state.serialize_field("lcn", &self.lcn)?;
+        state.serialize_field("cluster_count", &self.cluster_count)?;
+        state.end()
+    }
+}
+
+/// Sizes and on-disk cluster ranges of an entire attribute value, even if it is split over
+/// connected attributes of an Attribute List.
+///
+/// This is returned from [`NtfsAttribute::extent_map`] and [`Ntfs::memory_file_extents`].
+/// Only the Data Run headers are read to build it, the value data itself is never touched.
+/// A resident value has no extents.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct NtfsExtentMap {
+    allocated_size: u64,
+    data_size: u64,
+    extents: Vec<NtfsExtent>,
+}
+
+impl NtfsExtentMap {
+    pub(crate) fn new<T>(ntfs: &Ntfs, attribute: &NtfsAttribute, fs: &mut T) -> Result<Self>
+    where
+        T: Read + Seek,
+    {
+        let data_size = attribute.value_length();
+
+        if attribute.is_resident() {
+            return Ok(Self {
+                allocated_size: data_size,
+                data_size,
+                extents: Vec::new(),
+            });
+        }
+
+        let mut extents = Vec::new();
+
+        if let Some(list_entries) = attribute.list_entries() {
+            let mut connected_entries = AttributeListConnectedEntries::new(
+                list_entries.clone(),
+                attribute.instance(),
+                attribute.raw_ty(),
+            );
+
+            while let Some(entry) = connected_entries.next(fs) {
+                let entry = entry?;
+                let file = entry.to_file(ntfs, fs)?;
+                let connected_attribute = entry.to_attribute(&file)?;
+
+                if connected_attribute.is_resident() {
+                    return Err(NtfsError::UnexpectedResidentAttribute {
+                        position: connected_attribute.position(),
+                    });
+                }
+
+                add_extents(ntfs, &connected_attribute, &mut extents)?;
+            }
+        } else {
+            add_extents(ntfs, attribute, &mut extents)?;
+        }
+
+        Ok(Self {
+            allocated_size: attribute.non_resident_value_allocated_size(),
+            data_size,
+            extents,
+        })
+    }
+
+    /// Returns the allocated size of the attribute value, in bytes.
+    pub fn allocated_size(&self) -> u64 {
+        self.allocated_size
+    }
+
+    /// Returns the size of the attribute value, in bytes.
+    pub fn data_size(&self) -> u64 {
+        self.data_size
+    }
+
+    /// Returns all extents of the attribute value in VCN order.
+    pub fn extents(&self) -> &[NtfsExtent] {
+        &self.extents
+    }
+}
+
+#[cfg(feature = "serde")]
+#[cfg_attr(docsrs, doc(cfg(feature = "serde")))]
+impl Serialize for NtfsExtentMap {
+    fn serialize<S>(&self, serializer: S) -> core::result::Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut state = serializer.serialize_struct("NtfsExtentMap", 3)?;
+        state.serialize_field("allocated_size", &self.allocated_size)?;
+        state.serialize_field("data_size", &self.data_size)?;
+        state.serialize_field("extents", &self.extents)?;
+        state.end()
+    }
+}
+
+fn add_extents(
+    ntfs: &Ntfs,
+    attribute: &NtfsAttribute,
+    extents: &mut Vec<NtfsExtent>,
+) -> Result<()> {
+    let cluster_size = ntfs.cluster_size() as u64;
+    let (data, position) = attribute.non_resident_value_data_and_position()?;
+    let mut vcn = attribute.non_resident_value_lowest_vcn();
+
+    for data_run in NtfsDataRuns::new(ntfs, data, position) {
+        let data_run = data_run?;
+        let lcn = data_run
+            .data_position()
+            .value()
+            .map(|position| Lcn::from(position.get() / cluster_size));
+        let cluster_count = data_run.allocated_size() / cluster_size;
+
+        extents.push(NtfsExtent {
+            vcn,
+            lcn,
+            cluster_count,
+        });
+
+        vcn = Vcn::from(vcn.value() + cluster_count as i64);
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::upcase_table::NtfsCaseSensitivity;
+
+    #[test]
+    fn test_extent_map() {
+        let mut testfs1 = crate::helpers::tests::testfs1();
+        let mut ntfs = Ntfs::new(&mut testfs1).unwrap();
+        ntfs.read_upcase_table(&mut testfs1).unwrap();
+
+        let file = ntfs
+            .file_by_path(
+                &mut testfs1,
+                "sparse-file",
+                NtfsCaseSensitivity::Insensitive,
+            )
+            .unwrap()
+            .unwrap();
+        let data_item = file.data(&mut testfs1, "").unwrap().unwrap();
+        let data_attribute = data_item.to_attribute().unwrap();
+        let extent_map = data_attribute.extent_map(&mut testfs1).unwrap();
+        assert_eq!(extent_map.data_size(), 500005);
+        assert_eq!(extent_map.allocated_size() % 512, 0);
+
+        // The first extent has data, the second one is sparse, the third one has data again.
+        let extents = extent_map.extents();
+        assert_eq!(extents.len(), 3);
+        assert!(!extents[0].is_sparse());
+        assert!(extents[1].is_sparse());
+        assert!(!extents[2].is_sparse());
+        assert_eq!(extents[0].vcn(), Vcn::from(0));
+        assert_eq!(
+            extents[2].vcn().value(),
+            (extents[0].cluster_count() + extents[1].cluster_count()) as i64
+        );
+
+        let cluster_count = extents.iter().map(|e| e.cluster_count()).sum::<u64>();
+        assert_eq!(cluster_count * 512, extent_map.allocated_size());
+
+        // A resident value has no extents.
+        let file = ntfs
+            .file_by_path(
+                &mut testfs1,
+                "file-with-12345",
+                NtfsCaseSensitivity::Insensitive,
+            )
+            .unwrap()
+            .unwrap();
+        let data_item = file.data(&mut testfs1, "").unwrap().unwrap();
+        let data_attribute = data_item.to_attribute().unwrap();
+        let extent_map = data_attribute.extent_map(&mut testfs1).unwrap();
+        assert_eq!(extent_map.data_size(), 5);
+        assert!(extent_map.extents().is_empty());
+
+        // testfs1 has never been used as a Windows system volume.
+        for memory_file in [
+            NtfsMemoryFile::Hiberfil,
+            NtfsMemoryFile::Pagefile,
+            NtfsMemoryFile::Swapfile,
+        ] {
+            assert!(ntfs
+                .memory_file_extents(&mut testfs1, memory_file)
+                .is_none());
+        }
+    }
+
+    #[test]
+    fn test_memory_file_extents() {
+        // testfs2 has a (tiny) "pagefile.sys" full of "P" characters.
+        let mut testfs2 = crate::helpers::tests::testfs2();
+        let mut ntfs = Ntfs::new(&mut testfs2).unwrap();
+        ntfs.read_upcase_table(&mut testfs2).unwrap();
+        let cluster_size = ntfs.cluster_size() as u64;
+
+        let extent_map = ntfs
+            .memory_file_extents(&mut testfs2, NtfsMemoryFile::Pagefile)
+            .unwrap()
+            .unwrap();
+        assert_eq!(extent_map.data_size(), 20000);
+        assert_eq!(extent_map.allocated_size(), 5 * cluster_size);
+
+        let extents = extent_map.extents();
+        assert!(!extents.is_empty());
+        assert_eq!(extents[0].vcn(), Vcn::from(0));
+
+        // Reading the clusters of all extents must yield the file contents.
+        let mut contents = Vec::new();
+        let mut vcn = 0;
+
+        for extent in extents {
+            assert_eq!(extent.vcn(), Vcn::from(vcn));
+            let lcn = extent.lcn().unwrap();
+            let mut buf = vec![0u8; (extent.cluster_count() * cluster_size) as usize];
+            ntfs.read_clusters(&mut testfs2, lcn, extent.cluster_count(), &mut buf)
+                .unwrap();
+            contents.extend_from_slice(&buf);
+            vcn += extent.cluster_count() as i64;
+        }
+
+        assert_eq!(contents.len() as u64, extent_map.allocated_size());
+        assert!(contents[..20000].iter().all(|byte| *byte == b'P'));
+
+        assert!(ntfs
+            .memory_file_extents(&mut testfs2, NtfsMemoryFile::Hiberfil)
+            .is_none());
+    }
+}
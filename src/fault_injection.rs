@@ -0,0 +1,276 @@
+// Copyright 2021-2023 Colin Finck <colin@reactos.org>
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+use binrw::io;
+use binrw::io::{Read, Seek, SeekFrom, Write};
+
+/// What happens to the write selected by [`NtfsFaultInjector::with_fault`].
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum NtfsFault {
+    /// Nothing of the write reaches the underlying writer.
+    Fail,
+    /// Only the given number of leading bytes of the write reach the underlying writer,
+    /// like a write torn by a power loss.
+    Truncate(usize),
+}
+
+/// Test-oriented writer that simulates a crash at a configurable point of a write operation.
+///
+/// All functions of this crate that write to the filesystem may issue many separate writes.
+/// `NtfsFaultInjector` wraps the filesystem writer, counts these writes, and injects an [`NtfsFault`]
+/// into the write with the given index (starting at 0).
+/// That write returns an I/O error, and so does every further write, just like a device that has lost
+/// power.
+/// Reads and seeks keep working and always see what actually reached the underlying writer.
+///
+/// This makes it possible to verify that an operation leaves the filesystem in an acceptable state no matter
+/// where it is interrupted:
+/// Run the operation once without a fault to learn its [`NtfsFaultInjector::write_count`],
+/// then run it again on a fresh copy of the filesystem for every write index,
+/// mount the result via [`Ntfs::new`], and check your invariants (e.g. via [`Ntfs::check`]).
+///
+/// [`Ntfs::check`]: crate::Ntfs::check
+/// [`Ntfs::new`]: crate::Ntfs::new
+#[derive(Clone, Debug)]
+pub struct NtfsFaultInjector<T> {
+    inner: T,
+    fault: Option<(u64, NtfsFault)>,
+    write_count: u64,
+    crashed: bool,
+}
+
+impl<T> NtfsFaultInjector<T>
+where
+    T: Read + Seek + Write,
+{
+    /// Creates a new `NtfsFaultInjector` that passes all writes through and only counts them.
+    pub fn new(inner: T) -> Self {
+        Self {
+            inner,
+            fault: None,
+            write_count: 0,
+            crashed: false,
+        }
+    }
+
+    /// Injects `fault` into the write with index `write_index` (starting at 0) and fails all later writes.
+    pub fn with_fault(mut self, write_index: u64, fault: NtfsFault) -> Self {
+        self.fault = Some((write_index, fault));
+        self
+    }
+
+    /// Returns `true` if the fault has been injected.
+    pub fn crashed(&self) -> bool {
+        self.crashed
+    }
+
+    /// Returns a reference to the underlying writer.
+    pub fn get_ref(&self) -> &T {
+        &self.inner
+    }
+
+    /// Returns a mutable reference to the underlying writer.
+    pub fn get_mut(&mut self) -> &mut T {
+        &mut self.inner
+    }
+
+    /// Returns the underlying writer.
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+
+    /// Returns the number of writes that have been issued so far, including the faulted one and those
+    /// rejected after it.
+    pub fn write_count(&self) -> u64 {
+        self.write_count
+    }
+
+    fn crash_error() -> io::Error {
+        io::Error::new(io::ErrorKind::Other, "injected crash")
+    }
+}
+
+impl<T> Read for NtfsFaultInjector<T>
+where
+    T: Read + Seek + Write,
+{
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.inner.read(buf)
+    }
+}
+
+impl<T> Seek for NtfsFaultInjector<T>
+where
+    T: Read + Seek + Write,
+{
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        self.inner.seek(pos)
+    }
+}
+
+impl<T> Write for NtfsFaultInjector<T>
+where
+    T: Read + Seek + Write,
+{
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let write_index = self.write_count;
+        self.write_count += 1;
+
+        if self.crashed {
+            return Err(Self::crash_error());
+        }
+
+        match self.fault {
+            Some((fault_index, fault)) if fault_index == write_index => {
+                self.crashed = true;
+
+                if let NtfsFault::Truncate(bytes) = fault {
+                    self.inner.write_all(&buf[..bytes.min(buf.len())])?;
+                }
+
+                Err(Self::crash_error())
+            }
+            _ => self.inner.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        if self.crashed {
+            return Err(Self::crash_error());
+        }
+
+        self.inner.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec::Vec;
+
+    use super::*;
+    use crate::check::NtfsCheckFinding;
+    use crate::error::{NtfsError, Result};
+    use crate::file::KnownNtfsFileRecordNumber;
+    use crate::ntfs::Ntfs;
+    use crate::time::NtfsTime;
+
+    type TestFs = binrw::io::Cursor<Vec<u8>>;
+
+    /// Runs `operation` on a fresh testfs1 for every possible crash point and passes each crashed
+    /// filesystem to `check`.
+    ///
+    /// Every write is failed entirely, torn after its first sector (disks write single sectors
+    /// atomically), and completed right before the crash.
+    fn for_each_crash<O, C>(mut operation: O, mut check: C)
+    where
+        O: FnMut(&mut NtfsFaultInjector<&mut TestFs>) -> Result<()>,
+        C: FnMut(&mut TestFs),
+    {
+        let mut testfs1 = crate::helpers::tests::testfs1();
+        let mut writer = NtfsFaultInjector::new(&mut testfs1);
+        operation(&mut writer).unwrap();
+        let write_count = writer.write_count();
+        assert!(write_count > 0);
+
+        for write_index in 0..write_count {
+            for fault in [
+                NtfsFault::Fail,
+                NtfsFault::Truncate(512),
+                NtfsFault::Truncate(usize::MAX),
+            ] {
+                let mut testfs1 = crate::helpers::tests::testfs1();
+                let mut writer =
+                    NtfsFaultInjector::new(&mut testfs1).with_fault(write_index, fault);
+                assert!(matches!(operation(&mut writer), Err(NtfsError::Io(_))));
+                check(&mut testfs1);
+            }
+        }
+    }
+
+    #[test]
+    fn test_fault_injector() {
+        let mut testfs1 = crate::helpers::tests::testfs1();
+        let data = testfs1.get_ref().clone();
+
+        let mut writer = NtfsFaultInjector::new(&mut testfs1).with_fault(1, NtfsFault::Truncate(3));
+        writer.write_all(&[1; 4]).unwrap();
+        assert!(!writer.crashed());
+        assert!(writer.write_all(&[2; 4]).is_err());
+        assert!(writer.crashed());
+        assert!(writer.write_all(&[3; 4]).is_err());
+        assert_eq!(writer.write_count(), 3);
+
+        // Reads see what has actually been written.
+        let mut buf = [0u8; 8];
+        writer.seek(SeekFrom::Start(0)).unwrap();
+        writer.read_exact(&mut buf).unwrap();
+        assert_eq!(buf, [1, 1, 1, 1, 2, 2, 2, data[7]]);
+    }
+
+    #[test]
+    fn test_crash_write_standard_information() {
+        let time = NtfsTime::from(132_000_000_000_000_000);
+        let file_record_number = KnownNtfsFileRecordNumber::RootDirectory as u64;
+
+        let write_info = |fs: &mut NtfsFaultInjector<&mut TestFs>| {
+            let ntfs = Ntfs::new(fs)?;
+            let mut file = ntfs.file(fs, file_record_number)?;
+            let mut info = file.info()?;
+            info.set_modification_time(time);
+            file.write_standard_information(fs, &info)
+        };
+
+        // The File Record has either been written completely, not at all, or partially, which is
+        // detected by its fixups.
+        let mut outcomes = [0; 3];
+        for_each_crash(write_info, |fs| {
+            let ntfs = Ntfs::new(fs).unwrap();
+            match ntfs.file(fs, file_record_number) {
+                Ok(file) if file.info().unwrap().modification_time() == time => outcomes[0] += 1,
+                Ok(_) => outcomes[1] += 1,
+                Err(NtfsError::UpdateSequenceNumberMismatch { .. }) => outcomes[2] += 1,
+                Err(e) => panic!("unexpected error {e:?}"),
+            }
+        });
+        assert!(outcomes.iter().all(|count| *count > 0), "{outcomes:?}");
+    }
+
+    #[test]
+    fn test_crash_resize() {
+        let resize = |fs: &mut NtfsFaultInjector<&mut TestFs>| {
+            let mut ntfs = Ntfs::new(fs)?;
+            ntfs.resize(fs, 3546)
+        };
+
+        // $Bitmap, $BadClus, and the boot sectors are written in that order without any journal,
+        // so a crash leaves the volume at either size.
+        // It must always remain consistent though, except for torn File Records caught by their fixups.
+        let mut sizes = Vec::new();
+        for_each_crash(resize, |fs| {
+            let ntfs = Ntfs::new(fs).unwrap();
+            sizes.push(ntfs.size());
+
+            match ntfs.check(fs) {
+                Ok(report) => assert!(
+                    report.is_consistent()
+                        || report.findings().iter().any(|finding| matches!(
+                            finding,
+                            NtfsCheckFinding::UnreadableFileRecord {
+                                error: NtfsError::UpdateSequenceNumberMismatch { .. },
+                                ..
+                            }
+                        )),
+                    "{:?}",
+                    report.findings()
+                ),
+                Err(NtfsError::UpdateSequenceNumberMismatch { .. }) => (),
+                Err(e) => panic!("unexpected error {e:?}"),
+            }
+        });
+
+        assert!(sizes
+            .iter()
+            .all(|size| [4095 * 512, 3546 * 512].contains(size)));
+        assert!(sizes.contains(&(4095 * 512)) && sizes.contains(&(3546 * 512)));
+    }
+}
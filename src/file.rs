@@ -3,10 +3,10 @@
 
 use core::cmp::Ordering;
 use core::fmt;
-use core::num::NonZeroU64;
 
 use alloc::vec;
-use binrw::io::{Read, Seek, SeekFrom};
+use alloc::vec::Vec;
+use binrw::io::{Read, Seek, SeekFrom, Write};
 use bitflags::bitflags;
 use byteorder::{ByteOrder, LittleEndian};
 use memoffset::offset_of;
@@ -15,18 +15,23 @@ use nt_string::u16strle::U16StrLe;
 use crate::attribute::{
     NtfsAttribute, NtfsAttributeItem, NtfsAttributeType, NtfsAttributes, NtfsAttributesRaw,
 };
+use crate::duplicated_sizes::{size_mismatches, NtfsSizeMismatch};
 use crate::error::{NtfsError, Result};
 use crate::file_reference::NtfsFileReference;
 use crate::index::NtfsIndex;
-use crate::indexes::NtfsFileNameIndex;
+use crate::indexes::{NtfsFileNameIndex, NtfsIndexEntryType};
+use crate::mft_data::NtfsMftData;
 use crate::ntfs::Ntfs;
+use crate::options::NtfsUnsupportedPolicy;
+use crate::progress::{NtfsNoProgress, NtfsProgressSink};
 use crate::record::{Record, RecordHeader};
 use crate::structured_values::{
-    NtfsFileName, NtfsFileNamespace, NtfsIndexRoot, NtfsStandardInformation,
+    NtfsFileName, NtfsFileNamespace, NtfsIndexRoot, NtfsReparsePoint, NtfsStandardInformation,
     NtfsStructuredValueFromResidentAttributeValue,
 };
+use crate::sync::NtfsSyncReport;
 use crate::types::NtfsPosition;
-use crate::upcase_table::UpcaseOrd;
+use crate::upcase_table::{NtfsCaseSensitivity, UpcaseOrd};
 
 /// A list of standardized NTFS File Record Numbers.
 ///
@@ -112,6 +117,18 @@ impl fmt::Display for NtfsFileFlags {
     }
 }
 
+/// Serializes the raw bits of these flags.
+#[cfg(feature = "serde")]
+#[cfg_attr(docsrs, doc(cfg(feature = "serde")))]
+impl serde::Serialize for NtfsFileFlags {
+    fn serialize<S>(&self, serializer: S) -> core::result::Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serde::Serialize::serialize(&self.bits(), serializer)
+    }
+}
+
 /// A single NTFS File Record.
 ///
 /// These records are denoted via a `FILE` signature on the filesystem.
@@ -134,17 +151,27 @@ impl<'n> NtfsFile<'n> {
     pub(crate) fn new<T>(
         ntfs: &'n Ntfs,
         fs: &mut T,
-        position: NonZeroU64,
+        position: u64,
         file_record_number: u64,
     ) -> Result<Self>
     where
         T: Read + Seek,
     {
         let mut data = vec![0; ntfs.file_record_size() as usize];
-        fs.seek(SeekFrom::Start(position.get()))?;
+        fs.seek(SeekFrom::Start(position))?;
         fs.read_exact(&mut data)?;
 
-        let mut record = Record::new(data, position.into());
+        Self::from_data(ntfs, data, NtfsPosition::new(position), file_record_number)
+    }
+
+    /// Creates an [`NtfsFile`] from the raw (not yet fixed up) File Record data read from `position`.
+    pub(crate) fn from_data(
+        ntfs: &'n Ntfs,
+        data: Vec<u8>,
+        position: NtfsPosition,
+        file_record_number: u64,
+    ) -> Result<Self> {
+        let mut record = Record::new(data, position, ntfs.sector_size());
         Self::validate_signature(&record)?;
         record.fixup()?;
 
@@ -191,6 +218,35 @@ impl<'n> NtfsFile<'n> {
         NtfsAttributesRaw::new(self)
     }
 
+    /// Returns an [`NtfsFileReference`] to the base File Record if this is an extension File Record.
+    ///
+    /// NTFS stores the attributes of a file in additional extension File Records if they don't fit into
+    /// a single one.
+    /// For the base File Record itself, this reference is zero.
+    pub fn base_file_record(&self) -> NtfsFileReference {
+        let start = offset_of!(FileRecordHeader, base_file_record);
+        NtfsFileReference::new(self.record.data()[start..start + 8].try_into().unwrap())
+    }
+
+    /// Returns the case sensitivity of file name lookups in this directory.
+    ///
+    /// Since Windows 10, a directory can be marked case-sensitive (e.g. via
+    /// `fsutil file setCaseSensitiveInfo` or by WSL).
+    /// This is a flag of its $STANDARD_INFORMATION attribute
+    /// (see [`NtfsStandardInformation::is_case_sensitive_directory`]), and
+    /// this function returns [`NtfsCaseSensitivity::Sensitive`] for such a directory.
+    /// All other files and directories return [`NtfsCaseSensitivity::Insensitive`].
+    ///
+    /// As $STANDARD_INFORMATION is always stored in the base File Record, this doesn't need any further I/O.
+    /// [`Ntfs::file_by_path`] honors this flag automatically when passing [`NtfsCaseSensitivity::PerDirectory`].
+    pub fn case_sensitivity(&self) -> Result<NtfsCaseSensitivity> {
+        if self.is_directory() && self.info()?.is_case_sensitive_directory() {
+            Ok(NtfsCaseSensitivity::Sensitive)
+        } else {
+            Ok(NtfsCaseSensitivity::Insensitive)
+        }
+    }
+
     /// Convenience function to get a $DATA attribute of this file.
     ///
     /// As NTFS supports multiple data streams per file, you can specify the name of the $DATA attribute
@@ -228,8 +284,7 @@ impl<'n> NtfsFile<'n> {
             let item = iter_try!(item);
             let attribute = iter_try!(item.to_attribute());
 
-            let ty = iter_try!(attribute.ty());
-            if ty != NtfsAttributeType::Data {
+            if !iter_try!(attribute.is_ty(NtfsAttributeType::Data)) {
                 continue;
             }
 
@@ -274,26 +329,7 @@ impl<'n> NtfsFile<'n> {
         }
 
         // A File Record may contain multiple indexes, so we have to match the name of the directory index.
-        let directory_index_name = "$I30";
-
-        // The IndexRoot attribute is always resident and has to exist for every directory.
-        let index_root_item =
-            self.find_attribute(fs, NtfsAttributeType::IndexRoot, Some(directory_index_name))?;
-        let index_root_attribute = index_root_item.to_attribute()?;
-        let index_root = index_root_attribute.resident_structured_value::<NtfsIndexRoot>()?;
-
-        // The IndexAllocation attribute is only required for "large" indexes.
-        // It is always non-resident and may even be in an Attribute List.
-        let mut index_allocation_item = None;
-        if index_root.is_large_index() {
-            index_allocation_item = Some(self.find_attribute(
-                fs,
-                NtfsAttributeType::IndexAllocation,
-                Some(directory_index_name),
-            )?);
-        }
-
-        NtfsIndex::<NtfsFileNameIndex>::new(index_root_item, index_allocation_item)
+        self.index(fs, "$I30")
     }
 
     /// Returns the NTFS File Record Number of this file.
@@ -323,7 +359,7 @@ impl<'n> NtfsFile<'n> {
             let item = item?;
             let attribute = item.to_attribute()?;
 
-            if attribute.ty()? != ty {
+            if !attribute.is_ty(ty)? {
                 continue;
             }
 
@@ -359,7 +395,7 @@ impl<'n> NtfsFile<'n> {
         for attribute in self.attributes_raw() {
             let attribute = attribute?;
 
-            if attribute.ty()? != ty {
+            if !attribute.is_ty(ty)? {
                 continue;
             }
 
@@ -418,6 +454,40 @@ impl<'n> NtfsFile<'n> {
         LittleEndian::read_u16(&self.record.data()[start..])
     }
 
+    /// Returns the [`NtfsIndex`] of the given name, whose entries are of the [`NtfsIndexEntryType`] `E`.
+    ///
+    /// A File Record may contain multiple indexes, which are distinguished by the name of their
+    /// $INDEX_ROOT and $INDEX_ALLOCATION attributes.
+    /// Apart from directories (whose index is called "$I30" and more conveniently returned by
+    /// [`NtfsFile::directory_index`]), NTFS uses indexes for housekeeping information, like the
+    /// "$SII" and "$SDH" indexes of the $Secure file.
+    ///
+    /// [`NtfsIndexEntryType`]: crate::indexes::NtfsIndexEntryType
+    pub fn index<'f, E, T>(&'f self, fs: &mut T, index_name: &str) -> Result<NtfsIndex<'n, 'f, E>>
+    where
+        E: NtfsIndexEntryType,
+        T: Read + Seek,
+    {
+        // The IndexRoot attribute is always resident and has to exist for every index.
+        let index_root_item =
+            self.find_attribute(fs, NtfsAttributeType::IndexRoot, Some(index_name))?;
+        let index_root_attribute = index_root_item.to_attribute()?;
+        let index_root = index_root_attribute.resident_structured_value::<NtfsIndexRoot>()?;
+
+        // The IndexAllocation attribute is only required for "large" indexes.
+        // It is always non-resident and may even be in an Attribute List.
+        let mut index_allocation_item = None;
+        if index_root.is_large_index() {
+            index_allocation_item = Some(self.find_attribute(
+                fs,
+                NtfsAttributeType::IndexAllocation,
+                Some(index_name),
+            )?);
+        }
+
+        NtfsIndex::<E>::new(index_root_item, index_allocation_item)
+    }
+
     /// Convenience function to get the $STANDARD_INFORMATION attribute of this file
     /// (see [`NtfsStandardInformation`]).
     ///
@@ -438,8 +508,8 @@ impl<'n> NtfsFile<'n> {
     /// Files with hard links have further $FILE_NAME attributes for each directory they are in.
     /// You may optionally filter for a namespace and parent directory via the parameters.
     ///
-    /// This internally calls [`NtfsFile::attributes`] to iterate through the file's
-    /// attributes and pick up the first matching $FILE_NAME attribute.
+    /// This internally calls [`NtfsFile::names`] to iterate through the file's
+    /// $FILE_NAME attributes and pick up the first matching one.
     pub fn name<T>(
         &self,
         fs: &mut T,
@@ -449,21 +519,13 @@ impl<'n> NtfsFile<'n> {
     where
         T: Read + Seek,
     {
-        let mut iter = self.attributes();
+        let mut iter = self.names();
 
-        while let Some(item) = iter.next(fs) {
-            let item = iter_try!(item);
-            let attribute = iter_try!(item.to_attribute());
-
-            let ty = iter_try!(attribute.ty());
-            if ty != NtfsAttributeType::FileName {
-                continue;
-            }
-
-            let file_name = iter_try!(attribute.structured_value::<_, NtfsFileName>(fs));
+        while let Some(file_name) = iter.next(fs) {
+            let file_name = iter_try!(file_name);
 
             if let Some(namespace) = match_namespace {
-                if file_name.namespace() != namespace {
+                if file_name.namespace_value() != namespace as u8 {
                     continue;
                 }
             }
@@ -482,6 +544,23 @@ impl<'n> NtfsFile<'n> {
         None
     }
 
+    /// Returns an iterator over all $FILE_NAME attributes of this file (see [`NtfsFileName`]).
+    ///
+    /// NTFS creates a $FILE_NAME attribute for every hard link to a file, and each of them
+    /// stores the name and the parent directory of that link.
+    /// Together with [`NtfsFile::hard_link_count`], this lets you reconstruct all hard links of a file.
+    /// Note that a single hard link may have two $FILE_NAME attributes, one in the
+    /// [`NtfsFileNamespace::Win32`] and one in the [`NtfsFileNamespace::Dos`] namespace.
+    ///
+    /// This internally uses [`NtfsFile::attributes`] and therefore also finds $FILE_NAME
+    /// attributes in extension File Records referenced by an Attribute List.
+    pub fn names<'f>(&'f self) -> NtfsFileNames<'n, 'f> {
+        NtfsFileNames {
+            attributes: self.attributes(),
+            namespace_policy: self.ntfs.options().file_namespace_policy(),
+        }
+    }
+
     /// Returns the [`Ntfs`] object reference associated to this file.
     pub fn ntfs(&self) -> &'n Ntfs {
         self.ntfs
@@ -496,6 +575,80 @@ impl<'n> NtfsFile<'n> {
         self.record.data()
     }
 
+    pub(crate) fn record_data_mut(&mut self) -> &mut [u8] {
+        self.record.data_mut()
+    }
+
+    /// Convenience function to get the $REPARSE_POINT attribute of this file (see [`NtfsReparsePoint`]).
+    ///
+    /// Only reparse points (like symlinks and directory junctions) have this attribute, which is why
+    /// the return value is further encapsulated in an `Option`.
+    pub fn reparse_point<T>(&self, fs: &mut T) -> Option<Result<NtfsReparsePoint>>
+    where
+        T: Read + Seek,
+    {
+        let item = match self.find_attribute(fs, NtfsAttributeType::ReparsePoint, None) {
+            Ok(item) => item,
+            Err(NtfsError::AttributeNotFound { .. }) => return None,
+            Err(e) => return Some(Err(e)),
+        };
+        let attribute = iter_try!(item.to_attribute());
+
+        Some(attribute.structured_value::<_, NtfsReparsePoint>(fs))
+    }
+
+    /// Synchronizes the file times and data stream contents of this file in place onto the existing
+    /// file `destination` of another (writable) NTFS volume and returns an [`NtfsSyncReport`].
+    ///
+    /// This is not a full replication of the file:
+    /// It doesn't create files, names, or data streams, and it doesn't allocate space, so
+    /// `destination` must already have the same names and data streams of the same sizes.
+    /// Names are only compared, and the $EA, $SECURITY_DESCRIPTOR, $REPARSE_POINT, and $OBJECT_ID
+    /// attributes are not copied at all.
+    /// The synchronization then copies
+    ///   * the contents of every $DATA attribute (data stream) into the stream of the same name, and
+    ///   * the four file times and the "File Attributes" of the $STANDARD_INFORMATION attribute,
+    ///     but only if all names and data streams match.
+    ///
+    /// Names and data streams that don't exist on `destination`, data streams of a different size,
+    /// and data streams that cannot be overwritten in place (compressed, encrypted, sparse, or not fully
+    /// initialized ones) are skipped and listed in the report.
+    /// All destination streams are checked before anything is written.
+    /// Resident streams stored in extension File Records (via an $ATTRIBUTE_LIST) are written to
+    /// these File Records.
+    ///
+    /// Like [`NtfsFile::write_standard_information`], this doesn't update the $FILE_NAME attributes
+    /// and directory indexes, and it doesn't write to the $LogFile journal.
+    pub fn sync_to<S, T>(
+        &self,
+        src_fs: &mut S,
+        destination: &mut NtfsFile,
+        dst_fs: &mut T,
+    ) -> Result<NtfsSyncReport>
+    where
+        S: Read + Seek,
+        T: Read + Seek + Write,
+    {
+        self.sync_to_with_progress(src_fs, destination, dst_fs, &mut NtfsNoProgress)
+    }
+
+    /// Variant of [`NtfsFile::sync_to`] that reports the number of data stream bytes copied
+    /// to an [`NtfsProgressSink`], which may also cancel the synchronization.
+    pub fn sync_to_with_progress<S, T, P>(
+        &self,
+        src_fs: &mut S,
+        destination: &mut NtfsFile,
+        dst_fs: &mut T,
+        progress: &mut P,
+    ) -> Result<NtfsSyncReport>
+    where
+        S: Read + Seek,
+        T: Read + Seek + Write,
+        P: NtfsProgressSink + ?Sized,
+    {
+        NtfsSyncReport::new(self, src_fs, destination, dst_fs, progress)
+    }
+
     /// Returns the sequence number of this file.
     ///
     /// NTFS reuses records of deleted files when new files are created.
@@ -506,6 +659,22 @@ impl<'n> NtfsFile<'n> {
         LittleEndian::read_u16(&self.record.data()[start..])
     }
 
+    /// Compares the duplicated file sizes in the $FILE_NAME attributes and directory entries of
+    /// this file with the sizes of its unnamed $DATA attribute, and returns all mismatches
+    /// (see [`NtfsSizeMismatch`]).
+    ///
+    /// Directories and files without an unnamed $DATA attribute never have mismatches.
+    ///
+    /// # Panics
+    ///
+    /// Panics if [`read_upcase_table`][Ntfs::read_upcase_table] had not been called on the passed [`Ntfs`] object.
+    pub fn size_mismatches<T>(&self, fs: &mut T) -> Result<Vec<NtfsSizeMismatch>>
+    where
+        T: Read + Seek,
+    {
+        size_mismatches(self, fs)
+    }
+
     fn validate_signature(record: &Record) -> Result<()> {
         let signature = &record.signature();
         let expected = b"FILE";
@@ -540,4 +709,341 @@ impl<'n> NtfsFile<'n> {
 
         Ok(())
     }
+
+    /// Updates the four file times and the "File Attributes" of the resident $STANDARD_INFORMATION
+    /// attribute of this file from the given [`NtfsStandardInformation`] and writes the File Record
+    /// back to the filesystem.
+    ///
+    /// Get an [`NtfsStandardInformation`] via [`NtfsFile::info`] and change it via its setters first.
+    /// All other fields of the $STANDARD_INFORMATION attribute are left untouched.
+    /// The File Record is written back in place (with a new Update Sequence Number and regenerated
+    /// fixups), and this [`NtfsFile`] object reflects the changes afterwards.
+    ///
+    /// Note that this doesn't update the copies of the file times in the $FILE_NAME attributes and
+    /// directory indexes, and it doesn't write to the $LogFile journal.
+    pub fn write_standard_information<T>(
+        &mut self,
+        fs: &mut T,
+        info: &NtfsStandardInformation,
+    ) -> Result<()>
+    where
+        T: Read + Seek + Write,
+    {
+        let (range, position) = {
+            let attribute =
+                self.find_resident_attribute(NtfsAttributeType::StandardInformation, None, None)?;
+            (attribute.resident_value_range()?, attribute.position())
+        };
+
+        info.write_times_and_attributes(&mut self.record.data_mut()[range], position)?;
+        self.write_record(fs)
+    }
+
+    /// Writes this File Record back to its place in the Master File Table.
+    ///
+    /// File Records mirrored in $MFTMirr are written to both places.
+    pub(crate) fn write_record<T>(&mut self, fs: &mut T) -> Result<()>
+    where
+        T: Read + Seek + Write,
+    {
+        let offset = self
+            .file_record_number
+            .checked_mul(self.ntfs.file_record_size() as u64)
+            .ok_or(NtfsError::InvalidFileRecordNumber {
+                file_record_number: self.file_record_number,
+            })?;
+
+        // Only keep the incremented Update Sequence Number once the File Record has been written.
+        let (record, data) = self.record.protect()?;
+        self.ntfs.mft_data(fs)?.write_at(fs, offset, &data)?;
+        self.record = record;
+
+        // A standalone $MFT file (see `Ntfs::from_mft`) comes without the $MFTMirr data.
+        if !self.ntfs.is_standalone_mft() {
+            let mft_mirr_data = NtfsMftData::mirror(self.ntfs, fs)?;
+            if offset < mft_mirr_data.len() {
+                mft_mirr_data.write_at(fs, offset, &data)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Iterator over
+///   all $FILE_NAME attributes of an [`NtfsFile`],
+///   returning an [`NtfsFileName`] for each entry.
+///
+/// This iterator is returned from the [`NtfsFile::names`] function.
+#[derive(Clone, Debug)]
+pub struct NtfsFileNames<'n, 'f> {
+    attributes: NtfsAttributes<'n, 'f>,
+    namespace_policy: NtfsUnsupportedPolicy,
+}
+
+impl<'n, 'f> NtfsFileNames<'n, 'f> {
+    /// See [`Iterator::next`].
+    pub fn next<T>(&mut self, fs: &mut T) -> Option<Result<NtfsFileName>>
+    where
+        T: Read + Seek,
+    {
+        while let Some(item) = self.attributes.next(fs) {
+            let item = iter_try!(item);
+            let attribute = iter_try!(item.to_attribute());
+
+            if !iter_try!(attribute.is_ty(NtfsAttributeType::FileName)) {
+                continue;
+            }
+
+            let file_name = match attribute.structured_value::<_, NtfsFileName>(fs) {
+                Ok(file_name) => file_name,
+                Err(NtfsError::UnsupportedFileNamespace { .. })
+                    if self.namespace_policy == NtfsUnsupportedPolicy::Skip =>
+                {
+                    continue;
+                }
+                Err(NtfsError::UnsupportedFileNamespace { .. })
+                    if self.namespace_policy == NtfsUnsupportedPolicy::YieldUnknown =>
+                {
+                    iter_try!(NtfsFileName::from_attribute_with_unknown_namespace(
+                        fs, &attribute
+                    ))
+                }
+                Err(e) => return Some(Err(e)),
+            };
+
+            return Some(Ok(file_name));
+        }
+
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::structured_values::NtfsFileAttributeFlags;
+    use crate::time::NtfsTime;
+    use crate::traits::NtfsReadSeek;
+
+    #[test]
+    fn test_names() {
+        let mut testfs1 = crate::helpers::tests::testfs1();
+        let mut ntfs = Ntfs::new(&mut testfs1).unwrap();
+        ntfs.read_upcase_table(&mut testfs1).unwrap();
+
+        let file = ntfs
+            .file_by_path(
+                &mut testfs1,
+                "many_subdirs/1",
+                NtfsCaseSensitivity::Insensitive,
+            )
+            .unwrap()
+            .unwrap();
+        let parent = ntfs
+            .file_by_path(
+                &mut testfs1,
+                "many_subdirs",
+                NtfsCaseSensitivity::Insensitive,
+            )
+            .unwrap()
+            .unwrap();
+
+        let mut names = file.names();
+        let mut count = 0;
+
+        while let Some(file_name) = names.next(&mut testfs1) {
+            let file_name = file_name.unwrap();
+            assert_eq!(file_name.name(), "1");
+            assert_eq!(
+                file_name.parent_directory_reference().file_record_number(),
+                parent.file_record_number()
+            );
+            count += 1;
+        }
+
+        assert_eq!(count, file.hard_link_count());
+    }
+
+    #[test]
+    fn test_unsupported_policies() {
+        use crate::options::NtfsOptions;
+
+        let mut testfs1 = crate::helpers::tests::testfs1();
+        let mut ntfs = Ntfs::new(&mut testfs1).unwrap();
+        ntfs.read_upcase_table(&mut testfs1).unwrap();
+
+        // Find the positions of the $DATA attribute type and the $FILE_NAME namespace of "file-with-12345".
+        let file = ntfs
+            .file_by_path(
+                &mut testfs1,
+                "file-with-12345",
+                NtfsCaseSensitivity::Insensitive,
+            )
+            .unwrap()
+            .unwrap();
+        let file_record_number = file.file_record_number();
+        let data_attribute_item = file.data(&mut testfs1, "").unwrap().unwrap();
+        let data_attribute = data_attribute_item.to_attribute().unwrap();
+        let data_ty_position = data_attribute.position().value().unwrap().get() as usize;
+
+        // The namespace directly precedes the name.
+        let name = "file-with-12345"
+            .encode_utf16()
+            .flat_map(u16::to_le_bytes)
+            .collect::<Vec<u8>>();
+        let record_position = file.position().value().unwrap().get() as usize;
+        let record = &testfs1.get_ref()[record_position..];
+        let name_offset = record
+            .windows(name.len())
+            .position(|window| window == name)
+            .unwrap();
+        let namespace_position = record_position + name_offset - 1;
+
+        // Patch them to values unknown to this crate.
+        let data = testfs1.get_mut();
+        data[data_ty_position..data_ty_position + 4].copy_from_slice(&0x1234u32.to_le_bytes());
+        data[namespace_position] = 7;
+
+        let attribute_count = |ntfs: &Ntfs, testfs1: &mut _| {
+            let file = ntfs.file(testfs1, file_record_number).unwrap();
+            let mut attributes = file.attributes();
+            let mut count = 0;
+            while let Some(item) = attributes.next(testfs1) {
+                item.unwrap();
+                count += 1;
+            }
+            count
+        };
+
+        // The default policy returns errors when looking for attributes and file names.
+        let ntfs = Ntfs::new(&mut testfs1).unwrap();
+        let file = ntfs.file(&mut testfs1, file_record_number).unwrap();
+        assert!(matches!(
+            file.data(&mut testfs1, ""),
+            Some(Err(NtfsError::UnsupportedAttributeType {
+                actual: 0x1234,
+                ..
+            }))
+        ));
+        assert!(matches!(
+            file.name(&mut testfs1, None, None),
+            Some(Err(NtfsError::UnsupportedFileNamespace { actual: 7, .. }))
+        ));
+        let default_attribute_count = attribute_count(&ntfs, &mut testfs1);
+
+        // Skipping leaves out the unsupported attribute and file name.
+        let options = NtfsOptions::new()
+            .with_attribute_type_policy(NtfsUnsupportedPolicy::Skip)
+            .with_file_namespace_policy(NtfsUnsupportedPolicy::Skip);
+        let ntfs = Ntfs::new_with_options(&mut testfs1, options).unwrap();
+        let file = ntfs.file(&mut testfs1, file_record_number).unwrap();
+        assert!(file.data(&mut testfs1, "").is_none());
+        assert!(file.name(&mut testfs1, None, None).is_none());
+        assert_eq!(
+            attribute_count(&ntfs, &mut testfs1),
+            default_attribute_count - 1
+        );
+
+        // Yielding returns them without interpreting the unsupported values.
+        let options = NtfsOptions::new()
+            .with_attribute_type_policy(NtfsUnsupportedPolicy::YieldUnknown)
+            .with_file_namespace_policy(NtfsUnsupportedPolicy::YieldUnknown);
+        let ntfs = Ntfs::new_with_options(&mut testfs1, options).unwrap();
+        let file = ntfs.file(&mut testfs1, file_record_number).unwrap();
+        assert!(file.data(&mut testfs1, "").is_none());
+        assert_eq!(
+            attribute_count(&ntfs, &mut testfs1),
+            default_attribute_count
+        );
+
+        let file_name = file.name(&mut testfs1, None, None).unwrap().unwrap();
+        assert_eq!(file_name.name(), "file-with-12345");
+        assert_eq!(file_name.namespace(), None);
+        assert_eq!(file_name.namespace_value(), 7);
+        assert!(file
+            .name(&mut testfs1, Some(NtfsFileNamespace::Posix), None)
+            .is_none());
+    }
+
+    #[test]
+    fn test_write_standard_information() {
+        let mut testfs1 = crate::helpers::tests::testfs1();
+        let mut ntfs = Ntfs::new(&mut testfs1).unwrap();
+        ntfs.read_upcase_table(&mut testfs1).unwrap();
+
+        let mut file = ntfs
+            .file_by_path(
+                &mut testfs1,
+                "file-with-12345",
+                NtfsCaseSensitivity::Insensitive,
+            )
+            .unwrap()
+            .unwrap();
+        let file_record_number = file.file_record_number();
+        let original_info = file.info().unwrap();
+
+        let time = NtfsTime::from(132_000_000_000_000_000);
+        let mut info = original_info.clone();
+        info.set_creation_time(time);
+        info.set_modification_time(time);
+        info.set_mft_record_modification_time(time);
+        info.set_access_time(time);
+        info.set_file_attributes(NtfsFileAttributeFlags::HIDDEN | NtfsFileAttributeFlags::ARCHIVE);
+        file.write_standard_information(&mut testfs1, &info)
+            .unwrap();
+        assert_eq!(file.info().unwrap().creation_time(), time);
+
+        // Reading the File Record again proves that its fixups are valid.
+        let file = ntfs.file(&mut testfs1, file_record_number).unwrap();
+        let info = file.info().unwrap();
+        assert_eq!(info.creation_time(), time);
+        assert_eq!(info.modification_time(), time);
+        assert_eq!(info.mft_record_modification_time(), time);
+        assert_eq!(info.access_time(), time);
+        assert_eq!(
+            info.file_attributes(),
+            NtfsFileAttributeFlags::HIDDEN | NtfsFileAttributeFlags::ARCHIVE
+        );
+        assert_eq!(info.usn(), original_info.usn());
+
+        // The rest of the File Record is unchanged.
+        let file_name = file.name(&mut testfs1, None, None).unwrap().unwrap();
+        assert_eq!(file_name.name(), "file-with-12345");
+    }
+
+    #[test]
+    fn test_write_mirrored_file_record() {
+        let mut testfs1 = crate::helpers::tests::testfs1();
+        let ntfs = Ntfs::new(&mut testfs1).unwrap();
+        let file_record_size = ntfs.file_record_size() as usize;
+        let file_record_number = KnownNtfsFileRecordNumber::Volume as u64;
+
+        let mut volume = ntfs.file(&mut testfs1, file_record_number).unwrap();
+        let mut info = volume.info().unwrap();
+        info.set_access_time(NtfsTime::from(132_000_000_000_000_000));
+        volume
+            .write_standard_information(&mut testfs1, &info)
+            .unwrap();
+
+        // $MFTMirr has received the same bytes as the MFT.
+        let read_file_record = |data: &NtfsMftData, testfs1: &mut _| {
+            let mut value = data.value().unwrap();
+            let mut buf = vec![0u8; file_record_size];
+            value
+                .seek(
+                    testfs1,
+                    SeekFrom::Start(file_record_number * file_record_size as u64),
+                )
+                .unwrap();
+            value.read_exact(testfs1, &mut buf).unwrap();
+            buf
+        };
+        let mft_data = ntfs.mft_data(&mut testfs1).unwrap();
+        let mft_mirr_data = NtfsMftData::mirror(&ntfs, &mut testfs1).unwrap();
+        assert_eq!(
+            read_file_record(&mft_data, &mut testfs1),
+            read_file_record(&mft_mirr_data, &mut testfs1)
+        );
+    }
 }
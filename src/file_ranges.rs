@@ -0,0 +1,383 @@
+// Copyright 2021-2023 Colin Finck <colin@reactos.org>
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+use alloc::collections::BTreeSet;
+use alloc::vec::Vec;
+use binrw::io::{Read, Seek, SeekFrom};
+
+use crate::attribute::NtfsAttributeType;
+use crate::error::{NtfsError, Result};
+use crate::file::{NtfsFile, NtfsFileFlags};
+use crate::ntfs::Ntfs;
+use crate::structured_values::NtfsAttributeList;
+use crate::traits::NtfsReadSeek;
+
+/// A continuous range of bytes on the volume.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct NtfsByteRange {
+    position: u64,
+    length: u64,
+}
+
+impl NtfsByteRange {
+    /// Returns the length of this range, in bytes.
+    pub fn length(&self) -> u64 {
+        self.length
+    }
+
+    /// Returns the absolute byte position of the first byte of this range within the filesystem.
+    pub fn position(&self) -> u64 {
+        self.position
+    }
+}
+
+/// The on-disk byte ranges of a file that may have changed since the checkpoint, as part of
+/// [`NtfsFileRangesSinceUsn`].
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct NtfsFileRanges {
+    file_record_number: u64,
+    usn: Option<u64>,
+    ranges: Vec<NtfsByteRange>,
+}
+
+impl NtfsFileRanges {
+    /// Returns the File Record Number of the (base) File Record of this file.
+    pub fn file_record_number(&self) -> u64 {
+        self.file_record_number
+    }
+
+    /// Returns all byte ranges of this file on the volume in ascending order:
+    /// Its File Records and the allocated clusters of all its non-resident attributes.
+    ///
+    /// For a file that is no longer in use, this is just its File Record.
+    pub fn ranges(&self) -> &[NtfsByteRange] {
+        &self.ranges
+    }
+
+    /// Returns the Update Sequence Number (USN) stored in the $STANDARD_INFORMATION attribute of this
+    /// file, if any.
+    pub fn usn(&self) -> Option<u64> {
+        self.usn
+    }
+}
+
+/// On-disk byte ranges of those files of a given set that may have changed since a prior USN checkpoint,
+/// returned by [`Ntfs::file_ranges_since_usn`].
+///
+/// This is a building block for block-level incremental backups, but not a change tracker:
+/// The USN in $STANDARD_INFORMATION only tells whether a file has been modified, not which bytes have
+/// changed, so the ranges cover the File Records and all allocated clusters of a file.
+/// This crate also doesn't read the $UsnJrnl change journal.
+/// The caller has to collect the File Record Numbers of all files with journal records since the
+/// checkpoint and pass them here.
+/// Copying the [`merged_ranges`](NtfsFileRangesSinceUsn::merged_ranges) then covers every changed byte.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct NtfsFileRangesSinceUsn {
+    checkpoint_usn: u64,
+    files: Vec<NtfsFileRanges>,
+}
+
+impl NtfsFileRangesSinceUsn {
+    pub(crate) fn new<T>(
+        ntfs: &Ntfs,
+        fs: &mut T,
+        checkpoint_usn: u64,
+        file_record_numbers: &[u64],
+    ) -> Result<Self>
+    where
+        T: Read + Seek,
+    {
+        let mut base_file_record_numbers = BTreeSet::new();
+
+        for &file_record_number in file_record_numbers {
+            let file = ntfs.file(fs, file_record_number)?;
+
+            // Extension File Records are covered by their base File Record.
+            let base_file_record_number = match file.base_file_record().file_record_number() {
+                0 => file_record_number,
+                base_file_record_number => base_file_record_number,
+            };
+            base_file_record_numbers.insert(base_file_record_number);
+        }
+
+        let mut files = Vec::new();
+
+        for file_record_number in base_file_record_numbers {
+            let file = ntfs.file(fs, file_record_number)?;
+            let mut ranges = Vec::new();
+            add_file_record_ranges(ntfs, fs, file_record_number, &mut ranges)?;
+
+            // A deleted file has only changed its File Record, its clusters may already be reused.
+            if !file.flags().contains(NtfsFileFlags::IN_USE) {
+                files.push(NtfsFileRanges {
+                    file_record_number,
+                    usn: None,
+                    ranges,
+                });
+                continue;
+            }
+
+            // A USN of zero means that the file has never been journaled, so it may still have
+            // changed.
+            let usn = file.info()?.usn();
+            if matches!(usn, Some(usn) if usn != 0 && usn < checkpoint_usn) {
+                continue;
+            }
+
+            add_attribute_ranges(ntfs, fs, &file, &mut ranges)?;
+            merge_ranges(&mut ranges);
+
+            files.push(NtfsFileRanges {
+                file_record_number,
+                usn,
+                ranges,
+            });
+        }
+
+        Ok(Self {
+            checkpoint_usn,
+            files,
+        })
+    }
+
+    /// Returns the USN checkpoint this has been built for.
+    pub fn checkpoint_usn(&self) -> u64 {
+        self.checkpoint_usn
+    }
+
+    /// Returns the files that may have changed in ascending order of their File Record Numbers.
+    ///
+    /// Files whose USN shows that they haven't changed since the checkpoint are omitted.
+    pub fn files(&self) -> &[NtfsFileRanges] {
+        &self.files
+    }
+
+    /// Returns the byte ranges of all files that may have changed in ascending order, with overlapping and adjacent
+    /// ranges merged.
+    pub fn merged_ranges(&self) -> Vec<NtfsByteRange> {
+        let mut ranges = self
+            .files
+            .iter()
+            .flat_map(|file| file.ranges.iter().copied())
+            .collect::<Vec<_>>();
+        merge_ranges(&mut ranges);
+        ranges
+    }
+}
+
+/// Adds the byte ranges of the allocated clusters of all non-resident attributes of `file`, as well as
+/// the File Records holding its attributes.
+fn add_attribute_ranges<T>(
+    ntfs: &Ntfs,
+    fs: &mut T,
+    file: &NtfsFile,
+    ranges: &mut Vec<NtfsByteRange>,
+) -> Result<()>
+where
+    T: Read + Seek,
+{
+    let cluster_size = ntfs.cluster_size() as u64;
+    let mut extension_file_record_numbers = BTreeSet::new();
+
+    for attribute in file.attributes_raw() {
+        let attribute = attribute?;
+        if attribute.ty()? != NtfsAttributeType::AttributeList {
+            continue;
+        }
+
+        let attribute_list = attribute.structured_value::<_, NtfsAttributeList>(fs)?;
+        let mut entries = attribute_list.entries();
+        while let Some(entry) = entries.next(fs) {
+            let file_record_number = entry?.base_file_reference().file_record_number();
+            if file_record_number != file.file_record_number() {
+                extension_file_record_numbers.insert(file_record_number);
+            }
+        }
+    }
+
+    for file_record_number in extension_file_record_numbers {
+        add_file_record_ranges(ntfs, fs, file_record_number, ranges)?;
+    }
+
+    let mut attributes = file.attributes();
+    while let Some(item) = attributes.next(fs) {
+        let item = item?;
+        let attribute = item.to_attribute()?;
+        if attribute.is_resident() {
+            continue;
+        }
+
+        for extent in attribute.extent_map(fs)?.extents() {
+            if let Some(lcn) = extent.lcn() {
+                ranges.push(NtfsByteRange {
+                    position: lcn.value() * cluster_size,
+                    length: extent.cluster_count() * cluster_size,
+                });
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Adds the byte ranges of File Record `file_record_number` within the Master File Table.
+///
+/// A File Record may span multiple Data Runs of the MFT if the cluster size is smaller than the
+/// File Record size.
+fn add_file_record_ranges<T>(
+    ntfs: &Ntfs,
+    fs: &mut T,
+    file_record_number: u64,
+    ranges: &mut Vec<NtfsByteRange>,
+) -> Result<()>
+where
+    T: Read + Seek,
+{
+    let cluster_size = ntfs.cluster_size() as u64;
+    let file_record_size = ntfs.file_record_size() as u64;
+    let offset = file_record_number * file_record_size;
+
+    let mft_data = ntfs.mft_data(fs)?;
+    let mut value = mft_data.value()?;
+    let mut added = 0;
+
+    while added < file_record_size {
+        let chunk_offset = offset + added;
+        let chunk_length =
+            (cluster_size - chunk_offset % cluster_size).min(file_record_size - added);
+
+        value.seek(fs, SeekFrom::Start(chunk_offset))?;
+        let position = value
+            .data_position()
+            .value()
+            .ok_or(NtfsError::InvalidFileRecordNumber { file_record_number })?;
+
+        ranges.push(NtfsByteRange {
+            position: position.get(),
+            length: chunk_length,
+        });
+        added += chunk_length;
+    }
+
+    Ok(())
+}
+
+/// Sorts `ranges` and merges overlapping and adjacent ones.
+fn merge_ranges(ranges: &mut Vec<NtfsByteRange>) {
+    ranges.sort_unstable();
+
+    let mut merged = Vec::<NtfsByteRange>::with_capacity(ranges.len());
+    for range in ranges.drain(..) {
+        match merged.last_mut() {
+            Some(last) if last.position + last.length >= range.position => {
+                let end = (last.position + last.length).max(range.position + range.length);
+                last.length = end - last.position;
+            }
+            _ => merged.push(range),
+        }
+    }
+
+    *ranges = merged;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::file::KnownNtfsFileRecordNumber;
+    use crate::upcase_table::NtfsCaseSensitivity;
+
+    #[test]
+    fn test_file_ranges_since_usn() {
+        let mut testfs1 = crate::helpers::tests::testfs1();
+        let mut ntfs = Ntfs::new(&mut testfs1).unwrap();
+        ntfs.read_upcase_table(&mut testfs1).unwrap();
+
+        let file = ntfs
+            .file_by_path(
+                &mut testfs1,
+                "1000-bytes-file",
+                NtfsCaseSensitivity::Insensitive,
+            )
+            .unwrap()
+            .unwrap();
+        let file_record_number = file.file_record_number();
+        let file_record_position = file.position().value().unwrap().get();
+        let data_item = file.data(&mut testfs1, "").unwrap().unwrap();
+        let data_attribute = data_item.to_attribute().unwrap();
+        let extent = data_attribute.extent_map(&mut testfs1).unwrap().extents()[0];
+        let data_position = extent.lcn().unwrap().value() * ntfs.cluster_size() as u64;
+
+        // Files created without NTFS 3.x file information have no USN and always count as changed.
+        let changed = ntfs
+            .file_ranges_since_usn(&mut testfs1, 100, &[file_record_number])
+            .unwrap();
+        assert_eq!(changed.checkpoint_usn(), 100);
+        assert_eq!(changed.files().len(), 1);
+        assert_eq!(changed.files()[0].file_record_number(), file_record_number);
+        assert_eq!(changed.files()[0].usn(), None);
+
+        // The File Record and the Data Run are far apart.
+        let ranges = changed
+            .merged_ranges()
+            .iter()
+            .map(|range| (range.position(), range.length()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            ranges,
+            [(file_record_position, 1024), (data_position, 1024)]
+        );
+
+        // testfs1 has no change journal, so files with a USN have a USN of zero and count as changed.
+        let upcase_file_record_number = KnownNtfsFileRecordNumber::UpCase as u64;
+        let changed = ntfs
+            .file_ranges_since_usn(&mut testfs1, 100, &[upcase_file_record_number])
+            .unwrap();
+        assert_eq!(changed.files().len(), 1);
+        assert_eq!(changed.files()[0].usn(), Some(0));
+
+        // Set the USN of $UpCase to 50, right behind the first 64 bytes of its $STANDARD_INFORMATION.
+        let usn_position = {
+            let upcase_file = ntfs.file(&mut testfs1, upcase_file_record_number).unwrap();
+            let info_attribute = upcase_file
+                .attributes_raw()
+                .find(|attribute| {
+                    attribute.as_ref().unwrap().ty().unwrap()
+                        == NtfsAttributeType::StandardInformation
+                })
+                .unwrap()
+                .unwrap();
+            let value_range = info_attribute.resident_value_range().unwrap();
+            upcase_file.position().value().unwrap().get() as usize + value_range.start + 64
+        };
+        testfs1.get_mut()[usn_position..][..8].copy_from_slice(&50u64.to_le_bytes());
+
+        let changed = ntfs
+            .file_ranges_since_usn(&mut testfs1, 100, &[upcase_file_record_number])
+            .unwrap();
+        assert!(changed.files().is_empty());
+
+        let root_dir_file_record_number = KnownNtfsFileRecordNumber::RootDirectory as u64;
+        let changed = ntfs
+            .file_ranges_since_usn(
+                &mut testfs1,
+                50,
+                &[
+                    upcase_file_record_number,
+                    root_dir_file_record_number,
+                    upcase_file_record_number,
+                ],
+            )
+            .unwrap();
+        assert_eq!(changed.files().len(), 2);
+        assert_eq!(
+            changed.files()[0].file_record_number(),
+            root_dir_file_record_number
+        );
+        assert_eq!(changed.files()[1].usn(), Some(50));
+
+        // The root directory includes its Index Records.
+        let root_dir_ranges = changed.files()[0].ranges();
+        assert!(root_dir_ranges.len() > 1);
+        assert!(changed.merged_ranges().len() >= root_dir_ranges.len());
+    }
+}
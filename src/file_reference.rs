@@ -4,14 +4,17 @@
 use binrw::io::{Read, Seek};
 use binrw::BinRead;
 
-use crate::error::Result;
+use crate::error::{NtfsError, Result};
 use crate::file::NtfsFile;
 use crate::ntfs::Ntfs;
 
+#[cfg(feature = "serde")]
+use serde::ser::{Serialize, SerializeStruct, Serializer};
+
 /// Absolute reference to a File Record on the filesystem, composed out of a File Record Number and a Sequence Number.
 ///
 /// Reference: <https://flatcap.github.io/linux-ntfs/ntfs/concepts/file_reference.html>
-#[derive(BinRead, Clone, Copy, Debug)]
+#[derive(BinRead, Clone, Copy, Debug, Eq, Hash, PartialEq)]
 pub struct NtfsFileReference([u8; 8]);
 
 impl NtfsFileReference {
@@ -19,6 +22,10 @@ impl NtfsFileReference {
         Self(file_reference_bytes)
     }
 
+    pub(crate) const fn to_bytes(self) -> [u8; 8] {
+        self.0
+    }
+
     /// Returns the 48-bit File Record Number.
     ///
     /// This can be fed into [`Ntfs::file`] to create an [`NtfsFile`] object for the corresponding File Record
@@ -35,10 +42,84 @@ impl NtfsFileReference {
     }
 
     /// Returns an [`NtfsFile`] for the file referenced by this object.
+    ///
+    /// File Records are reused after a file has been deleted, and their sequence number is incremented then.
+    /// Therefore, this function returns [`NtfsError::StaleFileReference`] if the sequence number of the
+    /// File Record does not match the one of this reference.
+    /// A sequence number of zero in this reference is not checked.
     pub fn to_file<'n, T>(&self, ntfs: &'n Ntfs, fs: &mut T) -> Result<NtfsFile<'n>>
     where
         T: Read + Seek,
     {
-        ntfs.file(fs, self.file_record_number())
+        let file = ntfs.file(fs, self.file_record_number())?;
+
+        let expected = self.sequence_number();
+        let actual = file.sequence_number();
+        if expected != 0 && expected != actual {
+            return Err(NtfsError::StaleFileReference {
+                file_record_number: self.file_record_number(),
+                expected,
+                actual,
+            });
+        }
+
+        Ok(file)
+    }
+}
+
+#[cfg(feature = "serde")]
+#[cfg_attr(docsrs, doc(cfg(feature = "serde")))]
+impl Serialize for NtfsFileReference {
+    fn serialize<S>(&self, serializer: S) -> core::result::Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut state = serializer.serialize_struct("NtfsFileReference", 2)?;
+        state.serialize_field("file_record_number", &self.file_record_number())?;
+        state.serialize_field("sequence_number", &self.sequence_number())?;
+        state.end()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::indexes::NtfsFileNameIndex;
+
+    #[test]
+    fn test_file_reference() {
+        let mut testfs1 = crate::helpers::tests::testfs1();
+        let mut ntfs = Ntfs::new(&mut testfs1).unwrap();
+        ntfs.read_upcase_table(&mut testfs1).unwrap();
+        let root_dir = ntfs.root_directory(&mut testfs1).unwrap();
+        let root_dir_index = root_dir.directory_index(&mut testfs1).unwrap();
+        let mut root_dir_finder = root_dir_index.finder();
+        let entry =
+            NtfsFileNameIndex::find(&mut root_dir_finder, &ntfs, &mut testfs1, "file-with-12345")
+                .unwrap()
+                .unwrap();
+        let file_reference = entry.file_reference();
+        let file = file_reference.to_file(&ntfs, &mut testfs1).unwrap();
+        assert_eq!(
+            file.file_record_number(),
+            file_reference.file_record_number()
+        );
+        assert_eq!(file.sequence_number(), file_reference.sequence_number());
+
+        // A sequence number of zero matches any File Record.
+        let mut bytes = file_reference.file_record_number().to_le_bytes();
+        let reference = NtfsFileReference::new(bytes);
+        assert!(reference.to_file(&ntfs, &mut testfs1).is_ok());
+
+        // A different sequence number denotes a reused File Record.
+        let stale_sequence_number = file.sequence_number().wrapping_add(1);
+        bytes[6..].copy_from_slice(&stale_sequence_number.to_le_bytes());
+        let reference = NtfsFileReference::new(bytes);
+        assert_eq!(reference.sequence_number(), stale_sequence_number);
+        assert!(matches!(
+            reference.to_file(&ntfs, &mut testfs1),
+            Err(NtfsError::StaleFileReference { expected, actual, .. })
+                if expected == stale_sequence_number && actual == file.sequence_number()
+        ));
     }
 }
@@ -5,6 +5,9 @@ use core::fmt;
 
 use binrw::BinRead;
 
+#[cfg(feature = "serde")]
+use serde::ser::{Serialize, Serializer};
+
 /// Size of a single GUID on disk (= size of all GUID fields).
 pub(crate) const GUID_SIZE: usize = 16;
 
@@ -37,6 +40,18 @@ impl fmt::Display for NtfsGuid {
     }
 }
 
+/// Serializes the string form of this GUID.
+#[cfg(feature = "serde")]
+#[cfg_attr(docsrs, doc(cfg(feature = "serde")))]
+impl Serialize for NtfsGuid {
+    fn serialize<S>(&self, serializer: S) -> core::result::Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.collect_str(self)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -1,6 +1,10 @@
 // Copyright 2021 Colin Finck <colin@reactos.org>
 // SPDX-License-Identifier: MIT OR Apache-2.0
 
+use core::fmt::Write;
+
+use alloc::string::String;
+
 macro_rules! iter_try {
     ($e:expr) => {
         match $e {
@@ -10,6 +14,53 @@ macro_rules! iter_try {
     };
 }
 
+/// Appends the given UTF-16 string to `json` as a JSON string literal.
+///
+/// Unpaired surrogates, which NTFS allows in file names, are kept as `\uXXXX` escapes instead of
+/// being replaced, so the original name can be recovered from the JSON output.
+pub(crate) fn write_json_string<I>(json: &mut String, code_units: I)
+where
+    I: IntoIterator<Item = u16>,
+{
+    json.push('"');
+
+    for c in char::decode_utf16(code_units) {
+        match c {
+            Ok('"') => json.push_str("\\\""),
+            Ok('\\') => json.push_str("\\\\"),
+            Ok('\n') => json.push_str("\\n"),
+            Ok('\r') => json.push_str("\\r"),
+            Ok('\t') => json.push_str("\\t"),
+            Ok(c) if (c as u32) < 0x20 => {
+                let _ = write!(json, "\\u{:04x}", c as u32);
+            }
+            Ok(c) => json.push(c),
+            Err(e) => {
+                let _ = write!(json, "\\u{:04x}", e.unpaired_surrogate());
+            }
+        }
+    }
+
+    json.push('"');
+}
+
+/// Serializes any value implementing `Display` as a string (e.g. a `U16StrLe`).
+#[cfg(feature = "serde")]
+pub(crate) struct SerializeDisplay<T>(pub(crate) T);
+
+#[cfg(feature = "serde")]
+impl<T> serde::Serialize for SerializeDisplay<T>
+where
+    T: core::fmt::Display,
+{
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.collect_str(&self.0)
+    }
+}
+
 #[cfg(test)]
 pub mod tests {
     use std::fs::File;
@@ -23,4 +74,22 @@ pub mod tests {
             .unwrap();
         Cursor::new(buffer)
     }
+
+    pub fn testfs2() -> Cursor<Vec<u8>> {
+        let mut buffer = Vec::new();
+        File::open("testdata/testfs2")
+            .unwrap()
+            .read_to_end(&mut buffer)
+            .unwrap();
+        Cursor::new(buffer)
+    }
+
+    pub fn testfs3() -> Cursor<Vec<u8>> {
+        let mut buffer = Vec::new();
+        File::open("testdata/testfs3")
+            .unwrap()
+            .read_to_end(&mut buffer)
+            .unwrap();
+        Cursor::new(buffer)
+    }
 }
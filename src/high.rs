@@ -0,0 +1,62 @@
+// Copyright 2021-2023 Colin Finck <colin@reactos.org>
+// SPDX-License-Identifier: MIT OR Apache-2.0
+//
+//! High tier: Paths, walkers, and extraction.
+//!
+//! This tier is for applications that want to work with the contents of a filesystem rather than
+//! its structures:
+//! Looking up files by path (via [`Ntfs::file_by_path`]), exporting directory trees, extracting
+//! well-known artifacts, hashing and synchronizing files, checking a volume, and collecting the
+//! byte ranges of files modified since a backup.
+//!
+//! Path lookups (including those of the [`NtfsArtifactLocator`]) can degrade gracefully when a
+//! directory index along the path is damaged:
+//! Configure [`NtfsOptions::with_damaged_index_policy`] to scan the Master File Table instead, and
+//! use [`Ntfs::lookup_path`] to find out whether that has happened.
+//! All other functions of this tier, like the tree exporter and the other walkers, return errors of
+//! the [`mid`](crate::mid) tier as they are encountered.
+//!
+//! Together with the [`prelude`](crate::prelude), which this tier re-exports, this is everything
+//! needed to open a filesystem and look up a file:
+//!
+//! ```
+//! use ntfs::high::*;
+//!
+//! # fn main() -> Result<()> {
+//! # let mut fs = std::io::BufReader::new(std::fs::File::open("testdata/testfs1").unwrap());
+//! let mut ntfs = Ntfs::new(&mut fs)?;
+//! ntfs.read_upcase_table(&mut fs)?;
+//!
+//! let file = ntfs
+//!     .file_by_path(&mut fs, "many_subdirs/123", NtfsCaseSensitivity::Insensitive)
+//!     .unwrap()?;
+//! assert!(file.is_directory());
+//!
+//! match ntfs.lookup_path(&mut fs, "no-such-file", NtfsCaseSensitivity::Insensitive) {
+//!     Some(Ok(lookup)) => println!("Found File Record {}", lookup.file().file_record_number()),
+//!     Some(Err(e)) if e.kind() == NtfsErrorKind::Corrupted => println!("Damaged filesystem: {e}"),
+//!     Some(Err(e)) => return Err(e),
+//!     None => println!("Not found"),
+//! }
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! All types are also available from the crate root.
+//!
+//! [`Ntfs::file_by_path`]: crate::Ntfs::file_by_path
+//! [`Ntfs::lookup_path`]: crate::Ntfs::lookup_path
+
+pub use crate::artifacts::{
+    NtfsArtifact, NtfsArtifactLocator, NtfsLocatedArtifact, DEFAULT_NTFS_ARTIFACTS,
+};
+pub use crate::check;
+pub use crate::file_ranges::{NtfsByteRange, NtfsFileRanges, NtfsFileRangesSinceUsn};
+pub use crate::path_lookup::NtfsPathLookup;
+pub use crate::piecewise_hash::{NtfsPieceHash, NtfsPieceHasher};
+pub use crate::prelude::*;
+pub use crate::recycle_bin::{NtfsRecycleBinEntry, NtfsRecycleBinInfo};
+pub use crate::sync::{NtfsSyncReport, NtfsSyncSkip};
+pub use crate::tree_export::NtfsJsonTreeExporter;
+pub use crate::upcase_table::NtfsCaseSensitivity;
+pub use crate::volume_registry::{NtfsRegisteredVolume, NtfsVolumeRegistry};
@@ -4,18 +4,20 @@
 use core::cmp::Ordering;
 use core::marker::PhantomData;
 
-use alloc::vec;
 use alloc::vec::Vec;
 use binrw::io::{Read, Seek};
 
 use crate::attribute::{NtfsAttributeItem, NtfsAttributeType};
+use crate::collation::NtfsCollationRule;
 use crate::error::{NtfsError, Result};
 use crate::index_entry::{
-    IndexEntryRange, IndexNodeEntryRanges, NtfsIndexEntry, NtfsIndexEntryFlags,
+    IndexEntryRange, IndexNodeCursor, IndexNodeEntryRanges, NtfsIndexEntry, NtfsIndexEntryFlags,
 };
 use crate::indexes::NtfsIndexEntryType;
+use crate::ntfs::Ntfs;
+use crate::progress::{report_progress, NtfsProgressSink, NtfsProgressUnit};
 use crate::structured_values::{NtfsIndexAllocation, NtfsIndexRoot};
-use crate::types::NtfsPosition;
+use crate::types::{NtfsPosition, Vcn};
 
 /// Helper structure to iterate over all entries of an index or find a specific one.
 ///
@@ -31,10 +33,14 @@ pub struct NtfsIndex<'n, 'f, E>
 where
     E: NtfsIndexEntryType,
 {
+    collation_rule: u32,
     index_record_size: u32,
     index_root_entry_ranges: IndexNodeEntryRanges<E>,
+    index_root_entries_offset: usize,
+    index_root_file_record_number: u64,
     index_root_position: NtfsPosition,
     index_allocation_item: Option<NtfsAttributeItem<'n, 'f>>,
+    ntfs: &'n Ntfs,
     entry_type: PhantomData<E>,
 }
 
@@ -67,20 +73,37 @@ where
             });
         }
 
+        let collation_rule = index_root.collation_rule_value();
         let index_record_size = index_root.index_record_size();
         let index_root_entry_ranges = index_root.entry_ranges();
+        let index_root_entries_offset = index_root.entries_range_and_position().0.start;
+        let index_root_file_record_number = index_root_attribute.file().file_record_number();
         let index_root_position = index_root.position();
+        let ntfs = index_root_attribute.file().ntfs();
         let entry_type = PhantomData;
 
         Ok(Self {
+            collation_rule,
             index_record_size,
             index_root_entry_ranges,
+            index_root_entries_offset,
+            index_root_file_record_number,
             index_root_position,
             index_allocation_item,
+            ntfs,
             entry_type,
         })
     }
 
+    /// Returns the [`NtfsCollationRule`] declared by the Index Root, which defines the sort order of
+    /// the keys of this index.
+    pub fn collation_rule(&self) -> Result<NtfsCollationRule> {
+        NtfsCollationRule::n(self.collation_rule).ok_or(NtfsError::UnsupportedCollationRule {
+            position: self.index_root_position,
+            actual: self.collation_rule,
+        })
+    }
+
     /// Returns an [`NtfsIndexEntries`] iterator to perform an in-order traversal of this index.
     pub fn entries<'i>(&'i self) -> NtfsIndexEntries<'n, 'f, 'i, E> {
         NtfsIndexEntries::new(self)
@@ -92,20 +115,71 @@ where
     }
 }
 
+/// The B-tree node of an index that stores an index entry, as part of an [`NtfsIndexEntryLocation`].
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum NtfsIndexNode {
+    /// The top-level node in the $INDEX_ROOT attribute.
+    IndexRoot {
+        /// File Record Number of the File Record holding the $INDEX_ROOT attribute.
+        file_record_number: u64,
+    },
+    /// An Index Record of the $INDEX_ALLOCATION attribute.
+    IndexRecord {
+        /// Virtual Cluster Number (VCN) of the Index Record, as referenced by the
+        /// [`subnode_vcn`](NtfsIndexEntry::subnode_vcn) of its parent entry.
+        vcn: Vcn,
+    },
+}
+
+/// On-disk location of an index entry, returned by [`NtfsIndexEntries::next_with_location`].
+///
+/// Together with [`NtfsIndexEntry::position`] (the absolute byte position of the entry), this makes it
+/// possible to cite the exact place of an entry in reports.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct NtfsIndexEntryLocation {
+    node: NtfsIndexNode,
+    offset: usize,
+}
+
+impl NtfsIndexEntryLocation {
+    /// Returns the B-tree node that stores the entry.
+    pub fn node(&self) -> NtfsIndexNode {
+        self.node
+    }
+
+    /// Returns the byte offset of the entry within its node, i.e. within the value of the
+    /// $INDEX_ROOT attribute or within the Index Record.
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+}
+
 /// Iterator over
 ///   all index entries of an index,
 ///   sorted ascending by the index key,
 ///   returning an [`NtfsIndexEntry`] for each entry.
 ///
 /// This iterator is returned from the [`NtfsIndex::entries`] function.
+///
+/// Its memory usage is bounded by a single Index Record (plus a copy of the small Index Root)
+/// regardless of the number of entries:
+/// Only the B-tree node currently iterated is buffered.
+/// For each node on the path to it, just its VCN and a few bytes of iteration state are kept,
+/// and a node is read again when its subnode has been fully iterated.
+/// This means that the Index Records of a B-tree with multiple levels are read more than once.
+/// Wrap the filesystem reader in an [`NtfsReadCache`] if that matters.
+///
+/// [`NtfsReadCache`]: crate::NtfsReadCache
 #[derive(Clone, Debug)]
 pub struct NtfsIndexEntries<'n, 'f, 'i, E>
 where
     E: NtfsIndexEntryType,
 {
     index: &'i NtfsIndex<'n, 'f, E>,
-    inner_iterators: Vec<IndexNodeEntryRanges<E>>,
-    following_entries: Vec<Option<IndexEntryRange<E>>>,
+    node: IndexNodeEntryRanges<E>,
+    node_vcn: Option<Vcn>,
+    parent_nodes: Vec<IndexParentNode<E>>,
+    entries_returned: u64,
 }
 
 impl<'n, 'f, 'i, E> NtfsIndexEntries<'n, 'f, 'i, E>
@@ -113,18 +187,83 @@ where
     E: NtfsIndexEntryType,
 {
     fn new(index: &'i NtfsIndex<'n, 'f, E>) -> Self {
-        let inner_iterators = vec![index.index_root_entry_ranges.clone()];
-        let following_entries = Vec::new();
+        let node = index.index_root_entry_ranges.clone();
+        let node_vcn = None;
+        let parent_nodes = Vec::new();
+        let entries_returned = 0;
 
         Self {
             index,
-            inner_iterators,
-            following_entries,
+            node,
+            node_vcn,
+            parent_nodes,
+            entries_returned,
         }
     }
 
     /// See [`Iterator::next`].
     pub fn next<'a, T>(&'a mut self, fs: &mut T) -> Option<Result<NtfsIndexEntry<'a, E>>>
+    where
+        T: Read + Seek,
+    {
+        let (entry, _location) = iter_try!(self.next_with_location(fs)?);
+        Some(Ok(entry))
+    }
+
+    /// Variant of [`NtfsIndexEntries::next`] that first reports the number of entries returned so far
+    /// to an [`NtfsProgressSink`] (in [`NtfsProgressUnit::IndexEntries`], without a total).
+    ///
+    /// If the sink cancels, this returns [`NtfsError::Cancelled`] before reading anything.
+    /// Pass the same sink to every call while walking a large index.
+    pub fn next_with_progress<'a, T, P>(
+        &'a mut self,
+        fs: &mut T,
+        progress: &mut P,
+    ) -> Option<Result<NtfsIndexEntry<'a, E>>>
+    where
+        T: Read + Seek,
+        P: NtfsProgressSink + ?Sized,
+    {
+        iter_try!(report_progress(
+            progress,
+            NtfsProgressUnit::IndexEntries,
+            self.entries_returned,
+            None
+        ));
+        self.next(fs)
+    }
+
+    /// Variant of [`NtfsIndexEntries::next`] that also returns the [`NtfsIndexEntryLocation`] of the
+    /// entry, i.e. the B-tree node it is stored in and its offset within that node.
+    pub fn next_with_location<'a, T>(
+        &'a mut self,
+        fs: &mut T,
+    ) -> Option<Result<(NtfsIndexEntry<'a, E>, NtfsIndexEntryLocation)>>
+    where
+        T: Read + Seek,
+    {
+        loop {
+            let (entry_range, location) = iter_try!(self.next_entry_range(fs)?);
+
+            // Apply the `NtfsOptions` before handing out the entry.
+            let entry = iter_try!(entry_range.to_entry(self.node.data()));
+            if !iter_try!(entry.is_returned(self.index.ntfs.options())) {
+                continue;
+            }
+
+            // Recreate `entry` to please the borrow checker.
+            let entry = iter_try!(entry_range.to_entry(self.node.data()));
+            self.entries_returned += 1;
+
+            return Some(Ok((entry, location)));
+        }
+    }
+
+    /// Returns the range and location of the next entry in order.
+    fn next_entry_range<T>(
+        &mut self,
+        fs: &mut T,
+    ) -> Option<Result<(IndexEntryRange<E>, NtfsIndexEntryLocation)>>
     where
         T: Read + Seek,
     {
@@ -147,85 +286,119 @@ where
         //                                     -----
         //
         let entry_range = loop {
-            // Get the iterator from the current node level, if any.
-            let iter = self.inner_iterators.last_mut()?;
-
-            // Get the next `IndexEntryRange` from it.
-            if let Some(entry_range) = iter.next() {
+            // Get the next `IndexEntryRange` from the current node.
+            if let Some(entry_range) = self.node.next() {
                 let entry_range = iter_try!(entry_range);
 
                 // Convert that `IndexEntryRange` to a (lifetime-bound) `NtfsIndexEntry`.
-                let entry = iter_try!(entry_range.to_entry(iter.data()));
+                let entry = iter_try!(entry_range.to_entry(self.node.data()));
                 let is_last_entry = entry.flags().contains(NtfsIndexEntryFlags::LAST_ENTRY);
 
                 // Does this entry have a subnode that needs to be iterated first?
                 if let Some(subnode_vcn) = entry.subnode_vcn() {
                     let subnode_vcn = iter_try!(subnode_vcn);
-
-                    // Read the subnode from the filesystem and get an iterator for it.
-                    let index_allocation_item =
-                        iter_try!(self.index.index_allocation_item.as_ref().ok_or(
-                            NtfsError::MissingIndexAllocation {
-                                position: self.index.index_root_position,
-                            }
-                        ));
-                    let index_allocation_attribute =
-                        iter_try!(index_allocation_item.to_attribute());
-                    let index_allocation =
-                        iter_try!(index_allocation_attribute
-                            .structured_value::<_, NtfsIndexAllocation>(fs));
-
-                    let subnode = iter_try!(index_allocation.record_from_vcn(
-                        fs,
-                        self.index.index_record_size,
-                        subnode_vcn
-                    ));
-                    let subnode_iter = subnode.into_entry_ranges();
+                    let subnode = iter_try!(self.read_node(fs, Some(subnode_vcn)));
 
                     let following_entry = if !is_last_entry {
                         // This entry comes after the subnode lexicographically, so save it.
-                        // We'll pick it up again after the subnode iterator has been fully iterated.
+                        // We'll pick it up again after the subnode has been fully iterated.
                         Some(entry_range)
                     } else {
                         None
                     };
 
-                    // Save this subnode's iterator and any following entry.
-                    // We'll pick up the iterator through `self.inner_iterators.last_mut()` in the next loop iteration.
-                    self.inner_iterators.push(subnode_iter);
-                    self.following_entries.push(following_entry);
+                    // Only remember where we are in the current node, and continue with the subnode.
+                    self.parent_nodes.push(IndexParentNode {
+                        vcn: self.node_vcn,
+                        cursor: self.node.cursor(),
+                        following_entry,
+                    });
+                    self.node = subnode;
+                    self.node_vcn = Some(subnode_vcn);
                 } else if !is_last_entry {
                     // There is no subnode, and this is not the empty "last entry",
                     // so our entry comes next lexicographically.
                     break entry_range;
                 }
             } else {
-                // The iterator for this subnode level has been fully iterated.
-                // Drop it.
-                self.inner_iterators.pop();
-
-                // The entry, whose subnode we just fully iterated, may have been saved in `following_entries`.
+                // The current node has been fully iterated.
+                // If it is the root-level node, we are done.
+                // Otherwise, read its parent node again and continue where we left off.
+                let parent_node = self.parent_nodes.pop()?;
+                let mut node = iter_try!(self.read_node(fs, parent_node.vcn));
+                node.set_cursor(parent_node.cursor);
+                self.node = node;
+                self.node_vcn = parent_node.vcn;
+
+                // The entry, whose subnode we just fully iterated, may have been saved in `following_entry`.
                 // This depends on its `is_last_entry` flag:
-                //   * If it was not the last entry, it contains an entry that comes next lexicographically,
-                //     and has therefore been saved in `following_entries`.
+                //   * If it was not the last entry, it contains an entry that comes next lexicographically.
                 //   * If it was the last entry, it contains no further information.
-                //     `None` has been saved in `following_entries`, so that `following_entries.len()` always
-                //     matches `inner_iterators.len() - 1`.
-                //
-                // If we just finished iterating the root-level node, `following_entries` is empty and we are done.
-                // Otherwise, we can be sure that `inner_iterators.last()` is the matching iterator for converting
-                // `IndexEntryRange` to a (lifetime-bound) `NtfsIndexEntry`.
-                if let Some(entry_range) = self.following_entries.pop()? {
+                if let Some(entry_range) = parent_node.following_entry {
                     break entry_range;
                 }
             }
         };
 
-        let iter = self.inner_iterators.last().unwrap();
-        let entry = iter_try!(entry_range.to_entry(iter.data()));
+        let location = match self.node_vcn {
+            Some(vcn) => NtfsIndexEntryLocation {
+                node: NtfsIndexNode::IndexRecord { vcn },
+                offset: entry_range.offset(),
+            },
+            None => NtfsIndexEntryLocation {
+                node: NtfsIndexNode::IndexRoot {
+                    file_record_number: self.index.index_root_file_record_number,
+                },
+                offset: self.index.index_root_entries_offset + entry_range.offset(),
+            },
+        };
 
-        Some(Ok(entry))
+        Some(Ok((entry_range, location)))
     }
+
+    /// Reads the node at `vcn` of the $INDEX_ALLOCATION attribute, or the Index Root for `None`.
+    fn read_node<T>(&self, fs: &mut T, vcn: Option<Vcn>) -> Result<IndexNodeEntryRanges<E>>
+    where
+        T: Read + Seek,
+    {
+        let vcn = match vcn {
+            Some(vcn) => vcn,
+            None => return Ok(self.index.index_root_entry_ranges.clone()),
+        };
+
+        let index_allocation_item =
+            self.index
+                .index_allocation_item
+                .as_ref()
+                .ok_or(NtfsError::MissingIndexAllocation {
+                    position: self.index.index_root_position,
+                })?;
+        let index_allocation_attribute = index_allocation_item.to_attribute()?;
+        let index_allocation =
+            index_allocation_attribute.structured_value::<_, NtfsIndexAllocation>(fs)?;
+        let record = index_allocation.record_from_vcn(fs, self.index.index_record_size, vcn)?;
+
+        Ok(record.into_entry_ranges())
+    }
+
+    /// Returns the number of bytes of index data currently buffered by this iterator.
+    #[cfg(test)]
+    fn buffered_len(&self) -> usize {
+        self.node.data().len()
+    }
+}
+
+/// A node on the path from the Index Root to the node currently iterated by [`NtfsIndexEntries`].
+#[derive(Clone, Debug)]
+struct IndexParentNode<E>
+where
+    E: NtfsIndexEntryType,
+{
+    /// VCN of the Index Record, or `None` for the Index Root.
+    vcn: Option<Vcn>,
+    cursor: IndexNodeCursor,
+    /// The entry whose subnode is being iterated, unless that is the empty "last entry".
+    following_entry: Option<IndexEntryRange<E>>,
 }
 
 /// Helper structure to efficiently find an entry in an index, created by [`NtfsIndex::finder`].
@@ -254,12 +427,83 @@ where
         }
     }
 
+    /// Checks that the collation rule declared by the Index Root is the one implemented by the
+    /// comparisons of the index type `E` (if `E` declares one).
+    pub(crate) fn check_collation_rule(&self) -> Result<()> {
+        let expected = match E::COLLATION_RULE {
+            Some(expected) => expected,
+            None => return Ok(()),
+        };
+        let collation_rule = self.index.collation_rule()?;
+
+        if collation_rule == expected {
+            Ok(())
+        } else {
+            Err(NtfsError::UnsupportedCollationRule {
+                position: self.index.index_root_position,
+                actual: collation_rule as u32,
+            })
+        }
+    }
+
     /// Finds an entry in this index using the given comparison function and returns an [`NtfsIndexEntry`]
     /// (if there is one).
+    ///
+    /// The comparison function must follow the collation rule of the index type `E`, like the `find`
+    /// functions of the index types in [`indexes`](crate::indexes) do.
+    /// If the Index Root declares a different collation rule (see [`NtfsIndex::collation_rule`]),
+    /// this returns [`NtfsError::UnsupportedCollationRule`] instead of searching with the wrong sort order.
+    /// Use [`NtfsIndexFinder::find_by_key`] to look up a raw key with the declared collation rule instead.
     pub fn find<'a, T, F>(&'a mut self, fs: &mut T, cmp: F) -> Option<Result<NtfsIndexEntry<'a, E>>>
     where
         T: Read + Seek,
         F: Fn(&E::KeyType) -> Ordering,
+    {
+        iter_try!(self.check_collation_rule());
+
+        self.find_with(fs, |entry| {
+            let key = entry.key()?;
+            Some(key.map(|key| cmp(&key)))
+        })
+    }
+
+    /// Finds the entry with the raw key `key` in this index and returns an [`NtfsIndexEntry`]
+    /// (if there is one).
+    ///
+    /// Keys are compared according to the collation rule declared by the Index Root
+    /// (see [`NtfsIndex::collation_rule`]), so this works for every kind of index.
+    ///
+    /// # Panics
+    ///
+    /// Panics for indexes with [`NtfsCollationRule::FileName`] or [`NtfsCollationRule::UnicodeString`]
+    /// if [`read_upcase_table`][Ntfs::read_upcase_table] had not been called on the passed [`Ntfs`] object.
+    pub fn find_by_key<'a, T>(
+        &'a mut self,
+        ntfs: &Ntfs,
+        fs: &mut T,
+        key: &[u8],
+    ) -> Option<Result<NtfsIndexEntry<'a, E>>>
+    where
+        T: Read + Seek,
+    {
+        let collation_rule = iter_try!(self.index.collation_rule());
+
+        self.find_with(fs, |entry| {
+            let entry_key = entry.key_slice()?;
+            Some(entry_key.map(|entry_key| collation_rule.compare(ntfs, key, entry_key)))
+        })
+    }
+
+    /// Traverses the B-tree of this index, with `cmp` comparing what we look for with the key of an entry.
+    /// `cmp` returns `None` for entries without a key.
+    pub(crate) fn find_with<'a, T, F>(
+        &'a mut self,
+        fs: &mut T,
+        cmp: F,
+    ) -> Option<Result<NtfsIndexEntry<'a, E>>>
+    where
+        T: Read + Seek,
+        F: Fn(&NtfsIndexEntry<E>) -> Option<Result<Ordering>>,
     {
         // Always (re)start by iterating through the Index Root entry ranges.
         self.inner_iterator = self.index.index_root_entry_ranges.clone();
@@ -273,13 +517,15 @@ where
             let entry = iter_try!(entry_range.to_entry(self.inner_iterator.data()));
 
             // Check if this entry has a key.
-            if let Some(key) = entry.key() {
+            if let Some(ordering) = cmp(&entry) {
                 // The entry has a key, so compare it using the given function.
-                let key = iter_try!(key);
-
-                match cmp(&key) {
+                match iter_try!(ordering) {
                     Ordering::Equal => {
-                        // We found what we were looking for!
+                        // We found what we were looking for, unless the `NtfsOptions` tell to skip it.
+                        if !iter_try!(entry.is_returned(self.index.ntfs.options())) {
+                            return None;
+                        }
+
                         // Recreate `entry` from the last `self.inner_iterator` to please the borrow checker.
                         let entry = iter_try!(entry_range.to_entry(self.inner_iterator.data()));
                         return Some(Ok(entry));
@@ -322,9 +568,15 @@ where
 
 #[cfg(test)]
 mod tests {
+    use alloc::vec;
+    use core::ops::ControlFlow;
+
     use super::*;
-    use crate::indexes::NtfsFileNameIndex;
-    use crate::ntfs::Ntfs;
+    use crate::file::{KnownNtfsFileRecordNumber, NtfsFile};
+    use crate::indexes::{NtfsFileNameIndex, NtfsSecurityId, NtfsSecurityIdIndex};
+    use crate::options::{NtfsOptions, NtfsUnsupportedPolicy};
+    use crate::progress::NtfsProgress;
+    use crate::structured_values::NtfsFileNamespace;
 
     #[test]
     fn test_index_find() {
@@ -354,6 +606,106 @@ mod tests {
             let entry_name = entry.key().unwrap().unwrap();
             assert_eq!(entry_name.name(), dir_name.as_str());
         }
+
+        // The same works with names given as UTF-16 code units.
+        let dir_name = "512".encode_utf16().collect::<Vec<u16>>();
+        let entry = NtfsFileNameIndex::find_u16(&mut subdir_finder, &ntfs, &mut testfs1, &dir_name)
+            .unwrap()
+            .unwrap();
+        let entry_name = entry.key().unwrap().unwrap();
+        assert_eq!(entry_name.name(), "512");
+    }
+
+    #[test]
+    fn test_index_find_by_key() {
+        let mut testfs1 = crate::helpers::tests::testfs1();
+        let mut ntfs = Ntfs::new(&mut testfs1).unwrap();
+        ntfs.read_upcase_table(&mut testfs1).unwrap();
+        let root_dir = ntfs.root_directory(&mut testfs1).unwrap();
+
+        // Directory indexes declare the file name collation.
+        let index_root = root_dir
+            .find_resident_attribute_structured_value::<NtfsIndexRoot>(Some("$I30"))
+            .unwrap();
+        assert_eq!(
+            index_root.collation_rule().unwrap(),
+            NtfsCollationRule::FileName
+        );
+        assert_eq!(index_root.index_record_size(), 4096);
+        assert_eq!(index_root.clusters_per_index_record(), 8);
+
+        let file_name_key = |name: &str| {
+            let mut key = vec![0u8; 66];
+            key[64] = name.len() as u8;
+            key.extend(name.encode_utf16().flat_map(u16::to_le_bytes));
+            key
+        };
+
+        let root_dir_index = root_dir.directory_index(&mut testfs1).unwrap();
+        assert_eq!(
+            root_dir_index.collation_rule().unwrap(),
+            NtfsCollationRule::FileName
+        );
+        let mut root_dir_finder = root_dir_index.finder();
+
+        // The file name collation only considers names equal if they also match in case.
+        assert!(root_dir_finder
+            .find_by_key(&ntfs, &mut testfs1, &file_name_key("MANY_SUBDIRS"))
+            .is_none());
+        let entry = root_dir_finder
+            .find_by_key(&ntfs, &mut testfs1, &file_name_key("many_subdirs"))
+            .unwrap()
+            .unwrap();
+        let subdir = entry.to_file(&ntfs, &mut testfs1).unwrap();
+
+        let subdir_index = subdir.directory_index(&mut testfs1).unwrap();
+        let mut subdir_finder = subdir_index.finder();
+
+        for dir_name in ["1", "256", "512"] {
+            let entry = subdir_finder
+                .find_by_key(&ntfs, &mut testfs1, &file_name_key(dir_name))
+                .unwrap()
+                .unwrap();
+            let entry_name = entry.key().unwrap().unwrap();
+            assert_eq!(entry_name.name(), dir_name);
+        }
+
+        assert!(subdir_finder
+            .find_by_key(&ntfs, &mut testfs1, &file_name_key("513"))
+            .is_none());
+
+        // The $SII index of $Secure is sorted by security ID.
+        let secure_file = ntfs
+            .file(&mut testfs1, KnownNtfsFileRecordNumber::Secure as u64)
+            .unwrap();
+        let index = secure_file
+            .index::<NtfsSecurityIdIndex, _>(&mut testfs1, "$SII")
+            .unwrap();
+        assert_eq!(index.collation_rule().unwrap(), NtfsCollationRule::Ulong);
+        let mut finder = index.finder();
+        let entry = finder
+            .find_by_key(&ntfs, &mut testfs1, &0x101u32.to_le_bytes())
+            .unwrap()
+            .unwrap();
+        assert_eq!(entry.key().unwrap().unwrap().value(), 0x101);
+
+        // The typed comparisons refuse an index whose declared collation rule they don't implement,
+        // like the $SDH index (sorted by Security Descriptor hash) opened as a security ID index.
+        let index = secure_file
+            .index::<NtfsSecurityIdIndex, _>(&mut testfs1, "$SDH")
+            .unwrap();
+        assert_eq!(
+            index.collation_rule().unwrap(),
+            NtfsCollationRule::SecurityHash
+        );
+        let mut finder = index.finder();
+        assert!(matches!(
+            NtfsSecurityIdIndex::find(&mut finder, &mut testfs1, NtfsSecurityId::from(0x101)),
+            Some(Err(NtfsError::UnsupportedCollationRule {
+                actual: 0x12,
+                ..
+            }))
+        ));
     }
 
     #[test]
@@ -392,5 +744,234 @@ mod tests {
         }
 
         assert!(subdir_iter.next(&mut testfs1).is_none());
+
+        // Walking the index with progress reports the entries returned so far and can be cancelled.
+        let mut subdir_iter = subdir_index.entries();
+        let mut reports = Vec::new();
+        let mut sink = |progress: NtfsProgress| {
+            reports.push(progress.processed());
+            if progress.processed() == 100 {
+                ControlFlow::Break(())
+            } else {
+                ControlFlow::Continue(())
+            }
+        };
+        for _ in 0..100 {
+            subdir_iter
+                .next_with_progress(&mut testfs1, &mut sink)
+                .unwrap()
+                .unwrap();
+        }
+        assert!(matches!(
+            subdir_iter.next_with_progress(&mut testfs1, &mut sink),
+            Some(Err(NtfsError::Cancelled))
+        ));
+        assert_eq!(reports, (0..=100).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_index_file_namespace_policy() {
+        let mut testfs1 = crate::helpers::tests::testfs1();
+        let mut ntfs = Ntfs::new(&mut testfs1).unwrap();
+        ntfs.read_upcase_table(&mut testfs1).unwrap();
+
+        // Patch the namespace of the "123" entry in an Index Record of "many_subdirs" to a value unknown
+        // to this crate (the namespace is the last byte of the $FILE_NAME header before the name).
+        let subdir = ntfs
+            .file_by_path(&mut testfs1, "many_subdirs", Default::default())
+            .unwrap()
+            .unwrap();
+        let namespace_position = {
+            let subdir_index = subdir.directory_index(&mut testfs1).unwrap();
+            let mut subdir_finder = subdir_index.finder();
+            let entry = NtfsFileNameIndex::find(&mut subdir_finder, &ntfs, &mut testfs1, "123")
+                .unwrap()
+                .unwrap();
+            entry.position().value().unwrap().get() as usize + 16 + 65
+        };
+        assert_eq!(
+            testfs1.get_ref()[namespace_position],
+            NtfsFileNamespace::Posix as u8
+        );
+        testfs1.get_mut()[namespace_position] = 7;
+
+        let entry_names = |options: NtfsOptions, testfs1: &mut _| {
+            let ntfs = Ntfs::new_with_options(testfs1, options).unwrap();
+            let subdir = ntfs.file(testfs1, subdir.file_record_number()).unwrap();
+            let subdir_index = subdir.directory_index(testfs1).unwrap();
+            let mut subdir_iter = subdir_index.entries();
+            let mut names = Vec::new();
+
+            while let Some(entry) = subdir_iter.next(testfs1) {
+                let file_name = entry?.key().unwrap().unwrap();
+                names.push((file_name.name().to_string_lossy(), file_name.namespace()));
+            }
+
+            Ok(names)
+        };
+
+        // The default policy returns an error for the entry, both when iterating and when looking it up.
+        let error = entry_names(NtfsOptions::new(), &mut testfs1).unwrap_err();
+        assert!(matches!(
+            error,
+            NtfsError::UnsupportedFileNamespace { actual: 7, .. }
+        ));
+
+        let mut ntfs = Ntfs::new(&mut testfs1).unwrap();
+        ntfs.read_upcase_table(&mut testfs1).unwrap();
+        let subdir = ntfs
+            .file(&mut testfs1, subdir.file_record_number())
+            .unwrap();
+        let subdir_index = subdir.directory_index(&mut testfs1).unwrap();
+        let mut subdir_finder = subdir_index.finder();
+        assert!(matches!(
+            NtfsFileNameIndex::find(&mut subdir_finder, &ntfs, &mut testfs1, "123"),
+            Some(Err(NtfsError::UnsupportedFileNamespace { actual: 7, .. }))
+        ));
+        assert!(
+            NtfsFileNameIndex::find(&mut subdir_finder, &ntfs, &mut testfs1, "124")
+                .unwrap()
+                .is_ok()
+        );
+
+        // Skipping leaves out the entry, both when iterating and when looking it up.
+        let options = NtfsOptions::new().with_file_namespace_policy(NtfsUnsupportedPolicy::Skip);
+        let names = entry_names(options, &mut testfs1).unwrap();
+        assert_eq!(names.len(), 511);
+        assert!(!names.iter().any(|(name, _)| name == "123"));
+
+        let mut ntfs = Ntfs::new_with_options(&mut testfs1, options).unwrap();
+        ntfs.read_upcase_table(&mut testfs1).unwrap();
+        assert!(ntfs
+            .file_by_path(&mut testfs1, "many_subdirs/123", Default::default())
+            .is_none());
+        assert!(ntfs
+            .file_by_path(&mut testfs1, "many_subdirs/124", Default::default())
+            .unwrap()
+            .is_ok());
+
+        // Yielding returns the entry without a namespace.
+        let options =
+            NtfsOptions::new().with_file_namespace_policy(NtfsUnsupportedPolicy::YieldUnknown);
+        let names = entry_names(options, &mut testfs1).unwrap();
+        assert_eq!(names.len(), 512);
+        assert!(names.contains(&(String::from("123"), None)));
+
+        let mut ntfs = Ntfs::new_with_options(&mut testfs1, options).unwrap();
+        ntfs.read_upcase_table(&mut testfs1).unwrap();
+        let file = ntfs
+            .file_by_path(&mut testfs1, "many_subdirs/123", Default::default())
+            .unwrap()
+            .unwrap();
+        assert!(file.is_directory());
+    }
+
+    #[test]
+    fn test_index_iter_memory_bound() {
+        let mut testfs1 = crate::helpers::tests::testfs1();
+        let mut ntfs = Ntfs::new(&mut testfs1).unwrap();
+        ntfs.read_upcase_table(&mut testfs1).unwrap();
+        let root_dir = ntfs.root_directory(&mut testfs1).unwrap();
+        let root_dir_index = root_dir.directory_index(&mut testfs1).unwrap();
+        let mut root_dir_finder = root_dir_index.finder();
+        let entry =
+            NtfsFileNameIndex::find(&mut root_dir_finder, &ntfs, &mut testfs1, "many_subdirs")
+                .unwrap()
+                .unwrap();
+        let subdir = entry.to_file(&ntfs, &mut testfs1).unwrap();
+        let subdir_index = subdir.directory_index(&mut testfs1).unwrap();
+        let index_record_size = subdir_index.index_record_size as usize;
+
+        // The 512 entries are spread over multiple Index Records below the Index Root,
+        // but no more than a single node is buffered at any time.
+        let mut subdir_iter = subdir_index.entries();
+        let mut max_depth = 0;
+        let mut count = 0;
+
+        loop {
+            max_depth = max_depth.max(subdir_iter.parent_nodes.len());
+            assert!(subdir_iter.buffered_len() <= index_record_size);
+
+            match subdir_iter.next(&mut testfs1) {
+                Some(entry) => {
+                    entry.unwrap();
+                    count += 1;
+                }
+                None => break,
+            }
+        }
+
+        assert_eq!(count, 512);
+        assert!(max_depth >= 1);
+        assert!(subdir_iter.parent_nodes.is_empty());
+        assert!(subdir_iter.next(&mut testfs1).is_none());
+    }
+
+    fn check_index_entry_locations<T>(directory: &NtfsFile, fs: &mut T, node_counts: &mut [u32; 2])
+    where
+        T: Read + Seek,
+    {
+        let index_root = directory
+            .find_resident_attribute_structured_value::<NtfsIndexRoot>(Some("$I30"))
+            .unwrap();
+        let index_root_position = index_root.position().value().unwrap().get();
+        let index = directory.directory_index(fs).unwrap();
+
+        // The location of every entry must lead to its absolute position.
+        let mut iter = index.entries();
+
+        while let Some(entry_and_location) = iter.next_with_location(fs) {
+            let (entry, location) = entry_and_location.unwrap();
+            let node_position = match location.node() {
+                NtfsIndexNode::IndexRoot { file_record_number } => {
+                    assert_eq!(file_record_number, directory.file_record_number());
+                    node_counts[0] += 1;
+                    index_root_position
+                }
+                NtfsIndexNode::IndexRecord { vcn } => {
+                    node_counts[1] += 1;
+                    let index_allocation_item = index.index_allocation_item.as_ref().unwrap();
+                    let index_allocation_attribute = index_allocation_item.to_attribute().unwrap();
+                    let index_allocation = index_allocation_attribute
+                        .structured_value::<_, NtfsIndexAllocation>(fs)
+                        .unwrap();
+                    let record = index_allocation
+                        .record_from_vcn(fs, index.index_record_size, vcn)
+                        .unwrap();
+                    record.position().value().unwrap().get()
+                }
+            };
+
+            assert_eq!(
+                node_position + location.offset() as u64,
+                entry.position().value().unwrap().get()
+            );
+        }
+    }
+
+    #[test]
+    fn test_index_entry_location() {
+        let mut testfs1 = crate::helpers::tests::testfs1();
+        let mut ntfs = Ntfs::new(&mut testfs1).unwrap();
+        ntfs.read_upcase_table(&mut testfs1).unwrap();
+        let root_dir = ntfs.root_directory(&mut testfs1).unwrap();
+        let root_dir_index = root_dir.directory_index(&mut testfs1).unwrap();
+        let mut root_dir_finder = root_dir_index.finder();
+        let entry =
+            NtfsFileNameIndex::find(&mut root_dir_finder, &ntfs, &mut testfs1, "many_subdirs")
+                .unwrap()
+                .unwrap();
+        let subdir = entry.to_file(&ntfs, &mut testfs1).unwrap();
+
+        // The few entries of $Extend fit into its Index Root.
+        let extend_dir = ntfs
+            .file(&mut testfs1, KnownNtfsFileRecordNumber::Extend as u64)
+            .unwrap();
+
+        let mut node_counts = [0; 2];
+        for directory in [&root_dir, &subdir, &extend_dir] {
+            check_index_entry_locations(directory, &mut testfs1, &mut node_counts);
+        }
+        assert!(node_counts[0] > 0 && node_counts[1] > 0, "{node_counts:?}");
     }
 }
@@ -6,6 +6,7 @@ use core::marker::PhantomData;
 use core::ops::Range;
 use core::{fmt, mem};
 
+use alloc::vec;
 use alloc::vec::Vec;
 use binrw::io::{Read, Seek};
 use bitflags::bitflags;
@@ -20,6 +21,7 @@ use crate::indexes::{
     NtfsIndexEntryType,
 };
 use crate::ntfs::Ntfs;
+use crate::options::NtfsOptions;
 use crate::types::NtfsPosition;
 use crate::types::Vcn;
 
@@ -58,6 +60,18 @@ impl fmt::Display for NtfsIndexEntryFlags {
     }
 }
 
+/// Serializes the raw bits of these flags.
+#[cfg(feature = "serde")]
+#[cfg_attr(docsrs, doc(cfg(feature = "serde")))]
+impl serde::Serialize for NtfsIndexEntryFlags {
+    fn serialize<S>(&self, serializer: S) -> core::result::Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serde::Serialize::serialize(&self.bits(), serializer)
+    }
+}
+
 #[derive(Clone, Debug)]
 pub(crate) struct IndexEntryRange<E>
 where
@@ -81,6 +95,11 @@ where
         }
     }
 
+    /// Returns the byte offset of this entry within the data of its node.
+    pub(crate) fn offset(&self) -> usize {
+        self.range.start
+    }
+
     pub(crate) fn to_entry<'s>(&self, slice: &'s [u8]) -> Result<NtfsIndexEntry<'s, E>> {
         NtfsIndexEntry::new(&slice[self.range.clone()], self.position)
     }
@@ -131,6 +150,13 @@ where
         Ok(entry)
     }
 
+    /// Returns the raw bytes of this Index Entry, as read from its Index Root or fixed-up Index Record.
+    ///
+    /// This covers [`NtfsIndexEntry::index_entry_length`] bytes starting at [`NtfsIndexEntry::position`].
+    pub fn as_bytes(&self) -> &'s [u8] {
+        self.slice
+    }
+
     /// Returns the data of this Index Entry, if any and if supported by this Index Entry type.
     ///
     /// This function is mutually exclusive with [`NtfsIndexEntry::file_reference`].
@@ -209,7 +235,39 @@ where
     /// or `None` if this Index Entry has no key.
     ///
     /// The last Index Entry never has a key.
+    ///
+    /// This doesn't apply any [`NtfsOptions`] to the key:
+    /// A file name of an unsupported namespace is returned with [`NtfsFileName::namespace`] being `None`.
+    /// [`NtfsIndexEntries`] and [`NtfsIndexFinder`] apply [`NtfsOptions::with_file_namespace_policy`]
+    /// before returning an entry.
+    ///
+    /// [`NtfsFileName::namespace`]: crate::structured_values::NtfsFileName::namespace
+    /// [`NtfsIndexEntries`]: crate::NtfsIndexEntries
+    /// [`NtfsIndexFinder`]: crate::NtfsIndexFinder
     pub fn key(&self) -> Option<Result<E::KeyType>> {
+        let slice = iter_try!(self.key_slice()?);
+        let position = self.position + INDEX_ENTRY_HEADER_SIZE;
+
+        let key = iter_try!(E::KeyType::key_from_slice(slice, position));
+        Some(Ok(key))
+    }
+
+    /// Applies the policies of `options` to the key of this Index Entry and returns whether
+    /// the entry is to be returned (see [`NtfsIndexEntryKey::filter_key_slice`]).
+    pub(crate) fn is_returned(&self, options: &NtfsOptions) -> Result<bool> {
+        match self.key_slice() {
+            Some(slice) => E::KeyType::filter_key_slice(
+                slice?,
+                self.position + INDEX_ENTRY_HEADER_SIZE,
+                options,
+            ),
+            None => Ok(true),
+        }
+    }
+
+    /// Returns the raw bytes of the key of this Index Entry,
+    /// or `None` if this Index Entry has no key.
+    pub(crate) fn key_slice(&self) -> Option<Result<&'s [u8]>> {
         // The key/stream is only set when the last entry flag is not set.
         // https://flatcap.github.io/linux-ntfs/ntfs/concepts/index_entry.html
         if self.key_length() == 0 || self.flags().contains(NtfsIndexEntryFlags::LAST_ENTRY) {
@@ -218,7 +276,6 @@ where
 
         let start = INDEX_ENTRY_HEADER_SIZE;
         let end = start + self.key_length() as usize;
-        let position = self.position + start;
 
         let slice = self.slice.get(start..end);
         let slice = iter_try!(slice.ok_or(NtfsError::InvalidIndexEntryDataRange {
@@ -227,8 +284,7 @@ where
             size: self.slice.len() as u16
         }));
 
-        let key = iter_try!(E::KeyType::key_from_slice(slice, position));
-        Some(Ok(key))
+        Some(Ok(slice))
     }
 
     /// Returns the length of the key of this Index Entry.
@@ -297,6 +353,78 @@ where
     }
 }
 
+/// Returns the on-disk representation of an Index Entry with the given key and data bytes.
+///
+/// The data directly follows the key, and `data_padding` fills the gap up to the next 8-byte boundary.
+/// If a subnode VCN is given, it is stored in 8 additional bytes at the end of the entry.
+/// An entry without data still gets a data offset pointing behind the key, just like Windows writes it.
+///
+/// Returns [`NtfsError::IndexEntryTooBig`] if the entry exceeds the 16-bit length fields of its header.
+pub(crate) fn index_entry_to_bytes(
+    key: &[u8],
+    data: &[u8],
+    data_padding: &[u8],
+    subnode_vcn: Option<Vcn>,
+) -> Result<Vec<u8>> {
+    let data_offset = INDEX_ENTRY_HEADER_SIZE + key.len();
+    let data_end = data_offset + data.len();
+    let padding_end = (data_end + 7) & !7;
+    let mut index_entry_length = padding_end;
+
+    let mut flags = NtfsIndexEntryFlags::empty();
+    if subnode_vcn.is_some() {
+        flags |= NtfsIndexEntryFlags::HAS_SUBNODE;
+        index_entry_length += mem::size_of::<Vcn>();
+    }
+
+    // All other lengths are smaller than the entire entry, so they fit if the entry does.
+    let to_u16 = |value: usize| {
+        u16::try_from(value).map_err(|_| NtfsError::IndexEntryTooBig {
+            actual: index_entry_length,
+            max: u16::MAX as usize,
+        })
+    };
+
+    let mut bytes = vec![0u8; index_entry_length];
+    LittleEndian::write_u16(
+        &mut bytes[offset_of!(IndexEntryHeader, data_offset)..],
+        to_u16(data_offset)?,
+    );
+    LittleEndian::write_u16(
+        &mut bytes[offset_of!(IndexEntryHeader, data_length)..],
+        to_u16(data.len())?,
+    );
+    LittleEndian::write_u16(
+        &mut bytes[offset_of!(IndexEntryHeader, index_entry_length)..],
+        to_u16(index_entry_length)?,
+    );
+    LittleEndian::write_u16(
+        &mut bytes[offset_of!(IndexEntryHeader, key_length)..],
+        to_u16(key.len())?,
+    );
+    bytes[offset_of!(IndexEntryHeader, flags)] = flags.bits();
+
+    bytes[INDEX_ENTRY_HEADER_SIZE..data_offset].copy_from_slice(key);
+    bytes[data_offset..data_end].copy_from_slice(data);
+
+    let padding_length = usize::min(data_padding.len(), padding_end - data_end);
+    bytes[data_end..data_end + padding_length].copy_from_slice(&data_padding[..padding_length]);
+
+    if let Some(vcn) = subnode_vcn {
+        let start = index_entry_length - mem::size_of::<Vcn>();
+        LittleEndian::write_i64(&mut bytes[start..], vcn.value());
+    }
+
+    Ok(bytes)
+}
+
+/// Iteration state of an [`IndexNodeEntryRanges`] iterator without the node data.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct IndexNodeCursor {
+    start: usize,
+    position: NtfsPosition,
+}
+
 #[derive(Clone, Debug)]
 pub(crate) struct IndexNodeEntryRanges<E>
 where
@@ -324,9 +452,23 @@ where
         }
     }
 
+    /// Returns the current iteration state, which can be restored via [`Self::set_cursor`] on a
+    /// re-read copy of the same node.
+    pub(crate) fn cursor(&self) -> IndexNodeCursor {
+        IndexNodeCursor {
+            start: self.range.start,
+            position: self.position,
+        }
+    }
+
     pub(crate) fn data(&self) -> &[u8] {
         &self.data
     }
+
+    pub(crate) fn set_cursor(&mut self, cursor: IndexNodeCursor) {
+        self.range.start = cursor.start;
+        self.position = cursor.position;
+    }
 }
 
 impl<E> Iterator for IndexNodeEntryRanges<E>
@@ -434,3 +576,24 @@ where
 }
 
 impl<'s, E> FusedIterator for NtfsIndexNodeEntries<'s, E> where E: NtfsIndexEntryType {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_index_entry_to_bytes_too_big() {
+        let key = vec![0u8; 0xffe8];
+        let bytes = index_entry_to_bytes(&key, &[], &[], None).unwrap();
+        assert_eq!(bytes.len(), 0xffe8 + INDEX_ENTRY_HEADER_SIZE);
+
+        // The subnode VCN no longer fits into the 16-bit entry length.
+        assert!(matches!(
+            index_entry_to_bytes(&key, &[], &[], Some(Vcn::from(1))),
+            Err(NtfsError::IndexEntryTooBig {
+                actual: 0x10000,
+                max: 0xffff
+            })
+        ));
+    }
+}
@@ -12,6 +12,7 @@ use crate::attribute_value::NtfsAttributeValue;
 use crate::error::{NtfsError, Result};
 use crate::index_entry::{IndexNodeEntryRanges, NtfsIndexNodeEntries};
 use crate::indexes::NtfsIndexEntryType;
+use crate::ntfs::Ntfs;
 use crate::record::Record;
 use crate::record::RecordHeader;
 use crate::traits::NtfsReadSeek;
@@ -57,6 +58,7 @@ const HAS_SUBNODES_FLAG: u8 = 0x01;
 
 impl NtfsIndexRecord {
     pub(crate) fn new<T>(
+        ntfs: &Ntfs,
         fs: &mut T,
         mut value: NtfsAttributeValue,
         index_record_size: u32,
@@ -69,7 +71,7 @@ impl NtfsIndexRecord {
         let mut data = vec![0; index_record_size as usize];
         value.read_exact(fs, &mut data)?;
 
-        let mut record = Record::new(data, data_position);
+        let mut record = Record::new(data, data_position, ntfs.sector_size());
         Self::validate_signature(&record)?;
         record.fixup()?;
 
@@ -82,7 +84,7 @@ impl NtfsIndexRecord {
     /// Returns an iterator over all entries of this Index Record (cf. [`NtfsIndexEntry`]).
     ///
     /// [`NtfsIndexEntry`]: crate::NtfsIndexEntry
-    pub fn entries<E>(&self) -> Result<NtfsIndexNodeEntries<E>>
+    pub fn entries<E>(&self) -> Result<NtfsIndexNodeEntries<'_, E>>
     where
         E: NtfsIndexEntryType,
     {
@@ -133,6 +135,11 @@ impl NtfsIndexRecord {
         IndexNodeEntryRanges::new(self.record.into_data(), entries_range, position)
     }
 
+    /// Returns the absolute position of this Index Record within the filesystem, in bytes.
+    pub fn position(&self) -> NtfsPosition {
+        self.record.position()
+    }
+
     fn validate_signature(record: &Record) -> Result<()> {
         let signature = &record.signature();
         let expected = b"INDX";
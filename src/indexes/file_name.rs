@@ -2,14 +2,19 @@
 // SPDX-License-Identifier: MIT OR Apache-2.0
 
 use binrw::io::{Read, Seek};
+use nt_string::u16strle::U16StrLe;
 
+use crate::collation::NtfsCollationRule;
 use crate::error::Result;
 use crate::index::NtfsIndexFinder;
 use crate::index_entry::NtfsIndexEntry;
 use crate::indexes::{NtfsIndexEntryHasFileReference, NtfsIndexEntryType};
 use crate::ntfs::Ntfs;
 use crate::structured_values::NtfsFileName;
-use crate::upcase_table::UpcaseOrd;
+use crate::upcase_table::{file_name_cmp, NtfsCaseSensitivity};
+
+#[cfg(feature = "serde")]
+use serde::ser::{Error, Serialize, SerializeStruct, Serializer};
 
 /// Defines the [`NtfsIndexEntryType`] for filename indexes (commonly known as "directories").
 #[derive(Clone, Copy, Debug)]
@@ -31,14 +36,168 @@ impl NtfsFileNameIndex {
     where
         T: Read + Seek,
     {
-        // TODO: This always performs a case-insensitive comparison.
-        // There are some corner cases where NTFS uses case-sensitive filenames. These need to be considered!
-        index_finder.find(fs, |file_name| name.upcase_cmp(ntfs, &file_name.name()))
+        Self::find_with_case_sensitivity(
+            index_finder,
+            ntfs,
+            fs,
+            name,
+            NtfsCaseSensitivity::Insensitive,
+        )
+    }
+
+    /// Finds a file in a filename index by name and returns the [`NtfsIndexEntry`] (if any).
+    /// The name is compared according to the given [`NtfsCaseSensitivity`].
+    ///
+    /// Even a case-sensitive comparison requires the filesystem's $UpCase table, because
+    /// filename indexes are sorted case-insensitively.
+    ///
+    /// # Panics
+    ///
+    /// Panics if [`read_upcase_table`][Ntfs::read_upcase_table] had not been called on the passed [`Ntfs`] object.
+    pub fn find_with_case_sensitivity<'a, T>(
+        index_finder: &'a mut NtfsIndexFinder<Self>,
+        ntfs: &Ntfs,
+        fs: &mut T,
+        name: &str,
+        case_sensitivity: NtfsCaseSensitivity,
+    ) -> Option<Result<NtfsIndexEntry<'a, Self>>>
+    where
+        T: Read + Seek,
+    {
+        Self::find_code_units(
+            index_finder,
+            ntfs,
+            fs,
+            name.encode_utf16(),
+            case_sensitivity,
+        )
+    }
+
+    /// Finds a file in a filename index by a name given as UTF-16 code units and returns the
+    /// [`NtfsIndexEntry`] (if any).
+    /// The name is compared case-insensitively based on the filesystem's $UpCase table.
+    ///
+    /// This is a variant of [`NtfsFileNameIndex::find`] for names that are already available in UTF-16
+    /// (e.g. from Windows APIs or other NTFS structures), which avoids any conversion.
+    ///
+    /// # Panics
+    ///
+    /// Panics if [`read_upcase_table`][Ntfs::read_upcase_table] had not been called on the passed [`Ntfs`] object.
+    pub fn find_u16<'a, T>(
+        index_finder: &'a mut NtfsIndexFinder<Self>,
+        ntfs: &Ntfs,
+        fs: &mut T,
+        name: &[u16],
+    ) -> Option<Result<NtfsIndexEntry<'a, Self>>>
+    where
+        T: Read + Seek,
+    {
+        Self::find_u16_with_case_sensitivity(
+            index_finder,
+            ntfs,
+            fs,
+            name,
+            NtfsCaseSensitivity::Insensitive,
+        )
+    }
+
+    /// Finds a file in a filename index by a name given as UTF-16 code units and returns the
+    /// [`NtfsIndexEntry`] (if any).
+    /// The name is compared according to the given [`NtfsCaseSensitivity`].
+    ///
+    /// See [`NtfsFileNameIndex::find_with_case_sensitivity`] for details.
+    ///
+    /// # Panics
+    ///
+    /// Panics if [`read_upcase_table`][Ntfs::read_upcase_table] had not been called on the passed [`Ntfs`] object.
+    pub fn find_u16_with_case_sensitivity<'a, T>(
+        index_finder: &'a mut NtfsIndexFinder<Self>,
+        ntfs: &Ntfs,
+        fs: &mut T,
+        name: &[u16],
+        case_sensitivity: NtfsCaseSensitivity,
+    ) -> Option<Result<NtfsIndexEntry<'a, Self>>>
+    where
+        T: Read + Seek,
+    {
+        Self::find_code_units(
+            index_finder,
+            ntfs,
+            fs,
+            name.iter().copied(),
+            case_sensitivity,
+        )
+    }
+
+    /// Finds a file in a filename index by a name given as an iterator over UTF-16 code units.
+    pub(crate) fn find_code_units<'a, T, I>(
+        index_finder: &'a mut NtfsIndexFinder<Self>,
+        ntfs: &Ntfs,
+        fs: &mut T,
+        name: I,
+        case_sensitivity: NtfsCaseSensitivity,
+    ) -> Option<Result<NtfsIndexEntry<'a, Self>>>
+    where
+        T: Read + Seek,
+        I: Iterator<Item = u16> + Clone,
+    {
+        iter_try!(index_finder.check_collation_rule());
+
+        // Compare the names in place instead of copying every key into an `NtfsFileName`.
+        index_finder.find_with(fs, |entry| {
+            let entry_name = entry.key_name()?;
+            Some(
+                entry_name.map(|entry_name| {
+                    file_name_cmp(ntfs, name.clone(), &entry_name, case_sensitivity)
+                }),
+            )
+        })
+    }
+}
+
+impl<'s> NtfsIndexEntry<'s, NtfsFileNameIndex> {
+    /// Returns the file name stored in the key of this entry (if any), borrowing directly from
+    /// the Index Record buffer.
+    ///
+    /// This is a zero-copy alternative to calling [`NtfsFileName::name`] on the result of
+    /// [`NtfsIndexEntry::key`], e.g. for quickly scanning large directories.
+    /// Unlike [`NtfsIndexEntry::key`], it doesn't validate the namespace of the file name.
+    /// Convert the name into an [`NtfsStringBuf`] if it needs to outlive the entry.
+    ///
+    /// [`NtfsStringBuf`]: crate::NtfsStringBuf
+    pub fn key_name(&self) -> Option<Result<U16StrLe<'s>>> {
+        let key = iter_try!(self.key_slice()?);
+        let name = iter_try!(NtfsFileName::name_from_slice(key, self.position()));
+        Some(Ok(name))
     }
 }
 
 impl NtfsIndexEntryType for NtfsFileNameIndex {
     type KeyType = NtfsFileName;
+    const COLLATION_RULE: Option<NtfsCollationRule> = Some(NtfsCollationRule::FileName);
 }
 
 impl NtfsIndexEntryHasFileReference for NtfsFileNameIndex {}
+
+/// Serializes an entry of a filename index along with its [`NtfsFileName`] key.
+///
+/// Any error while reading the key or subnode VCN is reported as a serialization error.
+#[cfg(feature = "serde")]
+#[cfg_attr(docsrs, doc(cfg(feature = "serde")))]
+impl<'s> Serialize for NtfsIndexEntry<'s, NtfsFileNameIndex> {
+    fn serialize<S>(&self, serializer: S) -> core::result::Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let key = self.key().transpose().map_err(S::Error::custom)?;
+        let subnode_vcn = self.subnode_vcn().transpose().map_err(S::Error::custom)?;
+
+        let mut state = serializer.serialize_struct("NtfsIndexEntry", 5)?;
+        state.serialize_field("position", &self.position())?;
+        state.serialize_field("flags", &self.flags())?;
+        state.serialize_field("file_reference", &self.file_reference())?;
+        state.serialize_field("key", &key)?;
+        state.serialize_field("subnode_vcn", &subnode_vcn)?;
+        state.end()
+    }
+}
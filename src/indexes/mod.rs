@@ -15,12 +15,24 @@
 //! [`NtfsIndexRoot`]: crate::structured_values::NtfsIndexRoot
 
 mod file_name;
+mod object_id;
+mod quota;
+mod reparse;
+mod security_hash;
+mod security_id;
 
 pub use file_name::*;
+pub use object_id::*;
+pub use quota::*;
+pub use reparse::*;
+pub use security_hash::*;
+pub use security_id::*;
 
 use core::fmt;
 
+use crate::collation::NtfsCollationRule;
 use crate::error::Result;
+use crate::options::NtfsOptions;
 use crate::types::NtfsPosition;
 
 /// Trait implemented by structures that describe Index Entry types.
@@ -32,11 +44,32 @@ use crate::types::NtfsPosition;
 /// [`NtfsIndexEntry`]: crate::NtfsIndexEntry
 pub trait NtfsIndexEntryType: Clone + fmt::Debug {
     type KeyType: NtfsIndexEntryKey;
+
+    /// The collation rule implemented by the comparisons of this index type.
+    ///
+    /// Finding an entry checks it against the collation rule declared by the Index Root,
+    /// unless this is `None` (the default).
+    const COLLATION_RULE: Option<NtfsCollationRule> = None;
 }
 
 /// Trait implemented by a structure that describes an Index Entry key.
 pub trait NtfsIndexEntryKey: fmt::Debug + Sized {
     fn key_from_slice(slice: &[u8], position: NtfsPosition) -> Result<Self>;
+
+    /// Applies the policies of `options` to the raw key `slice` of an Index Entry at byte position `position`,
+    /// and returns whether [`NtfsIndexEntries`] and [`NtfsIndexFinder`] return that entry.
+    ///
+    /// The default implementation returns every entry.
+    ///
+    /// [`NtfsIndexEntries`]: crate::NtfsIndexEntries
+    /// [`NtfsIndexFinder`]: crate::NtfsIndexFinder
+    fn filter_key_slice(
+        _slice: &[u8],
+        _position: NtfsPosition,
+        _options: &NtfsOptions,
+    ) -> Result<bool> {
+        Ok(true)
+    }
 }
 
 /// Indicates that the Index Entry type has additional data (of [`NtfsIndexEntryData`] datatype).
@@ -0,0 +1,220 @@
+// Copyright 2021-2023 Colin Finck <colin@reactos.org>
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+use core::cmp::Ordering;
+use core::mem;
+
+use binrw::io::{Cursor, Read, Seek};
+use binrw::BinReaderExt;
+use byteorder::{ByteOrder, LittleEndian};
+
+use crate::collation::NtfsCollationRule;
+use crate::error::{NtfsError, Result};
+use crate::file_reference::NtfsFileReference;
+use crate::guid::{NtfsGuid, GUID_SIZE};
+use crate::index::NtfsIndexFinder;
+use crate::index_entry::NtfsIndexEntry;
+use crate::indexes::{
+    NtfsIndexEntryData, NtfsIndexEntryHasData, NtfsIndexEntryKey, NtfsIndexEntryType,
+};
+use crate::types::NtfsPosition;
+
+#[cfg(feature = "serde")]
+use serde::ser::{Error, Serialize, SerializeStruct, Serializer};
+
+/// Size of all [`NtfsObjectIdIndexData`] fields.
+const OBJECT_ID_INDEX_DATA_SIZE: usize = mem::size_of::<NtfsFileReference>() + 3 * GUID_SIZE;
+
+impl NtfsIndexEntryKey for NtfsGuid {
+    fn key_from_slice(slice: &[u8], position: NtfsPosition) -> Result<Self> {
+        if slice.len() < GUID_SIZE {
+            return Err(NtfsError::InvalidIndexEntryKeySize {
+                position,
+                expected: GUID_SIZE,
+                actual: slice.len(),
+            });
+        }
+
+        let mut cursor = Cursor::new(slice);
+        let guid = cursor.read_le::<Self>()?;
+        Ok(guid)
+    }
+}
+
+/// Compares two Object IDs in the order of the $O index, which treats them as sequences of
+/// little-endian 32-bit values.
+fn object_id_cmp(a: &NtfsGuid, b: &NtfsGuid) -> Ordering {
+    fn words(guid: &NtfsGuid) -> [u32; 4] {
+        [
+            guid.data1,
+            guid.data2 as u32 | (guid.data3 as u32) << 16,
+            LittleEndian::read_u32(&guid.data4[..4]),
+            LittleEndian::read_u32(&guid.data4[4..]),
+        ]
+    }
+
+    words(a).cmp(&words(b))
+}
+
+/// Data of an entry in the $O index of the $ObjId file.
+///
+/// This tells which file has a given Object ID, along with the additional IDs also stored in
+/// the $OBJECT_ID attribute of that file (see [`NtfsObjectId`]).
+///
+/// [`NtfsObjectId`]: crate::structured_values::NtfsObjectId
+#[derive(Clone, Debug)]
+pub struct NtfsObjectIdIndexData {
+    file_reference: NtfsFileReference,
+    birth_volume_id: NtfsGuid,
+    birth_object_id: NtfsGuid,
+    domain_id: NtfsGuid,
+}
+
+impl NtfsObjectIdIndexData {
+    /// Returns the first Object ID that has ever been assigned to the file.
+    pub fn birth_object_id(&self) -> &NtfsGuid {
+        &self.birth_object_id
+    }
+
+    /// Returns the Object ID of the volume on which the file was created.
+    pub fn birth_volume_id(&self) -> &NtfsGuid {
+        &self.birth_volume_id
+    }
+
+    /// Returns the domain ID (currently unused by Windows).
+    pub fn domain_id(&self) -> &NtfsGuid {
+        &self.domain_id
+    }
+
+    /// Returns an [`NtfsFileReference`] for the file that has this Object ID.
+    pub fn file_reference(&self) -> NtfsFileReference {
+        self.file_reference
+    }
+}
+
+impl NtfsIndexEntryData for NtfsObjectIdIndexData {
+    fn data_from_slice(slice: &[u8], position: NtfsPosition) -> Result<Self> {
+        if slice.len() < OBJECT_ID_INDEX_DATA_SIZE {
+            return Err(NtfsError::InvalidIndexEntryDataSize {
+                position,
+                expected: OBJECT_ID_INDEX_DATA_SIZE,
+                actual: slice.len(),
+            });
+        }
+
+        let mut cursor = Cursor::new(slice);
+        let file_reference = cursor.read_le::<NtfsFileReference>()?;
+        let birth_volume_id = cursor.read_le::<NtfsGuid>()?;
+        let birth_object_id = cursor.read_le::<NtfsGuid>()?;
+        let domain_id = cursor.read_le::<NtfsGuid>()?;
+
+        Ok(Self {
+            file_reference,
+            birth_volume_id,
+            birth_object_id,
+            domain_id,
+        })
+    }
+}
+
+#[cfg(feature = "serde")]
+#[cfg_attr(docsrs, doc(cfg(feature = "serde")))]
+impl Serialize for NtfsObjectIdIndexData {
+    fn serialize<S>(&self, serializer: S) -> core::result::Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut state = serializer.serialize_struct("NtfsObjectIdIndexData", 4)?;
+        state.serialize_field("file_reference", &self.file_reference)?;
+        state.serialize_field("birth_volume_id", &self.birth_volume_id)?;
+        state.serialize_field("birth_object_id", &self.birth_object_id)?;
+        state.serialize_field("domain_id", &self.domain_id)?;
+        state.end()
+    }
+}
+
+/// Defines the [`NtfsIndexEntryType`] for the $O index of the $ObjId file.
+///
+/// This index maps every Object ID of the filesystem to the file that has it
+/// (see [`NtfsObjectIdIndexData`]).
+///
+/// Reference: <https://flatcap.github.io/linux-ntfs/ntfs/files/objid.html>
+#[derive(Clone, Copy, Debug)]
+pub struct NtfsObjectIdIndex;
+
+impl NtfsObjectIdIndex {
+    /// Finds a file in an $O index of the $ObjId file by its Object ID and returns the
+    /// [`NtfsIndexEntry`] (if any).
+    pub fn find<'a, T>(
+        index_finder: &'a mut NtfsIndexFinder<Self>,
+        fs: &mut T,
+        object_id: &NtfsGuid,
+    ) -> Option<Result<NtfsIndexEntry<'a, Self>>>
+    where
+        T: Read + Seek,
+    {
+        index_finder.find(fs, |key| object_id_cmp(object_id, key))
+    }
+}
+
+impl NtfsIndexEntryType for NtfsObjectIdIndex {
+    type KeyType = NtfsGuid;
+    const COLLATION_RULE: Option<NtfsCollationRule> = Some(NtfsCollationRule::Ulongs);
+}
+
+impl NtfsIndexEntryHasData for NtfsObjectIdIndex {
+    type DataType = NtfsObjectIdIndexData;
+}
+
+/// Serializes an entry of an $O index of the $ObjId file along with its [`NtfsGuid`] key and
+/// [`NtfsObjectIdIndexData`] data.
+///
+/// Any error while reading the key, data, or subnode VCN is reported as a serialization error.
+#[cfg(feature = "serde")]
+#[cfg_attr(docsrs, doc(cfg(feature = "serde")))]
+impl<'s> Serialize for NtfsIndexEntry<'s, NtfsObjectIdIndex> {
+    fn serialize<S>(&self, serializer: S) -> core::result::Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let key = self.key().transpose().map_err(S::Error::custom)?;
+        let data = self.data().transpose().map_err(S::Error::custom)?;
+        let subnode_vcn = self.subnode_vcn().transpose().map_err(S::Error::custom)?;
+
+        let mut state = serializer.serialize_struct("NtfsIndexEntry", 5)?;
+        state.serialize_field("position", &self.position())?;
+        state.serialize_field("flags", &self.flags())?;
+        state.serialize_field("key", &key)?;
+        state.serialize_field("data", &data)?;
+        state.serialize_field("subnode_vcn", &subnode_vcn)?;
+        state.end()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_object_id_cmp() {
+        let guid = |data1, data3, data4_0| NtfsGuid {
+            data1,
+            data2: 0,
+            data3,
+            data4: [data4_0, 0, 0, 0, 0, 0, 0, 0],
+        };
+
+        assert_eq!(
+            object_id_cmp(&guid(1, 0, 0), &guid(1, 0, 0)),
+            Ordering::Equal
+        );
+        assert_eq!(
+            object_id_cmp(&guid(1, 9, 9), &guid(2, 0, 0)),
+            Ordering::Less
+        );
+        assert_eq!(
+            object_id_cmp(&guid(1, 1, 0), &guid(1, 0, 9)),
+            Ordering::Greater
+        );
+    }
+}
@@ -0,0 +1,333 @@
+// Copyright 2021-2023 Colin Finck <colin@reactos.org>
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+use core::fmt;
+use core::mem;
+
+use alloc::vec::Vec;
+use binrw::io::{Read, Seek};
+use byteorder::{ByteOrder, LittleEndian};
+
+use crate::collation::NtfsCollationRule;
+use crate::error::{NtfsError, Result};
+use crate::index::NtfsIndexFinder;
+use crate::index_entry::{index_entry_to_bytes, NtfsIndexEntry};
+use crate::indexes::{
+    NtfsIndexEntryData, NtfsIndexEntryHasData, NtfsIndexEntryKey, NtfsIndexEntryType,
+};
+use crate::quota::NtfsQuotaControlEntry;
+use crate::sid::NtfsSid;
+use crate::types::{NtfsPosition, Vcn};
+
+#[cfg(feature = "serde")]
+use serde::ser::{Error, Serialize, SerializeStruct, Serializer};
+
+/// Windows fills the padding after the data of every $O Index Entry with a 32-bit value of 32.
+const QUOTA_OWNER_ID_DATA_PADDING: [u8; 4] = [32, 0, 0, 0];
+
+/// Identifier of a quota owner, as used by the $O and $Q indexes of the $Quota file.
+///
+/// Owner ID 1 is reserved for the [`NtfsQuotaControlEntry`] holding the default limits.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct NtfsQuotaOwnerId(u32);
+
+impl NtfsQuotaOwnerId {
+    /// Owner ID of the [`NtfsQuotaControlEntry`] holding the default limits.
+    pub const DEFAULTS: Self = Self(1);
+
+    /// Returns the owner ID as a plain number.
+    pub fn value(&self) -> u32 {
+        self.0
+    }
+
+    fn from_slice(slice: &[u8]) -> Option<Self> {
+        if slice.len() < mem::size_of::<u32>() {
+            return None;
+        }
+
+        Some(Self(LittleEndian::read_u32(slice)))
+    }
+}
+
+impl fmt::Display for NtfsQuotaOwnerId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&self.0, f)
+    }
+}
+
+impl From<u32> for NtfsQuotaOwnerId {
+    fn from(value: u32) -> Self {
+        Self(value)
+    }
+}
+
+#[cfg(feature = "serde")]
+#[cfg_attr(docsrs, doc(cfg(feature = "serde")))]
+impl Serialize for NtfsQuotaOwnerId {
+    fn serialize<S>(&self, serializer: S) -> core::result::Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_newtype_struct("NtfsQuotaOwnerId", &self.0)
+    }
+}
+
+impl NtfsIndexEntryKey for NtfsQuotaOwnerId {
+    fn key_from_slice(slice: &[u8], position: NtfsPosition) -> Result<Self> {
+        Self::from_slice(slice).ok_or(NtfsError::InvalidIndexEntryKeySize {
+            position,
+            expected: mem::size_of::<u32>(),
+            actual: slice.len(),
+        })
+    }
+}
+
+impl NtfsIndexEntryData for NtfsQuotaOwnerId {
+    fn data_from_slice(slice: &[u8], position: NtfsPosition) -> Result<Self> {
+        Self::from_slice(slice).ok_or(NtfsError::InvalidIndexEntryDataSize {
+            position,
+            expected: mem::size_of::<u32>(),
+            actual: slice.len(),
+        })
+    }
+}
+
+/// Defines the [`NtfsIndexEntryType`] for the $O index of the $Quota file.
+///
+/// This index maps the [`NtfsSid`] of every quota owner to its [`NtfsQuotaOwnerId`].
+///
+/// Reference: <https://flatcap.github.io/linux-ntfs/ntfs/files/quota.html>
+#[derive(Clone, Copy, Debug)]
+pub struct NtfsQuotaOwnerIdIndex;
+
+impl NtfsQuotaOwnerIdIndex {
+    /// Returns the on-disk representation of an $O Index Entry with the given key and data.
+    ///
+    /// If `subnode_vcn` is given, the entry points to the subnode at that VCN.
+    /// Returns [`NtfsError::IndexEntryTooBig`] if the entry doesn't fit into the 16-bit length fields of an Index Entry.
+    pub fn entry_to_bytes(
+        sid: &NtfsSid,
+        owner_id: NtfsQuotaOwnerId,
+        subnode_vcn: Option<Vcn>,
+    ) -> Result<Vec<u8>> {
+        index_entry_to_bytes(
+            sid.as_bytes(),
+            &owner_id.value().to_le_bytes(),
+            &QUOTA_OWNER_ID_DATA_PADDING,
+            subnode_vcn,
+        )
+    }
+
+    /// Finds a quota owner in an $O index by its SID and returns the [`NtfsIndexEntry`] (if any).
+    pub fn find<'a, T>(
+        index_finder: &'a mut NtfsIndexFinder<Self>,
+        fs: &mut T,
+        sid: &NtfsSid,
+    ) -> Option<Result<NtfsIndexEntry<'a, Self>>>
+    where
+        T: Read + Seek,
+    {
+        index_finder.find(fs, |key| sid.cmp(key))
+    }
+}
+
+impl NtfsIndexEntryType for NtfsQuotaOwnerIdIndex {
+    type KeyType = NtfsSid;
+    const COLLATION_RULE: Option<NtfsCollationRule> = Some(NtfsCollationRule::Sid);
+}
+
+impl NtfsIndexEntryHasData for NtfsQuotaOwnerIdIndex {
+    type DataType = NtfsQuotaOwnerId;
+}
+
+/// Defines the [`NtfsIndexEntryType`] for the $Q index of the $Quota file.
+///
+/// This index maps an [`NtfsQuotaOwnerId`] to the [`NtfsQuotaControlEntry`] with the quota
+/// limits, usage, and SID of that owner.
+///
+/// Reference: <https://flatcap.github.io/linux-ntfs/ntfs/files/quota.html>
+#[derive(Clone, Copy, Debug)]
+pub struct NtfsQuotaIndex;
+
+impl NtfsQuotaIndex {
+    /// Returns the on-disk representation of a $Q Index Entry with the given key and data.
+    ///
+    /// If `subnode_vcn` is given, the entry points to the subnode at that VCN.
+    /// Returns [`NtfsError::IndexEntryTooBig`] if the entry doesn't fit into the 16-bit length fields of an Index Entry.
+    pub fn entry_to_bytes(
+        owner_id: NtfsQuotaOwnerId,
+        control_entry: &NtfsQuotaControlEntry,
+        subnode_vcn: Option<Vcn>,
+    ) -> Result<Vec<u8>> {
+        index_entry_to_bytes(
+            &owner_id.value().to_le_bytes(),
+            &control_entry.to_bytes(),
+            &[],
+            subnode_vcn,
+        )
+    }
+
+    /// Finds a Quota Control Entry in a $Q index by its owner ID and returns the [`NtfsIndexEntry`] (if any).
+    pub fn find<'a, T>(
+        index_finder: &'a mut NtfsIndexFinder<Self>,
+        fs: &mut T,
+        owner_id: NtfsQuotaOwnerId,
+    ) -> Option<Result<NtfsIndexEntry<'a, Self>>>
+    where
+        T: Read + Seek,
+    {
+        index_finder.find(fs, |key| owner_id.cmp(key))
+    }
+}
+
+impl NtfsIndexEntryType for NtfsQuotaIndex {
+    type KeyType = NtfsQuotaOwnerId;
+    const COLLATION_RULE: Option<NtfsCollationRule> = Some(NtfsCollationRule::Ulong);
+}
+
+impl NtfsIndexEntryHasData for NtfsQuotaIndex {
+    type DataType = NtfsQuotaControlEntry;
+}
+
+/// Serializes an entry of an $O index along with its [`NtfsSid`] key and [`NtfsQuotaOwnerId`] data.
+///
+/// Any error while reading the key, data, or subnode VCN is reported as a serialization error.
+#[cfg(feature = "serde")]
+#[cfg_attr(docsrs, doc(cfg(feature = "serde")))]
+impl<'s> Serialize for NtfsIndexEntry<'s, NtfsQuotaOwnerIdIndex> {
+    fn serialize<S>(&self, serializer: S) -> core::result::Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let key = self.key().transpose().map_err(S::Error::custom)?;
+        let data = self.data().transpose().map_err(S::Error::custom)?;
+        let subnode_vcn = self.subnode_vcn().transpose().map_err(S::Error::custom)?;
+
+        let mut state = serializer.serialize_struct("NtfsIndexEntry", 5)?;
+        state.serialize_field("position", &self.position())?;
+        state.serialize_field("flags", &self.flags())?;
+        state.serialize_field("key", &key)?;
+        state.serialize_field("data", &data)?;
+        state.serialize_field("subnode_vcn", &subnode_vcn)?;
+        state.end()
+    }
+}
+
+/// Serializes an entry of a $Q index along with its [`NtfsQuotaOwnerId`] key and
+/// [`NtfsQuotaControlEntry`] data.
+///
+/// Any error while reading the key, data, or subnode VCN is reported as a serialization error.
+#[cfg(feature = "serde")]
+#[cfg_attr(docsrs, doc(cfg(feature = "serde")))]
+impl<'s> Serialize for NtfsIndexEntry<'s, NtfsQuotaIndex> {
+    fn serialize<S>(&self, serializer: S) -> core::result::Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let key = self.key().transpose().map_err(S::Error::custom)?;
+        let data = self.data().transpose().map_err(S::Error::custom)?;
+        let subnode_vcn = self.subnode_vcn().transpose().map_err(S::Error::custom)?;
+
+        let mut state = serializer.serialize_struct("NtfsIndexEntry", 5)?;
+        state.serialize_field("position", &self.position())?;
+        state.serialize_field("flags", &self.flags())?;
+        state.serialize_field("key", &key)?;
+        state.serialize_field("data", &data)?;
+        state.serialize_field("subnode_vcn", &subnode_vcn)?;
+        state.end()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec::Vec;
+
+    use crate::indexes::{NtfsQuotaIndex, NtfsQuotaOwnerId, NtfsQuotaOwnerIdIndex};
+    use crate::ntfs::Ntfs;
+    use crate::quota::NtfsQuotaFlags;
+    use crate::sid::NtfsSid;
+
+    #[test]
+    fn test_quota_indexes() {
+        let mut testfs1 = crate::helpers::tests::testfs1();
+        let ntfs = Ntfs::new(&mut testfs1).unwrap();
+        let quota_file = ntfs.extend_file(&mut testfs1, "$Quota").unwrap().unwrap();
+
+        // mkntfs registers the Administrators group as the only quota owner.
+        let administrators = NtfsSid::new(5, &[32, 544]).unwrap();
+        let owner_id_index = quota_file
+            .index::<NtfsQuotaOwnerIdIndex, _>(&mut testfs1, "$O")
+            .unwrap();
+        let mut owner_id_finder = owner_id_index.finder();
+        let entry =
+            NtfsQuotaOwnerIdIndex::find(&mut owner_id_finder, &mut testfs1, &administrators)
+                .unwrap()
+                .unwrap();
+        let owner_id = entry.data().unwrap().unwrap();
+        assert_eq!(owner_id.value(), 0x100);
+
+        // Serializing the entry again must yield the bytes of the entry.
+        let bytes = NtfsQuotaOwnerIdIndex::entry_to_bytes(&administrators, owner_id, None).unwrap();
+        assert_eq!(entry.as_bytes(), bytes);
+
+        let everyone = NtfsSid::new(1, &[0]).unwrap();
+        assert!(
+            NtfsQuotaOwnerIdIndex::find(&mut owner_id_finder, &mut testfs1, &everyone).is_none()
+        );
+
+        // Resolve the owner ID back to the SID via the $Q index.
+        let quota_index = quota_file
+            .index::<NtfsQuotaIndex, _>(&mut testfs1, "$Q")
+            .unwrap();
+        let mut quota_finder = quota_index.finder();
+        let entry = NtfsQuotaIndex::find(&mut quota_finder, &mut testfs1, owner_id)
+            .unwrap()
+            .unwrap();
+        let control_entry = entry.data().unwrap().unwrap();
+        assert_eq!(control_entry.sid(), Some(&administrators));
+        assert_eq!(control_entry.limit(), None);
+        assert_eq!(control_entry.threshold(), None);
+
+        // Enumerate all entries, beginning with the defaults entry.
+        let mut entries = quota_index.entries();
+        let mut owner_ids = Vec::new();
+
+        while let Some(entry) = entries.next(&mut testfs1) {
+            let entry = entry.unwrap();
+            let key = match entry.key() {
+                Some(key) => key.unwrap(),
+                None => continue,
+            };
+            let control_entry = entry.data().unwrap().unwrap();
+
+            let bytes = NtfsQuotaIndex::entry_to_bytes(key, &control_entry, None).unwrap();
+            assert_eq!(entry.as_bytes(), bytes);
+
+            if key == NtfsQuotaOwnerId::DEFAULTS {
+                assert!(control_entry
+                    .flags()
+                    .contains(NtfsQuotaFlags::DEFAULT_LIMITS));
+                assert!(control_entry.sid().is_none());
+
+                // The defaults entry ends at the end of a sector in testfs1.
+                // Its zeroed padding is therefore partly replaced by the Update Sequence Number on disk,
+                // which is why the entry bytes must be compared after the fixups.
+                let position = entry.position().value().unwrap().get() as usize;
+                let end = position + bytes.len();
+                assert_eq!(end % 512, 0);
+                assert_eq!(
+                    &testfs1.get_ref()[position..end - 2],
+                    &bytes[..bytes.len() - 2]
+                );
+                assert_eq!(&bytes[bytes.len() - 4..], &[0, 0, 0, 0]);
+            }
+
+            owner_ids.push(key);
+        }
+
+        assert_eq!(
+            owner_ids,
+            [NtfsQuotaOwnerId::DEFAULTS, NtfsQuotaOwnerId::from(0x100)]
+        );
+    }
+}
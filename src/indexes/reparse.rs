@@ -0,0 +1,240 @@
+// Copyright 2021-2023 Colin Finck <colin@reactos.org>
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+use core::cmp::Ordering;
+use core::mem;
+
+use alloc::vec::Vec;
+use binrw::io::{Read, Seek};
+use byteorder::{ByteOrder, LittleEndian};
+
+use crate::collation::NtfsCollationRule;
+use crate::error::{NtfsError, Result};
+use crate::file_reference::NtfsFileReference;
+use crate::index::NtfsIndexFinder;
+use crate::index_entry::{index_entry_to_bytes, NtfsIndexEntry};
+use crate::indexes::{NtfsIndexEntryKey, NtfsIndexEntryType};
+use crate::structured_values::NtfsReparseTag;
+use crate::types::{NtfsPosition, Vcn};
+
+#[cfg(feature = "serde")]
+use serde::ser::{Error, Serialize, SerializeStruct, Serializer};
+
+/// Size of all [`NtfsReparseKey`] fields.
+const REPARSE_KEY_SIZE: usize = mem::size_of::<u32>() + mem::size_of::<NtfsFileReference>();
+
+/// Key of an entry in the $R index of the $Reparse file.
+///
+/// Keys are ordered by reparse tag first and file reference second, just like the index.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct NtfsReparseKey {
+    tag: NtfsReparseTag,
+    file_reference: NtfsFileReference,
+}
+
+impl NtfsReparseKey {
+    /// Creates a new key from a reparse tag and a reference to the file with that reparse point.
+    pub fn new(tag: NtfsReparseTag, file_reference: NtfsFileReference) -> Self {
+        Self {
+            tag,
+            file_reference,
+        }
+    }
+
+    /// Returns an [`NtfsFileReference`] for the file that has this reparse point.
+    pub fn file_reference(&self) -> NtfsFileReference {
+        self.file_reference
+    }
+
+    /// Returns the tag of the reparse point.
+    pub fn tag(&self) -> NtfsReparseTag {
+        self.tag
+    }
+
+    fn to_bytes(self) -> [u8; REPARSE_KEY_SIZE] {
+        let mut bytes = [0u8; REPARSE_KEY_SIZE];
+        LittleEndian::write_u32(&mut bytes, self.tag.value());
+        bytes[4..].copy_from_slice(&self.file_reference.to_bytes());
+        bytes
+    }
+
+    fn words(&self) -> [u32; 3] {
+        let file_reference = self.file_reference.file_record_number()
+            | (self.file_reference.sequence_number() as u64) << 48;
+
+        [
+            self.tag.value(),
+            file_reference as u32,
+            (file_reference >> 32) as u32,
+        ]
+    }
+}
+
+impl Ord for NtfsReparseKey {
+    /// Compares the keys as sequences of little-endian 32-bit values, as done by the $R index.
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.words().cmp(&other.words())
+    }
+}
+
+impl PartialOrd for NtfsReparseKey {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+#[cfg(feature = "serde")]
+#[cfg_attr(docsrs, doc(cfg(feature = "serde")))]
+impl Serialize for NtfsReparseKey {
+    fn serialize<S>(&self, serializer: S) -> core::result::Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut state = serializer.serialize_struct("NtfsReparseKey", 2)?;
+        state.serialize_field("tag", &self.tag)?;
+        state.serialize_field("file_reference", &self.file_reference)?;
+        state.end()
+    }
+}
+
+impl NtfsIndexEntryKey for NtfsReparseKey {
+    fn key_from_slice(slice: &[u8], position: NtfsPosition) -> Result<Self> {
+        if slice.len() < REPARSE_KEY_SIZE {
+            return Err(NtfsError::InvalidIndexEntryKeySize {
+                position,
+                expected: REPARSE_KEY_SIZE,
+                actual: slice.len(),
+            });
+        }
+
+        let tag = NtfsReparseTag::from(LittleEndian::read_u32(slice));
+        let file_reference = NtfsFileReference::new(slice[4..REPARSE_KEY_SIZE].try_into().unwrap());
+
+        Ok(Self {
+            tag,
+            file_reference,
+        })
+    }
+}
+
+/// Defines the [`NtfsIndexEntryType`] for the $R index of the $Reparse file.
+///
+/// This index lists all reparse points of the filesystem by their tag and the file they belong to.
+/// It has no data, all information is in the [`NtfsReparseKey`].
+///
+/// Reference: <https://flatcap.github.io/linux-ntfs/ntfs/files/reparse.html>
+#[derive(Clone, Copy, Debug)]
+pub struct NtfsReparseIndex;
+
+impl NtfsReparseIndex {
+    /// Returns the on-disk representation of an $R Index Entry with the given key.
+    ///
+    /// If `subnode_vcn` is given, the entry points to the subnode at that VCN.
+    /// Returns [`NtfsError::IndexEntryTooBig`] if the entry doesn't fit into the 16-bit length fields of an Index Entry.
+    pub fn entry_to_bytes(key: NtfsReparseKey, subnode_vcn: Option<Vcn>) -> Result<Vec<u8>> {
+        index_entry_to_bytes(&key.to_bytes(), &[], &[], subnode_vcn)
+    }
+
+    /// Finds a reparse point in an $R index by its tag and file reference and returns the
+    /// [`NtfsIndexEntry`] (if any).
+    pub fn find<'a, T>(
+        index_finder: &'a mut NtfsIndexFinder<Self>,
+        fs: &mut T,
+        key: NtfsReparseKey,
+    ) -> Option<Result<NtfsIndexEntry<'a, Self>>>
+    where
+        T: Read + Seek,
+    {
+        index_finder.find(fs, |other| key.cmp(other))
+    }
+}
+
+impl NtfsIndexEntryType for NtfsReparseIndex {
+    type KeyType = NtfsReparseKey;
+    const COLLATION_RULE: Option<NtfsCollationRule> = Some(NtfsCollationRule::Ulongs);
+}
+
+/// Serializes an entry of an $R index along with its [`NtfsReparseKey`] key.
+///
+/// Any error while reading the key or subnode VCN is reported as a serialization error.
+#[cfg(feature = "serde")]
+#[cfg_attr(docsrs, doc(cfg(feature = "serde")))]
+impl<'s> Serialize for NtfsIndexEntry<'s, NtfsReparseIndex> {
+    fn serialize<S>(&self, serializer: S) -> core::result::Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let key = self.key().transpose().map_err(S::Error::custom)?;
+        let subnode_vcn = self.subnode_vcn().transpose().map_err(S::Error::custom)?;
+
+        let mut state = serializer.serialize_struct("NtfsIndexEntry", 4)?;
+        state.serialize_field("position", &self.position())?;
+        state.serialize_field("flags", &self.flags())?;
+        state.serialize_field("key", &key)?;
+        state.serialize_field("subnode_vcn", &subnode_vcn)?;
+        state.end()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::index_entry::NtfsIndexEntryFlags;
+    use crate::ntfs::Ntfs;
+
+    #[test]
+    fn test_reparse_key() {
+        let key = NtfsReparseKey::key_from_slice(
+            &[
+                0x03, 0x00, 0x00, 0xa0, 0x40, 0x00, 0x00, 0x00, 0x00, 0x00, 0x02, 0x00,
+            ],
+            NtfsPosition::none(),
+        )
+        .unwrap();
+        assert_eq!(key.tag(), NtfsReparseTag::MOUNT_POINT);
+        assert_eq!(key.file_reference().file_record_number(), 0x40);
+        assert_eq!(key.file_reference().sequence_number(), 2);
+
+        // The tag has precedence over the file reference.
+        let symlink = NtfsReparseKey::new(
+            NtfsReparseTag::SYMLINK,
+            NtfsFileReference::new([0x10, 0, 0, 0, 0, 0, 0, 0]),
+        );
+        assert!(key < symlink);
+
+        // Windows lets the data offset of $R entries point behind the key, even though they have no data.
+        let bytes = NtfsReparseIndex::entry_to_bytes(key, None).unwrap();
+        assert_eq!(
+            bytes,
+            [
+                0x1c, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x20, 0x00, 0x0c, 0x00, 0x00, 0x00,
+                0x00, 0x00, 0x03, 0x00, 0x00, 0xa0, 0x40, 0x00, 0x00, 0x00, 0x00, 0x00, 0x02, 0x00,
+                0x00, 0x00, 0x00, 0x00,
+            ]
+        );
+
+        let bytes = NtfsReparseIndex::entry_to_bytes(key, Some(Vcn::from(5))).unwrap();
+        assert_eq!(bytes.len(), 0x28);
+        assert_eq!(bytes[0x08], 0x28);
+        assert_eq!(bytes[0x0c], NtfsIndexEntryFlags::HAS_SUBNODE.bits());
+        assert_eq!(bytes[0x20..], [5, 0, 0, 0, 0, 0, 0, 0]);
+
+        assert!(matches!(
+            NtfsReparseKey::key_from_slice(&[0; 8], NtfsPosition::none()),
+            Err(NtfsError::InvalidIndexEntryKeySize { .. })
+        ));
+    }
+
+    #[test]
+    fn test_reparse_index() {
+        let mut testfs1 = crate::helpers::tests::testfs1();
+        let ntfs = Ntfs::new(&mut testfs1).unwrap();
+        let reparse_file = ntfs.extend_file(&mut testfs1, "$Reparse").unwrap().unwrap();
+
+        // testfs1 has no reparse points.
+        let index = reparse_file
+            .index::<NtfsReparseIndex, _>(&mut testfs1, "$R")
+            .unwrap();
+        assert!(index.entries().next(&mut testfs1).is_none());
+    }
+}
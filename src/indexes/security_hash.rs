@@ -0,0 +1,218 @@
+// Copyright 2021-2023 Colin Finck <colin@reactos.org>
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+use core::mem;
+
+use alloc::vec::Vec;
+use binrw::io::{Read, Seek};
+use byteorder::{ByteOrder, LittleEndian};
+
+use crate::collation::NtfsCollationRule;
+use crate::error::{NtfsError, Result};
+use crate::index::NtfsIndexFinder;
+use crate::index_entry::{index_entry_to_bytes, NtfsIndexEntry};
+use crate::indexes::{
+    NtfsIndexEntryHasData, NtfsIndexEntryKey, NtfsIndexEntryType, NtfsSecurityId,
+};
+use crate::security_descriptor::NtfsSecurityDescriptorHeader;
+use crate::types::{NtfsPosition, Vcn};
+
+#[cfg(feature = "serde")]
+use serde::ser::{Error, Serialize, SerializeStruct, Serializer};
+
+/// Size of all [`NtfsSecurityHashKey`] fields.
+const SECURITY_HASH_KEY_SIZE: usize = 2 * mem::size_of::<u32>();
+
+/// Windows fills the padding after the data of every $SDH Index Entry with the UTF-16 string "II".
+const SECURITY_HASH_DATA_PADDING: [u8; 4] = [b'I', 0, b'I', 0];
+
+/// Key of an entry in the $SDH index of the $Secure file.
+///
+/// Different Security Descriptors may have the same hash, which is why the key also includes the security ID.
+/// Keys are ordered by hash first and security ID second, just like the index.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct NtfsSecurityHashKey {
+    hash: u32,
+    security_id: NtfsSecurityId,
+}
+
+impl NtfsSecurityHashKey {
+    /// Creates a new key from a Security Descriptor hash and a security ID.
+    pub fn new(hash: u32, security_id: NtfsSecurityId) -> Self {
+        Self { hash, security_id }
+    }
+
+    /// Returns the hash over the Security Descriptor.
+    pub fn hash(&self) -> u32 {
+        self.hash
+    }
+
+    /// Returns the security ID of the Security Descriptor.
+    pub fn security_id(&self) -> NtfsSecurityId {
+        self.security_id
+    }
+
+    fn to_bytes(self) -> [u8; SECURITY_HASH_KEY_SIZE] {
+        let mut bytes = [0u8; SECURITY_HASH_KEY_SIZE];
+        LittleEndian::write_u32(&mut bytes, self.hash);
+        LittleEndian::write_u32(&mut bytes[4..], self.security_id.value());
+        bytes
+    }
+}
+
+#[cfg(feature = "serde")]
+#[cfg_attr(docsrs, doc(cfg(feature = "serde")))]
+impl Serialize for NtfsSecurityHashKey {
+    fn serialize<S>(&self, serializer: S) -> core::result::Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut state = serializer.serialize_struct("NtfsSecurityHashKey", 2)?;
+        state.serialize_field("hash", &self.hash)?;
+        state.serialize_field("security_id", &self.security_id)?;
+        state.end()
+    }
+}
+
+impl NtfsIndexEntryKey for NtfsSecurityHashKey {
+    fn key_from_slice(slice: &[u8], position: NtfsPosition) -> Result<Self> {
+        if slice.len() < SECURITY_HASH_KEY_SIZE {
+            return Err(NtfsError::InvalidIndexEntryKeySize {
+                position,
+                expected: SECURITY_HASH_KEY_SIZE,
+                actual: slice.len(),
+            });
+        }
+
+        let hash = LittleEndian::read_u32(slice);
+        let security_id = NtfsSecurityId::from(LittleEndian::read_u32(&slice[4..]));
+
+        Ok(Self { hash, security_id })
+    }
+}
+
+/// Defines the [`NtfsIndexEntryType`] for the $SDH index of the $Secure file.
+///
+/// This index maps the hash of a Security Descriptor (along with its security ID) to the
+/// [`NtfsSecurityDescriptorHeader`] of that Security Descriptor in the $SDS data stream.
+/// It is used to find an existing identical Security Descriptor before adding a new one.
+///
+/// Reference: <https://flatcap.github.io/linux-ntfs/ntfs/files/secure.html>
+#[derive(Clone, Copy, Debug)]
+pub struct NtfsSecurityHashIndex;
+
+impl NtfsSecurityHashIndex {
+    /// Returns the on-disk representation of an $SDH Index Entry with the given key and data.
+    ///
+    /// If `subnode_vcn` is given, the entry points to the subnode at that VCN.
+    /// Returns [`NtfsError::IndexEntryTooBig`] if the entry doesn't fit into the 16-bit length fields of an Index Entry.
+    pub fn entry_to_bytes(
+        key: NtfsSecurityHashKey,
+        header: &NtfsSecurityDescriptorHeader,
+        subnode_vcn: Option<Vcn>,
+    ) -> Result<Vec<u8>> {
+        index_entry_to_bytes(
+            &key.to_bytes(),
+            &header.to_bytes(),
+            &SECURITY_HASH_DATA_PADDING,
+            subnode_vcn,
+        )
+    }
+
+    /// Finds a Security Descriptor in an $SDH index by its hash and security ID and returns the
+    /// [`NtfsIndexEntry`] (if any).
+    pub fn find<'a, T>(
+        index_finder: &'a mut NtfsIndexFinder<Self>,
+        fs: &mut T,
+        key: NtfsSecurityHashKey,
+    ) -> Option<Result<NtfsIndexEntry<'a, Self>>>
+    where
+        T: Read + Seek,
+    {
+        index_finder.find(fs, |other| key.cmp(other))
+    }
+}
+
+impl NtfsIndexEntryType for NtfsSecurityHashIndex {
+    type KeyType = NtfsSecurityHashKey;
+    const COLLATION_RULE: Option<NtfsCollationRule> = Some(NtfsCollationRule::SecurityHash);
+}
+
+impl NtfsIndexEntryHasData for NtfsSecurityHashIndex {
+    type DataType = NtfsSecurityDescriptorHeader;
+}
+
+/// Serializes an entry of an $SDH index along with its [`NtfsSecurityHashKey`] key and
+/// [`NtfsSecurityDescriptorHeader`] data.
+///
+/// Any error while reading the key, data, or subnode VCN is reported as a serialization error.
+#[cfg(feature = "serde")]
+#[cfg_attr(docsrs, doc(cfg(feature = "serde")))]
+impl<'s> Serialize for NtfsIndexEntry<'s, NtfsSecurityHashIndex> {
+    fn serialize<S>(&self, serializer: S) -> core::result::Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let key = self.key().transpose().map_err(S::Error::custom)?;
+        let data = self.data().transpose().map_err(S::Error::custom)?;
+        let subnode_vcn = self.subnode_vcn().transpose().map_err(S::Error::custom)?;
+
+        let mut state = serializer.serialize_struct("NtfsIndexEntry", 5)?;
+        state.serialize_field("position", &self.position())?;
+        state.serialize_field("flags", &self.flags())?;
+        state.serialize_field("key", &key)?;
+        state.serialize_field("data", &data)?;
+        state.serialize_field("subnode_vcn", &subnode_vcn)?;
+        state.end()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::file::KnownNtfsFileRecordNumber;
+    use crate::indexes::{NtfsSecurityHashIndex, NtfsSecurityHashKey, NtfsSecurityIdIndex};
+    use crate::ntfs::Ntfs;
+
+    #[test]
+    fn test_security_hash_index() {
+        let mut testfs1 = crate::helpers::tests::testfs1();
+        let ntfs = Ntfs::new(&mut testfs1).unwrap();
+        let secure_file = ntfs
+            .file(&mut testfs1, KnownNtfsFileRecordNumber::Secure as u64)
+            .unwrap();
+
+        // Every Security Descriptor of the $SII index can be found by its hash in the $SDH index.
+        let sii_index = secure_file
+            .index::<NtfsSecurityIdIndex, _>(&mut testfs1, "$SII")
+            .unwrap();
+        let sdh_index = secure_file
+            .index::<NtfsSecurityHashIndex, _>(&mut testfs1, "$SDH")
+            .unwrap();
+        let mut sii_entries = sii_index.entries();
+        let mut sdh_finder = sdh_index.finder();
+        let mut count = 0;
+
+        while let Some(sii_entry) = sii_entries.next(&mut testfs1) {
+            let sii_entry = sii_entry.unwrap();
+            let header = match sii_entry.data() {
+                Some(header) => header.unwrap(),
+                None => continue,
+            };
+
+            let key = NtfsSecurityHashKey::new(header.hash(), header.security_id());
+            let sdh_entry = NtfsSecurityHashIndex::find(&mut sdh_finder, &mut testfs1, key)
+                .unwrap()
+                .unwrap();
+            assert_eq!(sdh_entry.key().unwrap().unwrap(), key);
+            assert_eq!(sdh_entry.data().unwrap().unwrap().offset(), header.offset());
+
+            // Serializing the entry again must yield the bytes of the entry, including the "II" padding.
+            let bytes = NtfsSecurityHashIndex::entry_to_bytes(key, &header, None).unwrap();
+            assert_eq!(sdh_entry.as_bytes(), bytes);
+
+            count += 1;
+        }
+
+        assert_eq!(count, 2);
+    }
+}
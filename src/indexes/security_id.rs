@@ -0,0 +1,144 @@
+// Copyright 2021-2023 Colin Finck <colin@reactos.org>
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+use core::fmt;
+use core::mem;
+
+use alloc::vec::Vec;
+use binrw::io::{Read, Seek};
+use byteorder::{ByteOrder, LittleEndian};
+
+use crate::collation::NtfsCollationRule;
+use crate::error::{NtfsError, Result};
+use crate::index::NtfsIndexFinder;
+use crate::index_entry::{index_entry_to_bytes, NtfsIndexEntry};
+use crate::indexes::{NtfsIndexEntryHasData, NtfsIndexEntryKey, NtfsIndexEntryType};
+use crate::security_descriptor::NtfsSecurityDescriptorHeader;
+use crate::types::{NtfsPosition, Vcn};
+
+#[cfg(feature = "serde")]
+use serde::ser::{Error, Serialize, SerializeStruct, Serializer};
+
+/// Identifier of a Security Descriptor, as referenced by [`NtfsStandardInformation::security_id`].
+///
+/// [`NtfsStandardInformation::security_id`]: crate::structured_values::NtfsStandardInformation::security_id
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct NtfsSecurityId(u32);
+
+impl NtfsSecurityId {
+    /// Returns the security ID as a plain number.
+    pub fn value(&self) -> u32 {
+        self.0
+    }
+}
+
+impl fmt::Display for NtfsSecurityId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&self.0, f)
+    }
+}
+
+impl From<u32> for NtfsSecurityId {
+    fn from(value: u32) -> Self {
+        Self(value)
+    }
+}
+
+#[cfg(feature = "serde")]
+#[cfg_attr(docsrs, doc(cfg(feature = "serde")))]
+impl Serialize for NtfsSecurityId {
+    fn serialize<S>(&self, serializer: S) -> core::result::Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_newtype_struct("NtfsSecurityId", &self.0)
+    }
+}
+
+impl NtfsIndexEntryKey for NtfsSecurityId {
+    fn key_from_slice(slice: &[u8], position: NtfsPosition) -> Result<Self> {
+        if slice.len() < mem::size_of::<u32>() {
+            return Err(NtfsError::InvalidIndexEntryKeySize {
+                position,
+                expected: mem::size_of::<u32>(),
+                actual: slice.len(),
+            });
+        }
+
+        Ok(Self(LittleEndian::read_u32(slice)))
+    }
+}
+
+/// Defines the [`NtfsIndexEntryType`] for the $SII index of the $Secure file.
+///
+/// This index maps a security ID to the [`NtfsSecurityDescriptorHeader`] of the corresponding
+/// Security Descriptor in the $SDS data stream.
+///
+/// Reference: <https://flatcap.github.io/linux-ntfs/ntfs/files/secure.html>
+#[derive(Clone, Copy, Debug)]
+pub struct NtfsSecurityIdIndex;
+
+impl NtfsSecurityIdIndex {
+    /// Returns the on-disk representation of a $SII Index Entry with the given key and data.
+    ///
+    /// If `subnode_vcn` is given, the entry points to the subnode at that VCN.
+    /// Returns [`NtfsError::IndexEntryTooBig`] if the entry doesn't fit into the 16-bit length fields of an Index Entry.
+    pub fn entry_to_bytes(
+        security_id: NtfsSecurityId,
+        header: &NtfsSecurityDescriptorHeader,
+        subnode_vcn: Option<Vcn>,
+    ) -> Result<Vec<u8>> {
+        index_entry_to_bytes(
+            &security_id.value().to_le_bytes(),
+            &header.to_bytes(),
+            &[],
+            subnode_vcn,
+        )
+    }
+
+    /// Finds a Security Descriptor in a $SII index by its security ID and returns the [`NtfsIndexEntry`] (if any).
+    pub fn find<'a, T>(
+        index_finder: &'a mut NtfsIndexFinder<Self>,
+        fs: &mut T,
+        security_id: NtfsSecurityId,
+    ) -> Option<Result<NtfsIndexEntry<'a, Self>>>
+    where
+        T: Read + Seek,
+    {
+        index_finder.find(fs, |key| security_id.cmp(key))
+    }
+}
+
+impl NtfsIndexEntryType for NtfsSecurityIdIndex {
+    type KeyType = NtfsSecurityId;
+    const COLLATION_RULE: Option<NtfsCollationRule> = Some(NtfsCollationRule::Ulong);
+}
+
+impl NtfsIndexEntryHasData for NtfsSecurityIdIndex {
+    type DataType = NtfsSecurityDescriptorHeader;
+}
+
+/// Serializes an entry of a $SII index along with its [`NtfsSecurityId`] key and
+/// [`NtfsSecurityDescriptorHeader`] data.
+///
+/// Any error while reading the key, data, or subnode VCN is reported as a serialization error.
+#[cfg(feature = "serde")]
+#[cfg_attr(docsrs, doc(cfg(feature = "serde")))]
+impl<'s> Serialize for NtfsIndexEntry<'s, NtfsSecurityIdIndex> {
+    fn serialize<S>(&self, serializer: S) -> core::result::Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let key = self.key().transpose().map_err(S::Error::custom)?;
+        let data = self.data().transpose().map_err(S::Error::custom)?;
+        let subnode_vcn = self.subnode_vcn().transpose().map_err(S::Error::custom)?;
+
+        let mut state = serializer.serialize_struct("NtfsIndexEntry", 5)?;
+        state.serialize_field("position", &self.position())?;
+        state.serialize_field("flags", &self.flags())?;
+        state.serialize_field("key", &key)?;
+        state.serialize_field("data", &data)?;
+        state.serialize_field("subnode_vcn", &subnode_vcn)?;
+        state.end()
+    }
+}
@@ -14,6 +14,16 @@
 //! 2. Retrieve the [`NtfsFile`] of the root directory via [`Ntfs::root_directory`].
 //! 3. Dig into its attributes via [`NtfsFile::attributes`], go even deeper via [`NtfsFile::attributes_raw`] or use one of the convenience functions, like [`NtfsFile::directory_index`], [`NtfsFile::info`] or [`NtfsFile::name`].
 //!
+//! # API tiers
+//! The public API is organized in three tiers, which are available as modules:
+//!
+//! * [`raw`] gives access to on-disk structures like the boot sector, File Records, attributes, and Index Records.
+//! * [`mid`] builds files and indexes from them.
+//! * [`high`] works with paths, walks directory trees, and extracts data, and can degrade gracefully when structures of the lower tiers are damaged.
+//!
+//! Every tier also re-exports the types shared by all tiers from the [`prelude`], like [`Ntfs`], [`NtfsError`], and the I/O helpers, so that a single tier is enough to open a filesystem.
+//! Every type is also exported from the crate root.
+//!
 //! # Example
 //! The following example dumps the names of all files and folders in the root directory of a given NTFS filesystem.  
 //! The list is directly taken from the NTFS index, hence it's sorted in ascending order with respect to NTFS's understanding of case-insensitive string comparison.
@@ -42,34 +52,93 @@ extern crate alloc;
 #[macro_use]
 mod helpers;
 
+mod artifacts;
+#[cfg(feature = "async")]
+mod async_reader;
 mod attribute;
 pub mod attribute_value;
 mod boot_sector;
+pub mod check;
+mod cluster_owners;
+mod collation;
+mod dry_run;
+mod duplicated_sizes;
 mod error;
+mod extents;
+mod fault_injection;
 mod file;
+mod file_ranges;
 mod file_reference;
 mod guid;
+pub mod high;
 mod index;
 mod index_entry;
 mod index_record;
 pub mod indexes;
+mod mft_data;
+pub mod mid;
 mod ntfs;
+mod options;
+mod path_lookup;
+mod piecewise_hash;
+pub mod prelude;
+mod progress;
+mod quota;
+pub mod raw;
+mod read_at;
+mod read_cache;
 mod record;
+mod recycle_bin;
+mod resize;
+mod security_descriptor;
+mod sid;
+mod slice_reader;
+mod string_buf;
 pub mod structured_values;
+mod sync;
 mod time;
 mod traits;
+mod tree_export;
 pub mod types;
 mod upcase_table;
+mod volume_registry;
 
+pub use crate::artifacts::*;
+#[cfg(feature = "async")]
+pub use crate::async_reader::*;
 pub use crate::attribute::*;
+pub use crate::boot_sector::*;
+pub use crate::cluster_owners::*;
+pub use crate::collation::*;
+pub use crate::dry_run::*;
+pub use crate::duplicated_sizes::*;
 pub use crate::error::*;
+pub use crate::extents::*;
+pub use crate::fault_injection::*;
 pub use crate::file::*;
+pub use crate::file_ranges::*;
 pub use crate::file_reference::*;
 pub use crate::guid::*;
 pub use crate::index::*;
 pub use crate::index_entry::*;
 pub use crate::index_record::*;
+pub use crate::mft_data::*;
 pub use crate::ntfs::*;
+pub use crate::options::*;
+pub use crate::path_lookup::*;
+pub use crate::piecewise_hash::*;
+pub use crate::progress::*;
+pub use crate::quota::*;
+pub use crate::read_at::*;
+pub use crate::read_cache::*;
+pub use crate::recycle_bin::*;
+pub use crate::security_descriptor::*;
+pub use crate::sid::*;
+pub use crate::slice_reader::*;
+pub use crate::string_buf::*;
+pub use crate::sync::*;
 pub use crate::time::*;
 pub use crate::traits::*;
+pub use crate::tree_export::*;
 pub use crate::upcase_table::*;
+pub use crate::volume_registry::*;
@@ -0,0 +1,232 @@
+// Copyright 2021-2023 Colin Finck <colin@reactos.org>
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+use alloc::vec;
+use binrw::io::{Read, Seek, SeekFrom, Write};
+
+use crate::attribute::{NtfsAttribute, NtfsAttributeType};
+use crate::attribute_value::{NtfsDataRuns, NtfsNonResidentAttributeValue};
+use crate::error::{NtfsError, Result};
+use crate::file::{KnownNtfsFileRecordNumber, NtfsFile};
+use crate::ntfs::Ntfs;
+use crate::progress::{report_progress, NtfsNoProgress, NtfsProgressSink, NtfsProgressUnit};
+use crate::traits::NtfsReadSeek;
+
+/// The $DATA attribute of the Master File Table (MFT), as returned by [`Ntfs::mft_data`].
+///
+/// This attribute value contains all File Records of the filesystem.
+/// Its Data Runs tell where these File Records are stored on the filesystem, which is useful
+/// for copying the raw MFT out for external tools.
+///
+/// Note that this assumes that the MFT does not have an Attribute List (like the rest of this crate).
+#[derive(Clone, Debug)]
+pub struct NtfsMftData<'n> {
+    mft: NtfsFile<'n>,
+    attribute_offset: usize,
+}
+
+impl<'n> NtfsMftData<'n> {
+    pub(crate) fn new<T>(ntfs: &'n Ntfs, fs: &mut T) -> Result<Self>
+    where
+        T: Read + Seek,
+    {
+        // `ntfs.mft_position()` has been checked in `Ntfs::new`.
+        // Only a standalone $MFT file (see `Ntfs::from_mft`) begins at position zero.
+        let position = ntfs
+            .mft_position()
+            .value()
+            .map_or(0, |position| position.get());
+        let mft = NtfsFile::new(ntfs, fs, position, 0)?;
+        Self::from_file(mft)
+    }
+
+    /// Returns the $DATA attribute of the $MFTMirr file, which mirrors the first File Records of the MFT.
+    pub(crate) fn mirror<T>(ntfs: &'n Ntfs, fs: &mut T) -> Result<Self>
+    where
+        T: Read + Seek,
+    {
+        let mft_mirr = ntfs.file(fs, KnownNtfsFileRecordNumber::MFTMirr as u64)?;
+        Self::from_file(mft_mirr)
+    }
+
+    fn from_file(mft: NtfsFile<'n>) -> Result<Self> {
+        let mft_data_attribute =
+            mft.find_resident_attribute(NtfsAttributeType::Data, None, None)?;
+
+        if mft_data_attribute.is_resident() {
+            return Err(NtfsError::UnexpectedResidentAttribute {
+                position: mft_data_attribute.position(),
+            });
+        }
+
+        let attribute_offset = mft_data_attribute.offset();
+
+        Ok(Self {
+            mft,
+            attribute_offset,
+        })
+    }
+
+    fn attribute(&self) -> Result<NtfsAttribute<'n, '_>> {
+        NtfsAttribute::new(&self.mft, self.attribute_offset, None)
+    }
+
+    /// Copies the entire MFT $DATA attribute value to `w` and returns the number of bytes copied.
+    ///
+    /// Sparse Data Runs are written out as zeros.
+    /// Use [`NtfsMftData::data_runs`] if you want to skip them.
+    pub fn copy_to<T, W>(&self, fs: &mut T, w: &mut W) -> Result<u64>
+    where
+        T: Read + Seek,
+        W: Write,
+    {
+        self.copy_to_with_progress(fs, w, &mut NtfsNoProgress)
+    }
+
+    /// Variant of [`NtfsMftData::copy_to`] that reports the number of bytes copied to an
+    /// [`NtfsProgressSink`] after every cluster, which may also cancel the operation.
+    pub fn copy_to_with_progress<T, W, P>(
+        &self,
+        fs: &mut T,
+        w: &mut W,
+        progress: &mut P,
+    ) -> Result<u64>
+    where
+        T: Read + Seek,
+        W: Write,
+        P: NtfsProgressSink + ?Sized,
+    {
+        let mut value = self.value()?;
+        let mut buf = vec![0u8; self.mft.ntfs().cluster_size() as usize];
+        let mut bytes_copied = 0u64;
+        let total = Some(self.len());
+
+        loop {
+            report_progress(progress, NtfsProgressUnit::Bytes, bytes_copied, total)?;
+
+            let bytes_read = value.read(fs, &mut buf)?;
+            if bytes_read == 0 {
+                break;
+            }
+
+            w.write_all(&buf[..bytes_read])?;
+            bytes_copied += bytes_read as u64;
+        }
+
+        Ok(bytes_copied)
+    }
+
+    /// Returns an iterator over the Data Runs of the MFT $DATA attribute value.
+    ///
+    /// Each [`NtfsDataRun`] is a continuous cluster range of the MFT, and "sparse" Data Runs have no position.
+    /// Note that the last Data Run may extend beyond [`NtfsMftData::len`].
+    ///
+    /// [`NtfsDataRun`]: crate::attribute_value::NtfsDataRun
+    pub fn data_runs(&self) -> Result<NtfsDataRuns<'n, '_>> {
+        Ok(self.value()?.data_runs())
+    }
+
+    /// Returns `true` if the MFT $DATA attribute value contains no data.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the total length of the MFT $DATA attribute value, in bytes.
+    pub fn len(&self) -> u64 {
+        // This unwrap is safe, because the attribute has already been validated in `NtfsMftData::new`.
+        self.attribute().unwrap().value_length()
+    }
+
+    /// Returns the [`NtfsFile`] of the MFT itself.
+    pub fn mft(&self) -> &NtfsFile<'n> {
+        &self.mft
+    }
+
+    /// Writes `buf` to the MFT $DATA attribute value at byte offset `offset`.
+    ///
+    /// The write is split at cluster boundaries, as consecutive clusters of the MFT may be in
+    /// different Data Runs.
+    /// This never changes the size of the MFT: Writing beyond its end or into a sparse Data Run fails
+    /// with [`NtfsError::InvalidFileRecordNumber`] for the File Record at `offset`.
+    pub(crate) fn write_at<T>(&self, fs: &mut T, offset: u64, buf: &[u8]) -> Result<()>
+    where
+        T: Read + Seek + Write,
+    {
+        let ntfs = self.mft.ntfs();
+        let cluster_size = ntfs.cluster_size() as u64;
+        let file_record_number = offset / ntfs.file_record_size() as u64;
+
+        let end = offset
+            .checked_add(buf.len() as u64)
+            .ok_or(NtfsError::InvalidFileRecordNumber { file_record_number })?;
+        if end > self.len() {
+            return Err(NtfsError::InvalidFileRecordNumber { file_record_number });
+        }
+
+        let mut value = self.value()?;
+        let mut written = 0;
+
+        while written < buf.len() {
+            let chunk_offset = offset + written as u64;
+            let chunk_length = (cluster_size - chunk_offset % cluster_size)
+                .min((buf.len() - written) as u64) as usize;
+
+            value.seek(fs, SeekFrom::Start(chunk_offset))?;
+            let position = value
+                .data_position()
+                .value()
+                .ok_or(NtfsError::InvalidFileRecordNumber { file_record_number })?;
+
+            fs.seek(SeekFrom::Start(position.get()))?;
+            fs.write_all(&buf[written..written + chunk_length])?;
+            written += chunk_length;
+        }
+
+        Ok(())
+    }
+
+    /// Returns an [`NtfsNonResidentAttributeValue`] structure to read the MFT $DATA attribute value.
+    pub fn value(&self) -> Result<NtfsNonResidentAttributeValue<'n, '_>> {
+        self.attribute()?.non_resident_value()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec::Vec;
+
+    use crate::file::KnownNtfsFileRecordNumber;
+    use crate::ntfs::Ntfs;
+
+    #[test]
+    fn test_mft_data() {
+        let mut testfs1 = crate::helpers::tests::testfs1();
+        let ntfs = Ntfs::new(&mut testfs1).unwrap();
+        let mft_data = ntfs.mft_data(&mut testfs1).unwrap();
+        assert_eq!(
+            mft_data.mft().file_record_number(),
+            KnownNtfsFileRecordNumber::MFT as u64
+        );
+
+        // The Data Runs cover the entire MFT, starting at its position.
+        let data_runs = mft_data
+            .data_runs()
+            .unwrap()
+            .collect::<crate::error::Result<Vec<_>>>()
+            .unwrap();
+        assert_eq!(data_runs[0].data_position(), ntfs.mft_position());
+        let allocated_size = data_runs
+            .iter()
+            .map(|data_run| data_run.allocated_size())
+            .sum::<u64>();
+        assert!(allocated_size >= mft_data.len());
+
+        // The copy contains all File Records, beginning with the one of $MFT.
+        let mut copy = Vec::new();
+        let bytes_copied = mft_data.copy_to(&mut testfs1, &mut copy).unwrap();
+        assert_eq!(bytes_copied, mft_data.len());
+        assert_eq!(copy.len() as u64, mft_data.len());
+        assert_eq!(copy.len() as u64 % ntfs.file_record_size() as u64, 0);
+        assert_eq!(&copy[..4], b"FILE");
+    }
+}
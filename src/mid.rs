@@ -0,0 +1,31 @@
+// Copyright 2021-2023 Colin Finck <colin@reactos.org>
+// SPDX-License-Identifier: MIT OR Apache-2.0
+//
+//! Mid tier: Files and indexes.
+//!
+//! This tier combines the structures of the [`raw`](crate::raw) tier into the objects NTFS is
+//! built around:
+//! Files with their names, streams, and metadata, as well as B-tree indexes like directories,
+//! which can be iterated in sorted order or searched for a key.
+//! Errors in the underlying structures are returned as they are encountered.
+//!
+//! The types shared by all tiers are re-exported from the [`prelude`](crate::prelude).
+//! All types are also available from the crate root.
+
+pub use crate::collation::NtfsCollationRule;
+pub use crate::file::{KnownNtfsFileRecordNumber, NtfsFile, NtfsFileFlags, NtfsFileNames};
+pub use crate::guid::NtfsGuid;
+pub use crate::index::{
+    NtfsIndex, NtfsIndexEntries, NtfsIndexEntryLocation, NtfsIndexFinder, NtfsIndexNode,
+};
+pub use crate::index_entry::{NtfsIndexEntry, NtfsIndexNodeEntries};
+pub use crate::indexes;
+pub use crate::prelude::*;
+pub use crate::quota::NtfsQuotaControlEntry;
+pub use crate::security_descriptor::{
+    NtfsSecurityDescriptorHeader, NtfsSecurityDescriptorStatistics, NtfsSecurityDescriptorUsage,
+};
+pub use crate::sid::NtfsSid;
+pub use crate::string_buf::NtfsStringBuf;
+pub use crate::time::NtfsTime;
+pub use crate::upcase_table::{NtfsCaseSensitivity, UpcaseOrd};
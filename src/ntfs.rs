@@ -1,21 +1,40 @@
 // Copyright 2021-2023 Colin Finck <colin@reactos.org>
 // SPDX-License-Identifier: MIT OR Apache-2.0
 
-use binrw::io::{Read, Seek, SeekFrom};
-use binrw::BinReaderExt;
+use core::cmp::Ordering;
+use core::ops::RangeInclusive;
+
+use alloc::vec;
+use alloc::vec::Vec;
+use binrw::io::{Read, Seek, SeekFrom, Write};
 
 use crate::attribute::NtfsAttributeType;
-use crate::boot_sector::BootSector;
-use crate::error::{NtfsError, Result};
-use crate::file::{KnownNtfsFileRecordNumber, NtfsFile};
-use crate::structured_values::{NtfsVolumeInformation, NtfsVolumeName};
+use crate::boot_sector::NtfsBootSector;
+use crate::check::NtfsCheckReport;
+use crate::cluster_owners::{NtfsClusterOwner, NtfsClusterOwnerIndex};
+use crate::duplicated_sizes::{volume_size_mismatches, NtfsSizeMismatchReport};
+use crate::error::{NtfsError, NtfsErrorKind, Result};
+use crate::extents::{NtfsExtentMap, NtfsMemoryFile};
+use crate::file::{KnownNtfsFileRecordNumber, NtfsFile, NtfsFileFlags};
+use crate::file_ranges::NtfsFileRangesSinceUsn;
+use crate::indexes::NtfsFileNameIndex;
+use crate::mft_data::NtfsMftData;
+use crate::options::{NtfsDamagedIndexPolicy, NtfsOptions};
+use crate::path_lookup::NtfsPathLookup;
+use crate::progress::{NtfsNoProgress, NtfsProgressSink};
+use crate::recycle_bin::{recycle_bin_entries, NtfsRecycleBinEntry};
+use crate::resize::resize_volume;
+use crate::security_descriptor::NtfsSecurityDescriptorStatistics;
+use crate::structured_values::{NtfsObjectId, NtfsVolumeInformation, NtfsVolumeName};
 use crate::traits::NtfsReadSeek;
-use crate::types::NtfsPosition;
-use crate::upcase_table::UpcaseTable;
+use crate::types::{Lcn, NtfsPosition};
+use crate::upcase_table::{file_name_cmp, NtfsCaseSensitivity, UpcaseTable};
 
 /// Root structure describing an NTFS filesystem.
 #[derive(Debug)]
 pub struct Ntfs {
+    /// The boot sector this filesystem has been opened from (`None` for a standalone $MFT file).
+    boot_sector: Option<NtfsBootSector>,
     /// The size of a single cluster, in bytes. This is usually 4096.
     cluster_size: u32,
     /// The size of a single sector, in bytes. This is usually 512.
@@ -30,6 +49,10 @@ pub struct Ntfs {
     serial_number: u64,
     /// Table of Unicode uppercase characters (only required for case-insensitive comparisons).
     upcase_table: Option<UpcaseTable>,
+    /// Options passed to [`Ntfs::new_with_options`].
+    options: NtfsOptions,
+    /// Whether the reader only provides a standalone $MFT file (see [`Ntfs::from_mft`]).
+    standalone_mft: bool,
 }
 
 impl Ntfs {
@@ -37,47 +60,234 @@ impl Ntfs {
     ///
     /// The reader must cover the entire NTFS partition, not more and not less.
     /// It will be rewinded to the beginning before reading anything.
-    #[allow(clippy::seek_to_start_instead_of_rewind)]
+    ///
+    /// If the primary boot sector fails validation, the backup boot sector in the last sector of the
+    /// partition is tried before giving up (see [`NtfsBootSector::is_backup`]).
     pub fn new<T>(fs: &mut T) -> Result<Self>
     where
         T: Read + Seek,
     {
-        // Read and validate the boot sector.
-        fs.seek(SeekFrom::Start(0))?;
-        let boot_sector = fs.read_le::<BootSector>()?;
-        boot_sector.validate()?;
+        Self::new_with_options(fs, NtfsOptions::default())
+    }
+
+    /// Creates a new [`Ntfs`] object like [`Ntfs::new`], but with the given [`NtfsOptions`].
+    pub fn new_with_options<T>(fs: &mut T, options: NtfsOptions) -> Result<Self>
+    where
+        T: Read + Seek,
+    {
+        let boot_sector = NtfsBootSector::read(fs)?;
+
+        let mut ntfs = Self {
+            boot_sector: Some(boot_sector),
+            cluster_size: boot_sector.cluster_size(),
+            sector_size: boot_sector.sector_size(),
+            size: boot_sector.size(),
+            mft_position: NtfsPosition::none(),
+            file_record_size: boot_sector.file_record_size(),
+            serial_number: boot_sector.serial_number(),
+            upcase_table: None,
+            options,
+            standalone_mft: false,
+        };
+        ntfs.mft_position = boot_sector.mft_lcn().position(&ntfs)?;
+
+        Ok(ntfs)
+    }
+
+    /// Creates a new [`Ntfs`] object from a reader that only provides a standalone $MFT file
+    /// (like one extracted during a forensic acquisition) instead of an entire NTFS partition.
+    ///
+    /// The reader must cover the entire $MFT file, starting with File Record 0 at its beginning.
+    /// As there is no boot sector, the size of a single File Record must be passed as `file_record_size`
+    /// (this is usually 1024).
+    /// The cluster size is derived from the $DATA attribute of the $MFT file itself, and
+    /// [`NtfsError::UnsupportedClusterSize`] is returned if that doesn't result in a power of two between
+    /// 512 bytes and 2 MiB.
+    ///
+    /// All File Records can be read as usual via [`Ntfs::file`], including their names, timestamps,
+    /// and resident attribute values.
+    /// Every operation that needs the data of a cluster (like reading non-resident attribute values,
+    /// traversing large directory indexes, or [`Ntfs::read_upcase_table`]) fails with
+    /// [`NtfsError::ClusterDataUnavailable`] instead.
+    /// [`Ntfs::sector_size`] returns 512 and [`Ntfs::size`] and [`Ntfs::serial_number`] return zero,
+    /// as this information is only stored in the boot sector.
+    pub fn from_mft<T>(fs: &mut T, file_record_size: u32) -> Result<Self>
+    where
+        T: Read + Seek,
+    {
+        Self::from_mft_with_options(fs, file_record_size, NtfsOptions::default())
+    }
+
+    /// Creates a new [`Ntfs`] object like [`Ntfs::from_mft`], but with the given [`NtfsOptions`].
+    pub fn from_mft_with_options<T>(
+        fs: &mut T,
+        file_record_size: u32,
+        options: NtfsOptions,
+    ) -> Result<Self>
+    where
+        T: Read + Seek,
+    {
+        /// Windows uses 1024 or 4096 bytes, but File Records must at least span one protected block.
+        const FILE_RECORD_SIZE_RANGE: RangeInclusive<u32> = 512..=65536;
+
+        /// 2 MiB is the maximum cluster size currently supported by Windows.
+        const CLUSTER_SIZE_RANGE: RangeInclusive<u32> = 512..=2097152;
 
-        let bpb = boot_sector.bpb();
-        let cluster_size = bpb.cluster_size()?;
-        let sector_size = bpb.sector_size()?;
-        let total_sectors = bpb.total_sectors();
-        let size = total_sectors
-            .checked_mul(sector_size as u64)
-            .ok_or(NtfsError::TotalSectorsTooBig { total_sectors })?;
-        let mft_position = NtfsPosition::none();
-        let file_record_size = bpb.file_record_size()?;
-        let serial_number = bpb.serial_number();
-        let upcase_table = None;
+        if !FILE_RECORD_SIZE_RANGE.contains(&file_record_size)
+            || !file_record_size.is_power_of_two()
+        {
+            return Err(NtfsError::UnsupportedFileRecordSize {
+                min: *FILE_RECORD_SIZE_RANGE.start(),
+                max: *FILE_RECORD_SIZE_RANGE.end(),
+                actual: file_record_size,
+            });
+        }
 
+        // The cluster size is only known after reading the $MFT File Record, which doesn't need it.
         let mut ntfs = Self {
-            cluster_size,
-            sector_size,
-            size,
-            mft_position,
+            boot_sector: None,
+            cluster_size: file_record_size,
+            sector_size: 512,
+            size: 0,
+            mft_position: NtfsPosition::none(),
             file_record_size,
-            serial_number,
-            upcase_table,
+            serial_number: 0,
+            upcase_table: None,
+            options,
+            standalone_mft: true,
+        };
+
+        // The $DATA attribute of the $MFT file tells us how many bytes are allocated for how many clusters.
+        let cluster_size = {
+            let mft = ntfs.file(fs, KnownNtfsFileRecordNumber::MFT as u64)?;
+            let attribute = mft.find_resident_attribute(NtfsAttributeType::Data, None, None)?;
+            if attribute.is_resident() {
+                return Err(NtfsError::UnexpectedResidentAttribute {
+                    position: attribute.position(),
+                });
+            }
+
+            let cluster_count = attribute
+                .non_resident_value_highest_vcn()
+                .value()
+                .saturating_sub(attribute.non_resident_value_lowest_vcn().value())
+                .saturating_add(1)
+                .max(1) as u64;
+            let cluster_size = attribute.non_resident_value_allocated_size() / cluster_count;
+            u32::try_from(cluster_size).unwrap_or(u32::MAX)
         };
-        ntfs.mft_position = bpb.mft_lcn()?.position(&ntfs)?;
 
+        if !CLUSTER_SIZE_RANGE.contains(&cluster_size) || !cluster_size.is_power_of_two() {
+            return Err(NtfsError::UnsupportedClusterSize {
+                min: *CLUSTER_SIZE_RANGE.start(),
+                max: *CLUSTER_SIZE_RANGE.end(),
+                actual: cluster_size,
+            });
+        }
+
+        ntfs.cluster_size = cluster_size;
         Ok(ntfs)
     }
 
+    /// Returns the [`NtfsBootSector`] this filesystem has been opened from.
+    ///
+    /// This is `None` if the filesystem has been opened from a standalone $MFT file
+    /// (see [`Ntfs::from_mft`]).
+    pub fn boot_sector(&self) -> Option<&NtfsBootSector> {
+        self.boot_sector.as_ref()
+    }
+
+    /// Runs structural consistency checks over this NTFS volume and returns an [`NtfsCheckReport`].
+    ///
+    /// This verifies that every File Record in use can be read, its Data Runs lie inside the volume and
+    /// inside clusters allocated in $Bitmap, its attribute sizes are consistent, and every directory
+    /// Index Entry references a File Record in use with a matching sequence number.
+    /// Inconsistencies are collected in the report instead of aborting the check.
+    ///
+    /// This reads the entire Master File Table and all directory indexes, and keeps the $Bitmap
+    /// in memory during the check.
+    pub fn check<T>(&self, fs: &mut T) -> Result<NtfsCheckReport>
+    where
+        T: Read + Seek,
+    {
+        NtfsCheckReport::new(self, fs, &mut NtfsNoProgress)
+    }
+
+    /// Variant of [`Ntfs::check`] that reports the number of File Records and Index Entries checked
+    /// to an [`NtfsProgressSink`], which may also cancel the check.
+    ///
+    /// File Records are reported with their total count, directory Index Entries without one.
+    pub fn check_with_progress<T, P>(&self, fs: &mut T, progress: &mut P) -> Result<NtfsCheckReport>
+    where
+        T: Read + Seek,
+        P: NtfsProgressSink + ?Sized,
+    {
+        NtfsCheckReport::new(self, fs, progress)
+    }
+
+    /// Builds an [`NtfsClusterOwnerIndex`] to find the files owning arbitrary clusters of this volume.
+    ///
+    /// This reads the entire Master File Table and the Data Runs of all non-resident attributes.
+    pub fn cluster_owner_index<T>(&self, fs: &mut T) -> Result<NtfsClusterOwnerIndex>
+    where
+        T: Read + Seek,
+    {
+        NtfsClusterOwnerIndex::new(self, fs, &mut NtfsNoProgress)
+    }
+
+    /// Variant of [`Ntfs::cluster_owner_index`] that reports the number of File Records processed
+    /// to an [`NtfsProgressSink`], which may also cancel building the index.
+    pub fn cluster_owner_index_with_progress<T, P>(
+        &self,
+        fs: &mut T,
+        progress: &mut P,
+    ) -> Result<NtfsClusterOwnerIndex>
+    where
+        T: Read + Seek,
+        P: NtfsProgressSink + ?Sized,
+    {
+        NtfsClusterOwnerIndex::new(self, fs, progress)
+    }
+
     /// Returns the size of a single cluster, in bytes.
     pub fn cluster_size(&self) -> u32 {
         self.cluster_size
     }
 
+    /// Looks up a file of the $Extend directory (like `$Quota`, `$ObjId`, or `$Reparse`) by its
+    /// exact name and returns its [`NtfsFile`] (if any).
+    ///
+    /// Unlike the fixed system files listed in [`KnownNtfsFileRecordNumber`], these files have
+    /// no fixed File Record Numbers.
+    /// This doesn't need the $UpCase table:
+    /// The names of these files only consist of ASCII characters, so the B-tree of the $Extend directory
+    /// is traversed by comparing ASCII-uppercased names, and the found name must match exactly.
+    pub fn extend_file<'n, T>(&'n self, fs: &mut T, name: &str) -> Option<Result<NtfsFile<'n>>>
+    where
+        T: Read + Seek,
+    {
+        let extend = iter_try!(self.file(fs, KnownNtfsFileRecordNumber::Extend as u64));
+        let index = iter_try!(extend.directory_index(fs));
+        let mut finder = index.finder();
+
+        let ascii_uppercase = |code_unit: u16| match u8::try_from(code_unit) {
+            Ok(byte) => byte.to_ascii_uppercase() as u16,
+            Err(_) => code_unit,
+        };
+        let entry = iter_try!(finder.find(fs, |file_name| {
+            let other = file_name.name();
+            let name = name.encode_utf16().map(ascii_uppercase);
+            name.cmp(other.u16_iter().map(ascii_uppercase))
+        })?);
+
+        let file_name = iter_try!(entry.key()?);
+        if file_name.name() == name {
+            Some(entry.to_file(self, fs))
+        } else {
+            None
+        }
+    }
+
     /// Returns the [`NtfsFile`] for the given NTFS File Record Number.
     ///
     /// The first few NTFS files have fixed indexes and contain filesystem
@@ -90,15 +300,20 @@ impl Ntfs {
             .checked_mul(self.file_record_size as u64)
             .ok_or(NtfsError::InvalidFileRecordNumber { file_record_number })?;
 
+        if self.standalone_mft {
+            // A standalone $MFT file is the MFT $DATA attribute value without any Data Runs.
+            if offset >= fs.seek(SeekFrom::End(0))? {
+                return Err(NtfsError::InvalidFileRecordNumber { file_record_number });
+            }
+
+            return NtfsFile::new(self, fs, offset, file_record_number);
+        }
+
         // The MFT may be split into multiple data runs, referenced by its $DATA attribute.
         // We therefore read it just like any other non-resident attribute value.
         // However, this code assumes that the MFT does not have an Attribute List!
-        //
-        // This unwrap is safe, because `self.mft_position` has been checked in `Ntfs::new`.
-        let mft = NtfsFile::new(self, fs, self.mft_position.value().unwrap(), 0)?;
-        let mft_data_attribute =
-            mft.find_resident_attribute(NtfsAttributeType::Data, None, None)?;
-        let mut mft_data_value = mft_data_attribute.value(fs)?;
+        let mft_data = self.mft_data(fs)?;
+        let mut mft_data_value = mft_data.value()?;
 
         mft_data_value.seek(fs, SeekFrom::Start(offset))?;
         let position = mft_data_value
@@ -106,7 +321,223 @@ impl Ntfs {
             .value()
             .ok_or(NtfsError::InvalidFileRecordNumber { file_record_number })?;
 
-        NtfsFile::new(self, fs, position, file_record_number)
+        // With small clusters, a File Record may span two Data Runs of the MFT.
+        // Therefore, we read it through the MFT $DATA attribute value instead of directly from `position`.
+        let mut data = vec![0; self.file_record_size as usize];
+        mft_data_value.read_exact(fs, &mut data)?;
+
+        NtfsFile::from_data(self, data, position.into(), file_record_number)
+    }
+
+    /// Looks up a file by its path relative to the root directory and returns it as an [`NtfsFile`] (if any).
+    ///
+    /// Both `\\` and `/` are accepted as path separators, and empty path components are ignored.
+    /// Hence, an empty path returns the root directory.
+    /// Every path component is compared according to the given [`NtfsCaseSensitivity`].
+    /// Pass [`NtfsCaseSensitivity::PerDirectory`] to honor directories marked case-sensitive
+    /// (see [`NtfsFile::case_sensitivity`]).
+    ///
+    /// Configure [`NtfsDamagedIndexPolicy::ScanMft`] via [`NtfsOptions::with_damaged_index_policy`]
+    /// to still find files below a directory whose index is damaged.
+    /// Use [`Ntfs::lookup_path`] instead to find out whether the file has been found that way.
+    ///
+    /// # Panics
+    ///
+    /// Panics if [`read_upcase_table`][Ntfs::read_upcase_table] had not been called.
+    pub fn file_by_path<'n, T>(
+        &'n self,
+        fs: &mut T,
+        path: &str,
+        case_sensitivity: NtfsCaseSensitivity,
+    ) -> Option<Result<NtfsFile<'n>>>
+    where
+        T: Read + Seek,
+    {
+        self.lookup_path(fs, path, case_sensitivity)
+            .map(|result| result.map(NtfsPathLookup::into_file))
+    }
+
+    fn file_by_path_components<'n, T, C, I>(
+        &'n self,
+        fs: &mut T,
+        components: C,
+        case_sensitivity: NtfsCaseSensitivity,
+    ) -> Option<Result<NtfsPathLookup<'n>>>
+    where
+        T: Read + Seek,
+        C: Iterator<Item = I>,
+        I: Iterator<Item = u16> + Clone,
+    {
+        let mut file = iter_try!(self.root_directory(fs));
+        let mut found_by_mft_scan = false;
+
+        for component in components {
+            let directory_case_sensitivity = match case_sensitivity {
+                NtfsCaseSensitivity::PerDirectory => iter_try!(file.case_sensitivity()),
+                case_sensitivity => case_sensitivity,
+            };
+
+            let next_file = match self.file_in_directory_index(
+                fs,
+                &file,
+                component.clone(),
+                directory_case_sensitivity,
+            ) {
+                // Only damaged structures justify a scan.
+                // Other errors (like I/O errors) would equally affect it and are returned as they are.
+                Some(Err(e))
+                    if e.kind() == NtfsErrorKind::Corrupted
+                        && self.options.damaged_index_policy()
+                            == NtfsDamagedIndexPolicy::ScanMft =>
+                {
+                    found_by_mft_scan = true;
+                    self.file_in_directory_by_mft_scan(
+                        fs,
+                        &file,
+                        component,
+                        directory_case_sensitivity,
+                    )
+                }
+                result => result,
+            };
+
+            file = iter_try!(next_file?);
+        }
+
+        Some(Ok(NtfsPathLookup::new(file, found_by_mft_scan)))
+    }
+
+    fn file_in_directory_index<'n, T, I>(
+        &'n self,
+        fs: &mut T,
+        directory: &NtfsFile<'n>,
+        name: I,
+        case_sensitivity: NtfsCaseSensitivity,
+    ) -> Option<Result<NtfsFile<'n>>>
+    where
+        T: Read + Seek,
+        I: Iterator<Item = u16> + Clone,
+    {
+        let index = iter_try!(directory.directory_index(fs));
+        let mut finder = index.finder();
+        let entry = iter_try!(NtfsFileNameIndex::find_code_units(
+            &mut finder,
+            self,
+            fs,
+            name,
+            case_sensitivity
+        )?);
+
+        Some(entry.to_file(self, fs))
+    }
+
+    fn file_in_directory_by_mft_scan<'n, T, I>(
+        &'n self,
+        fs: &mut T,
+        directory: &NtfsFile<'n>,
+        name: I,
+        case_sensitivity: NtfsCaseSensitivity,
+    ) -> Option<Result<NtfsFile<'n>>>
+    where
+        T: Read + Seek,
+        I: Iterator<Item = u16> + Clone,
+    {
+        let file_record_count = iter_try!(self.file_record_count(fs));
+
+        for file_record_number in 0..file_record_count {
+            // Skip damaged and unused File Records instead of failing the entire scan.
+            // Any other error (like an I/O error) may hide the searched file and is therefore returned.
+            let file = match self.file(fs, file_record_number) {
+                Ok(file) => file,
+                Err(e) if e.kind() == NtfsErrorKind::Corrupted => continue,
+                Err(e) => return Some(Err(e)),
+            };
+
+            // `names` follows the $ATTRIBUTE_LIST of a base File Record, so it also returns the
+            // $FILE_NAME attributes moved into extension File Records (e.g. of heavily hard-linked files).
+            // Extension File Records are therefore covered by their base File Record.
+            if !file.flags().contains(NtfsFileFlags::IN_USE)
+                || file.base_file_record().file_record_number() != 0
+            {
+                continue;
+            }
+
+            let mut is_match = false;
+            let mut names = file.names();
+
+            while let Some(file_name) = names.next(fs) {
+                let file_name = match file_name {
+                    Ok(file_name) => file_name,
+                    Err(e) if e.kind() == NtfsErrorKind::Corrupted => break,
+                    Err(e) => return Some(Err(e)),
+                };
+                let parent = file_name.parent_directory_reference();
+
+                if parent.file_record_number() == directory.file_record_number()
+                    && parent.sequence_number() == directory.sequence_number()
+                    && file_name_cmp(self, name.clone(), &file_name.name(), case_sensitivity)
+                        == Ordering::Equal
+                {
+                    is_match = true;
+                    break;
+                }
+            }
+
+            if is_match {
+                return Some(Ok(file));
+            }
+        }
+
+        None
+    }
+
+    /// Looks up a file by its path given as UTF-16 code units and returns it as an [`NtfsFile`] (if any).
+    ///
+    /// This is a variant of [`Ntfs::file_by_path`] for paths that are already available in UTF-16
+    /// (e.g. from Windows APIs), which avoids any conversion and allocation.
+    /// Path separators, case sensitivity, and damaged directory indexes are handled just like in
+    /// [`Ntfs::file_by_path`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if [`read_upcase_table`][Ntfs::read_upcase_table] had not been called.
+    pub fn file_by_path_u16<'n, T>(
+        &'n self,
+        fs: &mut T,
+        path: &[u16],
+        case_sensitivity: NtfsCaseSensitivity,
+    ) -> Option<Result<NtfsFile<'n>>>
+    where
+        T: Read + Seek,
+    {
+        self.lookup_path_u16(fs, path, case_sensitivity)
+            .map(|result| result.map(NtfsPathLookup::into_file))
+    }
+
+    /// Returns the on-disk byte ranges of those files in `file_record_numbers` that may have changed since
+    /// the USN checkpoint `checkpoint_usn`, as [`NtfsFileRangesSinceUsn`].
+    ///
+    /// Pass the File Record Numbers of all files with records in the $UsnJrnl change journal since the
+    /// checkpoint (this crate doesn't read the journal itself).
+    /// A file is considered unchanged if the USN in its $STANDARD_INFORMATION attribute is nonzero and
+    /// lower than `checkpoint_usn`.
+    /// The byte ranges of a changed file cover its File Records as well as all clusters of its
+    /// non-resident attributes (like the $DATA attributes of a file and the Index Records of a directory),
+    /// because the USN only tells that, but not where, a file has changed.
+    ///
+    /// Extension File Records are mapped to their base File Record.
+    /// Note that changes to the $MFT and $Bitmap files are only covered if their File Record Numbers are
+    /// passed as well.
+    pub fn file_ranges_since_usn<T>(
+        &self,
+        fs: &mut T,
+        checkpoint_usn: u64,
+        file_record_numbers: &[u64],
+    ) -> Result<NtfsFileRangesSinceUsn>
+    where
+        T: Read + Seek,
+    {
+        NtfsFileRangesSinceUsn::new(self, fs, checkpoint_usn, file_record_numbers)
     }
 
     /// Returns the size of a File Record of this NTFS filesystem, in bytes.
@@ -114,13 +545,195 @@ impl Ntfs {
         self.file_record_size
     }
 
+    /// Returns the number of File Records of the Master File Table (MFT), including unused ones.
+    ///
+    /// This is derived from the size of the MFT $DATA attribute and the File Record size.
+    /// All valid File Record Numbers for [`Ntfs::file`] are below this value.
+    pub fn file_record_count<T>(&self, fs: &mut T) -> Result<u64>
+    where
+        T: Read + Seek,
+    {
+        let mft_size = if self.standalone_mft {
+            fs.seek(SeekFrom::End(0))?
+        } else {
+            self.mft_data(fs)?.len()
+        };
+
+        Ok(mft_size / self.file_record_size as u64)
+    }
+
+    /// Finds the file owning the given cluster (like a bad sector) and returns the owning file,
+    /// attribute type, and stream name as an [`NtfsClusterOwner`].
+    ///
+    /// This is `None` if no file owns the cluster.
+    /// As this builds an entire [`NtfsClusterOwnerIndex`], use [`Ntfs::cluster_owner_index`] instead
+    /// if you need to look up multiple clusters.
+    pub fn find_owner_of_lcn<T>(&self, fs: &mut T, lcn: Lcn) -> Result<Option<NtfsClusterOwner>>
+    where
+        T: Read + Seek,
+    {
+        let index = self.cluster_owner_index(fs)?;
+        Ok(index.find(lcn))
+    }
+
+    /// Returns `true` if this [`Ntfs`] object has been created via [`Ntfs::from_mft`] and therefore
+    /// only has access to the File Records, but not to any cluster data.
+    pub fn is_standalone_mft(&self) -> bool {
+        self.standalone_mft
+    }
+
+    /// Looks up a file by its path relative to the root directory and returns it as an [`NtfsPathLookup`] (if any).
+    ///
+    /// This works just like [`Ntfs::file_by_path`], but additionally tells whether a damaged directory
+    /// index along the path had to be bypassed by scanning the Master File Table
+    /// (see [`NtfsPathLookup::found_by_mft_scan`]).
+    ///
+    /// # Panics
+    ///
+    /// Panics if [`read_upcase_table`][Ntfs::read_upcase_table] had not been called.
+    pub fn lookup_path<'n, T>(
+        &'n self,
+        fs: &mut T,
+        path: &str,
+        case_sensitivity: NtfsCaseSensitivity,
+    ) -> Option<Result<NtfsPathLookup<'n>>>
+    where
+        T: Read + Seek,
+    {
+        let components = path
+            .split(&['\\', '/'][..])
+            .filter(|component| !component.is_empty())
+            .map(str::encode_utf16);
+
+        self.file_by_path_components(fs, components, case_sensitivity)
+    }
+
+    /// Looks up a file by its path given as UTF-16 code units and returns it as an [`NtfsPathLookup`] (if any).
+    ///
+    /// This works just like [`Ntfs::file_by_path_u16`], but additionally tells whether a damaged directory
+    /// index along the path had to be bypassed by scanning the Master File Table
+    /// (see [`NtfsPathLookup::found_by_mft_scan`]).
+    ///
+    /// # Panics
+    ///
+    /// Panics if [`read_upcase_table`][Ntfs::read_upcase_table] had not been called.
+    pub fn lookup_path_u16<'n, T>(
+        &'n self,
+        fs: &mut T,
+        path: &[u16],
+        case_sensitivity: NtfsCaseSensitivity,
+    ) -> Option<Result<NtfsPathLookup<'n>>>
+    where
+        T: Read + Seek,
+    {
+        let components = path
+            .split(|code_unit| *code_unit == b'\\' as u16 || *code_unit == b'/' as u16)
+            .filter(|component| !component.is_empty())
+            .map(|component| component.iter().copied());
+
+        self.file_by_path_components(fs, components, case_sensitivity)
+    }
+
+    /// Finds one of the well-known files holding the contents of physical memory in the root directory
+    /// (see [`NtfsMemoryFile`]) and returns the [`NtfsExtentMap`] of its unnamed $DATA attribute (if any).
+    ///
+    /// Only the File Record and the Data Run headers are read, so this is fast even for files
+    /// spanning several gigabytes.
+    /// Note that [`Ntfs::read_upcase_table`] must have been called prior to this function.
+    pub fn memory_file_extents<T>(
+        &self,
+        fs: &mut T,
+        memory_file: NtfsMemoryFile,
+    ) -> Option<Result<NtfsExtentMap>>
+    where
+        T: Read + Seek,
+    {
+        let root_dir = iter_try!(self.root_directory(fs));
+        let index = iter_try!(root_dir.directory_index(fs));
+        let mut finder = index.finder();
+        let entry = iter_try!(NtfsFileNameIndex::find(
+            &mut finder,
+            self,
+            fs,
+            memory_file.name()
+        )?);
+        let file = iter_try!(entry.to_file(self, fs));
+        let data_item = iter_try!(file.data(fs, "")?);
+        let data_attribute = iter_try!(data_item.to_attribute());
+
+        Some(data_attribute.extent_map(fs))
+    }
+
+    /// Returns the [`NtfsMftData`] of the Master File Table (MFT) to read or copy out its raw File Records.
+    pub fn mft_data<'n, T>(&'n self, fs: &mut T) -> Result<NtfsMftData<'n>>
+    where
+        T: Read + Seek,
+    {
+        NtfsMftData::new(self, fs)
+    }
+
     /// Returns the absolute byte position of the Master File Table (MFT).
     ///
-    /// This [`NtfsPosition`] is guaranteed to be nonzero.
+    /// This [`NtfsPosition`] is guaranteed to be nonzero, except for a standalone $MFT file opened via
+    /// [`Ntfs::from_mft`], which begins at position zero.
     pub fn mft_position(&self) -> NtfsPosition {
         self.mft_position
     }
 
+    /// Returns the [`NtfsOptions`] this [`Ntfs`] object has been created with.
+    pub fn options(&self) -> &NtfsOptions {
+        &self.options
+    }
+
+    /// Reads `count` clusters starting at the Logical Cluster Number `lcn` into the beginning of `buf`.
+    ///
+    /// The cluster range is checked against the total number of clusters of the filesystem,
+    /// and `buf` must have room for at least `count` clusters (see [`Ntfs::cluster_size`]).
+    /// This is a low-level primitive: The clusters are read as-is, without considering whether
+    /// they are allocated to a file.
+    pub fn read_clusters<T>(&self, fs: &mut T, lcn: Lcn, count: u64, buf: &mut [u8]) -> Result<()>
+    where
+        T: Read + Seek,
+    {
+        if self.standalone_mft {
+            return Err(NtfsError::ClusterDataUnavailable { lcn });
+        }
+
+        let total_clusters = self.size / self.cluster_size as u64;
+
+        if lcn
+            .value()
+            .checked_add(count)
+            .map_or(true, |end| end > total_clusters)
+        {
+            return Err(NtfsError::ClusterRangeOutOfBounds {
+                lcn,
+                count,
+                total_clusters,
+            });
+        }
+
+        let position = lcn.position(self)?;
+
+        // A length that doesn't even fit into a `usize` is too large for any buffer.
+        let length = count
+            .checked_mul(self.cluster_size as u64)
+            .and_then(|length| usize::try_from(length).ok())
+            .unwrap_or(usize::MAX);
+        if buf.len() < length {
+            return Err(NtfsError::BufferTooSmall {
+                expected: length,
+                actual: buf.len(),
+            });
+        }
+
+        // LCN 0 yields an `NtfsPosition` without a value.
+        let position = position.value().map_or(0, |position| position.get());
+        fs.seek(SeekFrom::Start(position))?;
+        fs.read_exact(&mut buf[..length])?;
+        Ok(())
+    }
+
     /// Reads the $UpCase file from the filesystem and stores it in this [`Ntfs`] object.
     ///
     /// This function only needs to be called if case-insensitive comparisons are later performed
@@ -134,6 +747,72 @@ impl Ntfs {
         Ok(())
     }
 
+    /// Finds the `$Recycle.Bin` directory in the root directory and returns an [`NtfsRecycleBinEntry`]
+    /// for every `$I` file in its per-user subdirectories, paired with the corresponding `$R` file.
+    ///
+    /// This is `None` if the volume has no `$Recycle.Bin` directory.
+    /// Errors while reading a single `$I` file or looking up its `$R` file are reported per entry
+    /// (see [`NtfsRecycleBinEntry::error`]).
+    /// A per-user subdirectory that cannot be enumerated results in an `Err` item, while the other
+    /// subdirectories are still listed.
+    /// Only an error while enumerating `$Recycle.Bin` itself aborts the entire operation.
+    /// Note that [`Ntfs::read_upcase_table`] must have been called prior to this function.
+    pub fn recycle_bin_entries<T>(
+        &self,
+        fs: &mut T,
+    ) -> Option<Result<Vec<Result<NtfsRecycleBinEntry>>>>
+    where
+        T: Read + Seek,
+    {
+        let root_dir = iter_try!(self.root_directory(fs));
+        let index = iter_try!(root_dir.directory_index(fs));
+        let mut finder = index.finder();
+        let entry = iter_try!(NtfsFileNameIndex::find(
+            &mut finder,
+            self,
+            fs,
+            "$Recycle.Bin"
+        )?);
+        let recycle_bin = iter_try!(entry.to_file(self, fs));
+
+        Some(recycle_bin_entries(self, &recycle_bin, fs))
+    }
+
+    /// Adjusts the filesystem metadata to a new total sector count, as required for resizing the
+    /// partition of this NTFS volume.
+    ///
+    /// This grows or shrinks the $Bitmap of allocated clusters and the $BadClus file, and updates the
+    /// total sector count in the boot sector and in the backup boot sector (which is written to the
+    /// sector right behind the new end of the volume).
+    /// No data is moved: Shrinking is refused with [`NtfsError::ResizeClusterInUse`] if any cluster
+    /// behind the new end is still in use.
+    /// Growing is limited by the clusters already allocated for $Bitmap and otherwise refused with
+    /// [`NtfsError::ResizeBitmapTooSmall`].
+    /// All checks are performed before anything is written.
+    ///
+    /// When shrinking, call this before shrinking the partition (to at least `total_sectors + 1` sectors).
+    /// When growing, grow the partition first, as the reader must already cover the backup boot sector.
+    /// This doesn't write to the $LogFile journal.
+    ///
+    /// On success, this `Ntfs` object immediately reflects the new size, even if the writes only went
+    /// to an [`NtfsDryRun`].
+    /// Discard it after such a simulation and create a new one from the real filesystem.
+    ///
+    /// [`NtfsDryRun`]: crate::NtfsDryRun
+    pub fn resize<T>(&mut self, fs: &mut T, total_sectors: u64) -> Result<()>
+    where
+        T: Read + Seek + Write,
+    {
+        resize_volume(self, fs, total_sectors)?;
+        self.size = total_sectors * self.sector_size as u64;
+
+        if let Some(boot_sector) = &mut self.boot_sector {
+            boot_sector.set_total_sectors(total_sectors);
+        }
+
+        Ok(())
+    }
+
     /// Returns the root directory of this NTFS volume as an [`NtfsFile`].
     pub fn root_directory<'n, T>(&'n self, fs: &mut T) -> Result<NtfsFile<'n>>
     where
@@ -142,6 +821,36 @@ impl Ntfs {
         self.file(fs, KnownNtfsFileRecordNumber::RootDirectory as u64)
     }
 
+    /// Analyzes how the files of this NTFS volume share Security Descriptors and returns
+    /// [`NtfsSecurityDescriptorStatistics`].
+    ///
+    /// This enumerates all Security Descriptors of the $SII index of the $Secure file and
+    /// counts their references from the $STANDARD_INFORMATION attributes of all files in use.
+    /// Hence, it needs to read the entire Master File Table.
+    pub fn security_descriptor_statistics<T>(
+        &self,
+        fs: &mut T,
+    ) -> Result<NtfsSecurityDescriptorStatistics>
+    where
+        T: Read + Seek,
+    {
+        NtfsSecurityDescriptorStatistics::new(self, fs, &mut NtfsNoProgress)
+    }
+
+    /// Variant of [`Ntfs::security_descriptor_statistics`] that reports the number of $SII Index Entries
+    /// and File Records processed to an [`NtfsProgressSink`], which may also cancel the operation.
+    pub fn security_descriptor_statistics_with_progress<T, P>(
+        &self,
+        fs: &mut T,
+        progress: &mut P,
+    ) -> Result<NtfsSecurityDescriptorStatistics>
+    where
+        T: Read + Seek,
+        P: NtfsProgressSink + ?Sized,
+    {
+        NtfsSecurityDescriptorStatistics::new(self, fs, progress)
+    }
+
     /// Returns the size of a single sector in bytes.
     pub fn sector_size(&self) -> u16 {
         self.sector_size
@@ -157,6 +866,41 @@ impl Ntfs {
         self.size
     }
 
+    /// Compares the duplicated file sizes in the $FILE_NAME attributes and directory entries of all
+    /// files with the sizes of their unnamed $DATA attributes, and returns an [`NtfsSizeMismatchReport`]
+    /// with all mismatches (see [`NtfsSizeMismatch`]).
+    ///
+    /// This is a cheap indicator of stale or tampered-with metadata, which [`Ntfs::check`] doesn't
+    /// report, as Windows itself updates these duplicated sizes lazily.
+    /// Directory entries are looked up according to the case sensitivity of each directory
+    /// (see [`NtfsFile::case_sensitivity`]).
+    ///
+    /// # Panics
+    ///
+    /// Panics if [`read_upcase_table`][Ntfs::read_upcase_table] had not been called.
+    ///
+    /// [`NtfsSizeMismatch`]: crate::NtfsSizeMismatch
+    pub fn size_mismatches<T>(&self, fs: &mut T) -> Result<NtfsSizeMismatchReport>
+    where
+        T: Read + Seek,
+    {
+        self.size_mismatches_with_progress(fs, &mut NtfsNoProgress)
+    }
+
+    /// Variant of [`Ntfs::size_mismatches`] that reports the number of File Records processed to an
+    /// [`NtfsProgressSink`], which may also cancel the operation.
+    pub fn size_mismatches_with_progress<T, P>(
+        &self,
+        fs: &mut T,
+        progress: &mut P,
+    ) -> Result<NtfsSizeMismatchReport>
+    where
+        T: Read + Seek,
+        P: NtfsProgressSink + ?Sized,
+    {
+        volume_size_mismatches(self, fs, progress)
+    }
+
     /// Returns the stored [`UpcaseTable`].
     ///
     /// # Panics
@@ -195,11 +939,51 @@ impl Ntfs {
             Err(e) => Some(Err(e)),
         }
     }
+
+    /// Returns the [`NtfsObjectId`] of the $Volume file, which holds the GUID of this NTFS volume
+    /// (as used by the Distributed Link Tracking service).
+    ///
+    /// Note that a volume may also have no Object ID, which is why the return value is further
+    /// encapsulated in an `Option`.
+    pub fn volume_object_id<T>(&self, fs: &mut T) -> Option<Result<NtfsObjectId>>
+    where
+        T: Read + Seek,
+    {
+        let volume_file = iter_try!(self.file(fs, KnownNtfsFileRecordNumber::Volume as u64));
+
+        match volume_file.find_resident_attribute_structured_value::<NtfsObjectId>(None) {
+            Ok(object_id) => Some(Ok(object_id)),
+            Err(NtfsError::AttributeNotFound { .. }) => None,
+            Err(e) => Some(Err(e)),
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use binrw::io::{Cursor, ErrorKind};
+
+    struct FailingReader {
+        inner: Cursor<Vec<u8>>,
+        failing_position: u64,
+    }
+
+    impl Read for FailingReader {
+        fn read(&mut self, buf: &mut [u8]) -> binrw::io::Result<usize> {
+            if self.inner.position() == self.failing_position {
+                Err(ErrorKind::Other.into())
+            } else {
+                self.inner.read(buf)
+            }
+        }
+    }
+
+    impl Seek for FailingReader {
+        fn seek(&mut self, pos: SeekFrom) -> binrw::io::Result<u64> {
+            self.inner.seek(pos)
+        }
+    }
 
     #[test]
     fn test_basics() {
@@ -210,6 +994,549 @@ mod tests {
         assert_eq!(ntfs.size(), 2096640);
     }
 
+    #[test]
+    fn test_4k_native_sectors() {
+        // testfs2 has been formatted for a 4K-native disk.
+        let mut testfs2 = crate::helpers::tests::testfs2();
+        let mut ntfs = Ntfs::new(&mut testfs2).unwrap();
+        ntfs.read_upcase_table(&mut testfs2).unwrap();
+        assert_eq!(ntfs.cluster_size(), 4096);
+        assert_eq!(ntfs.sector_size(), 4096);
+        assert_eq!(ntfs.file_record_size(), 4096);
+        assert_eq!(ntfs.size(), 2093056);
+
+        // NTFS-3G still protects the File Records in 512-byte blocks using 8 Update Sequence Array entries.
+        let mft = ntfs
+            .file(&mut testfs2, KnownNtfsFileRecordNumber::MFT as u64)
+            .unwrap();
+        let position = mft.position().value().unwrap().get() as usize;
+        assert_eq!(&testfs2.get_ref()[position + 6..position + 8], &[9, 0]);
+
+        let volume_name = ntfs.volume_name(&mut testfs2).unwrap().unwrap();
+        assert_eq!(volume_name.name(), "mylabel");
+
+        // Read a non-resident value spanning multiple clusters.
+        let file = ntfs
+            .file_by_path(&mut testfs2, "10000-bytes-file", Default::default())
+            .unwrap()
+            .unwrap();
+        let data_attribute_item = file.data(&mut testfs2, "").unwrap().unwrap();
+        let data_attribute = data_attribute_item.to_attribute().unwrap();
+        let mut data_attribute_value = data_attribute.value(&mut testfs2).unwrap();
+        let mut buf = vec![0u8; 10000];
+        data_attribute_value
+            .read_exact(&mut testfs2, &mut buf)
+            .unwrap();
+        assert!(buf.chunks(5).all(|chunk| chunk == b"12345"));
+
+        // The 100 numbered files need 4096-byte Index Records for the root directory.
+        let root_dir = ntfs.root_directory(&mut testfs2).unwrap();
+        assert!(root_dir
+            .attributes()
+            .attach(&mut testfs2)
+            .map(Result::unwrap)
+            .any(|item| item.to_attribute().unwrap().ty().unwrap()
+                == NtfsAttributeType::IndexAllocation));
+
+        for i in 1..=100 {
+            let file = ntfs
+                .file_by_path(&mut testfs2, &i.to_string(), Default::default())
+                .unwrap()
+                .unwrap();
+            assert_eq!(
+                file.data(&mut testfs2, "")
+                    .unwrap()
+                    .unwrap()
+                    .to_attribute()
+                    .unwrap()
+                    .value_length(),
+                5
+            );
+        }
+    }
+
+    #[test]
+    fn test_read_clusters() {
+        let mut testfs1 = crate::helpers::tests::testfs1();
+        let ntfs = Ntfs::new(&mut testfs1).unwrap();
+
+        // LCN 0 contains the boot sector.
+        let mut buf = vec![0u8; 1024];
+        ntfs.read_clusters(&mut testfs1, Lcn::from(0), 2, &mut buf)
+            .unwrap();
+        assert_eq!(&buf[3..11], b"NTFS    ");
+        assert_eq!(&buf[..1024], &testfs1.get_ref()[..1024]);
+
+        // The Master File Table starts with the "FILE" signature of File Record 0.
+        let mft_lcn = ntfs.boot_sector().unwrap().mft_lcn();
+        ntfs.read_clusters(&mut testfs1, mft_lcn, 1, &mut buf)
+            .unwrap();
+        assert_eq!(&buf[..4], b"FILE");
+
+        // The last cluster can be read, but not a single one behind it.
+        let total_clusters = ntfs.size() / ntfs.cluster_size() as u64;
+        ntfs.read_clusters(&mut testfs1, Lcn::from(total_clusters - 1), 1, &mut buf)
+            .unwrap();
+        assert!(matches!(
+            ntfs.read_clusters(&mut testfs1, Lcn::from(total_clusters - 1), 2, &mut buf),
+            Err(NtfsError::ClusterRangeOutOfBounds { .. })
+        ));
+        assert!(matches!(
+            ntfs.read_clusters(&mut testfs1, Lcn::from(u64::MAX), 1, &mut buf),
+            Err(NtfsError::ClusterRangeOutOfBounds { .. })
+        ));
+
+        // The buffer needs room for all clusters.
+        assert!(matches!(
+            ntfs.read_clusters(&mut testfs1, Lcn::from(0), 3, &mut buf),
+            Err(NtfsError::BufferTooSmall {
+                expected: 1536,
+                actual: 1024
+            })
+        ));
+    }
+
+    #[test]
+    fn test_extend_file() {
+        let mut testfs1 = crate::helpers::tests::testfs1();
+        let ntfs = Ntfs::new(&mut testfs1).unwrap();
+
+        for name in ["$ObjId", "$Quota", "$Reparse"] {
+            let file = ntfs.extend_file(&mut testfs1, name).unwrap().unwrap();
+            let file_name = file.name(&mut testfs1, None, None).unwrap().unwrap();
+            assert_eq!(file_name.name(), name);
+            assert_eq!(
+                file_name.parent_directory_reference().file_record_number(),
+                KnownNtfsFileRecordNumber::Extend as u64
+            );
+        }
+
+        // Names are compared exactly.
+        assert!(ntfs.extend_file(&mut testfs1, "$QUOTA").is_none());
+        assert!(ntfs.extend_file(&mut testfs1, "$UsnJrnl").is_none());
+    }
+
+    #[test]
+    fn test_file_by_path() {
+        let mut testfs1 = crate::helpers::tests::testfs1();
+        let mut ntfs = Ntfs::new(&mut testfs1).unwrap();
+        ntfs.read_upcase_table(&mut testfs1).unwrap();
+
+        let file = ntfs
+            .file_by_path(
+                &mut testfs1,
+                "/many_subdirs\\123",
+                NtfsCaseSensitivity::Sensitive,
+            )
+            .unwrap()
+            .unwrap();
+        let file_name = file.name(&mut testfs1, None, None).unwrap().unwrap();
+        assert_eq!(file_name.name(), "123");
+
+        let lookup = ntfs
+            .lookup_path(
+                &mut testfs1,
+                "many_subdirs/123",
+                NtfsCaseSensitivity::Sensitive,
+            )
+            .unwrap()
+            .unwrap();
+        assert!(!lookup.found_by_mft_scan());
+        assert_eq!(
+            lookup.file().file_record_number(),
+            file.file_record_number()
+        );
+
+        let root_dir = ntfs
+            .file_by_path(&mut testfs1, "", NtfsCaseSensitivity::Insensitive)
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            root_dir.file_record_number(),
+            KnownNtfsFileRecordNumber::RootDirectory as u64
+        );
+
+        // Names differing by case are only found by a case-insensitive lookup.
+        assert!(ntfs
+            .file_by_path(
+                &mut testfs1,
+                "FILE-WITH-12345",
+                NtfsCaseSensitivity::Insensitive
+            )
+            .unwrap()
+            .is_ok());
+        assert!(ntfs
+            .file_by_path(
+                &mut testfs1,
+                "FILE-WITH-12345",
+                NtfsCaseSensitivity::Sensitive
+            )
+            .is_none());
+        assert!(ntfs
+            .file_by_path(
+                &mut testfs1,
+                "file-with-12345",
+                NtfsCaseSensitivity::Sensitive
+            )
+            .unwrap()
+            .is_ok());
+
+        // No directory of testfs1 is marked case-sensitive.
+        assert_eq!(
+            root_dir.case_sensitivity().unwrap(),
+            NtfsCaseSensitivity::Insensitive
+        );
+        assert!(ntfs
+            .file_by_path(
+                &mut testfs1,
+                "MANY_SUBDIRS/123",
+                NtfsCaseSensitivity::default()
+            )
+            .unwrap()
+            .is_ok());
+
+        // Path components must be directories.
+        assert!(matches!(
+            ntfs.file_by_path(
+                &mut testfs1,
+                "file-with-12345/foo",
+                NtfsCaseSensitivity::Insensitive
+            ),
+            Some(Err(NtfsError::NotADirectory { .. }))
+        ));
+    }
+
+    #[test]
+    fn test_file_by_path_case_sensitive_directory() {
+        let mut testfs1 = crate::helpers::tests::testfs1();
+        let ntfs = Ntfs::new(&mut testfs1).unwrap();
+
+        // $Extend is the only directory of testfs1 with NTFS 3.x file information, which can carry the flag.
+        let extend = ntfs
+            .file(&mut testfs1, KnownNtfsFileRecordNumber::Extend as u64)
+            .unwrap();
+        let mut attributes = extend.attributes();
+        let mut standard_information_position = None;
+
+        while let Some(item) = attributes.next(&mut testfs1) {
+            let item = item.unwrap();
+            let attribute = item.to_attribute().unwrap();
+            if attribute.ty().unwrap() == NtfsAttributeType::StandardInformation {
+                let value = attribute.value(&mut testfs1).unwrap();
+                standard_information_position = value.data_position().value();
+            }
+        }
+
+        // Set the case-sensitivity flag of $Extend, just like `fsutil file setCaseSensitiveInfo`.
+        let position = standard_information_position.unwrap().get() as usize + 0x28;
+        testfs1.get_mut()[position] |= 0x1;
+
+        let mut ntfs = Ntfs::new(&mut testfs1).unwrap();
+        ntfs.read_upcase_table(&mut testfs1).unwrap();
+        let extend = ntfs
+            .file(&mut testfs1, KnownNtfsFileRecordNumber::Extend as u64)
+            .unwrap();
+        assert!(extend.info().unwrap().is_case_sensitive_directory());
+        assert_eq!(
+            extend.case_sensitivity().unwrap(),
+            NtfsCaseSensitivity::Sensitive
+        );
+
+        let root_dir = ntfs.root_directory(&mut testfs1).unwrap();
+        assert_eq!(
+            root_dir.case_sensitivity().unwrap(),
+            NtfsCaseSensitivity::Insensitive
+        );
+
+        // Lookups in $Extend are now case-sensitive by default, but not in its parent directory.
+        assert!(ntfs
+            .file_by_path(
+                &mut testfs1,
+                "$EXTEND/$quota",
+                NtfsCaseSensitivity::PerDirectory
+            )
+            .is_none());
+        assert!(ntfs
+            .file_by_path(
+                &mut testfs1,
+                "$EXTEND/$Quota",
+                NtfsCaseSensitivity::PerDirectory
+            )
+            .unwrap()
+            .is_ok());
+        assert!(ntfs
+            .file_by_path(
+                &mut testfs1,
+                "$EXTEND/$quota",
+                NtfsCaseSensitivity::Insensitive
+            )
+            .unwrap()
+            .is_ok());
+    }
+
+    #[test]
+    fn test_file_by_path_damaged_index() {
+        let mut testfs1 = crate::helpers::tests::testfs1();
+        let ntfs = Ntfs::new(&mut testfs1).unwrap();
+        let root_dir = ntfs.root_directory(&mut testfs1).unwrap();
+        let mut attributes = root_dir.attributes();
+        let mut index_allocation_position = None;
+
+        while let Some(item) = attributes.next(&mut testfs1) {
+            let item = item.unwrap();
+            let attribute = item.to_attribute().unwrap();
+            if attribute.ty().unwrap() == NtfsAttributeType::IndexAllocation {
+                let value = attribute.value(&mut testfs1).unwrap();
+                index_allocation_position = value.data_position().value();
+            }
+        }
+
+        // Destroy the signature of the first Index Record of the root directory.
+        let position = index_allocation_position.unwrap().get() as usize;
+        testfs1.get_mut()[position..position + 4].copy_from_slice(b"XXXX");
+
+        let mut ntfs = Ntfs::new(&mut testfs1).unwrap();
+        ntfs.read_upcase_table(&mut testfs1).unwrap();
+        assert!(matches!(
+            ntfs.file_by_path(
+                &mut testfs1,
+                "1000-bytes-file",
+                NtfsCaseSensitivity::Insensitive
+            ),
+            Some(Err(_))
+        ));
+
+        let options = NtfsOptions::new().with_damaged_index_policy(NtfsDamagedIndexPolicy::ScanMft);
+        let mut ntfs = Ntfs::new_with_options(&mut testfs1, options).unwrap();
+        ntfs.read_upcase_table(&mut testfs1).unwrap();
+
+        let lookup = ntfs
+            .lookup_path(
+                &mut testfs1,
+                "1000-BYTES-FILE",
+                NtfsCaseSensitivity::Insensitive,
+            )
+            .unwrap()
+            .unwrap();
+        assert!(lookup.found_by_mft_scan());
+        let file_name = lookup
+            .file()
+            .name(&mut testfs1, None, None)
+            .unwrap()
+            .unwrap();
+        assert_eq!(file_name.name(), "1000-bytes-file");
+        assert_eq!(lookup.into_file().file_record_number(), 66);
+
+        // The intact index of a subdirectory is used again below the damaged one.
+        let file = ntfs
+            .file_by_path(
+                &mut testfs1,
+                "many_subdirs/123",
+                NtfsCaseSensitivity::Sensitive,
+            )
+            .unwrap()
+            .unwrap();
+        let file_name = file.name(&mut testfs1, None, None).unwrap().unwrap();
+        assert_eq!(file_name.name(), "123");
+
+        // The scan honors the case sensitivity and doesn't make up files.
+        assert!(ntfs
+            .file_by_path(
+                &mut testfs1,
+                "1000-BYTES-FILE",
+                NtfsCaseSensitivity::Sensitive
+            )
+            .is_none());
+        assert!(ntfs
+            .file_by_path(
+                &mut testfs1,
+                "no-such-file",
+                NtfsCaseSensitivity::Insensitive
+            )
+            .is_none());
+
+        // An I/O error during the scan is returned instead of turning into "not found".
+        let failing_position = ntfs
+            .file(&mut testfs1, 30)
+            .unwrap()
+            .position()
+            .value()
+            .unwrap();
+        let mut failing_fs = FailingReader {
+            inner: testfs1,
+            failing_position: failing_position.get(),
+        };
+        let error = ntfs
+            .file_by_path(
+                &mut failing_fs,
+                "no-such-file",
+                NtfsCaseSensitivity::Insensitive,
+            )
+            .unwrap()
+            .unwrap_err();
+        assert_eq!(error.kind(), NtfsErrorKind::Io);
+    }
+
+    #[test]
+    fn test_file_by_path_u16() {
+        let mut testfs1 = crate::helpers::tests::testfs1();
+        let mut ntfs = Ntfs::new(&mut testfs1).unwrap();
+        ntfs.read_upcase_table(&mut testfs1).unwrap();
+
+        let path = "/many_subdirs\\123".encode_utf16().collect::<Vec<u16>>();
+        let file = ntfs
+            .file_by_path_u16(&mut testfs1, &path, NtfsCaseSensitivity::Sensitive)
+            .unwrap()
+            .unwrap();
+        let file_name = file.name(&mut testfs1, None, None).unwrap().unwrap();
+        assert_eq!(file_name.name(), "123");
+
+        let path = "FILE-WITH-12345".encode_utf16().collect::<Vec<u16>>();
+        assert!(ntfs
+            .file_by_path_u16(&mut testfs1, &path, NtfsCaseSensitivity::Insensitive)
+            .unwrap()
+            .is_ok());
+        assert!(ntfs
+            .file_by_path_u16(&mut testfs1, &path, NtfsCaseSensitivity::Sensitive)
+            .is_none());
+    }
+
+    #[test]
+    fn test_file_spanning_data_runs() {
+        let mut testfs1 = crate::helpers::tests::testfs1();
+        let ntfs = Ntfs::new(&mut testfs1).unwrap();
+        let mft_data = ntfs.mft_data(&mut testfs1).unwrap();
+        let file_record_size = ntfs.file_record_size() as u64;
+
+        // The first Data Run of the testfs1 MFT ends in the middle of File Record 255.
+        let mut data_runs = mft_data.data_runs().unwrap();
+        let first_data_run = data_runs.next().unwrap().unwrap();
+        let file_record_number = first_data_run.allocated_size() / file_record_size;
+        assert_eq!(file_record_number, 255);
+        assert_ne!(first_data_run.allocated_size() % file_record_size, 0);
+
+        // Reading the File Record as one contiguous block from its position hits unrelated data.
+        let position = first_data_run.data_position().value().unwrap().get()
+            + file_record_number * file_record_size;
+        assert!(matches!(
+            NtfsFile::new(&ntfs, &mut testfs1, position, file_record_number),
+            Err(NtfsError::UpdateSequenceNumberMismatch { .. })
+        ));
+
+        // `Ntfs::file` follows the MFT $DATA attribute value into the next Data Run.
+        let file = ntfs.file(&mut testfs1, file_record_number).unwrap();
+        assert_eq!(file.position().value().unwrap().get(), position);
+        assert!(file.flags().contains(NtfsFileFlags::IN_USE));
+
+        let mut expected = vec![0; file_record_size as usize];
+        let mut mft_data_value = mft_data.value().unwrap();
+        mft_data_value
+            .seek(
+                &mut testfs1,
+                SeekFrom::Start(file_record_number * file_record_size),
+            )
+            .unwrap();
+        mft_data_value
+            .read_exact(&mut testfs1, &mut expected)
+            .unwrap();
+
+        // Apart from the last two bytes of each sector, which are restored by the fixup,
+        // the File Record is the MFT $DATA attribute value at its offset.
+        let record_data = file.record_data();
+        assert_eq!(record_data[..510], expected[..510]);
+        assert_eq!(record_data[512..1022], expected[512..1022]);
+        let name = file.name(&mut testfs1, None, None).unwrap().unwrap();
+        assert!(!name.name().is_empty());
+    }
+
+    #[test]
+    fn test_file_record_count() {
+        let mut testfs1 = crate::helpers::tests::testfs1();
+        let ntfs = Ntfs::new(&mut testfs1).unwrap();
+        let file_record_count = ntfs.file_record_count(&mut testfs1).unwrap();
+        let mft_data = ntfs.mft_data(&mut testfs1).unwrap();
+        assert_eq!(
+            file_record_count * ntfs.file_record_size() as u64,
+            mft_data.len()
+        );
+
+        // The last File Record can be read, but the next one is out of bounds.
+        assert!(ntfs.file(&mut testfs1, file_record_count - 1).is_ok());
+        assert!(ntfs.file(&mut testfs1, file_record_count).is_err());
+    }
+
+    #[test]
+    fn test_from_mft() {
+        let mut testfs1 = crate::helpers::tests::testfs1();
+        let mut ntfs = Ntfs::new(&mut testfs1).unwrap();
+        ntfs.read_upcase_table(&mut testfs1).unwrap();
+        let file_record_count = ntfs.file_record_count(&mut testfs1).unwrap();
+        let resident_file_record_number = ntfs
+            .file_by_path(
+                &mut testfs1,
+                "file-with-12345",
+                NtfsCaseSensitivity::Insensitive,
+            )
+            .unwrap()
+            .unwrap()
+            .file_record_number();
+        let non_resident_file_record_number = ntfs
+            .file_by_path(
+                &mut testfs1,
+                "1000-bytes-file",
+                NtfsCaseSensitivity::Insensitive,
+            )
+            .unwrap()
+            .unwrap()
+            .file_record_number();
+
+        // Extract the $MFT file.
+        let mut mft = binrw::io::Cursor::new(Vec::new());
+        ntfs.mft_data(&mut testfs1)
+            .unwrap()
+            .copy_to(&mut testfs1, &mut mft)
+            .unwrap();
+
+        let ntfs = Ntfs::from_mft(&mut mft, ntfs.file_record_size()).unwrap();
+        assert!(ntfs.is_standalone_mft());
+        assert_eq!(ntfs.cluster_size(), 512);
+        assert_eq!(ntfs.file_record_count(&mut mft).unwrap(), file_record_count);
+
+        // Names, timestamps and resident values are available.
+        let file = ntfs.file(&mut mft, resident_file_record_number).unwrap();
+        let file_name = file.name(&mut mft, None, None).unwrap().unwrap();
+        assert_eq!(file_name.name(), "file-with-12345");
+        assert!(file.info().is_ok());
+
+        let data_item = file.data(&mut mft, "").unwrap().unwrap();
+        let data_attribute = data_item.to_attribute().unwrap();
+        let mut data_value = data_attribute.value(&mut mft).unwrap();
+        let mut buf = [0u8; 5];
+        data_value.read_exact(&mut mft, &mut buf).unwrap();
+        assert_eq!(&buf, b"12345");
+
+        // Cluster data isn't.
+        let file = ntfs
+            .file(&mut mft, non_resident_file_record_number)
+            .unwrap();
+        let data_item = file.data(&mut mft, "").unwrap().unwrap();
+        let data_attribute = data_item.to_attribute().unwrap();
+        assert_eq!(data_attribute.value_length(), 1000);
+        assert!(matches!(
+            data_attribute.value(&mut mft),
+            Err(NtfsError::ClusterDataUnavailable { .. })
+        ));
+
+        assert!(matches!(
+            ntfs.file(&mut mft, file_record_count),
+            Err(NtfsError::InvalidFileRecordNumber { .. })
+        ));
+        assert!(matches!(
+            Ntfs::from_mft(&mut mft, 1000),
+            Err(NtfsError::UnsupportedFileRecordSize { .. })
+        ));
+    }
+
     #[test]
     fn test_volume_info() {
         let mut testfs1 = crate::helpers::tests::testfs1();
@@ -0,0 +1,181 @@
+// Copyright 2021-2023 Colin Finck <colin@reactos.org>
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+/// Behavior of path lookups when a directory index along the path is damaged, as configured in [`NtfsOptions`].
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum NtfsDamagedIndexPolicy {
+    /// Return the error encountered while reading the directory index.
+    ///
+    /// This is the default behavior.
+    Error,
+    /// Fall back to scanning the entire Master File Table (MFT) for a file whose $FILE_NAME attribute
+    /// refers to the directory and carries the searched name.
+    ///
+    /// This is much slower than an index lookup, but only depends on intact File Records of the files
+    /// along the path.
+    ScanMft,
+}
+
+impl Default for NtfsDamagedIndexPolicy {
+    fn default() -> Self {
+        Self::Error
+    }
+}
+
+/// Behavior when encountering a value that this crate doesn't support, as configured in [`NtfsOptions`].
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum NtfsUnsupportedPolicy {
+    /// Return an error (like [`NtfsError::UnsupportedAttributeType`] or [`NtfsError::UnsupportedFileNamespace`]).
+    ///
+    /// This is the default behavior.
+    ///
+    /// [`NtfsError::UnsupportedAttributeType`]: crate::NtfsError::UnsupportedAttributeType
+    /// [`NtfsError::UnsupportedFileNamespace`]: crate::NtfsError::UnsupportedFileNamespace
+    Error,
+    /// Silently skip the affected item during iteration.
+    Skip,
+    /// Return the affected item during iteration, but leave the unsupported value uninterpreted.
+    YieldUnknown,
+}
+
+impl Default for NtfsUnsupportedPolicy {
+    fn default() -> Self {
+        Self::Error
+    }
+}
+
+/// Behavior when a non-resident attribute value declares a larger data size than its Data Runs provide,
+/// as configured in [`NtfsOptions`].
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum NtfsUnbackedDataPolicy {
+    /// Return [`NtfsError::AttributeValueNotBacked`] when reading past the last Data Run.
+    ///
+    /// This is the default behavior.
+    ///
+    /// [`NtfsError::AttributeValueNotBacked`]: crate::NtfsError::AttributeValueNotBacked
+    Error,
+    /// Read zeros from the end of the last Data Run up to the declared data size.
+    ZeroFill,
+}
+
+impl Default for NtfsUnbackedDataPolicy {
+    fn default() -> Self {
+        Self::Error
+    }
+}
+
+/// Options for opening an NTFS filesystem with [`Ntfs::new_with_options`].
+///
+/// This is a builder: Start with [`NtfsOptions::new`] and chain calls to configure the options you need.
+///
+/// [`Ntfs::new_with_options`]: crate::Ntfs::new_with_options
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub struct NtfsOptions {
+    attribute_type_policy: NtfsUnsupportedPolicy,
+    damaged_index_policy: NtfsDamagedIndexPolicy,
+    file_namespace_policy: NtfsUnsupportedPolicy,
+    unbacked_data_policy: NtfsUnbackedDataPolicy,
+}
+
+impl NtfsOptions {
+    /// Creates a new [`NtfsOptions`] object with default options.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the [`NtfsUnsupportedPolicy`] for attributes of unsupported types.
+    pub fn attribute_type_policy(&self) -> NtfsUnsupportedPolicy {
+        self.attribute_type_policy
+    }
+
+    /// Returns the [`NtfsDamagedIndexPolicy`] for path lookups through damaged directory indexes.
+    pub fn damaged_index_policy(&self) -> NtfsDamagedIndexPolicy {
+        self.damaged_index_policy
+    }
+
+    /// Returns the [`NtfsUnsupportedPolicy`] for file names of unsupported namespaces.
+    pub fn file_namespace_policy(&self) -> NtfsUnsupportedPolicy {
+        self.file_namespace_policy
+    }
+
+    /// Returns the [`NtfsUnbackedDataPolicy`] for attribute values whose Data Runs end before their data size.
+    pub fn unbacked_data_policy(&self) -> NtfsUnbackedDataPolicy {
+        self.unbacked_data_policy
+    }
+
+    /// Sets the [`NtfsUnsupportedPolicy`] for attributes of unsupported types.
+    ///
+    /// This affects [`NtfsAttributes`] iteration and all functions of [`NtfsFile`] that look for
+    /// attributes of a specific type:
+    ///
+    /// * [`NtfsUnsupportedPolicy::Error`] returns [`NtfsError::UnsupportedAttributeType`] when looking
+    ///   for an attribute and encountering an unsupported one.
+    /// * [`NtfsUnsupportedPolicy::Skip`] leaves out unsupported attributes.
+    /// * [`NtfsUnsupportedPolicy::YieldUnknown`] returns unsupported attributes from [`NtfsAttributes`]
+    ///   iteration (where [`NtfsAttribute::ty`] still returns an error), but doesn't consider them when
+    ///   looking for an attribute.
+    ///
+    /// [`NtfsAttribute::ty`]: crate::NtfsAttribute::ty
+    /// [`NtfsAttributes`]: crate::NtfsAttributes
+    /// [`NtfsError::UnsupportedAttributeType`]: crate::NtfsError::UnsupportedAttributeType
+    /// [`NtfsFile`]: crate::NtfsFile
+    pub fn with_attribute_type_policy(mut self, policy: NtfsUnsupportedPolicy) -> Self {
+        self.attribute_type_policy = policy;
+        self
+    }
+
+    /// Sets the [`NtfsDamagedIndexPolicy`] for path lookups through damaged directory indexes.
+    ///
+    /// This only affects path lookups via [`Ntfs::file_by_path`], [`Ntfs::lookup_path`], and their UTF-16
+    /// variants (and therefore the [`NtfsArtifactLocator`]):
+    /// Whenever looking up a path component in the index of its directory fails with an error of kind
+    /// [`NtfsErrorKind::Corrupted`], [`NtfsDamagedIndexPolicy::ScanMft`] retries the lookup by scanning the MFT.
+    /// Any other error (like an I/O error) is returned as it is, and so is any such error encountered
+    /// during the scan, except for damaged File Records, which are skipped.
+    /// A component that is simply not found in an intact index is never looked up again.
+    /// [`NtfsPathLookup::found_by_mft_scan`] tells whether a scan has been necessary.
+    ///
+    /// [`Ntfs::file_by_path`]: crate::Ntfs::file_by_path
+    /// [`Ntfs::lookup_path`]: crate::Ntfs::lookup_path
+    /// [`NtfsArtifactLocator`]: crate::NtfsArtifactLocator
+    /// [`NtfsErrorKind::Corrupted`]: crate::NtfsErrorKind::Corrupted
+    /// [`NtfsPathLookup::found_by_mft_scan`]: crate::NtfsPathLookup::found_by_mft_scan
+    pub fn with_damaged_index_policy(mut self, policy: NtfsDamagedIndexPolicy) -> Self {
+        self.damaged_index_policy = policy;
+        self
+    }
+
+    /// Sets the [`NtfsUnsupportedPolicy`] for file names of unsupported namespaces.
+    ///
+    /// This affects [`NtfsFile::names`] iteration and [`NtfsFile::name`], as well as the directory entries
+    /// returned by [`NtfsIndexEntries`] and found via [`NtfsIndexFinder`] (and therefore path lookups,
+    /// the tree exporter, and all other directory walkers):
+    ///
+    /// * [`NtfsUnsupportedPolicy::Error`] returns [`NtfsError::UnsupportedFileNamespace`].
+    /// * [`NtfsUnsupportedPolicy::Skip`] leaves out file names of unsupported namespaces.
+    /// * [`NtfsUnsupportedPolicy::YieldUnknown`] returns such file names, with
+    ///   [`NtfsFileName::namespace`] returning `None` (see [`NtfsFileName::namespace_value`]).
+    ///
+    /// [`NtfsError::UnsupportedFileNamespace`]: crate::NtfsError::UnsupportedFileNamespace
+    /// [`NtfsFile::name`]: crate::NtfsFile::name
+    /// [`NtfsFile::names`]: crate::NtfsFile::names
+    /// [`NtfsFileName::namespace`]: crate::structured_values::NtfsFileName::namespace
+    /// [`NtfsFileName::namespace_value`]: crate::structured_values::NtfsFileName::namespace_value
+    /// [`NtfsIndexEntries`]: crate::NtfsIndexEntries
+    /// [`NtfsIndexFinder`]: crate::NtfsIndexFinder
+    pub fn with_file_namespace_policy(mut self, policy: NtfsUnsupportedPolicy) -> Self {
+        self.file_namespace_policy = policy;
+        self
+    }
+
+    /// Sets the [`NtfsUnbackedDataPolicy`] for non-resident attribute values whose Data Runs end before
+    /// their declared data size (see [`NtfsAttributeValue::backed_len`]).
+    ///
+    /// This only affects reading from the end of the last Data Run up to the declared data size.
+    ///
+    /// [`NtfsAttributeValue::backed_len`]: crate::attribute_value::NtfsAttributeValue::backed_len
+    pub fn with_unbacked_data_policy(mut self, policy: NtfsUnbackedDataPolicy) -> Self {
+        self.unbacked_data_policy = policy;
+        self
+    }
+}
@@ -0,0 +1,47 @@
+// Copyright 2021-2023 Colin Finck <colin@reactos.org>
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+use crate::file::NtfsFile;
+
+/// Result of looking up a file by its path, as returned by [`Ntfs::lookup_path`] and [`Ntfs::lookup_path_u16`].
+///
+/// Besides the found [`NtfsFile`], this tells whether the lookup had to scan the Master File Table
+/// because a directory index along the path was damaged (see [`NtfsDamagedIndexPolicy::ScanMft`]).
+///
+/// [`Ntfs::lookup_path`]: crate::Ntfs::lookup_path
+/// [`Ntfs::lookup_path_u16`]: crate::Ntfs::lookup_path_u16
+/// [`NtfsDamagedIndexPolicy::ScanMft`]: crate::NtfsDamagedIndexPolicy::ScanMft
+#[derive(Clone, Debug)]
+pub struct NtfsPathLookup<'n> {
+    file: NtfsFile<'n>,
+    found_by_mft_scan: bool,
+}
+
+impl<'n> NtfsPathLookup<'n> {
+    pub(crate) fn new(file: NtfsFile<'n>, found_by_mft_scan: bool) -> Self {
+        Self {
+            file,
+            found_by_mft_scan,
+        }
+    }
+
+    /// Returns the found [`NtfsFile`].
+    pub fn file(&self) -> &NtfsFile<'n> {
+        &self.file
+    }
+
+    /// Returns whether at least one path component was found by scanning the Master File Table
+    /// instead of through the index of its directory.
+    ///
+    /// Such a result is only as trustworthy as the $FILE_NAME attributes of the scanned File Records.
+    /// In particular, a scan returns the first file in use that carries a matching name,
+    /// while the damaged index may have referred to another one.
+    pub fn found_by_mft_scan(&self) -> bool {
+        self.found_by_mft_scan
+    }
+
+    /// Returns the found [`NtfsFile`], consuming this object.
+    pub fn into_file(self) -> NtfsFile<'n> {
+        self.file
+    }
+}
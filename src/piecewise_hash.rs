@@ -0,0 +1,248 @@
+// Copyright 2021-2023 Colin Finck <colin@reactos.org>
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+use alloc::vec::Vec;
+use binrw::io::{Read, Seek, SeekFrom};
+
+use crate::attribute_value::{NtfsAttributeValue, COPY_BUFFER_SIZE};
+use crate::error::Result;
+use crate::traits::NtfsReadSeek;
+
+/// Hash function used for piecewise hashing of a data stream via [`NtfsAttribute::piecewise_hashes`].
+///
+/// This crate doesn't come with any hash function itself.
+/// Implement this trait for the hash function you need (e.g. SHA-256 or BLAKE3 from their respective crates).
+///
+/// [`NtfsAttribute::piecewise_hashes`]: crate::NtfsAttribute::piecewise_hashes
+pub trait NtfsPieceHasher {
+    /// Hash value of a single piece.
+    type Digest;
+
+    /// Feeds the next bytes of the current piece into the hash function.
+    fn update(&mut self, data: &[u8]);
+
+    /// Returns the hash value of the current piece and resets the hash function for the next piece.
+    fn finish_piece(&mut self) -> Self::Digest;
+}
+
+/// Hash value of a single piece of a data stream, returned by [`NtfsAttribute::piecewise_hashes`].
+///
+/// [`NtfsAttribute::piecewise_hashes`]: crate::NtfsAttribute::piecewise_hashes
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct NtfsPieceHash<D> {
+    offset: u64,
+    length: u64,
+    digest: D,
+}
+
+impl<D> NtfsPieceHash<D> {
+    /// Returns the hash value of this piece.
+    pub fn digest(&self) -> &D {
+        &self.digest
+    }
+
+    /// Returns the length of this piece, in bytes.
+    ///
+    /// All pieces have the requested piece size, except for the last one, which may be shorter.
+    pub fn length(&self) -> u64 {
+        self.length
+    }
+
+    /// Returns the byte offset of this piece within the data stream.
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+}
+
+/// Hashes the entire `value` in pieces of `piece_size` bytes.
+///
+/// If reading fails in the middle of a piece, `hasher` is reset before returning the error,
+/// so that it can be reused for another value.
+pub(crate) fn piecewise_hashes<T, H>(
+    value: &mut NtfsAttributeValue,
+    fs: &mut T,
+    piece_size: u64,
+    hasher: &mut H,
+) -> Result<Vec<NtfsPieceHash<H::Digest>>>
+where
+    T: Read + Seek,
+    H: NtfsPieceHasher + ?Sized,
+{
+    assert!(piece_size > 0, "piece_size must not be zero");
+
+    value.seek(fs, SeekFrom::Start(0))?;
+
+    let mut pieces = Vec::new();
+    let mut offset = 0;
+    let mut piece_bytes_hashed = 0;
+
+    let result = value.read_all_chunked(fs, COPY_BUFFER_SIZE, |mut chunk| {
+        // Pieces need not be aligned to chunks, so a chunk may finish one piece and start the next.
+        while !chunk.is_empty() {
+            let length = (piece_size - piece_bytes_hashed).min(chunk.len() as u64) as usize;
+            hasher.update(&chunk[..length]);
+            piece_bytes_hashed += length as u64;
+            chunk = &chunk[length..];
+
+            if piece_bytes_hashed == piece_size {
+                pieces.push(NtfsPieceHash {
+                    offset,
+                    length: piece_size,
+                    digest: hasher.finish_piece(),
+                });
+                offset += piece_size;
+                piece_bytes_hashed = 0;
+            }
+        }
+    });
+
+    if let Err(e) = result {
+        if piece_bytes_hashed > 0 {
+            hasher.finish_piece();
+        }
+
+        return Err(e);
+    }
+
+    if piece_bytes_hashed > 0 {
+        pieces.push(NtfsPieceHash {
+            offset,
+            length: piece_bytes_hashed,
+            digest: hasher.finish_piece(),
+        });
+    }
+
+    Ok(pieces)
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec;
+    use binrw::io::{Cursor, ErrorKind};
+
+    use super::*;
+    use crate::ntfs::Ntfs;
+    use crate::upcase_table::NtfsCaseSensitivity;
+
+    /// 64-bit FNV-1a, which is good enough to tell pieces apart in a test.
+    struct Fnv1a(u64);
+
+    impl Fnv1a {
+        const OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+        const PRIME: u64 = 0x0100_0000_01b3;
+
+        fn new() -> Self {
+            Self(Self::OFFSET_BASIS)
+        }
+
+        fn hash(data: &[u8]) -> u64 {
+            let mut hasher = Self::new();
+            hasher.update(data);
+            hasher.finish_piece()
+        }
+    }
+
+    impl NtfsPieceHasher for Fnv1a {
+        type Digest = u64;
+
+        fn update(&mut self, data: &[u8]) {
+            for byte in data {
+                self.0 = (self.0 ^ *byte as u64).wrapping_mul(Self::PRIME);
+            }
+        }
+
+        fn finish_piece(&mut self) -> u64 {
+            core::mem::replace(&mut self.0, Self::OFFSET_BASIS)
+        }
+    }
+
+    /// Filesystem reader that fails to read a single cluster.
+    struct FailingReader {
+        inner: Cursor<Vec<u8>>,
+        failing_position: u64,
+    }
+
+    impl Read for FailingReader {
+        fn read(&mut self, buf: &mut [u8]) -> binrw::io::Result<usize> {
+            if self.inner.position() == self.failing_position {
+                Err(ErrorKind::Other.into())
+            } else {
+                self.inner.read(buf)
+            }
+        }
+    }
+
+    impl Seek for FailingReader {
+        fn seek(&mut self, pos: SeekFrom) -> binrw::io::Result<u64> {
+            self.inner.seek(pos)
+        }
+    }
+
+    #[test]
+    fn test_piecewise_hashes() {
+        let mut testfs1 = crate::helpers::tests::testfs1();
+        let mut ntfs = Ntfs::new(&mut testfs1).unwrap();
+        ntfs.read_upcase_table(&mut testfs1).unwrap();
+        let file = ntfs
+            .file_by_path(
+                &mut testfs1,
+                "sparse-file",
+                NtfsCaseSensitivity::Insensitive,
+            )
+            .unwrap()
+            .unwrap();
+        let data_item = file.data(&mut testfs1, "").unwrap().unwrap();
+        let data_attribute = data_item.to_attribute().unwrap();
+
+        let mut data = vec![0u8; 500005];
+        data_attribute
+            .value(&mut testfs1)
+            .unwrap()
+            .read_exact(&mut testfs1, &mut data)
+            .unwrap();
+
+        // Pieces span the sparse Data Run, which is hashed as zeros.
+        let piece_size = 65536;
+        let pieces = data_attribute
+            .piecewise_hashes(&mut testfs1, piece_size, &mut Fnv1a::new())
+            .unwrap();
+        assert_eq!(pieces.len(), 8);
+
+        for (piece, expected_data) in pieces.iter().zip(data.chunks(piece_size as usize)) {
+            assert_eq!(piece.length(), expected_data.len() as u64);
+            assert_eq!(*piece.digest(), Fnv1a::hash(expected_data));
+        }
+
+        assert_eq!(pieces[7].offset(), 7 * piece_size);
+        assert_eq!(pieces[7].length(), 500005 - 7 * piece_size);
+
+        // The middle pieces only consist of zeros and therefore have the same hash value.
+        assert_eq!(pieces[1].digest(), pieces[2].digest());
+        assert_ne!(pieces[0].digest(), pieces[1].digest());
+
+        // The value is always hashed from its beginning.
+        let mut value = data_attribute.value(&mut testfs1).unwrap();
+        value.seek(&mut testfs1, SeekFrom::Start(1000)).unwrap();
+        let whole = value
+            .piecewise_hashes(&mut testfs1, u64::MAX, &mut Fnv1a::new())
+            .unwrap();
+        assert_eq!(whole.len(), 1);
+        assert_eq!(*whole[0].digest(), Fnv1a::hash(&data));
+
+        // A read error in the middle of a piece resets the hasher.
+        // Fail when reading the first cluster of the last Data Run, which is hashed after the sparse one.
+        value
+            .seek(&mut testfs1, SeekFrom::Start(500000 / 512 * 512))
+            .unwrap();
+        let failing_position = value.data_position().value().unwrap().get();
+        let mut failing_fs = FailingReader {
+            inner: testfs1,
+            failing_position,
+        };
+        let mut hasher = Fnv1a::new();
+        assert!(value
+            .piecewise_hashes(&mut failing_fs, u64::MAX, &mut hasher)
+            .is_err());
+        assert_eq!(hasher.0, Fnv1a::OFFSET_BASIS);
+    }
+}
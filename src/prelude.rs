@@ -0,0 +1,20 @@
+// Copyright 2021-2023 Colin Finck <colin@reactos.org>
+// SPDX-License-Identifier: MIT OR Apache-2.0
+//
+//! Types shared by all tiers.
+//!
+//! This covers opening a filesystem via [`Ntfs`], configuring it via [`NtfsOptions`], handling
+//! errors, reporting progress, and the I/O helpers used by all reading functions.
+//! The [`raw`](crate::raw), [`mid`](crate::mid), and [`high`](crate::high) tiers re-export
+//! everything in here, so a single `use` of any tier is enough to get started.
+
+pub use crate::error::{NtfsError, NtfsErrorKind, Result};
+pub use crate::ntfs::Ntfs;
+pub use crate::options::{
+    NtfsDamagedIndexPolicy, NtfsOptions, NtfsUnbackedDataPolicy, NtfsUnsupportedPolicy,
+};
+pub use crate::progress::{NtfsNoProgress, NtfsProgress, NtfsProgressSink, NtfsProgressUnit};
+pub use crate::read_at::{NtfsReadAt, NtfsReadAtReader};
+pub use crate::read_cache::NtfsReadCache;
+pub use crate::slice_reader::NtfsSliceReader;
+pub use crate::traits::NtfsReadSeek;
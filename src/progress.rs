@@ -0,0 +1,223 @@
+// Copyright 2021-2023 Colin Finck <colin@reactos.org>
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+use core::ops::ControlFlow;
+
+use crate::error::{NtfsError, Result};
+
+/// Unit of the counters reported in an [`NtfsProgress`].
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum NtfsProgressUnit {
+    /// Bytes of an attribute value that have been read.
+    Bytes,
+    /// File Records of the Master File Table that have been processed.
+    FileRecords,
+    /// Index Entries of directory indexes that have been processed.
+    IndexEntries,
+    /// Lines of output that have been written.
+    Lines,
+}
+
+/// Progress of a long-running operation, as reported to an [`NtfsProgressSink`].
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct NtfsProgress {
+    unit: NtfsProgressUnit,
+    processed: u64,
+    total: Option<u64>,
+}
+
+impl NtfsProgress {
+    pub(crate) const fn new(unit: NtfsProgressUnit, processed: u64, total: Option<u64>) -> Self {
+        Self {
+            unit,
+            processed,
+            total,
+        }
+    }
+
+    /// Returns the number of items (in [`NtfsProgress::unit`]) processed so far.
+    pub fn processed(&self) -> u64 {
+        self.processed
+    }
+
+    /// Returns the total number of items (in [`NtfsProgress::unit`]) to process.
+    ///
+    /// This is `None` if the total is not known in advance (like for the entries of a directory index).
+    pub fn total(&self) -> Option<u64> {
+        self.total
+    }
+
+    /// Returns the [`NtfsProgressUnit`] of [`NtfsProgress::processed`] and [`NtfsProgress::total`].
+    pub fn unit(&self) -> NtfsProgressUnit {
+        self.unit
+    }
+}
+
+/// Receiver of progress reports from long-running operations, which may also cancel them.
+///
+/// This is passed to the `_with_progress` variants of functions that may take minutes on large
+/// filesystems:
+///
+/// * Walks over the entire Master File Table: [`Ntfs::check_with_progress`],
+///   [`Ntfs::cluster_owner_index_with_progress`], [`Ntfs::security_descriptor_statistics_with_progress`],
+///   and [`Ntfs::size_mismatches_with_progress`].
+/// * Walks over directory indexes: [`NtfsIndexEntries::next_with_progress`] and
+///   [`NtfsJsonTreeExporter::export_with_progress`].
+/// * Large value reads: [`NtfsAttributeValue::copy_to_with_progress`] and
+///   [`NtfsMftData::copy_to_with_progress`].
+/// * [`NtfsFile::sync_to_with_progress`].
+///
+/// Other operations don't report progress.
+/// In particular, a loop over [`Ntfs::file`] for all File Record Numbers written by the caller
+/// has to check for cancellation on its own.
+///
+/// It is implemented for closures taking an [`NtfsProgress`] and returning a [`ControlFlow`].
+/// Use [`NtfsNoProgress`] if you need a sink that ignores all reports.
+///
+/// [`Ntfs::check_with_progress`]: crate::Ntfs::check_with_progress
+/// [`Ntfs::cluster_owner_index_with_progress`]: crate::Ntfs::cluster_owner_index_with_progress
+/// [`Ntfs::file`]: crate::Ntfs::file
+/// [`Ntfs::security_descriptor_statistics_with_progress`]: crate::Ntfs::security_descriptor_statistics_with_progress
+/// [`Ntfs::size_mismatches_with_progress`]: crate::Ntfs::size_mismatches_with_progress
+/// [`NtfsAttributeValue::copy_to_with_progress`]: crate::attribute_value::NtfsAttributeValue::copy_to_with_progress
+/// [`NtfsFile::sync_to_with_progress`]: crate::NtfsFile::sync_to_with_progress
+/// [`NtfsIndexEntries::next_with_progress`]: crate::NtfsIndexEntries::next_with_progress
+/// [`NtfsJsonTreeExporter::export_with_progress`]: crate::NtfsJsonTreeExporter::export_with_progress
+/// [`NtfsMftData::copy_to_with_progress`]: crate::NtfsMftData::copy_to_with_progress
+pub trait NtfsProgressSink {
+    /// Receives the current progress of the operation.
+    ///
+    /// Return [`ControlFlow::Break`] to cancel the operation, which then returns [`NtfsError::Cancelled`]
+    /// without performing any further I/O.
+    fn report(&mut self, progress: NtfsProgress) -> ControlFlow<()>;
+}
+
+impl<F> NtfsProgressSink for F
+where
+    F: FnMut(NtfsProgress) -> ControlFlow<()>,
+{
+    fn report(&mut self, progress: NtfsProgress) -> ControlFlow<()> {
+        self(progress)
+    }
+}
+
+/// An [`NtfsProgressSink`] that ignores all reports and never cancels.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub struct NtfsNoProgress;
+
+impl NtfsProgressSink for NtfsNoProgress {
+    fn report(&mut self, _progress: NtfsProgress) -> ControlFlow<()> {
+        ControlFlow::Continue(())
+    }
+}
+
+/// Reports the given progress to `sink` and returns [`NtfsError::Cancelled`] if it requests cancellation.
+pub(crate) fn report_progress<P>(
+    sink: &mut P,
+    unit: NtfsProgressUnit,
+    processed: u64,
+    total: Option<u64>,
+) -> Result<()>
+where
+    P: NtfsProgressSink + ?Sized,
+{
+    match sink.report(NtfsProgress::new(unit, processed, total)) {
+        ControlFlow::Continue(()) => Ok(()),
+        ControlFlow::Break(()) => Err(NtfsError::Cancelled),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec::Vec;
+
+    use super::*;
+    use crate::ntfs::Ntfs;
+    use crate::upcase_table::NtfsCaseSensitivity;
+
+    #[test]
+    fn test_progress() {
+        let mut testfs1 = crate::helpers::tests::testfs1();
+        let mut ntfs = Ntfs::new(&mut testfs1).unwrap();
+        ntfs.read_upcase_table(&mut testfs1).unwrap();
+
+        // A full check reports every File Record with the total count.
+        let mut reports = Vec::new();
+        let report = ntfs
+            .check_with_progress(&mut testfs1, &mut |progress: NtfsProgress| {
+                reports.push(progress);
+                ControlFlow::Continue(())
+            })
+            .unwrap();
+        let file_record_count = ntfs.file_record_count(&mut testfs1).unwrap();
+        let file_record_reports = reports
+            .iter()
+            .filter(|progress| progress.unit() == NtfsProgressUnit::FileRecords)
+            .collect::<Vec<_>>();
+        assert_eq!(file_record_reports.len() as u64, file_record_count);
+        assert!(file_record_reports
+            .iter()
+            .all(|progress| progress.total() == Some(file_record_count)));
+        assert_eq!(
+            reports
+                .iter()
+                .filter(|progress| progress.unit() == NtfsProgressUnit::IndexEntries)
+                .count() as u64,
+            report.index_entries_checked()
+        );
+
+        // Cancelling aborts the check.
+        let result = ntfs.check_with_progress(&mut testfs1, &mut |progress: NtfsProgress| {
+            if progress.processed() == 10 {
+                ControlFlow::Break(())
+            } else {
+                ControlFlow::Continue(())
+            }
+        });
+        assert!(matches!(result, Err(NtfsError::Cancelled)));
+
+        // Copying a value reports the bytes copied so far.
+        let file = ntfs
+            .file_by_path(
+                &mut testfs1,
+                "1000-bytes-file",
+                NtfsCaseSensitivity::Insensitive,
+            )
+            .unwrap()
+            .unwrap();
+        let data_item = file.data(&mut testfs1, "").unwrap().unwrap();
+        let data_attribute = data_item.to_attribute().unwrap();
+        let mut data_value = data_attribute.value(&mut testfs1).unwrap();
+
+        let mut last_progress = None;
+        let mut data = Vec::new();
+        let bytes_copied = data_value
+            .copy_to_with_progress(&mut testfs1, &mut data, &mut |progress| {
+                last_progress = Some(progress);
+                ControlFlow::Continue(())
+            })
+            .unwrap();
+        assert_eq!(bytes_copied, 1000);
+        assert_eq!(data, [b'1', b'2', b'3', b'4', b'5'].repeat(200));
+        assert_eq!(
+            last_progress,
+            Some(NtfsProgress::new(NtfsProgressUnit::Bytes, 1000, Some(1000)))
+        );
+
+        let mft_data = ntfs.mft_data(&mut testfs1).unwrap();
+        let mut data = Vec::new();
+        let result = mft_data.copy_to_with_progress(
+            &mut testfs1,
+            &mut data,
+            &mut |progress: NtfsProgress| {
+                if progress.processed() > 0 {
+                    ControlFlow::Break(())
+                } else {
+                    ControlFlow::Continue(())
+                }
+            },
+        );
+        assert!(matches!(result, Err(NtfsError::Cancelled)));
+        assert_eq!(data.len(), ntfs.cluster_size() as usize);
+    }
+}
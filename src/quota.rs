@@ -0,0 +1,217 @@
+// Copyright 2021-2023 Colin Finck <colin@reactos.org>
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+use core::fmt;
+
+use alloc::vec;
+use alloc::vec::Vec;
+use binrw::io::Cursor;
+use binrw::{BinRead, BinReaderExt};
+use bitflags::bitflags;
+use byteorder::{ByteOrder, LittleEndian};
+
+use crate::error::{NtfsError, Result};
+use crate::indexes::NtfsIndexEntryData;
+use crate::sid::NtfsSid;
+use crate::time::NtfsTime;
+use crate::types::NtfsPosition;
+
+#[cfg(feature = "serde")]
+use serde::ser::{Serialize, SerializeStruct, Serializer};
+
+/// Size of all [`QuotaControlEntryHeader`] fields.
+const QUOTA_CONTROL_ENTRY_HEADER_SIZE: usize = 48;
+
+/// Value of a quota threshold or limit that means "no limit".
+const QUOTA_NO_LIMIT: i64 = -1;
+
+#[derive(BinRead, Clone, Debug)]
+struct QuotaControlEntryHeader {
+    version: u32,
+    flags: u32,
+    bytes_used: u64,
+    change_time: NtfsTime,
+    threshold: i64,
+    limit: i64,
+    exceeded_time: NtfsTime,
+}
+
+bitflags! {
+    /// Flags returned by [`NtfsQuotaControlEntry::flags`].
+    #[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+    pub struct NtfsQuotaFlags: u32 {
+        /// This entry holds the default limits for new owners.
+        const DEFAULT_LIMITS = 0x0000_0001;
+        /// The owner has reached the quota limit.
+        const LIMIT_REACHED = 0x0000_0002;
+        /// The owner has been deleted.
+        const ID_DELETED = 0x0000_0004;
+        /// Quota tracking is enabled (only valid in the defaults entry).
+        const TRACKING_ENABLED = 0x0000_0010;
+        /// Quota enforcement is enabled (only valid in the defaults entry).
+        const ENFORCEMENT_ENABLED = 0x0000_0020;
+        /// Quota tracking has been requested (only valid in the defaults entry).
+        const TRACKING_REQUESTED = 0x0000_0040;
+        /// Exceeding the threshold is logged (only valid in the defaults entry).
+        const LOG_THRESHOLD = 0x0000_0080;
+        /// Exceeding the limit is logged (only valid in the defaults entry).
+        const LOG_LIMIT = 0x0000_0100;
+        /// The usage information is out of date (only valid in the defaults entry).
+        const OUT_OF_DATE = 0x0000_0200;
+        /// The quota information is corrupt (only valid in the defaults entry).
+        const CORRUPT = 0x0000_0400;
+        /// There are pending deletes (only valid in the defaults entry).
+        const PENDING_DELETES = 0x0000_0800;
+    }
+}
+
+impl fmt::Display for NtfsQuotaFlags {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(&self.0, f)
+    }
+}
+
+/// Serializes the raw bits of these flags.
+#[cfg(feature = "serde")]
+#[cfg_attr(docsrs, doc(cfg(feature = "serde")))]
+impl Serialize for NtfsQuotaFlags {
+    fn serialize<S>(&self, serializer: S) -> core::result::Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        Serialize::serialize(&self.bits(), serializer)
+    }
+}
+
+/// Quota Control Entry of a single owner, as stored in the $Q index of the $Quota file.
+///
+/// Every owner of files on an NTFS volume with quotas is known by an owner ID.
+/// Its entry holds the quota limit, threshold, and current usage of that owner along with its SID.
+/// The entry with owner ID 1 is special, holding the default limits and the volume-wide quota
+/// flags instead of a SID.
+///
+/// Reference: <https://flatcap.github.io/linux-ntfs/ntfs/files/quota.html>
+#[derive(Clone, Debug)]
+pub struct NtfsQuotaControlEntry {
+    header: QuotaControlEntryHeader,
+    sid: Option<NtfsSid>,
+}
+
+impl NtfsQuotaControlEntry {
+    /// Returns the number of bytes currently charged to this owner.
+    pub fn bytes_used(&self) -> u64 {
+        self.header.bytes_used
+    }
+
+    /// Returns the time when this entry was last changed.
+    pub fn change_time(&self) -> NtfsTime {
+        self.header.change_time
+    }
+
+    /// Returns the time when this owner exceeded the quota threshold, or zero if the owner is below it.
+    pub fn exceeded_time(&self) -> NtfsTime {
+        self.header.exceeded_time
+    }
+
+    /// Returns flags set for this entry as specified by [`NtfsQuotaFlags`].
+    pub fn flags(&self) -> NtfsQuotaFlags {
+        NtfsQuotaFlags::from_bits_truncate(self.header.flags)
+    }
+
+    /// Returns the quota limit of this owner in bytes, or `None` if the owner has no limit.
+    pub fn limit(&self) -> Option<u64> {
+        Self::limit_from_raw(self.header.limit)
+    }
+
+    fn limit_from_raw(value: i64) -> Option<u64> {
+        if value == QUOTA_NO_LIMIT {
+            None
+        } else {
+            Some(value as u64)
+        }
+    }
+
+    /// Returns the SID of this owner.
+    ///
+    /// This is `None` for the entry holding the default limits.
+    pub fn sid(&self) -> Option<&NtfsSid> {
+        self.sid.as_ref()
+    }
+
+    /// Returns the quota threshold (warning level) of this owner in bytes, or `None` if the owner has no threshold.
+    pub fn threshold(&self) -> Option<u64> {
+        Self::limit_from_raw(self.header.threshold)
+    }
+
+    /// Returns the version of this entry (2 for NTFS 3.x).
+    pub fn version(&self) -> u32 {
+        self.header.version
+    }
+
+    pub(crate) fn to_bytes(&self) -> Vec<u8> {
+        let sid = self.sid.as_ref().map(NtfsSid::as_bytes).unwrap_or_default();
+        let mut bytes = vec![0u8; QUOTA_CONTROL_ENTRY_HEADER_SIZE + sid.len()];
+
+        LittleEndian::write_u32(&mut bytes[0..], self.header.version);
+        LittleEndian::write_u32(&mut bytes[4..], self.header.flags);
+        LittleEndian::write_u64(&mut bytes[8..], self.header.bytes_used);
+        LittleEndian::write_u64(&mut bytes[16..], self.header.change_time.nt_timestamp());
+        LittleEndian::write_i64(&mut bytes[24..], self.header.threshold);
+        LittleEndian::write_i64(&mut bytes[32..], self.header.limit);
+        LittleEndian::write_u64(&mut bytes[40..], self.header.exceeded_time.nt_timestamp());
+        bytes[QUOTA_CONTROL_ENTRY_HEADER_SIZE..].copy_from_slice(sid);
+
+        bytes
+    }
+}
+
+impl NtfsIndexEntryData for NtfsQuotaControlEntry {
+    fn data_from_slice(slice: &[u8], position: NtfsPosition) -> Result<Self> {
+        if slice.len() < QUOTA_CONTROL_ENTRY_HEADER_SIZE {
+            return Err(NtfsError::InvalidIndexEntryDataSize {
+                position,
+                expected: QUOTA_CONTROL_ENTRY_HEADER_SIZE,
+                actual: slice.len(),
+            });
+        }
+
+        let mut cursor = Cursor::new(slice);
+        let header = cursor.read_le::<QuotaControlEntryHeader>()?;
+
+        // The owner's SID follows the header, unless this is the entry of the default limits.
+        let sid_slice = &slice[QUOTA_CONTROL_ENTRY_HEADER_SIZE..];
+        let sid = if sid_slice.is_empty() {
+            None
+        } else {
+            let sid =
+                NtfsSid::from_slice(sid_slice).ok_or(NtfsError::InvalidIndexEntryDataSize {
+                    position,
+                    expected: QUOTA_CONTROL_ENTRY_HEADER_SIZE + NtfsSid::size_from_slice(sid_slice),
+                    actual: slice.len(),
+                })?;
+            Some(sid)
+        };
+
+        Ok(Self { header, sid })
+    }
+}
+
+#[cfg(feature = "serde")]
+#[cfg_attr(docsrs, doc(cfg(feature = "serde")))]
+impl Serialize for NtfsQuotaControlEntry {
+    fn serialize<S>(&self, serializer: S) -> core::result::Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut state = serializer.serialize_struct("NtfsQuotaControlEntry", 8)?;
+        state.serialize_field("version", &self.version())?;
+        state.serialize_field("flags", &self.flags())?;
+        state.serialize_field("bytes_used", &self.bytes_used())?;
+        state.serialize_field("change_time", &self.change_time())?;
+        state.serialize_field("threshold", &self.threshold())?;
+        state.serialize_field("limit", &self.limit())?;
+        state.serialize_field("exceeded_time", &self.exceeded_time())?;
+        state.serialize_field("sid", &self.sid)?;
+        state.end()
+    }
+}
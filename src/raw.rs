@@ -0,0 +1,30 @@
+// Copyright 2021-2023 Colin Finck <colin@reactos.org>
+// SPDX-License-Identifier: MIT OR Apache-2.0
+//
+//! Raw tier: On-disk structures of NTFS, with as little interpretation as possible.
+//!
+//! This tier is for tools that need to look at every byte, like parsers, forensic tools, and
+//! repair utilities.
+//! It gives access to the boot sector, the Master File Table, individual attributes and their
+//! values, Index Records, Data Runs, and cluster ownership.
+//! Nothing in here follows references between structures on its own.
+//!
+//! The types shared by all tiers are re-exported from the [`prelude`](crate::prelude).
+//! All types are also available from the crate root.
+//! See the [`mid`](crate::mid) and [`high`](crate::high) tiers for more convenient abstractions.
+
+pub use crate::attribute::{
+    NtfsAttribute, NtfsAttributeItem, NtfsAttributeType, NtfsAttributes, NtfsAttributesAttached,
+    NtfsAttributesRaw,
+};
+pub use crate::attribute_value;
+pub use crate::boot_sector::NtfsBootSector;
+pub use crate::cluster_owners::{NtfsClusterOwner, NtfsClusterOwnerIndex};
+pub use crate::duplicated_sizes::{NtfsSizeLocation, NtfsSizeMismatch, NtfsSizeMismatchReport};
+pub use crate::extents::{NtfsExtent, NtfsExtentMap, NtfsMemoryFile};
+pub use crate::file_reference::NtfsFileReference;
+pub use crate::index_record::NtfsIndexRecord;
+pub use crate::mft_data::NtfsMftData;
+pub use crate::prelude::*;
+pub use crate::structured_values;
+pub use crate::types::{Lcn, NtfsPosition, Vcn};
@@ -0,0 +1,262 @@
+// Copyright 2021-2023 Colin Finck <colin@reactos.org>
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use binrw::io;
+use binrw::io::{Read, Seek, SeekFrom};
+
+/// Positional reads from a filesystem backend, without a shared seek position.
+///
+/// All functions take `&self`, so a single backend can serve many readers at once.
+/// Wrap it in an [`NtfsReadAtReader`] to get the [`Read`] and [`Seek`] implementation required by
+/// the rest of this crate.
+///
+/// This is implemented for byte slices and vectors, [`Arc`]s and references of other implementations,
+/// and for `std::fs::File` on Unix and Windows (with the `std` feature).
+pub trait NtfsReadAt {
+    /// Reads bytes starting at the absolute byte position `offset` into `buf` and returns the number
+    /// of bytes read.
+    ///
+    /// Like [`Read::read`], this may read less than `buf.len()` bytes and returns 0 at the end of
+    /// the backend.
+    fn read_at(&self, offset: u64, buf: &mut [u8]) -> io::Result<usize>;
+
+    /// Returns the total size of the backend, in bytes.
+    fn size(&self) -> io::Result<u64>;
+}
+
+impl NtfsReadAt for [u8] {
+    fn read_at(&self, offset: u64, buf: &mut [u8]) -> io::Result<usize> {
+        let start = usize::try_from(offset)
+            .unwrap_or(usize::MAX)
+            .min(self.len());
+        let remaining = &self[start..];
+
+        let bytes_to_copy = usize::min(remaining.len(), buf.len());
+        buf[..bytes_to_copy].copy_from_slice(&remaining[..bytes_to_copy]);
+
+        Ok(bytes_to_copy)
+    }
+
+    fn size(&self) -> io::Result<u64> {
+        Ok(self.len() as u64)
+    }
+}
+
+impl NtfsReadAt for Vec<u8> {
+    fn read_at(&self, offset: u64, buf: &mut [u8]) -> io::Result<usize> {
+        self.as_slice().read_at(offset, buf)
+    }
+
+    fn size(&self) -> io::Result<u64> {
+        self.as_slice().size()
+    }
+}
+
+impl<R> NtfsReadAt for &R
+where
+    R: NtfsReadAt + ?Sized,
+{
+    fn read_at(&self, offset: u64, buf: &mut [u8]) -> io::Result<usize> {
+        (**self).read_at(offset, buf)
+    }
+
+    fn size(&self) -> io::Result<u64> {
+        (**self).size()
+    }
+}
+
+impl<R> NtfsReadAt for Arc<R>
+where
+    R: NtfsReadAt + ?Sized,
+{
+    fn read_at(&self, offset: u64, buf: &mut [u8]) -> io::Result<usize> {
+        (**self).read_at(offset, buf)
+    }
+
+    fn size(&self) -> io::Result<u64> {
+        (**self).size()
+    }
+}
+
+#[cfg(all(feature = "std", any(unix, windows)))]
+#[cfg_attr(docsrs, doc(cfg(all(feature = "std", any(unix, windows)))))]
+impl NtfsReadAt for std::fs::File {
+    fn read_at(&self, offset: u64, buf: &mut [u8]) -> io::Result<usize> {
+        #[cfg(unix)]
+        {
+            std::os::unix::fs::FileExt::read_at(self, buf, offset)
+        }
+
+        // `seek_read` also moves the file pointer, but no reader relies on it.
+        #[cfg(windows)]
+        {
+            std::os::windows::fs::FileExt::seek_read(self, buf, offset)
+        }
+    }
+
+    fn size(&self) -> io::Result<u64> {
+        Ok(self.metadata()?.len())
+    }
+}
+
+/// Reader with its own seek position over a shared [`NtfsReadAt`] backend.
+///
+/// This implements [`Read`] and [`Seek`], so it can be passed to every function of this crate.
+/// Creating and cloning it is cheap, which makes it possible to give every thread its own reader:
+///
+/// ```ignore
+/// let image = std::fs::File::open("ntfs.img").unwrap();
+/// let mut fs = NtfsReadAtReader::new(&image);
+/// let mut ntfs = Ntfs::new(&mut fs).unwrap();
+/// ntfs.read_upcase_table(&mut fs).unwrap();
+///
+/// file_record_numbers.par_iter().for_each(|&file_record_number| {
+///     let mut fs = NtfsReadAtReader::new(&image);
+///     let file = ntfs.file(&mut fs, file_record_number).unwrap();
+///     // ...
+/// });
+/// ```
+///
+/// [`Ntfs`], [`NtfsFile`], and all other structures of this crate are `Send` and `Sync`, and only
+/// access the filesystem through the reader passed to each call.
+/// Therefore, they can be shared between threads as long as every thread uses its own reader.
+///
+/// [`Ntfs`]: crate::Ntfs
+/// [`NtfsFile`]: crate::NtfsFile
+#[derive(Clone, Copy, Debug)]
+pub struct NtfsReadAtReader<R> {
+    inner: R,
+    stream_position: u64,
+}
+
+impl<R> NtfsReadAtReader<R>
+where
+    R: NtfsReadAt,
+{
+    /// Creates a new `NtfsReadAtReader` positioned at the beginning of the given backend.
+    pub fn new(inner: R) -> Self {
+        Self {
+            inner,
+            stream_position: 0,
+        }
+    }
+
+    /// Returns a reference to the underlying backend.
+    pub fn get_ref(&self) -> &R {
+        &self.inner
+    }
+
+    /// Returns the underlying backend.
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+impl<R> Read for NtfsReadAtReader<R>
+where
+    R: NtfsReadAt,
+{
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let bytes_read = self.inner.read_at(self.stream_position, buf)?;
+        self.stream_position += bytes_read as u64;
+        Ok(bytes_read)
+    }
+}
+
+impl<R> Seek for NtfsReadAtReader<R>
+where
+    R: NtfsReadAt,
+{
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let (base, offset) = match pos {
+            SeekFrom::Start(n) => {
+                self.stream_position = n;
+                return Ok(n);
+            }
+            SeekFrom::End(n) => (self.inner.size()?, n),
+            SeekFrom::Current(n) => (self.stream_position, n),
+        };
+
+        let new_position = if offset >= 0 {
+            base.checked_add(offset as u64)
+        } else {
+            base.checked_sub(offset.wrapping_neg() as u64)
+        };
+
+        match new_position {
+            Some(n) => {
+                self.stream_position = n;
+                Ok(self.stream_position)
+            }
+            None => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "invalid seek to a negative or overflowing position",
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::attribute_value::NtfsAttributeValue;
+    use crate::file::NtfsFile;
+    use crate::ntfs::Ntfs;
+    use crate::traits::NtfsReadSeek;
+    use crate::upcase_table::NtfsCaseSensitivity;
+
+    fn assert_send_sync<T: Send + Sync>() {}
+
+    #[test]
+    fn test_read_at_reader() {
+        assert_send_sync::<Ntfs>();
+        assert_send_sync::<NtfsFile<'static>>();
+        assert_send_sync::<NtfsAttributeValue<'static, 'static>>();
+
+        let image = Arc::new(crate::helpers::tests::testfs1().into_inner());
+        let mut fs = NtfsReadAtReader::new(Arc::clone(&image));
+        let mut ntfs = Ntfs::new(&mut fs).unwrap();
+        ntfs.read_upcase_table(&mut fs).unwrap();
+        let ntfs = Arc::new(ntfs);
+
+        // Read the same file from multiple threads, each with its own reader.
+        let threads = (0..4)
+            .map(|_| {
+                let image = Arc::clone(&image);
+                let ntfs = Arc::clone(&ntfs);
+
+                std::thread::spawn(move || {
+                    let mut fs = NtfsReadAtReader::new(&*image);
+                    let file = ntfs
+                        .file_by_path(&mut fs, "1000-bytes-file", NtfsCaseSensitivity::Insensitive)
+                        .unwrap()
+                        .unwrap();
+                    let data_item = file.data(&mut fs, "").unwrap().unwrap();
+                    let data_attribute = data_item.to_attribute().unwrap();
+                    let mut data_value = data_attribute.value(&mut fs).unwrap();
+
+                    let mut buf = [0u8; 1000];
+                    data_value.read_exact(&mut fs, &mut buf).unwrap();
+                    buf
+                })
+            })
+            .collect::<Vec<_>>();
+
+        for thread in threads {
+            assert_eq!(
+                thread.join().unwrap()[..],
+                [b'1', b'2', b'3', b'4', b'5'].repeat(200)
+            );
+        }
+
+        // Reads behind the end return no data, while seeking before the start fails.
+        let mut buf = [0u8; 4];
+        let len = image.len() as u64;
+        assert_eq!(fs.seek(SeekFrom::End(-2)).unwrap(), len - 2);
+        assert_eq!(fs.read(&mut buf).unwrap(), 2);
+        assert_eq!(fs.read(&mut buf).unwrap(), 0);
+        assert!(fs.seek(SeekFrom::Current(-(len as i64) - 1)).is_err());
+    }
+}
@@ -0,0 +1,359 @@
+// Copyright 2021-2023 Colin Finck <colin@reactos.org>
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+use alloc::collections::BTreeMap;
+use alloc::vec;
+use alloc::vec::Vec;
+
+use binrw::io;
+use binrw::io::{Read, Seek, SeekFrom};
+
+/// Default size of a single cached block, in bytes.
+///
+/// This matches the most common NTFS cluster size and is a multiple of the usual
+/// File Record (1024 bytes) and Index Record (4096 bytes) sizes.
+const DEFAULT_BLOCK_SIZE: u32 = 4096;
+
+#[derive(Clone, Debug)]
+struct CachedBlock {
+    data: Vec<u8>,
+    last_use: u64,
+}
+
+/// Caching reader that sits between this crate and a slow filesystem reader.
+///
+/// Every structure of an NTFS filesystem (File Records, Index Records, the Upcase Table, Data Runs of
+/// fragmented attributes) is read via many small seeks and reads.
+/// This is fine for a local file, but causes a lot of round-trips for slow backends like network block devices.
+///
+/// `NtfsReadCache` wraps such a reader and splits it into aligned blocks of a fixed size.
+/// Each read is served from a cached block if possible, and only cache misses hit the underlying reader.
+/// Once `capacity` blocks are cached, the least recently used block is evicted.
+///
+/// As the reader is passed to every function of this crate, you just wrap it once and use the
+/// `NtfsReadCache` in its place:
+///
+/// ```ignore
+/// let mut fs = NtfsReadCache::new(fs, 1024);
+/// let ntfs = Ntfs::new(&mut fs).unwrap();
+/// let root_dir = ntfs.root_directory(&mut fs).unwrap();
+/// ```
+///
+/// The cache assumes that the underlying data does not change while it is in use.
+/// Call [`NtfsReadCache::clear`] if it does.
+#[derive(Clone, Debug)]
+pub struct NtfsReadCache<T> {
+    inner: T,
+    block_size: u32,
+    capacity: usize,
+    blocks: BTreeMap<u64, CachedBlock>,
+    lru: BTreeMap<u64, u64>,
+    use_counter: u64,
+    stream_position: u64,
+    len: Option<u64>,
+}
+
+impl<T> NtfsReadCache<T>
+where
+    T: Read + Seek,
+{
+    /// Creates a new `NtfsReadCache` that caches up to `capacity` blocks of 4096 bytes each.
+    pub fn new(inner: T, capacity: usize) -> Self {
+        Self::with_block_size(inner, DEFAULT_BLOCK_SIZE, capacity)
+    }
+
+    /// Creates a new `NtfsReadCache` that caches up to `capacity` blocks of `block_size` bytes each.
+    ///
+    /// A good choice for `block_size` is the cluster size of the filesystem (see [`Ntfs::cluster_size`]).
+    ///
+    /// # Panics
+    ///
+    /// Panics if `block_size` is zero.
+    ///
+    /// [`Ntfs::cluster_size`]: crate::Ntfs::cluster_size
+    pub fn with_block_size(inner: T, block_size: u32, capacity: usize) -> Self {
+        assert!(block_size > 0, "The block size must not be zero");
+
+        Self {
+            inner,
+            block_size,
+            capacity: capacity.max(1),
+            blocks: BTreeMap::new(),
+            lru: BTreeMap::new(),
+            use_counter: 0,
+            stream_position: 0,
+            len: None,
+        }
+    }
+
+    /// Returns the size of a single cached block, in bytes.
+    pub fn block_size(&self) -> u32 {
+        self.block_size
+    }
+
+    /// Returns the maximum number of blocks held in the cache.
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Drops all cached blocks.
+    pub fn clear(&mut self) {
+        self.blocks.clear();
+        self.lru.clear();
+        self.len = None;
+    }
+
+    /// Returns the number of blocks that are currently cached.
+    pub fn cached_blocks(&self) -> usize {
+        self.blocks.len()
+    }
+
+    /// Returns a reference to the underlying reader.
+    pub fn get_ref(&self) -> &T {
+        &self.inner
+    }
+
+    /// Returns a mutable reference to the underlying reader.
+    ///
+    /// Any data read through it bypasses the cache.
+    /// If you modify the underlying data, call [`NtfsReadCache::clear`] afterwards.
+    pub fn get_mut(&mut self) -> &mut T {
+        &mut self.inner
+    }
+
+    /// Consumes this `NtfsReadCache` and returns the underlying reader.
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+
+    fn block(&mut self, block_number: u64) -> io::Result<&[u8]> {
+        self.use_counter += 1;
+        let last_use = self.use_counter;
+
+        if let Some(block) = self.blocks.get_mut(&block_number) {
+            // Cache hit: Just mark the block as the most recently used one.
+            self.lru.remove(&block.last_use);
+            block.last_use = last_use;
+            self.lru.insert(last_use, block_number);
+        } else {
+            // Cache miss: Make room for the new block if necessary and read it from the underlying reader.
+            if self.blocks.len() >= self.capacity {
+                if let Some(oldest_use) = self.lru.keys().next().copied() {
+                    let evicted_block_number = self.lru.remove(&oldest_use).unwrap();
+                    self.blocks.remove(&evicted_block_number);
+                }
+            }
+
+            let position = block_number
+                .checked_mul(self.block_size as u64)
+                .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "invalid position"))?;
+            let data = self.read_block_data(position)?;
+
+            self.blocks
+                .insert(block_number, CachedBlock { data, last_use });
+            self.lru.insert(last_use, block_number);
+        }
+
+        Ok(&self.blocks[&block_number].data)
+    }
+
+    fn read_block_data(&mut self, position: u64) -> io::Result<Vec<u8>> {
+        self.inner.seek(SeekFrom::Start(position))?;
+
+        let mut data = vec![0u8; self.block_size as usize];
+        let mut filled = 0;
+
+        // The last block may be incomplete.
+        // We therefore have to read until end of file instead of using `read_exact`.
+        while filled < data.len() {
+            match self.inner.read(&mut data[filled..]) {
+                Ok(0) => break,
+                Ok(n) => filled += n,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(e),
+            }
+        }
+
+        data.truncate(filled);
+        Ok(data)
+    }
+
+    fn len(&mut self) -> io::Result<u64> {
+        if let Some(len) = self.len {
+            return Ok(len);
+        }
+
+        let len = self.inner.seek(SeekFrom::End(0))?;
+        self.len = Some(len);
+        Ok(len)
+    }
+}
+
+impl<T> Read for NtfsReadCache<T>
+where
+    T: Read + Seek,
+{
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let mut bytes_read = 0;
+
+        while bytes_read < buf.len() {
+            let block_number = self.stream_position / self.block_size as u64;
+            let offset_in_block = (self.stream_position % self.block_size as u64) as usize;
+
+            let block = self.block(block_number)?;
+            if offset_in_block >= block.len() {
+                // We have reached the end of the underlying data.
+                break;
+            }
+
+            let bytes_to_copy = usize::min(block.len() - offset_in_block, buf.len() - bytes_read);
+            buf[bytes_read..bytes_read + bytes_to_copy]
+                .copy_from_slice(&block[offset_in_block..offset_in_block + bytes_to_copy]);
+
+            bytes_read += bytes_to_copy;
+            self.stream_position += bytes_to_copy as u64;
+
+            if offset_in_block + bytes_to_copy < self.block_size as usize {
+                // This was an incomplete block, so there is nothing more to read.
+                break;
+            }
+        }
+
+        Ok(bytes_read)
+    }
+}
+
+impl<T> Seek for NtfsReadCache<T>
+where
+    T: Read + Seek,
+{
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let new_position = match pos {
+            SeekFrom::Start(n) => Some(n),
+            SeekFrom::End(n) => {
+                let len = self.len()?;
+                if n >= 0 {
+                    len.checked_add(n as u64)
+                } else {
+                    len.checked_sub(n.wrapping_neg() as u64)
+                }
+            }
+            SeekFrom::Current(n) => {
+                if n >= 0 {
+                    self.stream_position.checked_add(n as u64)
+                } else {
+                    self.stream_position.checked_sub(n.wrapping_neg() as u64)
+                }
+            }
+        };
+
+        match new_position {
+            Some(n) => {
+                self.stream_position = n;
+                Ok(self.stream_position)
+            }
+            None => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "invalid seek to a negative or overflowing position",
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+    use crate::indexes::NtfsFileNameIndex;
+    use crate::ntfs::Ntfs;
+
+    /// Reader that counts the reads hitting the underlying data.
+    struct CountingReader {
+        inner: Cursor<Vec<u8>>,
+        reads: usize,
+    }
+
+    impl Read for CountingReader {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            self.reads += 1;
+            self.inner.read(buf)
+        }
+    }
+
+    impl Seek for CountingReader {
+        fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+            self.inner.seek(pos)
+        }
+    }
+
+    fn walk_many_subdirs<T>(ntfs: &Ntfs, fs: &mut T) -> usize
+    where
+        T: Read + Seek,
+    {
+        let root_dir = ntfs.root_directory(fs).unwrap();
+        let root_dir_index = root_dir.directory_index(fs).unwrap();
+        let mut root_dir_finder = root_dir_index.finder();
+        let entry = NtfsFileNameIndex::find(&mut root_dir_finder, ntfs, fs, "many_subdirs")
+            .unwrap()
+            .unwrap();
+        let subdir = entry.to_file(ntfs, fs).unwrap();
+        let subdir_index = subdir.directory_index(fs).unwrap();
+        let mut iter = subdir_index.entries();
+
+        let mut count = 0;
+        while let Some(entry) = iter.next(fs) {
+            entry.unwrap();
+            count += 1;
+        }
+
+        count
+    }
+
+    #[test]
+    fn test_read_cache() {
+        let testfs1 = crate::helpers::tests::testfs1();
+        let data = testfs1.get_ref().clone();
+
+        let mut cache = NtfsReadCache::with_block_size(testfs1, 512, 16);
+
+        // Reads crossing block boundaries must return the same data as the underlying reader.
+        let mut buf = [0u8; 1500];
+        cache.seek(SeekFrom::Start(700)).unwrap();
+        cache.read_exact(&mut buf).unwrap();
+        assert_eq!(&buf[..], &data[700..2200]);
+        assert_eq!(cache.cached_blocks(), 4);
+
+        // The capacity is never exceeded.
+        let mut buf = vec![0u8; 64 * 512];
+        cache.seek(SeekFrom::Start(0)).unwrap();
+        cache.read_exact(&mut buf).unwrap();
+        assert_eq!(&buf[..], &data[..64 * 512]);
+        assert_eq!(cache.cached_blocks(), 16);
+
+        // Reading at the end returns the remaining bytes and then EOF.
+        let len = data.len() as u64;
+        assert_eq!(cache.seek(SeekFrom::End(-10)).unwrap(), len - 10);
+        let mut buf = [0u8; 100];
+        assert_eq!(cache.read(&mut buf).unwrap(), 10);
+        assert_eq!(cache.read(&mut buf).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_read_cache_saves_reads() {
+        let inner = CountingReader {
+            inner: crate::helpers::tests::testfs1(),
+            reads: 0,
+        };
+        let mut fs = NtfsReadCache::new(inner, 1024);
+
+        let mut ntfs = Ntfs::new(&mut fs).unwrap();
+        ntfs.read_upcase_table(&mut fs).unwrap();
+        assert_eq!(walk_many_subdirs(&ntfs, &mut fs), 512);
+
+        // The entire walk is served from the cache the second time.
+        let reads = fs.get_ref().reads;
+        assert_eq!(walk_many_subdirs(&ntfs, &mut fs), 512);
+        assert_eq!(fs.get_ref().reads, reads);
+    }
+}
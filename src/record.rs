@@ -24,36 +24,32 @@ pub(crate) struct RecordHeader {
 pub(crate) struct Record {
     data: Vec<u8>,
     position: NtfsPosition,
+    sector_size: u16,
 }
 
 impl Record {
-    pub(crate) fn new(data: Vec<u8>, position: NtfsPosition) -> Self {
-        Self { data, position }
+    pub(crate) fn new(data: Vec<u8>, position: NtfsPosition, sector_size: u16) -> Self {
+        Self {
+            data,
+            position,
+            sector_size,
+        }
     }
 
     pub(crate) fn data(&self) -> &[u8] {
         &self.data
     }
 
+    pub(crate) fn data_mut(&mut self) -> &mut [u8] {
+        &mut self.data
+    }
+
     pub(crate) fn fixup(&mut self) -> Result<()> {
         let update_sequence_number = self.update_sequence_number()?;
-        let array_count = self.update_sequence_array_count()?;
-
-        let mut array_position = self.update_sequence_array_start() as usize;
-        let array_end =
-            self.update_sequence_offset() as usize + self.update_sequence_size() as usize;
-        let sectors_end = array_count as usize * NTFS_BLOCK_SIZE;
-
-        if array_end > self.data.len() || sectors_end > self.data.len() {
-            return Err(NtfsError::UpdateSequenceArrayExceedsRecordSize {
-                position: self.position,
-                array_count,
-                record_size: self.data.len(),
-            });
-        }
+        let (mut array_position, array_end, stride) = self.update_sequence_array_layout()?;
 
         // The Update Sequence Number (USN) is written to the last 2 bytes of each sector.
-        let mut sector_position = NTFS_BLOCK_SIZE - mem::size_of::<u16>();
+        let mut sector_position = stride - mem::size_of::<u16>();
 
         while array_position < array_end {
             let array_position_end = array_position + mem::size_of::<u16>();
@@ -80,12 +76,56 @@ impl Record {
 
             // Advance to the next array entry and sector.
             array_position += mem::size_of::<u16>();
-            sector_position += NTFS_BLOCK_SIZE;
+            sector_position += stride;
         }
 
         Ok(())
     }
 
+    /// Prepares this fixed-up record for being written back to disk.
+    ///
+    /// This is the inverse of [`Record::fixup`]: The Update Sequence Number (USN) is incremented,
+    /// the last 2 bytes of each sector are saved in the Update Sequence Array, and the returned bytes
+    /// have them replaced by the new USN.
+    /// Alongside these bytes, this returns the updated record, which is still fixed up.
+    /// `self` is left untouched, so that the caller can replace it only after the write has succeeded.
+    pub(crate) fn protect(&self) -> Result<(Self, Vec<u8>)> {
+        let usn_position = self.update_sequence_offset() as usize;
+        let (mut array_position, array_end, stride) = self.update_sequence_array_layout()?;
+
+        // Skip 0 and 0xFFFF when incrementing the USN, as done by Windows and NTFS-3G.
+        let update_sequence_number = match LittleEndian::read_u16(&self.update_sequence_number()?) {
+            0xfffe | 0xffff => 1,
+            usn => usn + 1,
+        };
+        let mut record = self.clone();
+        LittleEndian::write_u16(&mut record.data[usn_position..], update_sequence_number);
+        let update_sequence_number = update_sequence_number.to_le_bytes();
+
+        let mut protected_data = record.data.clone();
+        let mut sector_position = stride - mem::size_of::<u16>();
+
+        while array_position < array_end {
+            let array_position_end = array_position + mem::size_of::<u16>();
+            let sector_position_end = sector_position + mem::size_of::<u16>();
+
+            // Save the actual 2 bytes at `sector_position` in the array and replace them by the USN.
+            let saved_bytes: [u8; 2] = record.data[sector_position..sector_position_end]
+                .try_into()
+                .unwrap();
+            record.data[array_position..array_position_end].copy_from_slice(&saved_bytes);
+            protected_data[array_position..array_position_end].copy_from_slice(&saved_bytes);
+            protected_data[sector_position..sector_position_end]
+                .copy_from_slice(&update_sequence_number);
+
+            // Advance to the next array entry and sector.
+            array_position += mem::size_of::<u16>();
+            sector_position += stride;
+        }
+
+        Ok((record, protected_data))
+    }
+
     pub(crate) fn into_data(self) -> Vec<u8> {
         self.data
     }
@@ -119,6 +159,43 @@ impl Record {
             })
     }
 
+    /// Returns the start and end of the Update Sequence Array and the size of the blocks it protects,
+    /// after checking that everything fits into the record.
+    fn update_sequence_array_layout(&self) -> Result<(usize, usize, usize)> {
+        let array_count = self.update_sequence_array_count()?;
+
+        let array_start = self.update_sequence_array_start() as usize;
+        let array_end =
+            self.update_sequence_offset() as usize + self.update_sequence_size() as usize;
+        let stride = self.update_sequence_stride(array_count);
+        let sectors_end = array_count as usize * stride;
+
+        if array_end > self.data.len() || sectors_end > self.data.len() {
+            return Err(NtfsError::UpdateSequenceArrayExceedsRecordSize {
+                position: self.position,
+                array_count,
+                record_size: self.data.len(),
+            });
+        }
+
+        Ok((array_start, array_end, stride))
+    }
+
+    /// Returns the size of the blocks protected by the Update Sequence Array, in bytes.
+    ///
+    /// Windows and NTFS-3G protect records in 512-byte blocks, even on 4K-native disks with 4096-byte sectors.
+    /// However, some implementations protect them in blocks of the volume's sector size instead.
+    /// We use the sector size only if the array has exactly one entry per sector of the record.
+    fn update_sequence_stride(&self, array_count: u16) -> usize {
+        let sector_size = self.sector_size as usize;
+
+        if sector_size > NTFS_BLOCK_SIZE && array_count as usize * sector_size == self.data.len() {
+            sector_size
+        } else {
+            NTFS_BLOCK_SIZE
+        }
+    }
+
     fn update_sequence_array_start(&self) -> u16 {
         // The Update Sequence Number (USN) comes first and the array begins right after that.
         self.update_sequence_offset() + mem::size_of::<u16>() as u16
@@ -148,3 +225,99 @@ impl Record {
         update_sequence_count as u32 * mem::size_of::<u16>() as u32
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec;
+
+    /// Creates a record of `record_size` bytes with an Update Sequence Array protecting blocks of `stride` bytes.
+    fn record_with_fixups(record_size: usize, stride: usize) -> Vec<u8> {
+        const UPDATE_SEQUENCE_OFFSET: usize = 0x30;
+        const UPDATE_SEQUENCE_NUMBER: [u8; 2] = [0x34, 0x12];
+
+        let array_count = record_size / stride;
+        let mut data = vec![0xffu8; record_size];
+        data[..4].copy_from_slice(b"FILE");
+        data[4..6].copy_from_slice(&(UPDATE_SEQUENCE_OFFSET as u16).to_le_bytes());
+        data[6..8].copy_from_slice(&(array_count as u16 + 1).to_le_bytes());
+        data[UPDATE_SEQUENCE_OFFSET..UPDATE_SEQUENCE_OFFSET + 2]
+            .copy_from_slice(&UPDATE_SEQUENCE_NUMBER);
+
+        for i in 0..array_count {
+            // Save the original last 2 bytes of each block in the array and replace them by the USN.
+            let array_position = UPDATE_SEQUENCE_OFFSET + 2 + i * 2;
+            let sector_position = (i + 1) * stride - 2;
+            data[array_position..array_position + 2].copy_from_slice(&[i as u8, 0xab]);
+            data[sector_position..sector_position + 2].copy_from_slice(&UPDATE_SEQUENCE_NUMBER);
+        }
+
+        data
+    }
+
+    #[test]
+    fn test_fixup_512_byte_blocks() {
+        // Windows and NTFS-3G protect records in 512-byte blocks, regardless of the sector size.
+        for sector_size in [512, 4096] {
+            let data = record_with_fixups(4096, 512);
+            let mut record = Record::new(data, NtfsPosition::new(0x1000), sector_size);
+            record.fixup().unwrap();
+
+            for i in 0..8 {
+                let sector_position = (i + 1) * 512 - 2;
+                assert_eq!(
+                    &record.data()[sector_position..sector_position + 2],
+                    &[i as u8, 0xab]
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_fixup_4096_byte_blocks() {
+        // A record of 8192 bytes on a 4K-native disk, protected by only 2 array entries.
+        let data = record_with_fixups(8192, 4096);
+        let mut record = Record::new(data, NtfsPosition::new(0x1000), 4096);
+        record.fixup().unwrap();
+
+        assert_eq!(&record.data()[4094..4096], &[0, 0xab]);
+        assert_eq!(&record.data()[8190..8192], &[1, 0xab]);
+
+        // Bytes at 512-byte boundaries must be left untouched.
+        assert_eq!(&record.data()[510..512], &[0xff, 0xff]);
+    }
+
+    #[test]
+    fn test_fixup_mismatch() {
+        let mut data = record_with_fixups(4096, 4096);
+        data[4094] = 0;
+        let mut record = Record::new(data, NtfsPosition::new(0x1000), 4096);
+        assert!(matches!(
+            record.fixup(),
+            Err(NtfsError::UpdateSequenceNumberMismatch { .. })
+        ));
+    }
+
+    #[test]
+    fn test_protect() {
+        let data = record_with_fixups(4096, 512);
+        let mut record = Record::new(data, NtfsPosition::new(0x1000), 512);
+        record.fixup().unwrap();
+        record.data_mut()[1022..1024].copy_from_slice(&[0xcd, 0xef]);
+
+        // The protected bytes carry the incremented USN at the end of each sector,
+        // while the original record keeps the old USN.
+        let (protected_record, protected_data) = record.protect().unwrap();
+        assert_eq!(&record.data()[0x30..0x32], &[0x34, 0x12]);
+        assert_eq!(&protected_record.data()[0x30..0x32], &[0x35, 0x12]);
+        assert_eq!(&protected_data[0x30..0x32], &[0x35, 0x12]);
+        assert_eq!(&protected_data[1022..1024], &[0x35, 0x12]);
+        assert_eq!(&protected_data[0x32 + 2..0x32 + 4], &[0xcd, 0xef]);
+
+        // Fixing them up again restores the modified record.
+        let mut reread = Record::new(protected_data, NtfsPosition::new(0x1000), 512);
+        reread.fixup().unwrap();
+        assert_eq!(reread.data(), protected_record.data());
+        assert_eq!(&reread.data()[1022..1024], &[0xcd, 0xef]);
+    }
+}
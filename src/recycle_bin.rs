@@ -0,0 +1,456 @@
+// Copyright 2021-2023 Colin Finck <colin@reactos.org>
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+use core::mem;
+
+use alloc::vec;
+use alloc::vec::Vec;
+use binrw::io::{Read, Seek};
+use byteorder::{ByteOrder, LittleEndian};
+use nt_string::u16strle::U16StrLe;
+
+use crate::attribute::NtfsAttributeType;
+use crate::error::{NtfsError, Result};
+use crate::file::NtfsFile;
+use crate::file_reference::NtfsFileReference;
+use crate::indexes::NtfsFileNameIndex;
+use crate::ntfs::Ntfs;
+use crate::structured_values::NtfsFileNamespace;
+use crate::time::NtfsTime;
+use crate::traits::NtfsReadSeek;
+
+/// Offset of the original path in a $I file, following the version, size, and deletion time fields.
+const INFO_V1_PATH_OFFSET: usize = 24;
+
+/// Windows Vista to 8.1 store the original path in a fixed-size field of `MAX_PATH` UTF-16 code points.
+const INFO_V1_PATH_SIZE: usize = 260 * mem::size_of::<u16>();
+
+/// Offset of the path length (in UTF-16 code points) in a $I file of version 2.
+const INFO_V2_PATH_LENGTH_OFFSET: usize = 24;
+
+/// Offset of the variable-length original path in a $I file of version 2.
+const INFO_V2_PATH_OFFSET: usize = 28;
+
+/// Windows paths are limited to 32767 UTF-16 code points (plus the terminating NUL character).
+const INFO_MAX_SIZE: usize = INFO_V2_PATH_OFFSET + 32768 * mem::size_of::<u16>();
+
+/// Metadata of a deleted file, as stored in a `$I` file of the Windows Recycle Bin.
+///
+/// Windows Vista and later replace a deleted file by a pair of files in the `$Recycle.Bin\<SID>`
+/// directory of the deleting user:
+/// The `$R` file holds the original data under a random name, and the `$I` file of the same name
+/// holds this metadata.
+/// Version 1 `$I` files are written by Windows Vista to 8.1, version 2 by Windows 10 and later.
+///
+/// Get all entries of the Recycle Bin via [`Ntfs::recycle_bin_entries`], or parse carved `$I` files
+/// via [`NtfsRecycleBinInfo::from_bytes`].
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct NtfsRecycleBinInfo {
+    version: u64,
+    original_size: u64,
+    deletion_time: NtfsTime,
+    original_path: Vec<u8>,
+}
+
+impl NtfsRecycleBinInfo {
+    /// Parses the contents of a `$I` file.
+    pub fn from_bytes(data: &[u8]) -> Result<Self> {
+        if data.len() < INFO_V1_PATH_OFFSET {
+            return Err(NtfsError::InvalidRecycleBinInfoSize {
+                expected: INFO_V1_PATH_OFFSET,
+                actual: data.len(),
+            });
+        }
+
+        let version = LittleEndian::read_u64(&data[0..]);
+        let original_size = LittleEndian::read_u64(&data[8..]);
+        let deletion_time = NtfsTime::from(LittleEndian::read_u64(&data[16..]));
+
+        let path_range = match version {
+            1 => INFO_V1_PATH_OFFSET..INFO_V1_PATH_OFFSET + INFO_V1_PATH_SIZE,
+            2 => {
+                if data.len() < INFO_V2_PATH_OFFSET {
+                    return Err(NtfsError::InvalidRecycleBinInfoSize {
+                        expected: INFO_V2_PATH_OFFSET,
+                        actual: data.len(),
+                    });
+                }
+
+                let path_length = LittleEndian::read_u32(&data[INFO_V2_PATH_LENGTH_OFFSET..]);
+                let path_size = (path_length as usize).saturating_mul(mem::size_of::<u16>());
+                INFO_V2_PATH_OFFSET..INFO_V2_PATH_OFFSET.saturating_add(path_size)
+            }
+            _ => return Err(NtfsError::UnsupportedRecycleBinInfoVersion { actual: version }),
+        };
+
+        let path = data
+            .get(path_range.clone())
+            .ok_or(NtfsError::InvalidRecycleBinInfoSize {
+                expected: path_range.end,
+                actual: data.len(),
+            })?;
+
+        // The path is NUL-terminated within its field.
+        let path_length = path
+            .chunks_exact(2)
+            .position(|code_point| code_point == [0, 0])
+            .map_or(path.len(), |index| index * mem::size_of::<u16>());
+
+        Ok(Self {
+            version,
+            original_size,
+            deletion_time,
+            original_path: path[..path_length].to_vec(),
+        })
+    }
+
+    /// Returns the time this file was moved to the Recycle Bin.
+    pub fn deletion_time(&self) -> NtfsTime {
+        self.deletion_time
+    }
+
+    /// Gets the absolute path of the file before it was deleted (like `C:\Users\user\file.txt`)
+    /// and returns it wrapped in a [`U16StrLe`].
+    pub fn original_path(&self) -> U16StrLe<'_> {
+        U16StrLe(&self.original_path)
+    }
+
+    /// Returns the size of the file before it was deleted, in bytes.
+    ///
+    /// For a deleted directory, this is the total size of all contained files.
+    pub fn original_size(&self) -> u64 {
+        self.original_size
+    }
+
+    /// Returns the format version of the `$I` file (1 up to Windows 8.1, 2 since Windows 10).
+    pub fn version(&self) -> u64 {
+        self.version
+    }
+}
+
+/// A `$I` file of the Windows Recycle Bin paired with its `$R` counterpart, as returned by
+/// [`Ntfs::recycle_bin_entries`].
+#[derive(Debug)]
+pub struct NtfsRecycleBinEntry {
+    sid: Vec<u8>,
+    info_file_name: Vec<u8>,
+    info_file_reference: NtfsFileReference,
+    info: Result<NtfsRecycleBinInfo>,
+    data_file_reference: Result<Option<NtfsFileReference>>,
+}
+
+impl NtfsRecycleBinEntry {
+    /// Returns the [`NtfsFileReference`] of the `$R` file holding the original data.
+    ///
+    /// This is `None` if only the `$I` file is left (e.g. after the data has been wiped),
+    /// or if looking up the `$R` file failed (see [`NtfsRecycleBinEntry::error`]).
+    pub fn data_file_reference(&self) -> Option<NtfsFileReference> {
+        self.data_file_reference.as_ref().ok().copied().flatten()
+    }
+
+    /// Returns the error that occurred while reading or parsing the `$I` file, or otherwise while
+    /// looking up the `$R` file (if any).
+    pub fn error(&self) -> Option<&NtfsError> {
+        self.info
+            .as_ref()
+            .err()
+            .or_else(|| self.data_file_reference.as_ref().err())
+    }
+
+    /// Returns the parsed metadata of the `$I` file, or `None` if it couldn't be read.
+    pub fn info(&self) -> Option<&NtfsRecycleBinInfo> {
+        self.info.as_ref().ok()
+    }
+
+    /// Gets the name of the `$I` file (like `$I3F2A1B.txt`) and returns it wrapped in a [`U16StrLe`].
+    pub fn info_file_name(&self) -> U16StrLe<'_> {
+        U16StrLe(&self.info_file_name)
+    }
+
+    /// Returns the [`NtfsFileReference`] of the `$I` file.
+    pub fn info_file_reference(&self) -> NtfsFileReference {
+        self.info_file_reference
+    }
+
+    /// Gets the name of the per-user directory this entry belongs to, which is the string form of the
+    /// user's Security Identifier (like `S-1-5-21-...-1001`), and returns it wrapped in a [`U16StrLe`].
+    pub fn sid(&self) -> U16StrLe<'_> {
+        U16StrLe(&self.sid)
+    }
+}
+
+pub(crate) fn recycle_bin_entries<T>(
+    ntfs: &Ntfs,
+    recycle_bin: &NtfsFile,
+    fs: &mut T,
+) -> Result<Vec<Result<NtfsRecycleBinEntry>>>
+where
+    T: Read + Seek,
+{
+    let mut entries = Vec::new();
+
+    for (sid, sid_directory_reference) in directory_entries(recycle_bin, fs)? {
+        if let Err(e) = sid_directory_entries(ntfs, sid, sid_directory_reference, fs, &mut entries)
+        {
+            entries.push(Err(e));
+        }
+    }
+
+    Ok(entries)
+}
+
+/// Appends an [`NtfsRecycleBinEntry`] for every `$I` file in the given per-user directory to `entries`.
+fn sid_directory_entries<T>(
+    ntfs: &Ntfs,
+    sid: Vec<u8>,
+    sid_directory_reference: NtfsFileReference,
+    fs: &mut T,
+    entries: &mut Vec<Result<NtfsRecycleBinEntry>>,
+) -> Result<()>
+where
+    T: Read + Seek,
+{
+    let sid_directory = sid_directory_reference.to_file(ntfs, fs)?;
+    if !sid_directory.is_directory() {
+        return Ok(());
+    }
+
+    let info_files = directory_entries(&sid_directory, fs)?
+        .into_iter()
+        .filter(|(name, _)| name.starts_with(&[b'$', 0, b'I', 0]));
+
+    let index = sid_directory.directory_index(fs)?;
+    let mut finder = index.finder();
+
+    for (info_file_name, info_file_reference) in info_files {
+        let info = info_file_reference
+            .to_file(ntfs, fs)
+            .and_then(|info_file| read_info_file(&info_file, fs));
+
+        // The `$R` file has the same name as the `$I` file, except for the second character.
+        let data_file_name = info_file_name
+            .chunks_exact(2)
+            .enumerate()
+            .map(|(i, code_point)| match i {
+                1 => u16::from(b'R'),
+                _ => LittleEndian::read_u16(code_point),
+            })
+            .collect::<Vec<u16>>();
+        let data_file_reference =
+            NtfsFileNameIndex::find_u16(&mut finder, ntfs, fs, &data_file_name)
+                .transpose()
+                .map(|entry| entry.map(|entry| entry.file_reference()));
+
+        entries.push(Ok(NtfsRecycleBinEntry {
+            sid: sid.clone(),
+            info_file_name,
+            info_file_reference,
+            info,
+            data_file_reference,
+        }));
+    }
+
+    Ok(())
+}
+
+/// Returns the names (as UTF-16LE bytes) and references of all files in the given directory.
+///
+/// MS-DOS names are skipped, so every file is only returned once.
+fn directory_entries<T>(
+    directory: &NtfsFile,
+    fs: &mut T,
+) -> Result<Vec<(Vec<u8>, NtfsFileReference)>>
+where
+    T: Read + Seek,
+{
+    let index = directory.directory_index(fs)?;
+    let mut iter = index.entries();
+    let mut entries = Vec::new();
+
+    while let Some(entry) = iter.next(fs) {
+        let entry = entry?;
+        let file_name = match entry.key() {
+            Some(file_name) => file_name?,
+            None => continue,
+        };
+
+        if file_name.namespace() == Some(NtfsFileNamespace::Dos) {
+            continue;
+        }
+
+        entries.push((file_name.name().0.to_vec(), entry.file_reference()));
+    }
+
+    Ok(entries)
+}
+
+fn read_info_file<T>(info_file: &NtfsFile, fs: &mut T) -> Result<NtfsRecycleBinInfo>
+where
+    T: Read + Seek,
+{
+    let data_item = info_file
+        .data(fs, "")
+        .ok_or(NtfsError::AttributeNotFound {
+            position: info_file.position(),
+            ty: NtfsAttributeType::Data,
+        })??;
+    let data_attribute = data_item.to_attribute()?;
+    let length = usize::try_from(data_attribute.value_length())
+        .unwrap_or(usize::MAX)
+        .min(INFO_MAX_SIZE);
+
+    let mut data = vec![0u8; length];
+    data_attribute.value(fs)?.read_exact(fs, &mut data)?;
+
+    NtfsRecycleBinInfo::from_bytes(&data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn utf16le(s: &str) -> Vec<u8> {
+        s.encode_utf16().flat_map(u16::to_le_bytes).collect()
+    }
+
+    #[test]
+    fn test_recycle_bin_info() {
+        let path = "C:\\Users\\user\\Documents\\report.docx";
+        let header = [
+            1u64.to_le_bytes(),
+            12345u64.to_le_bytes(),
+            132_000_000_000_000_000u64.to_le_bytes(),
+        ]
+        .concat();
+
+        // Version 1 with a fixed-size path field.
+        let mut data = header.clone();
+        data.extend(utf16le(path));
+        data.resize(INFO_V1_PATH_OFFSET + INFO_V1_PATH_SIZE, 0);
+        let info = NtfsRecycleBinInfo::from_bytes(&data).unwrap();
+        assert_eq!(info.version(), 1);
+        assert_eq!(info.original_size(), 12345);
+        assert_eq!(info.deletion_time().nt_timestamp(), 132_000_000_000_000_000);
+        assert_eq!(info.original_path(), path);
+
+        // A truncated version 1 file.
+        assert!(matches!(
+            NtfsRecycleBinInfo::from_bytes(&data[..100]),
+            Err(NtfsError::InvalidRecycleBinInfoSize { .. })
+        ));
+
+        // Version 2 with a NUL-terminated path of variable length.
+        let mut data = header;
+        data[0] = 2;
+        data.extend((path.len() as u32 + 1).to_le_bytes());
+        data.extend(utf16le(path));
+        data.extend([0, 0]);
+        let info = NtfsRecycleBinInfo::from_bytes(&data).unwrap();
+        assert_eq!(info.version(), 2);
+        assert_eq!(info.original_path(), path);
+
+        data[0] = 3;
+        assert!(matches!(
+            NtfsRecycleBinInfo::from_bytes(&data),
+            Err(NtfsError::UnsupportedRecycleBinInfoVersion { actual: 3 })
+        ));
+    }
+
+    #[test]
+    fn test_recycle_bin_entries() {
+        let mut testfs1 = crate::helpers::tests::testfs1();
+        let mut ntfs = Ntfs::new(&mut testfs1).unwrap();
+        ntfs.read_upcase_table(&mut testfs1).unwrap();
+
+        // testfs1 has never been used as a Windows system volume.
+        assert!(ntfs.recycle_bin_entries(&mut testfs1).is_none());
+
+        // Any directory follows the same enumeration rules.
+        let directory = ntfs
+            .file_by_path(
+                &mut testfs1,
+                "many_subdirs",
+                crate::upcase_table::NtfsCaseSensitivity::Insensitive,
+            )
+            .unwrap()
+            .unwrap();
+        let entries = directory_entries(&directory, &mut testfs1).unwrap();
+        assert!(entries.iter().any(|(name, _)| *name == utf16le("123")));
+        assert!(recycle_bin_entries(&ntfs, &directory, &mut testfs1)
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_recycle_bin_entries_testfs3() {
+        let mut testfs3 = crate::helpers::tests::testfs3();
+        let mut ntfs = Ntfs::new(&mut testfs3).unwrap();
+        ntfs.read_upcase_table(&mut testfs3).unwrap();
+
+        let sid = "S-1-5-21-1004336348-1177238915-682003330-1001";
+        let entries = ntfs
+            .recycle_bin_entries(&mut testfs3)
+            .unwrap()
+            .unwrap()
+            .into_iter()
+            .collect::<Result<Vec<_>>>()
+            .unwrap();
+        assert_eq!(entries.len(), 2);
+
+        // A version 2 `$I` file paired with its `$R` file.
+        let entry = &entries[0];
+        assert!(entry.error().is_none());
+        assert_eq!(entry.sid(), sid);
+        assert_eq!(entry.info_file_name(), "$IAB12CD.txt");
+        let info = entry.info().unwrap();
+        assert_eq!(info.version(), 2);
+        assert_eq!(info.original_size(), 5000);
+        assert_eq!(info.deletion_time().nt_timestamp(), 132_000_000_000_000_000);
+        assert_eq!(info.original_path(), "C:\\Users\\user\\Desktop\\notes.txt");
+
+        let info_file = entry
+            .info_file_reference()
+            .to_file(&ntfs, &mut testfs3)
+            .unwrap();
+        let info_file_name = info_file.name(&mut testfs3, None, None).unwrap().unwrap();
+        assert_eq!(info_file_name.name(), "$IAB12CD.txt");
+
+        let data_file = entry
+            .data_file_reference()
+            .unwrap()
+            .to_file(&ntfs, &mut testfs3)
+            .unwrap();
+        let data_file_name = data_file.name(&mut testfs3, None, None).unwrap().unwrap();
+        assert_eq!(data_file_name.name(), "$RAB12CD.txt");
+        let data_item = data_file.data(&mut testfs3, "").unwrap().unwrap();
+        assert_eq!(data_item.to_attribute().unwrap().value_length(), 5000);
+
+        // A version 1 `$I` file without a `$R` file.
+        let entry = &entries[1];
+        assert!(entry.error().is_none());
+        assert_eq!(entry.sid(), sid);
+        assert_eq!(entry.info_file_name(), "$IEF34GH.doc");
+        let info = entry.info().unwrap();
+        assert_eq!(info.version(), 1);
+        assert_eq!(info.original_size(), 12345);
+        assert_eq!(info.deletion_time().nt_timestamp(), 131_000_000_000_000_000);
+        assert_eq!(info.original_path(), "C:\\Users\\user\\Documents\\old.doc");
+        assert!(entry.data_file_reference().is_none());
+
+        // A per-user directory that cannot be read is reported as an `Err` item.
+        let sid_directory = ntfs
+            .file_by_path(
+                &mut testfs3,
+                &alloc::format!("$Recycle.Bin/{sid}"),
+                crate::upcase_table::NtfsCaseSensitivity::Insensitive,
+            )
+            .unwrap()
+            .unwrap();
+        let position = sid_directory.position().value().unwrap().get() as usize;
+        testfs3.get_mut()[position..position + 4].copy_from_slice(b"BAAD");
+
+        let entries = ntfs.recycle_bin_entries(&mut testfs3).unwrap().unwrap();
+        assert_eq!(entries.len(), 1);
+        assert!(entries[0].is_err());
+    }
+}
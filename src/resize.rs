@@ -0,0 +1,382 @@
+// Copyright 2021-2023 Colin Finck <colin@reactos.org>
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+use core::ops::Range;
+
+use alloc::vec;
+use alloc::vec::Vec;
+use binrw::io::{Read, Seek, SeekFrom, Write};
+
+use crate::attribute::{write_non_resident_value_sizes, NtfsAttribute, NtfsAttributeType};
+use crate::boot_sector::write_total_sectors;
+use crate::error::{NtfsError, Result};
+use crate::file::{KnownNtfsFileRecordNumber, NtfsFile};
+use crate::ntfs::Ntfs;
+use crate::traits::NtfsReadSeek;
+use crate::types::{Lcn, Vcn};
+
+/// A Data Run as a Logical Cluster Number (`None` for a "sparse" Data Run) and a cluster count.
+type DataRunClusters = (Option<u64>, u64);
+
+/// Adjusts $Bitmap, $BadClus, and both boot sectors of `ntfs` to a new total sector count.
+///
+/// Everything is prepared before the first write, so a refused resize leaves the volume untouched.
+pub(crate) fn resize_volume<T>(ntfs: &Ntfs, fs: &mut T, total_sectors: u64) -> Result<()>
+where
+    T: Read + Seek + Write,
+{
+    let cluster_size = ntfs.cluster_size() as u64;
+    let new_size = total_sectors
+        .checked_mul(ntfs.sector_size() as u64)
+        .ok_or(NtfsError::TotalSectorsTooBig { total_sectors })?;
+    let old_cluster_count = ntfs.size() / cluster_size;
+    let new_cluster_count = new_size / cluster_size;
+
+    // Prepare the new $Bitmap.
+    let mut bitmap_file = ntfs.file(fs, KnownNtfsFileRecordNumber::Bitmap as u64)?;
+    let (mut bitmap, bitmap_offset, bitmap_highest_vcn, bitmap_allocated_size, bitmap_runs) = {
+        let attribute = find_non_resident_data_attribute(&bitmap_file, "")?;
+
+        let mut value = attribute.value(fs)?;
+        let mut bitmap = vec![0u8; value.len() as usize];
+        value.read_exact(fs, &mut bitmap)?;
+
+        // Only the clusters up to the first sparse Data Run can hold the $Bitmap.
+        let mut bitmap_runs = Vec::new();
+        for (lcn, cluster_count) in data_run_clusters(ntfs, &attribute)? {
+            match lcn {
+                Some(lcn) => bitmap_runs.push((lcn * cluster_size, cluster_count * cluster_size)),
+                None => break,
+            }
+        }
+
+        (
+            bitmap,
+            attribute.offset(),
+            attribute.non_resident_value_highest_vcn(),
+            attribute.non_resident_value_allocated_size(),
+            bitmap_runs,
+        )
+    };
+
+    // NTFS allocates $Bitmap in multiples of 8 bytes.
+    let new_bitmap_size = (new_cluster_count + 63) / 64 * 8;
+    let backed_size = bitmap_runs.iter().map(|(_, length)| length).sum::<u64>();
+    if new_bitmap_size > backed_size {
+        return Err(NtfsError::ResizeBitmapTooSmall {
+            expected: new_bitmap_size,
+            actual: backed_size,
+        });
+    }
+
+    if let Some(lcn) = first_set_bit(&bitmap, new_cluster_count..old_cluster_count) {
+        return Err(NtfsError::ResizeClusterInUse {
+            lcn: Lcn::from(lcn),
+        });
+    }
+
+    bitmap.resize(new_bitmap_size as usize, 0);
+    for lcn in old_cluster_count..new_cluster_count {
+        bitmap[(lcn / 8) as usize] &= !(1 << (lcn % 8));
+    }
+
+    // The bits behind the last cluster are marked as allocated, so that they are never handed out.
+    for lcn in new_cluster_count..new_bitmap_size * 8 {
+        bitmap[(lcn / 8) as usize] |= 1 << (lcn % 8);
+    }
+
+    // Prepare the new $BadClus:$Bad, which is a (mostly sparse) value spanning the entire volume.
+    let mut bad_clus_file = ntfs.file(fs, KnownNtfsFileRecordNumber::BadClus as u64)?;
+    let (bad_offset, bad_initialized_size, bad_runs_range, bad_runs) = {
+        let attribute = find_non_resident_data_attribute(&bad_clus_file, "$Bad")?;
+
+        let mut remaining = new_cluster_count;
+        let mut runs = Vec::<DataRunClusters>::new();
+        for (lcn, cluster_count) in data_run_clusters(ntfs, &attribute)? {
+            if remaining == 0 {
+                break;
+            }
+
+            let cluster_count = cluster_count.min(remaining);
+            runs.push((lcn, cluster_count));
+            remaining -= cluster_count;
+        }
+
+        if remaining > 0 {
+            match runs.last_mut() {
+                Some((None, cluster_count)) => *cluster_count += remaining,
+                _ => runs.push((None, remaining)),
+            }
+        }
+
+        let bad_runs = data_runs_to_bytes(&runs);
+        let bad_runs_range = attribute.non_resident_value_data_runs_range()?;
+        if bad_runs.len() > bad_runs_range.len() {
+            return Err(NtfsError::ResizeDataRunsTooBig {
+                position: attribute.position(),
+                expected: bad_runs.len(),
+                actual: bad_runs_range.len(),
+            });
+        }
+
+        (
+            attribute.offset(),
+            attribute.non_resident_value_initialized_size(),
+            bad_runs_range,
+            bad_runs,
+        )
+    };
+
+    // Start from the boot sector the filesystem has been opened from, which may be the backup one.
+    let boot_sector_position = ntfs
+        .boot_sector()
+        .map_or(0, |boot_sector| boot_sector.position());
+    let sector_size = ntfs.sector_size() as usize;
+    let mut boot_sector = vec![0u8; sector_size];
+    fs.seek(SeekFrom::Start(boot_sector_position))?;
+    fs.read_exact(&mut boot_sector)?;
+    write_total_sectors(&mut boot_sector, total_sectors);
+
+    // Write everything.
+    let mut written = 0;
+    for (position, length) in bitmap_runs {
+        if written == bitmap.len() {
+            break;
+        }
+
+        let chunk_length = (length as usize).min(bitmap.len() - written);
+        fs.seek(SeekFrom::Start(position))?;
+        fs.write_all(&bitmap[written..written + chunk_length])?;
+        written += chunk_length;
+    }
+
+    write_non_resident_value_sizes(
+        bitmap_file.record_data_mut(),
+        bitmap_offset,
+        bitmap_highest_vcn,
+        bitmap_allocated_size,
+        new_bitmap_size,
+        new_bitmap_size,
+    );
+    bitmap_file.write_record(fs)?;
+
+    let bad_size = new_cluster_count * cluster_size;
+    let record_data = bad_clus_file.record_data_mut();
+    write_non_resident_value_sizes(
+        record_data,
+        bad_offset,
+        Vcn::from(new_cluster_count as i64 - 1),
+        bad_size,
+        bad_size,
+        bad_initialized_size.min(bad_size),
+    );
+    let runs_data = &mut record_data[bad_runs_range];
+    runs_data.fill(0);
+    runs_data[..bad_runs.len()].copy_from_slice(&bad_runs);
+    bad_clus_file.write_record(fs)?;
+
+    // The backup boot sector is located in the sector right behind the volume.
+    fs.seek(SeekFrom::Start(0))?;
+    fs.write_all(&boot_sector)?;
+    fs.seek(SeekFrom::Start(new_size))?;
+    fs.write_all(&boot_sector)?;
+
+    Ok(())
+}
+
+/// Returns the Logical Cluster Number and cluster count of every Data Run of a non-resident attribute.
+fn data_run_clusters(ntfs: &Ntfs, attribute: &NtfsAttribute) -> Result<Vec<DataRunClusters>> {
+    let cluster_size = ntfs.cluster_size() as u64;
+    let mut runs = Vec::new();
+
+    for data_run in attribute.non_resident_value()?.data_runs() {
+        let data_run = data_run?;
+        let lcn = data_run
+            .data_position()
+            .value()
+            .map(|position| position.get() / cluster_size);
+        runs.push((lcn, data_run.allocated_size() / cluster_size));
+    }
+
+    Ok(runs)
+}
+
+/// Encodes the given Data Runs in the on-disk format, including the terminating zero byte.
+fn data_runs_to_bytes(runs: &[DataRunClusters]) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    let mut previous_lcn = 0i64;
+
+    for &(lcn, cluster_count) in runs {
+        let cluster_count_byte_count = signed_byte_count(cluster_count as i64);
+
+        // A "sparse" Data Run is denoted by omitting the LCN entirely.
+        let (lcn_delta, lcn_delta_byte_count) = match lcn {
+            Some(lcn) => {
+                let lcn_delta = lcn as i64 - previous_lcn;
+                previous_lcn = lcn as i64;
+                (lcn_delta, signed_byte_count(lcn_delta))
+            }
+            None => (0, 0),
+        };
+
+        bytes.push(lcn_delta_byte_count << 4 | cluster_count_byte_count);
+        bytes.extend_from_slice(&cluster_count.to_le_bytes()[..cluster_count_byte_count as usize]);
+        bytes.extend_from_slice(&lcn_delta.to_le_bytes()[..lcn_delta_byte_count as usize]);
+    }
+
+    bytes.push(0);
+    bytes
+}
+
+fn find_non_resident_data_attribute<'n, 'f>(
+    file: &'f NtfsFile<'n>,
+    name: &str,
+) -> Result<NtfsAttribute<'n, 'f>> {
+    for attribute in file.attributes_raw() {
+        let attribute = attribute?;
+
+        // Compare the name exactly, as the $UpCase table may not have been read.
+        if attribute.ty()? == NtfsAttributeType::Data && attribute.name()? == name {
+            if attribute.is_resident() {
+                return Err(NtfsError::UnexpectedResidentAttribute {
+                    position: attribute.position(),
+                });
+            }
+
+            return Ok(attribute);
+        }
+    }
+
+    Err(NtfsError::AttributeNotFound {
+        position: file.position(),
+        ty: NtfsAttributeType::Data,
+    })
+}
+
+fn first_set_bit(bitmap: &[u8], range: Range<u64>) -> Option<u64> {
+    let mut index = range.start;
+
+    while index < range.end {
+        let byte = *bitmap.get((index / 8) as usize)?;
+
+        // Skip entirely free bytes at once.
+        if byte == 0 && index % 8 == 0 {
+            index += 8;
+            continue;
+        }
+
+        if byte & (1 << (index % 8)) != 0 {
+            return Some(index);
+        }
+
+        index += 1;
+    }
+
+    None
+}
+
+/// Returns the minimum number of bytes to store `value` as a sign-extended little-endian integer.
+fn signed_byte_count(value: i64) -> u8 {
+    (1..8)
+        .find(|&byte_count| {
+            let unused_bits = (8 - byte_count) * 8;
+            value.wrapping_shl(unused_bits).wrapping_shr(unused_bits) == value
+        })
+        .unwrap_or(8) as u8
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resize() {
+        let mut testfs1 = crate::helpers::tests::testfs1();
+        let original_image = testfs1.get_ref().clone();
+        let mut ntfs = Ntfs::new(&mut testfs1).unwrap();
+        ntfs.read_upcase_table(&mut testfs1).unwrap();
+        assert_eq!(ntfs.size(), 4095 * 512);
+
+        // Cluster 3545 is the highest one in use.
+        assert!(matches!(
+            ntfs.resize(&mut testfs1, 3545),
+            Err(NtfsError::ResizeClusterInUse { lcn }) if lcn == Lcn::from(3545)
+        ));
+        assert!(matches!(
+            ntfs.resize(&mut testfs1, 4097),
+            Err(NtfsError::ResizeBitmapTooSmall {
+                expected: 520,
+                actual: 512
+            })
+        ));
+        assert_eq!(testfs1.get_ref(), &original_image);
+
+        ntfs.resize(&mut testfs1, 3546).unwrap();
+        assert_eq!(ntfs.size(), 3546 * 512);
+
+        let mut ntfs = Ntfs::new(&mut testfs1).unwrap();
+        ntfs.read_upcase_table(&mut testfs1).unwrap();
+        assert_eq!(ntfs.size(), 3546 * 512);
+        let report = ntfs.check(&mut testfs1).unwrap();
+        assert!(report.is_consistent(), "{:?}", report.findings());
+
+        let backup_boot_sector = &testfs1.get_ref()[3546 * 512..3547 * 512];
+        assert_eq!(backup_boot_sector, &testfs1.get_ref()[..512]);
+        assert_eq!(backup_boot_sector[0x28..0x30], 3546u64.to_le_bytes());
+
+        let bad_clus_file = ntfs
+            .file(&mut testfs1, KnownNtfsFileRecordNumber::BadClus as u64)
+            .unwrap();
+        let bad_item = bad_clus_file.data(&mut testfs1, "$Bad").unwrap().unwrap();
+        let bad_attribute = bad_item.to_attribute().unwrap();
+        assert_eq!(bad_attribute.value_length(), 3546 * 512);
+        assert_eq!(
+            data_run_clusters(&ntfs, &bad_attribute).unwrap(),
+            [(None, 3546)]
+        );
+
+        // Growing to the original size restores the original $Bitmap.
+        ntfs.resize(&mut testfs1, 4095).unwrap();
+        let report = ntfs.check(&mut testfs1).unwrap();
+        assert!(report.is_consistent(), "{:?}", report.findings());
+
+        let bitmap_file = ntfs
+            .file(&mut testfs1, KnownNtfsFileRecordNumber::Bitmap as u64)
+            .unwrap();
+        let bitmap_item = bitmap_file.data(&mut testfs1, "").unwrap().unwrap();
+        let bitmap_attribute = bitmap_item.to_attribute().unwrap();
+        let bitmap_position = bitmap_attribute
+            .non_resident_value()
+            .unwrap()
+            .data_position()
+            .value()
+            .unwrap()
+            .get() as usize;
+        assert_eq!(
+            testfs1.get_ref()[bitmap_position..bitmap_position + 512],
+            original_image[bitmap_position..bitmap_position + 512]
+        );
+    }
+
+    #[test]
+    fn test_data_runs_to_bytes() {
+        assert_eq!(
+            data_runs_to_bytes(&[(None, 0xfff)]),
+            [0x02, 0xff, 0x0f, 0x00]
+        );
+
+        // LCN deltas are signed, and a delta of zero is still written.
+        assert_eq!(
+            data_runs_to_bytes(&[
+                (Some(0x235), 1),
+                (Some(0x100), 0x80),
+                (None, 2),
+                (Some(0x100), 1)
+            ]),
+            [
+                0x21, 0x01, 0x35, 0x02, 0x22, 0x80, 0x00, 0xcb, 0xfe, 0x01, 0x02, 0x11, 0x01, 0x00,
+                0x00
+            ]
+        );
+    }
+}
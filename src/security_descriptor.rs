@@ -0,0 +1,340 @@
+// Copyright 2021-2023 Colin Finck <colin@reactos.org>
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+use alloc::collections::BTreeMap;
+use alloc::vec::Vec;
+use binrw::io::{Cursor, Read, Seek};
+use binrw::{BinRead, BinReaderExt};
+use byteorder::{ByteOrder, LittleEndian};
+
+use crate::error::{NtfsError, Result};
+use crate::file::{KnownNtfsFileRecordNumber, NtfsFileFlags};
+use crate::indexes::{NtfsIndexEntryData, NtfsSecurityId, NtfsSecurityIdIndex};
+use crate::ntfs::Ntfs;
+use crate::progress::{report_progress, NtfsProgressSink, NtfsProgressUnit};
+use crate::types::NtfsPosition;
+
+#[cfg(feature = "serde")]
+use serde::ser::{Serialize, SerializeStruct, Serializer};
+
+/// Size of all [`NtfsSecurityDescriptorHeader`] fields.
+pub(crate) const SECURITY_DESCRIPTOR_HEADER_SIZE: usize = 20;
+
+/// Header of a Security Descriptor in the $SDS data stream of the $Secure file.
+///
+/// The same structure is also the data of every entry of the $SII and $SDH indexes.
+///
+/// Reference: <https://flatcap.github.io/linux-ntfs/ntfs/files/secure.html>
+#[derive(BinRead, Clone, Debug)]
+pub struct NtfsSecurityDescriptorHeader {
+    hash: u32,
+    security_id: u32,
+    offset: u64,
+    length: u32,
+}
+
+impl NtfsSecurityDescriptorHeader {
+    /// Returns the hash over the Security Descriptor, which is the key of the $SDH index.
+    pub fn hash(&self) -> u32 {
+        self.hash
+    }
+
+    /// Returns the total length of this header and the following Security Descriptor, in bytes.
+    pub fn length(&self) -> u32 {
+        self.length
+    }
+
+    /// Returns the byte offset of this header within the $SDS data stream.
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+
+    /// Returns the security ID assigned to this Security Descriptor.
+    pub fn security_id(&self) -> NtfsSecurityId {
+        NtfsSecurityId::from(self.security_id)
+    }
+
+    pub(crate) fn to_bytes(&self) -> [u8; SECURITY_DESCRIPTOR_HEADER_SIZE] {
+        let mut bytes = [0u8; SECURITY_DESCRIPTOR_HEADER_SIZE];
+        LittleEndian::write_u32(&mut bytes[0..], self.hash);
+        LittleEndian::write_u32(&mut bytes[4..], self.security_id);
+        LittleEndian::write_u64(&mut bytes[8..], self.offset);
+        LittleEndian::write_u32(&mut bytes[16..], self.length);
+        bytes
+    }
+}
+
+impl NtfsIndexEntryData for NtfsSecurityDescriptorHeader {
+    fn data_from_slice(slice: &[u8], position: NtfsPosition) -> Result<Self> {
+        if slice.len() < SECURITY_DESCRIPTOR_HEADER_SIZE {
+            return Err(NtfsError::InvalidIndexEntryDataSize {
+                position,
+                expected: SECURITY_DESCRIPTOR_HEADER_SIZE,
+                actual: slice.len(),
+            });
+        }
+
+        let mut cursor = Cursor::new(slice);
+        let header = cursor.read_le::<Self>()?;
+        Ok(header)
+    }
+}
+
+#[cfg(feature = "serde")]
+#[cfg_attr(docsrs, doc(cfg(feature = "serde")))]
+impl Serialize for NtfsSecurityDescriptorHeader {
+    fn serialize<S>(&self, serializer: S) -> core::result::Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut state = serializer.serialize_struct("NtfsSecurityDescriptorHeader", 4)?;
+        state.serialize_field("hash", &self.hash)?;
+        state.serialize_field("security_id", &self.security_id())?;
+        state.serialize_field("offset", &self.offset)?;
+        state.serialize_field("length", &self.length)?;
+        state.end()
+    }
+}
+
+/// Usage information about a single Security Descriptor, as returned by
+/// [`NtfsSecurityDescriptorStatistics::descriptors`].
+#[derive(Clone, Debug)]
+pub struct NtfsSecurityDescriptorUsage {
+    header: NtfsSecurityDescriptorHeader,
+    file_count: u64,
+}
+
+impl NtfsSecurityDescriptorUsage {
+    /// Returns the number of files referencing this Security Descriptor.
+    pub fn file_count(&self) -> u64 {
+        self.file_count
+    }
+
+    /// Returns the [`NtfsSecurityDescriptorHeader`] of this Security Descriptor.
+    pub fn header(&self) -> &NtfsSecurityDescriptorHeader {
+        &self.header
+    }
+
+    /// Returns whether no file references this Security Descriptor.
+    pub fn is_orphaned(&self) -> bool {
+        self.file_count == 0
+    }
+
+    /// Returns the security ID of this Security Descriptor.
+    pub fn security_id(&self) -> NtfsSecurityId {
+        self.header.security_id()
+    }
+}
+
+/// Statistics about how files share Security Descriptors, as returned by
+/// [`Ntfs::security_descriptor_statistics`].
+///
+/// NTFS 3.x deduplicates Security Descriptors: Every distinct descriptor is stored only once in the
+/// $SDS data stream of the $Secure file, and files reference it by the security ID in their
+/// $STANDARD_INFORMATION attribute.
+#[derive(Clone, Debug)]
+pub struct NtfsSecurityDescriptorStatistics {
+    descriptors: Vec<NtfsSecurityDescriptorUsage>,
+    unknown_security_ids: Vec<(NtfsSecurityId, u64)>,
+    files_without_security_id: u64,
+}
+
+impl NtfsSecurityDescriptorStatistics {
+    pub(crate) fn new<T, P>(ntfs: &Ntfs, fs: &mut T, progress: &mut P) -> Result<Self>
+    where
+        T: Read + Seek,
+        P: NtfsProgressSink + ?Sized,
+    {
+        // Collect all Security Descriptors known to the $SII index.
+        let secure_file = ntfs.file(fs, KnownNtfsFileRecordNumber::Secure as u64)?;
+        let index = secure_file.index::<NtfsSecurityIdIndex, _>(fs, "$SII")?;
+        let mut entries = index.entries();
+        let mut usages = BTreeMap::new();
+
+        while let Some(entry) = entries.next_with_progress(fs, progress) {
+            let entry = entry?;
+
+            if let Some(header) = entry.data() {
+                let header = header?;
+                usages.insert(header.security_id(), (header, 0u64));
+            }
+        }
+
+        // Count the references from all base File Records that are in use.
+        let mut unknown_security_ids = BTreeMap::new();
+        let mut files_without_security_id = 0;
+
+        let file_record_count = ntfs.file_record_count(fs)?;
+
+        for file_record_number in 0..file_record_count {
+            report_progress(
+                progress,
+                NtfsProgressUnit::FileRecords,
+                file_record_number,
+                Some(file_record_count),
+            )?;
+
+            let file = match ntfs.file(fs, file_record_number) {
+                Ok(file) => file,
+                // Unused File Records may never have been initialized.
+                Err(NtfsError::InvalidFileSignature { .. }) => continue,
+                Err(e) => return Err(e),
+            };
+
+            if !file.flags().contains(NtfsFileFlags::IN_USE)
+                || file.base_file_record().file_record_number() != 0
+            {
+                continue;
+            }
+
+            let security_id = match file.info()?.security_id() {
+                Some(security_id) => NtfsSecurityId::from(security_id),
+                None => {
+                    // An NTFS 1.x $STANDARD_INFORMATION attribute without a security ID.
+                    files_without_security_id += 1;
+                    continue;
+                }
+            };
+
+            if let Some((_, file_count)) = usages.get_mut(&security_id) {
+                *file_count += 1;
+            } else {
+                *unknown_security_ids.entry(security_id).or_insert(0u64) += 1;
+            }
+        }
+
+        let descriptors = usages
+            .into_values()
+            .map(|(header, file_count)| NtfsSecurityDescriptorUsage { header, file_count })
+            .collect();
+        let unknown_security_ids = unknown_security_ids.into_iter().collect();
+
+        Ok(Self {
+            descriptors,
+            unknown_security_ids,
+            files_without_security_id,
+        })
+    }
+
+    /// Returns usage information about all Security Descriptors of the $SII index,
+    /// sorted ascending by security ID.
+    pub fn descriptors(&self) -> &[NtfsSecurityDescriptorUsage] {
+        &self.descriptors
+    }
+
+    /// Returns the number of files that have an NTFS 1.x $STANDARD_INFORMATION attribute without security ID.
+    pub fn files_without_security_id(&self) -> u64 {
+        self.files_without_security_id
+    }
+
+    /// Returns an iterator over all Security Descriptors that are not referenced by any file.
+    pub fn orphaned(&self) -> impl Iterator<Item = &NtfsSecurityDescriptorUsage> {
+        self.descriptors.iter().filter(|usage| usage.is_orphaned())
+    }
+
+    /// Returns all security IDs that are referenced by files, but missing from the $SII index,
+    /// along with the number of referencing files.
+    ///
+    /// This list is empty on a consistent filesystem.
+    pub fn unknown_security_ids(&self) -> &[(NtfsSecurityId, u64)] {
+        &self.unknown_security_ids
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::ops::ControlFlow;
+
+    use crate::error::NtfsError;
+    use crate::file::KnownNtfsFileRecordNumber;
+    use crate::indexes::{NtfsSecurityId, NtfsSecurityIdIndex};
+    use crate::ntfs::Ntfs;
+    use crate::progress::{NtfsProgress, NtfsProgressUnit};
+
+    #[test]
+    fn test_security_descriptor_statistics() {
+        let mut testfs1 = crate::helpers::tests::testfs1();
+        let ntfs = Ntfs::new(&mut testfs1).unwrap();
+        let statistics = ntfs.security_descriptor_statistics(&mut testfs1).unwrap();
+
+        // mkntfs creates two Security Descriptors for the system files.
+        let descriptors = statistics.descriptors();
+        assert_eq!(descriptors.len(), 2);
+        assert_eq!(descriptors[0].security_id().value(), 0x100);
+        assert_eq!(descriptors[0].file_count(), 5);
+        assert_eq!(descriptors[1].security_id().value(), 0x101);
+        assert_eq!(descriptors[1].file_count(), 5);
+        assert_eq!(statistics.orphaned().count(), 0);
+
+        // All other files have been created by ntfs-3g with NTFS 1.x $STANDARD_INFORMATION attributes.
+        // The only exception is the root directory with a security ID of zero.
+        assert_eq!(statistics.files_without_security_id(), 525);
+        assert_eq!(
+            statistics.unknown_security_ids(),
+            &[(NtfsSecurityId::from(0), 1)]
+        );
+
+        // The variant with progress reports every File Record and can be cancelled.
+        let file_record_count = ntfs.file_record_count(&mut testfs1).unwrap();
+        let mut file_records_reported = 0;
+        let statistics_with_progress = ntfs
+            .security_descriptor_statistics_with_progress(
+                &mut testfs1,
+                &mut |progress: NtfsProgress| {
+                    if progress.unit() == NtfsProgressUnit::FileRecords {
+                        assert_eq!(progress.processed(), file_records_reported);
+                        assert_eq!(progress.total(), Some(file_record_count));
+                        file_records_reported += 1;
+                    }
+                    ControlFlow::Continue(())
+                },
+            )
+            .unwrap();
+        assert_eq!(file_records_reported, file_record_count);
+        assert_eq!(statistics_with_progress.descriptors().len(), 2);
+        assert_eq!(statistics_with_progress.files_without_security_id(), 525);
+
+        let result = ntfs.security_descriptor_statistics_with_progress(
+            &mut testfs1,
+            &mut |progress: NtfsProgress| {
+                if progress.unit() == NtfsProgressUnit::FileRecords {
+                    ControlFlow::Break(())
+                } else {
+                    ControlFlow::Continue(())
+                }
+            },
+        );
+        assert!(matches!(result, Err(NtfsError::Cancelled)));
+    }
+
+    #[test]
+    fn test_security_id_index() {
+        let mut testfs1 = crate::helpers::tests::testfs1();
+        let ntfs = Ntfs::new(&mut testfs1).unwrap();
+        let secure_file = ntfs
+            .file(&mut testfs1, KnownNtfsFileRecordNumber::Secure as u64)
+            .unwrap();
+        let index = secure_file
+            .index::<NtfsSecurityIdIndex, _>(&mut testfs1, "$SII")
+            .unwrap();
+        let mut finder = index.finder();
+
+        let entry =
+            NtfsSecurityIdIndex::find(&mut finder, &mut testfs1, NtfsSecurityId::from(0x101))
+                .unwrap()
+                .unwrap();
+        let header = entry.data().unwrap().unwrap();
+        assert_eq!(header.security_id().value(), 0x101);
+        assert_eq!(header.length(), 124);
+
+        // Serializing the entry again must yield the bytes of the entry.
+        let bytes =
+            NtfsSecurityIdIndex::entry_to_bytes(header.security_id(), &header, None).unwrap();
+        assert_eq!(entry.as_bytes(), bytes);
+
+        assert!(
+            NtfsSecurityIdIndex::find(&mut finder, &mut testfs1, NtfsSecurityId::from(0x102))
+                .is_none()
+        );
+    }
+}
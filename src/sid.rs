@@ -0,0 +1,200 @@
+// Copyright 2021-2023 Colin Finck <colin@reactos.org>
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+use core::cmp::Ordering;
+use core::fmt;
+
+use arrayvec::ArrayVec;
+use byteorder::{BigEndian, ByteOrder, LittleEndian};
+
+use crate::error::{NtfsError, Result};
+use crate::indexes::NtfsIndexEntryKey;
+use crate::types::NtfsPosition;
+
+#[cfg(feature = "serde")]
+use serde::ser::{Serialize, Serializer};
+
+/// Size of the fixed part of a SID (revision, sub authority count, and identifier authority).
+const SID_HEADER_SIZE: usize = 8;
+
+/// Maximum number of sub authorities of a SID.
+const SID_MAX_SUB_AUTHORITIES: usize = 15;
+
+/// Maximum size of a SID, in bytes.
+const SID_MAX_SIZE: usize = SID_HEADER_SIZE + SID_MAX_SUB_AUTHORITIES * 4;
+
+/// A Windows Security Identifier (SID) that identifies a user or group.
+///
+/// NTFS stores SIDs in their binary form, e.g. as keys of the $O index of the $Quota file.
+/// The [`fmt::Display`] implementation outputs the familiar string form (like `S-1-5-32-544`).
+///
+/// Comparisons follow the collation rule of NTFS indexes keyed by SID, which compares
+/// the binary SIDs as sequences of little-endian 32-bit values.
+///
+/// Reference: <https://learn.microsoft.com/en-us/openspecs/windows_protocols/ms-dtyp/f992ad60-0fe4-4b87-9fed-beb478836861>
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct NtfsSid {
+    data: ArrayVec<u8, SID_MAX_SIZE>,
+}
+
+impl NtfsSid {
+    /// Creates a revision 1 SID from an identifier authority and a list of sub authorities.
+    ///
+    /// Returns `None` if the identifier authority doesn't fit into 48 bits or there are more than
+    /// 15 sub authorities.
+    pub fn new(identifier_authority: u64, sub_authorities: &[u32]) -> Option<Self> {
+        if identifier_authority >> 48 != 0 || sub_authorities.len() > SID_MAX_SUB_AUTHORITIES {
+            return None;
+        }
+
+        let mut data = ArrayVec::new();
+        data.push(1);
+        data.push(sub_authorities.len() as u8);
+        data.extend(identifier_authority.to_be_bytes()[2..].iter().copied());
+
+        for sub_authority in sub_authorities {
+            data.extend(sub_authority.to_le_bytes());
+        }
+
+        Some(Self { data })
+    }
+
+    /// Parses a binary SID at the beginning of `slice`.
+    ///
+    /// Returns `None` if `slice` is too short or the SID has more than 15 sub authorities.
+    pub fn from_slice(slice: &[u8]) -> Option<Self> {
+        let sub_authority_count = *slice.get(1)? as usize;
+        if sub_authority_count > SID_MAX_SUB_AUTHORITIES {
+            return None;
+        }
+
+        let size = SID_HEADER_SIZE + sub_authority_count * 4;
+        let data = ArrayVec::try_from(slice.get(..size)?).ok()?;
+
+        Some(Self { data })
+    }
+
+    /// Returns the size of the binary SID at the beginning of `slice`, as announced by its
+    /// sub authority count.
+    pub(crate) fn size_from_slice(slice: &[u8]) -> usize {
+        slice.get(1).map_or(SID_HEADER_SIZE, |sub_authority_count| {
+            SID_HEADER_SIZE + *sub_authority_count as usize * 4
+        })
+    }
+
+    /// Returns the binary form of this SID.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.data
+    }
+
+    /// Returns the 48-bit identifier authority of this SID (like 5 for `SECURITY_NT_AUTHORITY`).
+    pub fn identifier_authority(&self) -> u64 {
+        BigEndian::read_u48(&self.data[2..SID_HEADER_SIZE])
+    }
+
+    /// Returns the revision of this SID (always 1 in practice).
+    pub fn revision(&self) -> u8 {
+        self.data[0]
+    }
+
+    /// Returns the number of sub authorities of this SID.
+    pub fn sub_authority_count(&self) -> u8 {
+        self.data[1]
+    }
+
+    /// Returns an iterator over the sub authorities of this SID.
+    pub fn sub_authorities(&self) -> impl Iterator<Item = u32> + '_ {
+        self.data[SID_HEADER_SIZE..]
+            .chunks_exact(4)
+            .map(LittleEndian::read_u32)
+    }
+}
+
+impl fmt::Display for NtfsSid {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "S-{}-", self.revision())?;
+
+        // Large identifier authorities are output in hexadecimal, as Windows does.
+        let identifier_authority = self.identifier_authority();
+        if identifier_authority >> 32 == 0 {
+            write!(f, "{identifier_authority}")?;
+        } else {
+            write!(f, "{identifier_authority:#014x}")?;
+        }
+
+        for sub_authority in self.sub_authorities() {
+            write!(f, "-{sub_authority}")?;
+        }
+
+        Ok(())
+    }
+}
+
+impl NtfsIndexEntryKey for NtfsSid {
+    fn key_from_slice(slice: &[u8], position: NtfsPosition) -> Result<Self> {
+        Self::from_slice(slice).ok_or(NtfsError::InvalidIndexEntryKeySize {
+            position,
+            expected: Self::size_from_slice(slice),
+            actual: slice.len(),
+        })
+    }
+}
+
+impl Ord for NtfsSid {
+    fn cmp(&self, other: &Self) -> Ordering {
+        let words = self.data.chunks_exact(4).map(LittleEndian::read_u32);
+        let other_words = other.data.chunks_exact(4).map(LittleEndian::read_u32);
+        words.cmp(other_words)
+    }
+}
+
+impl PartialOrd for NtfsSid {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Serializes the string form of this SID (like `S-1-5-32-544`).
+#[cfg(feature = "serde")]
+#[cfg_attr(docsrs, doc(cfg(feature = "serde")))]
+impl Serialize for NtfsSid {
+    fn serialize<S>(&self, serializer: S) -> core::result::Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.collect_str(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::string::ToString;
+    use alloc::vec::Vec;
+
+    use super::*;
+
+    #[test]
+    fn test_sid() {
+        let bytes = [
+            0x01, 0x02, 0x00, 0x00, 0x00, 0x00, 0x00, 0x05, 0x20, 0x00, 0x00, 0x00, 0x20, 0x02,
+            0x00, 0x00, 0xff,
+        ];
+        let sid = NtfsSid::from_slice(&bytes).unwrap();
+        assert_eq!(sid.revision(), 1);
+        assert_eq!(sid.identifier_authority(), 5);
+        assert_eq!(sid.sub_authorities().collect::<Vec<_>>(), [32, 544]);
+        assert_eq!(sid.as_bytes(), &bytes[..16]);
+        assert_eq!(sid.to_string(), "S-1-5-32-544");
+        assert_eq!(NtfsSid::new(5, &[32, 544]).unwrap(), sid);
+
+        // A SID with a sub authority count beyond the slice can't be parsed.
+        assert!(NtfsSid::from_slice(&bytes[..12]).is_none());
+        assert!(NtfsSid::new(1 << 48, &[]).is_none());
+
+        let large_authority = NtfsSid::new(0x1234_5678_9abc, &[1]).unwrap();
+        assert_eq!(large_authority.to_string(), "S-1-0x123456789abc-1");
+
+        // S-1-5-18 sorts before S-1-5-32-544, because it has fewer sub authorities.
+        assert!(NtfsSid::new(5, &[18]).unwrap() < sid);
+    }
+}
@@ -0,0 +1,116 @@
+// Copyright 2021-2023 Colin Finck <colin@reactos.org>
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+use crate::attribute_value::NtfsDataRun;
+use crate::read_at::NtfsReadAtReader;
+use crate::types::NtfsPosition;
+
+/// Reader over an NTFS filesystem image that is entirely available in memory (or memory-mapped).
+///
+/// This is an [`NtfsReadAtReader`] over a plain byte slice, so it works in `no_std`
+/// environments just like with `std`.
+/// Use it in place of any other filesystem reader:
+///
+/// ```ignore
+/// let mut fs = NtfsSliceReader::new(&image);
+/// let ntfs = Ntfs::new(&mut fs).unwrap();
+/// let root_dir = ntfs.root_directory(&mut fs).unwrap();
+/// ```
+///
+/// On top of that, [`slice_at`] and [`data_run_slice`] borrow directly from the image,
+/// so the clusters of non-resident values can be accessed without copying.
+/// Resident values are accessible without further copies via [`NtfsResidentAttributeValue::data`].
+///
+/// Note that File Records and Index Records are still copied once when they are read, because
+/// their Update Sequence Array has to be applied before they can be parsed.
+///
+/// [`data_run_slice`]: NtfsReadAtReader::data_run_slice
+/// [`slice_at`]: NtfsReadAtReader::slice_at
+/// [`NtfsResidentAttributeValue::data`]: crate::attribute_value::NtfsResidentAttributeValue::data
+pub type NtfsSliceReader<'a> = NtfsReadAtReader<&'a [u8]>;
+
+impl<'a> NtfsReadAtReader<&'a [u8]> {
+    /// Returns the entire filesystem image.
+    pub fn data(&self) -> &'a [u8] {
+        self.get_ref()
+    }
+
+    /// Returns the clusters of the given [`NtfsDataRun`] directly from the filesystem image.
+    ///
+    /// This is `None` for a sparse Data Run or if the Data Run is not entirely part of the image.
+    /// Keep in mind that the last Data Run of a value may contain allocated, but unused bytes
+    /// (see [`NtfsDataRun`]).
+    pub fn data_run_slice(&self, data_run: &NtfsDataRun) -> Option<&'a [u8]> {
+        let length = usize::try_from(data_run.allocated_size()).ok()?;
+        self.slice_at(data_run.data_position(), length)
+    }
+
+    /// Returns `length` bytes of the filesystem image at the given absolute position.
+    ///
+    /// This is `None` if the position is unknown (like for a sparse Data Run) or the range exceeds the image.
+    pub fn slice_at(&self, position: NtfsPosition, length: usize) -> Option<&'a [u8]> {
+        let start = usize::try_from(position.value()?.get()).ok()?;
+        let end = start.checked_add(length)?;
+        self.data().get(start..end)
+    }
+}
+
+impl<'a> From<&'a [u8]> for NtfsSliceReader<'a> {
+    fn from(data: &'a [u8]) -> Self {
+        Self::new(data)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use binrw::io::{Read, Seek, SeekFrom};
+
+    use super::*;
+    use crate::indexes::NtfsFileNameIndex;
+    use crate::ntfs::Ntfs;
+
+    #[test]
+    fn test_slice_reader() {
+        let image = crate::helpers::tests::testfs1().into_inner();
+        let mut fs = NtfsSliceReader::new(&image);
+
+        let mut ntfs = Ntfs::new(&mut fs).unwrap();
+        ntfs.read_upcase_table(&mut fs).unwrap();
+        let root_dir = ntfs.root_directory(&mut fs).unwrap();
+        let root_dir_index = root_dir.directory_index(&mut fs).unwrap();
+        let mut root_dir_finder = root_dir_index.finder();
+        let entry =
+            NtfsFileNameIndex::find(&mut root_dir_finder, &ntfs, &mut fs, "1000-bytes-file")
+                .unwrap()
+                .unwrap();
+        let file = entry.to_file(&ntfs, &mut fs).unwrap();
+
+        // The single Data Run of this file can be accessed straight from the image.
+        let data_item = file.data(&mut fs, "").unwrap().unwrap();
+        let data_attribute = data_item.to_attribute().unwrap();
+        let data_value = data_attribute.non_resident_value().unwrap();
+        let data_run = data_value.data_runs().next().unwrap().unwrap();
+        let slice = fs.data_run_slice(&data_run).unwrap();
+        assert_eq!(slice.len(), 1024);
+        assert_eq!(&slice[..1000], &[b'1', b'2', b'3', b'4', b'5'].repeat(200));
+
+        assert!(fs.slice_at(NtfsPosition::none(), 1).is_none());
+        assert!(fs
+            .slice_at(NtfsPosition::new(image.len() as u64 - 1), 2)
+            .is_none());
+
+        // Reads behind the end of the image return no data, while seeking before the start fails.
+        let mut buf = [0u8; 4];
+        assert_eq!(fs.seek(SeekFrom::End(-2)).unwrap(), image.len() as u64 - 2);
+        assert_eq!(fs.read(&mut buf).unwrap(), 2);
+        assert_eq!(fs.read(&mut buf).unwrap(), 0);
+        assert_eq!(
+            fs.seek(SeekFrom::Current(10)).unwrap(),
+            image.len() as u64 + 10
+        );
+        assert_eq!(fs.read(&mut buf).unwrap(), 0);
+        assert!(fs
+            .seek(SeekFrom::Current(-(image.len() as i64) - 11))
+            .is_err());
+    }
+}
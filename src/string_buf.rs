@@ -0,0 +1,193 @@
+// Copyright 2021-2023 Colin Finck <colin@reactos.org>
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+use core::cmp::Ordering;
+use core::fmt;
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use nt_string::u16strle::U16StrLe;
+
+#[cfg(feature = "serde")]
+use serde::ser::{Serialize, Serializer};
+
+/// An owned little-endian UTF-16 string, as stored in NTFS structures.
+///
+/// All functions of this crate returning names (like [`NtfsFileName::name`] or [`NtfsAttribute::name`])
+/// return a borrowed [`U16StrLe`], which ties the name to the lifetime of the structure or record buffer
+/// it comes from and avoids any copying.
+/// This is the owned counterpart for names that need to outlive their source, e.g. for collecting the
+/// names of an entire directory or for using them as map keys.
+///
+/// Create one via `NtfsStringBuf::from(name)` and get a [`U16StrLe`] back via [`NtfsStringBuf::as_u16strle`].
+/// Just like [`U16StrLe`], the string isn't validated and may contain unpaired surrogates.
+///
+/// [`NtfsAttribute::name`]: crate::NtfsAttribute::name
+/// [`NtfsFileName::name`]: crate::structured_values::NtfsFileName::name
+#[derive(Clone, Debug, Default, Eq, Hash, PartialEq)]
+pub struct NtfsStringBuf(Vec<u8>);
+
+impl NtfsStringBuf {
+    /// Creates a new empty [`NtfsStringBuf`].
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns a borrowed [`U16StrLe`] of this string.
+    pub fn as_u16strle(&self) -> U16StrLe<'_> {
+        U16StrLe(&self.0)
+    }
+
+    /// Returns the raw little-endian UTF-16 bytes of this string.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+
+    /// Returns `true` if this string is empty.
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Returns the length of this string, in bytes.
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Converts this string into a [`String`], replacing invalid UTF-16 sequences by
+    /// [`char::REPLACEMENT_CHARACTER`].
+    pub fn to_string_lossy(&self) -> String {
+        self.as_u16strle().to_string_lossy()
+    }
+
+    /// Returns an iterator over the UTF-16 code units of this string.
+    pub fn u16_iter(&self) -> impl Iterator<Item = u16> + '_ {
+        self.0
+            .chunks_exact(2)
+            .map(|two_bytes| u16::from_le_bytes([two_bytes[0], two_bytes[1]]))
+    }
+}
+
+impl fmt::Display for NtfsStringBuf {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.as_u16strle().fmt(f)
+    }
+}
+
+impl<'a> From<U16StrLe<'a>> for NtfsStringBuf {
+    fn from(string: U16StrLe<'a>) -> Self {
+        Self(string.0.to_vec())
+    }
+}
+
+impl From<&str> for NtfsStringBuf {
+    fn from(string: &str) -> Self {
+        Self(string.encode_utf16().flat_map(u16::to_le_bytes).collect())
+    }
+}
+
+impl Ord for NtfsStringBuf {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.as_u16strle().cmp(&other.as_u16strle())
+    }
+}
+
+impl PartialOrd for NtfsStringBuf {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<'a> PartialEq<U16StrLe<'a>> for NtfsStringBuf {
+    fn eq(&self, other: &U16StrLe<'a>) -> bool {
+        self.as_u16strle() == *other
+    }
+}
+
+impl<'a> PartialEq<NtfsStringBuf> for U16StrLe<'a> {
+    fn eq(&self, other: &NtfsStringBuf) -> bool {
+        *self == other.as_u16strle()
+    }
+}
+
+impl PartialEq<str> for NtfsStringBuf {
+    fn eq(&self, other: &str) -> bool {
+        self.as_u16strle() == other
+    }
+}
+
+impl PartialEq<&str> for NtfsStringBuf {
+    fn eq(&self, other: &&str) -> bool {
+        self.as_u16strle() == *other
+    }
+}
+
+/// Serializes this string as a UTF-8 string, replacing invalid UTF-16 sequences.
+#[cfg(feature = "serde")]
+#[cfg_attr(docsrs, doc(cfg(feature = "serde")))]
+impl Serialize for NtfsStringBuf {
+    fn serialize<S>(&self, serializer: S) -> core::result::Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.collect_str(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::collections::BTreeSet;
+    use alloc::vec::Vec;
+
+    use super::*;
+    use crate::indexes::NtfsFileNameIndex;
+    use crate::ntfs::Ntfs;
+    use crate::upcase_table::UpcaseOrd;
+
+    #[test]
+    fn test_string_buf() {
+        let mut testfs1 = crate::helpers::tests::testfs1();
+        let mut ntfs = Ntfs::new(&mut testfs1).unwrap();
+        ntfs.read_upcase_table(&mut testfs1).unwrap();
+        let root_dir = ntfs.root_directory(&mut testfs1).unwrap();
+        let index = root_dir.directory_index(&mut testfs1).unwrap();
+        let mut entries = index.entries();
+
+        // Collect owned names, which outlive the entries borrowing from the Index Record buffers.
+        let mut names = BTreeSet::new();
+        while let Some(entry) = entries.next(&mut testfs1) {
+            let entry = entry.unwrap();
+            let name = match entry.key_name() {
+                Some(name) => name.unwrap(),
+                None => continue,
+            };
+
+            let file_name = entry.key().unwrap().unwrap();
+            assert_eq!(name, file_name.name());
+            names.insert(NtfsStringBuf::from(name));
+        }
+
+        let name = NtfsStringBuf::from("1000-bytes-file");
+        assert!(names.contains(&name));
+        assert_eq!(name, "1000-bytes-file");
+        assert_eq!(name.len(), 30);
+        assert_eq!(name.to_string_lossy(), "1000-bytes-file");
+        assert_eq!(
+            name.u16_iter().collect::<Vec<u16>>(),
+            "1000-bytes-file".encode_utf16().collect::<Vec<u16>>()
+        );
+
+        // Owned names can be used for lookups again.
+        let mut finder = index.finder();
+        let code_units = name.u16_iter().collect::<Vec<u16>>();
+        let entry = NtfsFileNameIndex::find_u16(&mut finder, &ntfs, &mut testfs1, &code_units)
+            .unwrap()
+            .unwrap();
+        assert_eq!(entry.key_name().unwrap().unwrap(), name);
+
+        assert_eq!(
+            NtfsStringBuf::from("1000-BYTES-FILE").upcase_cmp(&ntfs, &name.as_u16strle()),
+            Ordering::Equal
+        );
+        assert!(NtfsStringBuf::new().is_empty());
+    }
+}
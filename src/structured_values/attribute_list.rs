@@ -229,7 +229,7 @@ impl NtfsAttributeListEntry {
     }
 
     /// Gets the attribute name and returns it wrapped in a [`U16StrLe`].
-    pub fn name(&self) -> U16StrLe {
+    pub fn name(&self) -> U16StrLe<'_> {
         U16StrLe(&self.name)
     }
 
@@ -274,10 +274,22 @@ impl NtfsAttributeListEntry {
             Always use NtfsAttributeListEntry::to_file to retrieve the correct NtfsFile."
         );
 
+        // Compare the raw types to also find attributes of unsupported types.
         let instance = self.instance();
-        let ty = self.ty()?;
+        let ty = self.raw_ty();
 
-        file.find_resident_attribute(ty, None, Some(instance))
+        for attribute in file.attributes_raw() {
+            let attribute = attribute?;
+
+            if attribute.raw_ty() == ty && attribute.instance() == instance {
+                return Ok(attribute);
+            }
+        }
+
+        Err(NtfsError::AttributeNotFound {
+            position: file.position(),
+            ty: self.ty()?,
+        })
     }
 
     /// Reads the entire File Record referenced by this attribute and returns it.
@@ -285,8 +297,11 @@ impl NtfsAttributeListEntry {
     where
         T: Read + Seek,
     {
-        let file_record_number = self.base_file_reference().file_record_number();
-        ntfs.file(fs, file_record_number)
+        self.base_file_reference().to_file(ntfs, fs)
+    }
+
+    pub(crate) fn raw_ty(&self) -> u32 {
+        self.header.ty
     }
 
     /// Returns the type of this NTFS Attribute, or [`NtfsError::UnsupportedAttributeType`]
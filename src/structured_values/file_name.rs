@@ -9,18 +9,31 @@ use binrw::{BinRead, BinReaderExt};
 use enumn::N;
 use nt_string::u16strle::U16StrLe;
 
-use crate::attribute::NtfsAttributeType;
+use crate::attribute::{NtfsAttribute, NtfsAttributeType};
 use crate::attribute_value::NtfsAttributeValue;
 use crate::error::{NtfsError, Result};
 use crate::file_reference::NtfsFileReference;
 use crate::indexes::NtfsIndexEntryKey;
+use crate::options::{NtfsOptions, NtfsUnsupportedPolicy};
 use crate::structured_values::{NtfsFileAttributeFlags, NtfsStructuredValue};
 use crate::time::NtfsTime;
 use crate::types::NtfsPosition;
 
+#[cfg(feature = "serde")]
+use {
+    crate::helpers::SerializeDisplay,
+    serde::ser::{Serialize, SerializeStruct, Serializer},
+};
+
 /// Size of all [`FileNameHeader`] fields.
 const FILE_NAME_HEADER_SIZE: usize = 66;
 
+/// Byte offset of [`FileNameHeader`]'s `name_length` field.
+const NAME_LENGTH_OFFSET: usize = 64;
+
+/// Byte offset of [`FileNameHeader`]'s `namespace` field.
+const NAMESPACE_OFFSET: usize = 65;
+
 /// The smallest FileName attribute has a name containing just a single character.
 const FILE_NAME_MIN_SIZE: usize = FILE_NAME_HEADER_SIZE + mem::size_of::<u16>();
 
@@ -84,6 +97,41 @@ pub struct NtfsFileName {
 
 impl NtfsFileName {
     fn new<T>(r: &mut T, position: NtfsPosition, value_length: u64) -> Result<Self>
+    where
+        T: Read + Seek,
+    {
+        let file_name = Self::new_with_unknown_namespace(r, position, value_length)?;
+        file_name.validate_namespace(position)?;
+
+        Ok(file_name)
+    }
+
+    /// Reads a $FILE_NAME attribute even if it belongs to an unsupported namespace.
+    ///
+    /// This is used for [`NtfsUnsupportedPolicy::YieldUnknown`].
+    ///
+    /// [`NtfsUnsupportedPolicy::YieldUnknown`]: crate::NtfsUnsupportedPolicy::YieldUnknown
+    pub(crate) fn from_attribute_with_unknown_namespace<T>(
+        fs: &mut T,
+        attribute: &NtfsAttribute,
+    ) -> Result<Self>
+    where
+        T: Read + Seek,
+    {
+        attribute.ensure_ty(NtfsAttributeType::FileName)?;
+        let value = attribute.value(fs)?;
+        let position = value.data_position();
+        let value_length = value.len();
+
+        let mut value_attached = value.attach(fs);
+        Self::new_with_unknown_namespace(&mut value_attached, position, value_length)
+    }
+
+    fn new_with_unknown_namespace<T>(
+        r: &mut T,
+        position: NtfsPosition,
+        value_length: u64,
+    ) -> Result<Self>
     where
         T: Read + Seek,
     {
@@ -103,7 +151,6 @@ impl NtfsFileName {
             name: ArrayVec::from([0u8; NAME_MAX_SIZE]),
         };
         file_name.validate_name_length(value_length, position)?;
-        file_name.validate_namespace(position)?;
         file_name.read_name(r)?;
 
         Ok(file_name)
@@ -207,10 +254,29 @@ impl NtfsFileName {
     }
 
     /// Gets the file name and returns it wrapped in a [`U16StrLe`].
-    pub fn name(&self) -> U16StrLe {
+    pub fn name(&self) -> U16StrLe<'_> {
         U16StrLe(&self.name)
     }
 
+    /// Returns the name of the raw $FILE_NAME structure `slice` without copying it.
+    pub(crate) fn name_from_slice(slice: &[u8], position: NtfsPosition) -> Result<U16StrLe<'_>> {
+        let name_length = slice.get(NAME_LENGTH_OFFSET).map_or(0, |name_length| {
+            *name_length as usize * mem::size_of::<u16>()
+        });
+        let total_size = FILE_NAME_HEADER_SIZE + name_length;
+
+        if slice.len() < FILE_NAME_MIN_SIZE || slice.len() < total_size {
+            return Err(NtfsError::InvalidStructuredValueSize {
+                position,
+                ty: NtfsAttributeType::FileName,
+                expected: total_size.max(FILE_NAME_MIN_SIZE) as u64,
+                actual: slice.len() as u64,
+            });
+        }
+
+        Ok(U16StrLe(&slice[FILE_NAME_HEADER_SIZE..total_size]))
+    }
+
     /// Returns the file name length, in bytes.
     ///
     /// A file name has a maximum length of 255 UTF-16 code points (510 bytes).
@@ -219,8 +285,20 @@ impl NtfsFileName {
     }
 
     /// Returns the [`NtfsFileNamespace`] of this file name.
-    pub fn namespace(&self) -> NtfsFileNamespace {
-        NtfsFileNamespace::n(self.header.namespace).unwrap()
+    ///
+    /// File names of unsupported namespaces are only returned under [`NtfsUnsupportedPolicy::YieldUnknown`].
+    /// This function returns `None` for them, and [`NtfsFileName::namespace_value`] returns their raw value.
+    ///
+    /// [`NtfsUnsupportedPolicy::YieldUnknown`]: crate::NtfsUnsupportedPolicy::YieldUnknown
+    pub fn namespace(&self) -> Option<NtfsFileNamespace> {
+        NtfsFileNamespace::n(self.header.namespace)
+    }
+
+    /// Returns the raw namespace value of this file name.
+    ///
+    /// This is the only way to get the namespace of file names of unsupported namespaces.
+    pub fn namespace_value(&self) -> u8 {
+        self.header.namespace
     }
 
     /// Returns an [`NtfsFileReference`] for the directory where this file is located.
@@ -288,8 +366,81 @@ impl NtfsIndexEntryKey for NtfsFileName {
     fn key_from_slice(slice: &[u8], position: NtfsPosition) -> Result<Self> {
         let value_length = slice.len() as u64;
 
+        // The namespace has already been checked against the `NtfsOptions` by `filter_key_slice`.
         let mut cursor = Cursor::new(slice);
-        Self::new(&mut cursor, position, value_length)
+        Self::new_with_unknown_namespace(&mut cursor, position, value_length)
+    }
+
+    fn filter_key_slice(
+        slice: &[u8],
+        position: NtfsPosition,
+        options: &NtfsOptions,
+    ) -> Result<bool> {
+        // Keys too short to have a namespace are reported by `key_from_slice`.
+        let namespace = match slice.get(NAMESPACE_OFFSET) {
+            Some(namespace) => *namespace,
+            None => return Ok(true),
+        };
+
+        if NtfsFileNamespace::n(namespace).is_some() {
+            return Ok(true);
+        }
+
+        match options.file_namespace_policy() {
+            NtfsUnsupportedPolicy::Error => Err(NtfsError::UnsupportedFileNamespace {
+                position,
+                actual: namespace,
+            }),
+            NtfsUnsupportedPolicy::Skip => Ok(false),
+            NtfsUnsupportedPolicy::YieldUnknown => Ok(true),
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+#[cfg_attr(docsrs, doc(cfg(feature = "serde")))]
+impl Serialize for NtfsFileNamespace {
+    fn serialize<S>(&self, serializer: S) -> core::result::Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let variant = match self {
+            Self::Posix => "Posix",
+            Self::Win32 => "Win32",
+            Self::Dos => "Dos",
+            Self::Win32AndDos => "Win32AndDos",
+        };
+
+        serializer.serialize_unit_variant("NtfsFileNamespace", *self as u32, variant)
+    }
+}
+
+#[cfg(feature = "serde")]
+#[cfg_attr(docsrs, doc(cfg(feature = "serde")))]
+impl Serialize for NtfsFileName {
+    fn serialize<S>(&self, serializer: S) -> core::result::Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        // Invalid UTF-16 code units are replaced, as in `Display` for `U16StrLe`.
+        let mut state = serializer.serialize_struct("NtfsFileName", 10)?;
+        state.serialize_field("name", &SerializeDisplay(self.name()))?;
+        state.serialize_field("namespace", &self.namespace())?;
+        state.serialize_field(
+            "parent_directory_reference",
+            &self.parent_directory_reference(),
+        )?;
+        state.serialize_field("file_attributes", &self.file_attributes())?;
+        state.serialize_field("creation_time", &self.creation_time())?;
+        state.serialize_field("modification_time", &self.modification_time())?;
+        state.serialize_field(
+            "mft_record_modification_time",
+            &self.mft_record_modification_time(),
+        )?;
+        state.serialize_field("access_time", &self.access_time())?;
+        state.serialize_field("allocated_size", &self.allocated_size())?;
+        state.serialize_field("data_size", &self.data_size())?;
+        state.end()
     }
 }
 
@@ -342,4 +493,75 @@ mod tests {
             U16StrLe(&[b'$', 0, b'M', 0, b'F', 0, b'T', 0])
         );
     }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_file_name_serialize() {
+        use serde_json::json;
+
+        use crate::indexes::NtfsFileNameIndex;
+
+        let mut testfs1 = crate::helpers::tests::testfs1();
+        let mut ntfs = Ntfs::new(&mut testfs1).unwrap();
+        ntfs.read_upcase_table(&mut testfs1).unwrap();
+        let root_dir = ntfs.root_directory(&mut testfs1).unwrap();
+        let root_dir_index = root_dir.directory_index(&mut testfs1).unwrap();
+        let mut root_dir_finder = root_dir_index.finder();
+        let entry =
+            NtfsFileNameIndex::find(&mut root_dir_finder, &ntfs, &mut testfs1, "file-with-12345")
+                .unwrap()
+                .unwrap();
+
+        let file_name = entry.key().unwrap().unwrap();
+        let expected_file_name = json!({
+            "name": "file-with-12345",
+            "namespace": "Posix",
+            "parent_directory_reference": {"file_record_number": 5, "sequence_number": 5},
+            "file_attributes": file_name.file_attributes().bits(),
+            "creation_time": file_name.creation_time().nt_timestamp(),
+            "modification_time": file_name.modification_time().nt_timestamp(),
+            "mft_record_modification_time": file_name.mft_record_modification_time().nt_timestamp(),
+            "access_time": file_name.access_time().nt_timestamp(),
+            "allocated_size": file_name.allocated_size(),
+            "data_size": file_name.data_size(),
+        });
+        assert_eq!(
+            serde_json::to_value(&file_name).unwrap(),
+            expected_file_name
+        );
+
+        let file_reference = entry.file_reference();
+        let expected_entry = json!({
+            "position": entry.position().value().unwrap().get(),
+            "flags": entry.flags().bits(),
+            "file_reference": {
+                "file_record_number": file_reference.file_record_number(),
+                "sequence_number": file_reference.sequence_number(),
+            },
+            "key": expected_file_name,
+            "subnode_vcn": null,
+        });
+        assert_eq!(serde_json::to_value(&entry).unwrap(), expected_entry);
+
+        // NTFS 1.x $STANDARD_INFORMATION attributes serialize the missing fields as `null`.
+        let file = entry.to_file(&ntfs, &mut testfs1).unwrap();
+        let info = serde_json::to_value(file.info().unwrap()).unwrap();
+        for field in ["security_id", "quota_charged", "usn"] {
+            assert_eq!(info[field], serde_json::Value::Null);
+        }
+        assert_eq!(
+            info["creation_time"],
+            json!(file.info().unwrap().creation_time().nt_timestamp())
+        );
+
+        let volume_info = ntfs.volume_info(&mut testfs1).unwrap();
+        assert_eq!(
+            serde_json::to_value(&volume_info).unwrap(),
+            json!({
+                "major_version": 3,
+                "minor_version": 1,
+                "flags": volume_info.flags().bits(),
+            })
+        );
+    }
 }
@@ -14,6 +14,21 @@ use crate::structured_values::NtfsStructuredValue;
 use crate::traits::NtfsReadSeek;
 use crate::types::Vcn;
 
+/// Index Records smaller than a cluster are addressed in units of this size instead of clusters.
+const SMALL_INDEX_RECORD_VCN_SIZE: u32 = 512;
+
+/// Returns the size of a single VCN unit for addressing Index Records of the given size, in bytes.
+///
+/// Large clusters (like 64K or more) can hold multiple Index Records of the usual size of 4096 bytes.
+/// Their VCNs are counted in 512-byte units then.
+fn index_vcn_size(cluster_size: u32, index_record_size: u32) -> u32 {
+    if index_record_size < cluster_size {
+        SMALL_INDEX_RECORD_VCN_SIZE
+    } else {
+        cluster_size
+    }
+}
+
 /// Structure of an $INDEX_ALLOCATION attribute.
 ///
 /// This attribute describes the sub-nodes of a B-tree.
@@ -38,6 +53,8 @@ impl<'n, 'f> NtfsIndexAllocation<'n, 'f> {
     /// Returns the [`NtfsIndexRecord`] located at the given Virtual Cluster Number (VCN).
     ///
     /// The record is fully read, fixed up, and validated.
+    /// If `index_record_size` is smaller than the cluster size, the VCN is counted in 512-byte units
+    /// instead of clusters.
     ///
     /// This function is usually called on the return value of [`NtfsIndexEntry::subnode_vcn`] to move further
     /// down in the B-tree.
@@ -54,7 +71,11 @@ impl<'n, 'f> NtfsIndexAllocation<'n, 'f> {
     {
         // Seek to the byte offset of the given VCN.
         let mut value = self.value.clone();
-        let offset = vcn.offset(self.ntfs)?;
+        let vcn_size = index_vcn_size(self.ntfs.cluster_size(), index_record_size);
+        let offset = vcn
+            .value()
+            .checked_mul(vcn_size as i64)
+            .ok_or(NtfsError::VcnTooBig { vcn })?;
         value.seek(fs, SeekFrom::Current(offset))?;
 
         if value.stream_position() >= value.len() {
@@ -65,7 +86,7 @@ impl<'n, 'f> NtfsIndexAllocation<'n, 'f> {
         }
 
         // Get the record.
-        let record = NtfsIndexRecord::new(fs, value, index_record_size)?;
+        let record = NtfsIndexRecord::new(self.ntfs, fs, value, index_record_size)?;
 
         // Validate that the VCN in the record is the requested one.
         if record.vcn() != vcn {
@@ -148,6 +169,7 @@ impl<'n, 'f> NtfsIndexRecords<'n, 'f> {
 
         // Get the current record.
         let record = iter_try!(NtfsIndexRecord::new(
+            self.index_allocation.ntfs,
             fs,
             self.index_allocation.value.clone(),
             self.index_record_size
@@ -205,3 +227,19 @@ where
 }
 
 impl<'n, 'f, 'a, T> FusedIterator for NtfsIndexRecordsAttached<'n, 'f, 'a, T> where T: Read + Seek {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_index_vcn_size() {
+        // Index Records spanning one or more clusters are addressed by cluster.
+        assert_eq!(index_vcn_size(512, 4096), 512);
+        assert_eq!(index_vcn_size(4096, 4096), 4096);
+
+        // Index Records within large clusters are addressed in 512-byte units.
+        assert_eq!(index_vcn_size(65536, 4096), 512);
+        assert_eq!(index_vcn_size(2097152, 4096), 512);
+    }
+}
@@ -1,14 +1,17 @@
 // Copyright 2021-2023 Colin Finck <colin@reactos.org>
 // SPDX-License-Identifier: MIT OR Apache-2.0
 
+use core::fmt;
 use core::ops::Range;
 
 use binrw::io::{Read, Seek};
+use bitflags::bitflags;
 use byteorder::{ByteOrder, LittleEndian};
 use memoffset::offset_of;
 
 use crate::attribute::NtfsAttributeType;
 use crate::attribute_value::{NtfsAttributeValue, NtfsResidentAttributeValue};
+use crate::collation::NtfsCollationRule;
 use crate::error::{NtfsError, Result};
 use crate::index_entry::{IndexNodeEntryRanges, NtfsIndexNodeEntries};
 use crate::index_record::{IndexNodeHeader, INDEX_NODE_HEADER_SIZE};
@@ -49,7 +52,32 @@ pub struct NtfsIndexRoot<'f> {
     position: NtfsPosition,
 }
 
-const LARGE_INDEX_FLAG: u8 = 0x01;
+bitflags! {
+    /// Flags returned by [`NtfsIndexRoot::flags`].
+    #[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+    pub struct NtfsIndexRootFlags: u8 {
+        /// The index is too large for the Index Root and has its sub-nodes in an Index Allocation.
+        const LARGE_INDEX = 0x01;
+    }
+}
+
+impl fmt::Display for NtfsIndexRootFlags {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(&self.0, f)
+    }
+}
+
+/// Serializes the raw bits of these flags.
+#[cfg(feature = "serde")]
+#[cfg_attr(docsrs, doc(cfg(feature = "serde")))]
+impl serde::Serialize for NtfsIndexRootFlags {
+    fn serialize<S>(&self, serializer: S) -> core::result::Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serde::Serialize::serialize(&self.bits(), serializer)
+    }
+}
 
 impl<'f> NtfsIndexRoot<'f> {
     fn new(slice: &'f [u8], position: NtfsPosition) -> Result<Self> {
@@ -68,6 +96,30 @@ impl<'f> NtfsIndexRoot<'f> {
         Ok(index_root)
     }
 
+    /// Returns the number of clusters per Index Record, as stored in the Index Root.
+    ///
+    /// Like in the boot sector, a negative value `-n` denotes an Index Record size of `2^n` bytes
+    /// that is smaller than a cluster.
+    /// Use [`NtfsIndexRoot::index_record_size`] to get the Index Record size in bytes.
+    pub fn clusters_per_index_record(&self) -> i8 {
+        let start = offset_of!(IndexRootHeader, clusters_per_index_record);
+        self.slice[start] as i8
+    }
+
+    /// Returns the [`NtfsCollationRule`] that defines the sort order of the keys of this index.
+    pub fn collation_rule(&self) -> Result<NtfsCollationRule> {
+        let collation_rule = self.collation_rule_value();
+        NtfsCollationRule::n(collation_rule).ok_or(NtfsError::UnsupportedCollationRule {
+            position: self.position,
+            actual: collation_rule,
+        })
+    }
+
+    pub(crate) fn collation_rule_value(&self) -> u32 {
+        let start = offset_of!(IndexRootHeader, collation_rule);
+        LittleEndian::read_u32(&self.slice[start..])
+    }
+
     /// Returns an iterator over all top-level nodes of the B-tree.
     pub fn entries<E>(&self) -> Result<NtfsIndexNodeEntries<'f, E>>
     where
@@ -79,7 +131,7 @@ impl<'f> NtfsIndexRoot<'f> {
         Ok(NtfsIndexNodeEntries::new(slice, position))
     }
 
-    fn entries_range_and_position(&self) -> (Range<usize>, NtfsPosition) {
+    pub(crate) fn entries_range_and_position(&self) -> (Range<usize>, NtfsPosition) {
         let start = INDEX_ROOT_HEADER_SIZE + self.index_entries_offset() as usize;
         let end = INDEX_ROOT_HEADER_SIZE + self.index_data_size() as usize;
         let position = self.position + start;
@@ -98,6 +150,12 @@ impl<'f> NtfsIndexRoot<'f> {
         IndexNodeEntryRanges::new(entries_data, range, position)
     }
 
+    /// Returns flags set for the top-level node of the B-tree as specified by [`NtfsIndexRootFlags`].
+    pub fn flags(&self) -> NtfsIndexRootFlags {
+        let start = INDEX_ROOT_HEADER_SIZE + offset_of!(IndexNodeHeader, flags);
+        NtfsIndexRootFlags::from_bits_truncate(self.slice[start])
+    }
+
     /// Returns the allocated size of this NTFS Index Root, in bytes.
     pub fn index_allocated_size(&self) -> u32 {
         let start = INDEX_ROOT_HEADER_SIZE + offset_of!(IndexNodeHeader, allocated_size);
@@ -125,8 +183,7 @@ impl<'f> NtfsIndexRoot<'f> {
     /// to need an extra Index Allocation attribute.
     /// Otherwise, the entire index information is stored in this Index Root.
     pub fn is_large_index(&self) -> bool {
-        let start = INDEX_ROOT_HEADER_SIZE + offset_of!(IndexNodeHeader, flags);
-        (self.slice[start] & LARGE_INDEX_FLAG) != 0
+        self.flags().contains(NtfsIndexRootFlags::LARGE_INDEX)
     }
 
     /// Returns the absolute position of this Index Root within the filesystem, in bytes.
@@ -8,6 +8,7 @@ mod file_name;
 mod index_allocation;
 mod index_root;
 mod object_id;
+mod reparse_point;
 mod standard_information;
 mod volume_information;
 mod volume_name;
@@ -19,6 +20,7 @@ pub use file_name::*;
 pub use index_allocation::*;
 pub use index_root::*;
 pub use object_id::*;
+pub use reparse_point::*;
 pub use standard_information::*;
 pub use volume_information::*;
 pub use volume_name::*;
@@ -81,6 +83,18 @@ impl fmt::Display for NtfsFileAttributeFlags {
     }
 }
 
+/// Serializes the raw bits of these flags.
+#[cfg(feature = "serde")]
+#[cfg_attr(docsrs, doc(cfg(feature = "serde")))]
+impl serde::Serialize for NtfsFileAttributeFlags {
+    fn serialize<S>(&self, serializer: S) -> core::result::Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serde::Serialize::serialize(&self.bits(), serializer)
+    }
+}
+
 /// Trait implemented by every NTFS attribute structured value.
 pub trait NtfsStructuredValue<'n, 'f>: Sized {
     const TY: NtfsAttributeType;
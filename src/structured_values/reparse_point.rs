@@ -0,0 +1,354 @@
+// Copyright 2021-2023 Colin Finck <colin@reactos.org>
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+use core::fmt;
+use core::mem;
+use core::str;
+
+use alloc::vec;
+use alloc::vec::Vec;
+use binrw::io::{Cursor, Read, Seek};
+use binrw::BinReaderExt;
+use byteorder::{ByteOrder, LittleEndian};
+
+use crate::attribute::NtfsAttributeType;
+use crate::attribute_value::{NtfsAttributeValue, NtfsResidentAttributeValue};
+use crate::error::{NtfsError, Result};
+use crate::guid::{NtfsGuid, GUID_SIZE};
+use crate::structured_values::{
+    NtfsStructuredValue, NtfsStructuredValueFromResidentAttributeValue,
+};
+use crate::types::NtfsPosition;
+
+/// Size of all fixed fields of a reparse point (tag, data length, reserved).
+const REPARSE_POINT_HEADER_SIZE: usize = 8;
+
+/// Windows refuses to handle reparse points larger than 16 KiB.
+const REPARSE_POINT_MAX_SIZE: usize = 16 * 1024;
+
+/// The only version of WSL symlink reparse data that stores the target in the reparse point itself.
+const LX_SYMLINK_VERSION: u32 = 2;
+
+/// Tag of a reparse point, which identifies the driver responsible for its data.
+///
+/// Returned by [`NtfsReparsePoint::tag`].
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct NtfsReparseTag(u32);
+
+impl NtfsReparseTag {
+    /// Mount point or directory junction (`IO_REPARSE_TAG_MOUNT_POINT`).
+    pub const MOUNT_POINT: Self = Self(0xA000_0003);
+    /// Windows symbolic link (`IO_REPARSE_TAG_SYMLINK`).
+    pub const SYMLINK: Self = Self(0xA000_000C);
+    /// Symbolic link created by the Windows Subsystem for Linux (`IO_REPARSE_TAG_LX_SYMLINK`).
+    pub const LX_SYMLINK: Self = Self(0xA000_001D);
+
+    /// Returns whether this tag has been assigned by Microsoft.
+    ///
+    /// Reparse points with other tags additionally store a GUID (see [`NtfsReparsePoint::guid`]).
+    pub fn is_microsoft(&self) -> bool {
+        self.0 & 0x8000_0000 != 0
+    }
+
+    /// Returns whether this reparse point redirects to another file or directory (like a symlink does).
+    pub fn is_name_surrogate(&self) -> bool {
+        self.0 & 0x2000_0000 != 0
+    }
+
+    /// Returns the tag as a plain number.
+    pub fn value(&self) -> u32 {
+        self.0
+    }
+}
+
+impl fmt::Display for NtfsReparseTag {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:#010x}", self.0)
+    }
+}
+
+impl From<u32> for NtfsReparseTag {
+    fn from(value: u32) -> Self {
+        Self(value)
+    }
+}
+
+#[cfg(feature = "serde")]
+#[cfg_attr(docsrs, doc(cfg(feature = "serde")))]
+impl serde::Serialize for NtfsReparseTag {
+    fn serialize<S>(&self, serializer: S) -> core::result::Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.serialize_newtype_struct("NtfsReparseTag", &self.0)
+    }
+}
+
+/// Structure of a $REPARSE_POINT attribute.
+///
+/// This optional attribute turns a file or directory into a reparse point, whose data is
+/// interpreted by the driver identified by the [`NtfsReparseTag`].
+/// Reparse points implement symlinks, directory junctions, and similar features.
+///
+/// Apart from reading existing reparse points, this structure can be used to create the
+/// $REPARSE_POINT attribute value of a WSL symlink via [`NtfsReparsePoint::new_lx_symlink`].
+///
+/// A $REPARSE_POINT attribute may be resident or non-resident.
+///
+/// Reference: <https://flatcap.github.io/linux-ntfs/ntfs/attributes/reparse_point.html>
+#[derive(Clone, Debug)]
+pub struct NtfsReparsePoint {
+    tag: NtfsReparseTag,
+    guid: Option<NtfsGuid>,
+    data: Vec<u8>,
+    position: NtfsPosition,
+}
+
+impl NtfsReparsePoint {
+    fn new<T>(r: &mut T, position: NtfsPosition, value_length: u64) -> Result<Self>
+    where
+        T: Read + Seek,
+    {
+        if value_length < REPARSE_POINT_HEADER_SIZE as u64 {
+            return Err(NtfsError::InvalidStructuredValueSize {
+                position,
+                ty: NtfsAttributeType::ReparsePoint,
+                expected: REPARSE_POINT_HEADER_SIZE as u64,
+                actual: value_length,
+            });
+        }
+
+        if value_length > REPARSE_POINT_MAX_SIZE as u64 {
+            return Err(NtfsError::InvalidStructuredValueSize {
+                position,
+                ty: NtfsAttributeType::ReparsePoint,
+                expected: REPARSE_POINT_MAX_SIZE as u64,
+                actual: value_length,
+            });
+        }
+
+        let tag = NtfsReparseTag(r.read_le::<u32>()?);
+        let data_length = r.read_le::<u16>()?;
+        let _reserved = r.read_le::<u16>()?;
+
+        let mut header_size = REPARSE_POINT_HEADER_SIZE;
+        let mut guid = None;
+        if !tag.is_microsoft() {
+            header_size += GUID_SIZE;
+
+            if value_length >= header_size as u64 {
+                guid = Some(r.read_le::<NtfsGuid>()?);
+            }
+        }
+
+        let expected_length = header_size as u64 + data_length as u64;
+        if value_length < expected_length {
+            return Err(NtfsError::InvalidStructuredValueSize {
+                position,
+                ty: NtfsAttributeType::ReparsePoint,
+                expected: expected_length,
+                actual: value_length,
+            });
+        }
+
+        let mut data = vec![0u8; data_length as usize];
+        r.read_exact(&mut data)?;
+
+        Ok(Self {
+            tag,
+            guid,
+            data,
+            position,
+        })
+    }
+
+    /// Creates the reparse point of a WSL symlink pointing to `target`.
+    ///
+    /// The target is stored as-is in UTF-8, just like WSL does.
+    /// Use [`NtfsReparsePoint::to_bytes`] to get the value of the $REPARSE_POINT attribute to write.
+    /// Note that the file additionally needs [`NtfsFileAttributeFlags::REPARSE_POINT`] set.
+    ///
+    /// Returns [`NtfsError::ReparseDataTooBig`] if the target is too long for a reparse point.
+    ///
+    /// [`NtfsFileAttributeFlags::REPARSE_POINT`]: crate::structured_values::NtfsFileAttributeFlags::REPARSE_POINT
+    pub fn new_lx_symlink(target: &str) -> Result<Self> {
+        let max = REPARSE_POINT_MAX_SIZE - REPARSE_POINT_HEADER_SIZE;
+        let actual = mem::size_of::<u32>() + target.len();
+        if actual > max {
+            return Err(NtfsError::ReparseDataTooBig { actual, max });
+        }
+
+        let mut data = Vec::with_capacity(actual);
+        data.extend_from_slice(&LX_SYMLINK_VERSION.to_le_bytes());
+        data.extend_from_slice(target.as_bytes());
+
+        Ok(Self {
+            tag: NtfsReparseTag::LX_SYMLINK,
+            guid: None,
+            data,
+            position: NtfsPosition::none(),
+        })
+    }
+
+    /// Returns the tag-specific data of this reparse point.
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    /// Returns the GUID of this reparse point, which is only stored for tags not assigned by Microsoft
+    /// (see [`NtfsReparseTag::is_microsoft`]).
+    pub fn guid(&self) -> Option<&NtfsGuid> {
+        self.guid.as_ref()
+    }
+
+    /// Returns the target of a WSL symlink, or `None` if this reparse point is not a WSL symlink.
+    ///
+    /// Returns [`NtfsError::InvalidLxSymlinkData`] if the data has an unknown version or the target is
+    /// not valid UTF-8.
+    pub fn lx_symlink_target(&self) -> Option<Result<&str>> {
+        if self.tag != NtfsReparseTag::LX_SYMLINK {
+            return None;
+        }
+
+        let version_size = mem::size_of::<u32>();
+        if self.data.len() < version_size
+            || LittleEndian::read_u32(&self.data) != LX_SYMLINK_VERSION
+        {
+            return Some(Err(NtfsError::InvalidLxSymlinkData {
+                position: self.position,
+            }));
+        }
+
+        let target = str::from_utf8(&self.data[version_size..]).map_err(|_| {
+            NtfsError::InvalidLxSymlinkData {
+                position: self.position,
+            }
+        });
+        Some(target)
+    }
+
+    /// Returns the absolute position of this $REPARSE_POINT attribute value within the filesystem, in bytes.
+    ///
+    /// The returned [`NtfsPosition`] has no value for a reparse point created via [`NtfsReparsePoint::new_lx_symlink`].
+    pub fn position(&self) -> NtfsPosition {
+        self.position
+    }
+
+    /// Returns the [`NtfsReparseTag`] of this reparse point.
+    pub fn tag(&self) -> NtfsReparseTag {
+        self.tag
+    }
+
+    /// Serializes this reparse point into the on-disk format of a $REPARSE_POINT attribute value.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let guid_size = if self.guid.is_some() { GUID_SIZE } else { 0 };
+        let mut bytes = Vec::with_capacity(REPARSE_POINT_HEADER_SIZE + guid_size + self.data.len());
+
+        bytes.extend_from_slice(&self.tag.value().to_le_bytes());
+        bytes.extend_from_slice(&(self.data.len() as u16).to_le_bytes());
+        bytes.extend_from_slice(&0u16.to_le_bytes());
+
+        if let Some(guid) = &self.guid {
+            bytes.extend_from_slice(&guid.data1.to_le_bytes());
+            bytes.extend_from_slice(&guid.data2.to_le_bytes());
+            bytes.extend_from_slice(&guid.data3.to_le_bytes());
+            bytes.extend_from_slice(&guid.data4);
+        }
+
+        bytes.extend_from_slice(&self.data);
+        bytes
+    }
+}
+
+impl<'n, 'f> NtfsStructuredValue<'n, 'f> for NtfsReparsePoint {
+    const TY: NtfsAttributeType = NtfsAttributeType::ReparsePoint;
+
+    fn from_attribute_value<T>(fs: &mut T, value: NtfsAttributeValue<'n, 'f>) -> Result<Self>
+    where
+        T: Read + Seek,
+    {
+        let position = value.data_position();
+        let value_length = value.len();
+
+        let mut value_attached = value.attach(fs);
+        Self::new(&mut value_attached, position, value_length)
+    }
+}
+
+impl<'n, 'f> NtfsStructuredValueFromResidentAttributeValue<'n, 'f> for NtfsReparsePoint {
+    fn from_resident_attribute_value(value: NtfsResidentAttributeValue<'f>) -> Result<Self> {
+        let position = value.data_position();
+        let value_length = value.len();
+
+        let mut cursor = Cursor::new(value.data());
+        Self::new(&mut cursor, position, value_length)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lx_symlink() {
+        let reparse_point = NtfsReparsePoint::new_lx_symlink("../lib/libfoo.so.1").unwrap();
+        let bytes = reparse_point.to_bytes();
+        assert_eq!(&bytes[..8], &[0x1d, 0x00, 0x00, 0xa0, 22, 0, 0, 0]);
+        assert_eq!(&bytes[8..12], &[2, 0, 0, 0]);
+        assert_eq!(&bytes[12..], b"../lib/libfoo.so.1");
+
+        // Parse the serialized reparse point again.
+        let position = NtfsPosition::new(0x1000);
+        let reparse_point =
+            NtfsReparsePoint::new(&mut Cursor::new(&bytes), position, bytes.len() as u64).unwrap();
+        assert_eq!(reparse_point.tag(), NtfsReparseTag::LX_SYMLINK);
+        assert!(reparse_point.tag().is_microsoft());
+        assert!(reparse_point.guid().is_none());
+        assert_eq!(
+            reparse_point.lx_symlink_target().unwrap().unwrap(),
+            "../lib/libfoo.so.1"
+        );
+
+        // An unknown version is rejected.
+        let mut bytes = bytes;
+        bytes[8] = 1;
+        let reparse_point =
+            NtfsReparsePoint::new(&mut Cursor::new(&bytes), position, bytes.len() as u64).unwrap();
+        assert!(matches!(
+            reparse_point.lx_symlink_target(),
+            Some(Err(NtfsError::InvalidLxSymlinkData { .. }))
+        ));
+
+        // The target must fit into the maximum reparse point size.
+        let target = "a".repeat(REPARSE_POINT_MAX_SIZE);
+        assert!(matches!(
+            NtfsReparsePoint::new_lx_symlink(&target),
+            Err(NtfsError::ReparseDataTooBig { .. })
+        ));
+    }
+
+    #[test]
+    fn test_reparse_point_with_guid() {
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(&0x0000_1234u32.to_le_bytes());
+        bytes.extend_from_slice(&3u16.to_le_bytes());
+        bytes.extend_from_slice(&0u16.to_le_bytes());
+        bytes.extend_from_slice(&[0x11; GUID_SIZE]);
+        bytes.extend_from_slice(b"abc");
+
+        let position = NtfsPosition::new(0x1000);
+        let reparse_point =
+            NtfsReparsePoint::new(&mut Cursor::new(&bytes), position, bytes.len() as u64).unwrap();
+        assert!(!reparse_point.tag().is_microsoft());
+        assert_eq!(reparse_point.guid().unwrap().data1, 0x1111_1111);
+        assert_eq!(reparse_point.data(), b"abc");
+        assert!(reparse_point.lx_symlink_target().is_none());
+        assert_eq!(reparse_point.to_bytes(), bytes);
+
+        // The data length must not exceed the attribute value.
+        assert!(matches!(
+            NtfsReparsePoint::new(&mut Cursor::new(&bytes), position, bytes.len() as u64 - 1),
+            Err(NtfsError::InvalidStructuredValueSize { .. })
+        ));
+    }
+}
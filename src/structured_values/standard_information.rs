@@ -3,6 +3,7 @@
 
 use binrw::io::{Cursor, Read, Seek};
 use binrw::{BinRead, BinReaderExt};
+use byteorder::{ByteOrder, LittleEndian};
 
 use crate::attribute::NtfsAttributeType;
 use crate::attribute_value::{NtfsAttributeValue, NtfsResidentAttributeValue};
@@ -13,12 +14,18 @@ use crate::structured_values::{
 use crate::time::NtfsTime;
 use crate::types::NtfsPosition;
 
+#[cfg(feature = "serde")]
+use serde::ser::{Serialize, SerializeStruct, Serializer};
+
 /// Size of all [`StandardInformationData`] fields plus some reserved bytes.
 const STANDARD_INFORMATION_SIZE_NTFS1: usize = 48;
 
 /// Size of all [`StandardInformationData`] plus [`StandardInformationDataNtfs3`] fields.
 const STANDARD_INFORMATION_SIZE_NTFS3: usize = 72;
 
+/// Bit of the [`StandardInformationDataNtfs3::version`] field that marks a directory as case-sensitive.
+const CASE_SENSITIVE_DIRECTORY_FLAG: u32 = 0x1;
+
 #[derive(BinRead, Clone, Debug)]
 struct StandardInformationDataNtfs1 {
     creation_time: NtfsTime,
@@ -101,6 +108,19 @@ impl NtfsStandardInformation {
         NtfsFileAttributeFlags::from_bits_truncate(self.ntfs1_data.file_attributes)
     }
 
+    /// Returns `true` if this is the $STANDARD_INFORMATION of a directory marked case-sensitive.
+    ///
+    /// Since Windows 10, a directory can be marked case-sensitive (e.g. via `fsutil file setCaseSensitiveInfo`
+    /// or by WSL).
+    /// This is indicated by bit 0x1 of the field that older NTFS versions used for the version number
+    /// (see [`NtfsStandardInformation::version`]).
+    /// NTFS 1.x file information never marks a directory case-sensitive.
+    pub fn is_case_sensitive_directory(&self) -> bool {
+        self.version().map_or(false, |version| {
+            version & CASE_SENSITIVE_DIRECTORY_FLAG != 0
+        })
+    }
+
     /// Returns the maximum allowed versions for this file, if stored via NTFS 3.x file information.
     ///
     /// A value of zero means that versioning is disabled for this file.
@@ -133,6 +153,83 @@ impl NtfsStandardInformation {
         self.ntfs3_data.as_ref().map(|x| x.security_id)
     }
 
+    /// Sets the time this file was last accessed.
+    ///
+    /// Use [`NtfsFile::write_standard_information`] to write the change back to the filesystem.
+    ///
+    /// [`NtfsFile::write_standard_information`]: crate::NtfsFile::write_standard_information
+    pub fn set_access_time(&mut self, time: NtfsTime) {
+        self.ntfs1_data.access_time = time;
+    }
+
+    /// Sets the time this file was created.
+    ///
+    /// Use [`NtfsFile::write_standard_information`] to write the change back to the filesystem.
+    ///
+    /// [`NtfsFile::write_standard_information`]: crate::NtfsFile::write_standard_information
+    pub fn set_creation_time(&mut self, time: NtfsTime) {
+        self.ntfs1_data.creation_time = time;
+    }
+
+    /// Sets the "File Attributes" of this file (Read-Only, Hidden, System, Archive, etc.).
+    ///
+    /// Use [`NtfsFile::write_standard_information`] to write the change back to the filesystem.
+    ///
+    /// [`NtfsFile::write_standard_information`]: crate::NtfsFile::write_standard_information
+    pub fn set_file_attributes(&mut self, file_attributes: NtfsFileAttributeFlags) {
+        self.ntfs1_data.file_attributes = file_attributes.bits();
+    }
+
+    /// Sets the time the MFT record of this file was last modified.
+    ///
+    /// Use [`NtfsFile::write_standard_information`] to write the change back to the filesystem.
+    ///
+    /// [`NtfsFile::write_standard_information`]: crate::NtfsFile::write_standard_information
+    pub fn set_mft_record_modification_time(&mut self, time: NtfsTime) {
+        self.ntfs1_data.mft_record_modification_time = time;
+    }
+
+    /// Sets the time this file was last modified.
+    ///
+    /// Use [`NtfsFile::write_standard_information`] to write the change back to the filesystem.
+    ///
+    /// [`NtfsFile::write_standard_information`]: crate::NtfsFile::write_standard_information
+    pub fn set_modification_time(&mut self, time: NtfsTime) {
+        self.ntfs1_data.modification_time = time;
+    }
+
+    /// Writes the four file times and the "File Attributes" into the given $STANDARD_INFORMATION
+    /// attribute value data, leaving all other fields untouched.
+    pub(crate) fn write_times_and_attributes(
+        &self,
+        data: &mut [u8],
+        position: NtfsPosition,
+    ) -> Result<()> {
+        if data.len() < STANDARD_INFORMATION_SIZE_NTFS1 {
+            return Err(NtfsError::InvalidStructuredValueSize {
+                position,
+                ty: NtfsAttributeType::StandardInformation,
+                expected: STANDARD_INFORMATION_SIZE_NTFS1 as u64,
+                actual: data.len() as u64,
+            });
+        }
+
+        let times = [
+            self.ntfs1_data.creation_time,
+            self.ntfs1_data.modification_time,
+            self.ntfs1_data.mft_record_modification_time,
+            self.ntfs1_data.access_time,
+        ];
+
+        for (chunk, time) in data.chunks_exact_mut(8).zip(times) {
+            LittleEndian::write_u64(chunk, time.nt_timestamp());
+        }
+
+        LittleEndian::write_u32(&mut data[32..], self.ntfs1_data.file_attributes);
+
+        Ok(())
+    }
+
     /// Returns the Update Sequence Number (USN) of the file, if stored via NTFS 3.x file information.
     pub fn usn(&self) -> Option<u64> {
         self.ntfs3_data.as_ref().map(|x| x.usn)
@@ -171,6 +268,34 @@ impl<'n, 'f> NtfsStructuredValueFromResidentAttributeValue<'n, 'f> for NtfsStand
     }
 }
 
+/// Fields only available in NTFS 3.x are serialized as `None` for an NTFS 1.x $STANDARD_INFORMATION attribute.
+#[cfg(feature = "serde")]
+#[cfg_attr(docsrs, doc(cfg(feature = "serde")))]
+impl Serialize for NtfsStandardInformation {
+    fn serialize<S>(&self, serializer: S) -> core::result::Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut state = serializer.serialize_struct("NtfsStandardInformation", 12)?;
+        state.serialize_field("file_attributes", &self.file_attributes())?;
+        state.serialize_field("creation_time", &self.creation_time())?;
+        state.serialize_field("modification_time", &self.modification_time())?;
+        state.serialize_field(
+            "mft_record_modification_time",
+            &self.mft_record_modification_time(),
+        )?;
+        state.serialize_field("access_time", &self.access_time())?;
+        state.serialize_field("maximum_versions", &self.maximum_versions())?;
+        state.serialize_field("version", &self.version())?;
+        state.serialize_field("class_id", &self.class_id())?;
+        state.serialize_field("owner_id", &self.owner_id())?;
+        state.serialize_field("security_id", &self.security_id())?;
+        state.serialize_field("quota_charged", &self.quota_charged())?;
+        state.serialize_field("usn", &self.usn())?;
+        state.end()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
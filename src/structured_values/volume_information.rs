@@ -15,6 +15,9 @@ use crate::structured_values::{
 };
 use crate::types::NtfsPosition;
 
+#[cfg(feature = "serde")]
+use serde::ser::{Serialize, SerializeStruct, Serializer};
+
 /// Size of all [`VolumeInformationData`] fields.
 const VOLUME_INFORMATION_SIZE: usize = 12;
 
@@ -48,6 +51,18 @@ impl fmt::Display for NtfsVolumeFlags {
     }
 }
 
+/// Serializes the raw bits of these flags.
+#[cfg(feature = "serde")]
+#[cfg_attr(docsrs, doc(cfg(feature = "serde")))]
+impl serde::Serialize for NtfsVolumeFlags {
+    fn serialize<S>(&self, serializer: S) -> core::result::Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serde::Serialize::serialize(&self.bits(), serializer)
+    }
+}
+
 /// Structure of a $VOLUME_INFORMATION attribute.
 ///
 /// This attribute is only used by the top-level $Volume file and contains general information about the filesystem.
@@ -122,3 +137,18 @@ impl<'n, 'f> NtfsStructuredValueFromResidentAttributeValue<'n, 'f> for NtfsVolum
         Self::new(&mut cursor, position, value_length)
     }
 }
+
+#[cfg(feature = "serde")]
+#[cfg_attr(docsrs, doc(cfg(feature = "serde")))]
+impl Serialize for NtfsVolumeInformation {
+    fn serialize<S>(&self, serializer: S) -> core::result::Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut state = serializer.serialize_struct("NtfsVolumeInformation", 3)?;
+        state.serialize_field("major_version", &self.major_version())?;
+        state.serialize_field("minor_version", &self.minor_version())?;
+        state.serialize_field("flags", &self.flags())?;
+        state.end()
+    }
+}
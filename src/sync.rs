@@ -0,0 +1,643 @@
+// Copyright 2021-2023 Colin Finck <colin@reactos.org>
+// SPDX-License-Identifier: MIT OR Apache-2.0
+//
+//! In-place synchronization of the file times and data stream contents of a file onto an existing
+//! file of another NTFS volume.
+//!
+//! Start with [`NtfsFile::sync_to`] to get an [`NtfsSyncReport`].
+//!
+//! [`NtfsFile::sync_to`]: crate::NtfsFile::sync_to
+
+use core::ops::Range;
+
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+use binrw::io::{Read, Seek, SeekFrom, Write};
+use displaydoc::Display;
+
+use crate::attribute::{NtfsAttribute, NtfsAttributeFlags, NtfsAttributeItem, NtfsAttributeType};
+use crate::attribute_value::COPY_BUFFER_SIZE;
+use crate::error::Result;
+use crate::file::NtfsFile;
+use crate::progress::{report_progress, NtfsProgressSink, NtfsProgressUnit};
+use crate::structured_values::{NtfsFileName, NtfsFileNamespace};
+use crate::traits::NtfsReadSeek;
+
+/// A single part of a file that [`NtfsFile::sync_to`] could not synchronize.
+///
+/// [`NtfsFile::sync_to`]: crate::NtfsFile::sync_to
+#[derive(Clone, Debug, Display, Eq, PartialEq)]
+#[non_exhaustive]
+pub enum NtfsSyncSkip {
+    /// The destination file has no file name {name:?}
+    MissingName { name: String },
+    /// The destination file has no data stream {name:?}
+    MissingStream { name: String },
+    /// The data stream {name:?} has {expected} bytes, but {actual} bytes on the destination file
+    StreamSizeMismatch {
+        name: String,
+        expected: u64,
+        actual: u64,
+    },
+    /// The data stream {name:?} is compressed, encrypted, sparse, or not fully initialized on the destination file
+    StreamNotWritable { name: String },
+}
+
+/// Result of synchronizing a file via [`NtfsFile::sync_to`].
+///
+/// Parts of the file that cannot be synchronized in place don't stop the synchronization, but are
+/// collected as [`NtfsSyncSkip`]s.
+///
+/// [`NtfsFile::sync_to`]: crate::NtfsFile::sync_to
+#[derive(Debug)]
+pub struct NtfsSyncReport {
+    bytes_copied: u64,
+    skipped: Vec<NtfsSyncSkip>,
+    standard_information_copied: bool,
+    streams_copied: u64,
+}
+
+impl NtfsSyncReport {
+    pub(crate) fn new<S, T, P>(
+        source: &NtfsFile,
+        src_fs: &mut S,
+        destination: &mut NtfsFile,
+        dst_fs: &mut T,
+        progress: &mut P,
+    ) -> Result<Self>
+    where
+        S: Read + Seek,
+        T: Read + Seek + Write,
+        P: NtfsProgressSink + ?Sized,
+    {
+        let mut report = Self {
+            bytes_copied: 0,
+            skipped: Vec::new(),
+            standard_information_copied: false,
+            streams_copied: 0,
+        };
+
+        report.check_names(source, src_fs, destination, dst_fs)?;
+
+        // Find out where every data stream goes before writing anything, so that no stream is left
+        // half-written because of a destination that turns out to be unsuitable.
+        let mut streams = Vec::new();
+        let mut total = 0;
+
+        {
+            let destination_streams = data_streams(destination, dst_fs)?;
+            let cluster_size = destination.ntfs().cluster_size() as u64;
+
+            for (item, name) in data_streams(source, src_fs)? {
+                let attribute = item.to_attribute()?;
+                let length = attribute.value_length();
+
+                if let Some(target) =
+                    report.plan_stream(&name, length, &destination_streams, cluster_size, dst_fs)?
+                {
+                    total += length;
+                    streams.push((item, target));
+                }
+            }
+        }
+
+        // Copy the file times and "File Attributes" of $STANDARD_INFORMATION, but only if everything
+        // else is synchronized as well.
+        // Otherwise, an incomplete synchronization would make the destination file look up to date.
+        if report.is_complete() {
+            let source_info = source.info()?;
+            let mut info = destination.info()?;
+            info.set_access_time(source_info.access_time());
+            info.set_creation_time(source_info.creation_time());
+            info.set_file_attributes(source_info.file_attributes());
+            info.set_mft_record_modification_time(source_info.mft_record_modification_time());
+            info.set_modification_time(source_info.modification_time());
+
+            let (range, position) = {
+                let attribute = destination.find_resident_attribute(
+                    NtfsAttributeType::StandardInformation,
+                    None,
+                    None,
+                )?;
+                (attribute.resident_value_range()?, attribute.position())
+            };
+            info.write_times_and_attributes(&mut destination.record_data_mut()[range], position)?;
+            report.standard_information_copied = true;
+        }
+
+        // Copy the data streams.
+        let mut buf = vec![0u8; COPY_BUFFER_SIZE];
+
+        for (item, target) in streams {
+            let attribute = item.to_attribute()?;
+            let mut value = attribute.value(src_fs)?;
+
+            match target {
+                StreamTarget::Resident {
+                    file_record_number,
+                    range,
+                } if file_record_number == destination.file_record_number() => {
+                    value.read_exact(src_fs, &mut destination.record_data_mut()[range.clone()])?;
+                    report.bytes_copied += range.len() as u64;
+                }
+                StreamTarget::Resident {
+                    file_record_number,
+                    range,
+                } => {
+                    // The stream is stored in an extension File Record, which has to be written on its own.
+                    let mut extension = destination.ntfs().file(dst_fs, file_record_number)?;
+                    value.read_exact(src_fs, &mut extension.record_data_mut()[range.clone()])?;
+                    extension.write_record(dst_fs)?;
+                    report.bytes_copied += range.len() as u64;
+                }
+                StreamTarget::NonResident(extents) => {
+                    for (position, length) in extents {
+                        let mut extent_copied = 0;
+
+                        while extent_copied < length {
+                            report_progress(
+                                progress,
+                                NtfsProgressUnit::Bytes,
+                                report.bytes_copied,
+                                Some(total),
+                            )?;
+
+                            let chunk_length =
+                                (length - extent_copied).min(COPY_BUFFER_SIZE as u64) as usize;
+                            value.read_exact(src_fs, &mut buf[..chunk_length])?;
+
+                            dst_fs.seek(SeekFrom::Start(position + extent_copied))?;
+                            dst_fs.write_all(&buf[..chunk_length])?;
+
+                            extent_copied += chunk_length as u64;
+                            report.bytes_copied += chunk_length as u64;
+                        }
+                    }
+                }
+            }
+
+            report.streams_copied += 1;
+        }
+
+        report_progress(
+            progress,
+            NtfsProgressUnit::Bytes,
+            report.bytes_copied,
+            Some(total),
+        )?;
+
+        // $STANDARD_INFORMATION and the resident streams of the base File Record are written back
+        // with it.
+        destination.write_record(dst_fs)?;
+
+        Ok(report)
+    }
+
+    /// Returns the number of data stream bytes that have been copied.
+    pub fn bytes_copied(&self) -> u64 {
+        self.bytes_copied
+    }
+
+    fn check_names<S, T>(
+        &mut self,
+        source: &NtfsFile,
+        src_fs: &mut S,
+        destination: &NtfsFile,
+        dst_fs: &mut T,
+    ) -> Result<()>
+    where
+        S: Read + Seek,
+        T: Read + Seek,
+    {
+        let destination_names = file_names(destination, dst_fs)?;
+
+        for name in file_names(source, src_fs)? {
+            if !destination_names
+                .iter()
+                .any(|destination_name| destination_name.name() == name.name())
+            {
+                self.skipped.push(NtfsSyncSkip::MissingName {
+                    name: name.name().to_string_lossy(),
+                });
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Returns whether all names and data streams of the source file have been synchronized.
+    pub fn is_complete(&self) -> bool {
+        self.skipped.is_empty()
+    }
+
+    /// Finds the matching stream among the `destination_streams` and returns where its data goes,
+    /// or `None` if it cannot be written in place.
+    fn plan_stream<T>(
+        &mut self,
+        name: &str,
+        length: u64,
+        destination_streams: &[(NtfsAttributeItem, String)],
+        cluster_size: u64,
+        dst_fs: &mut T,
+    ) -> Result<Option<StreamTarget>>
+    where
+        T: Read + Seek,
+    {
+        let item = match destination_streams
+            .iter()
+            .find(|(_, destination_name)| destination_name == name)
+        {
+            Some((item, _)) => item,
+            None => {
+                self.skipped.push(NtfsSyncSkip::MissingStream {
+                    name: String::from(name),
+                });
+                return Ok(None);
+            }
+        };
+
+        let attribute = item.to_attribute()?;
+        if attribute.value_length() != length {
+            self.skipped.push(NtfsSyncSkip::StreamSizeMismatch {
+                name: String::from(name),
+                expected: length,
+                actual: attribute.value_length(),
+            });
+            return Ok(None);
+        }
+
+        let target = if attribute.is_resident() {
+            // `data_streams` follows the $ATTRIBUTE_LIST, so the attribute may be stored in an
+            // extension File Record.
+            Some(StreamTarget::Resident {
+                file_record_number: attribute.file().file_record_number(),
+                range: attribute.resident_value_range()?,
+            })
+        } else {
+            non_resident_extents(&attribute, cluster_size, dst_fs)?.map(StreamTarget::NonResident)
+        };
+
+        if target.is_none() {
+            self.skipped.push(NtfsSyncSkip::StreamNotWritable {
+                name: String::from(name),
+            });
+        }
+
+        Ok(target)
+    }
+
+    /// Returns all names and data streams that could not be synchronized, in the order they were encountered.
+    pub fn skipped(&self) -> &[NtfsSyncSkip] {
+        &self.skipped
+    }
+
+    /// Returns whether the file times and "File Attributes" of $STANDARD_INFORMATION have been copied.
+    ///
+    /// This is only done if the synchronization [`is_complete`](Self::is_complete).
+    pub fn standard_information_copied(&self) -> bool {
+        self.standard_information_copied
+    }
+
+    /// Returns the number of data streams that have been copied.
+    pub fn streams_copied(&self) -> u64 {
+        self.streams_copied
+    }
+}
+
+/// Where the value of a destination data stream is stored.
+enum StreamTarget {
+    /// Byte range within the data of the base or extension File Record storing the attribute.
+    Resident {
+        file_record_number: u64,
+        range: Range<usize>,
+    },
+    /// Absolute filesystem positions and lengths of the contiguous extents, in bytes.
+    NonResident(Vec<(u64, u64)>),
+}
+
+/// Returns all $DATA attributes of `file` along with their names.
+fn data_streams<'n, 'f, T>(
+    file: &'f NtfsFile<'n>,
+    fs: &mut T,
+) -> Result<Vec<(NtfsAttributeItem<'n, 'f>, String)>>
+where
+    T: Read + Seek,
+{
+    let mut streams = Vec::new();
+    let mut attributes = file.attributes();
+
+    while let Some(item) = attributes.next(fs) {
+        let item = item?;
+        let attribute = item.to_attribute()?;
+
+        if attribute.is_ty(NtfsAttributeType::Data)? {
+            let name = attribute.name()?.to_string_lossy();
+            streams.push((item, name));
+        }
+    }
+
+    Ok(streams)
+}
+
+/// Returns all names of `file`, except for MS-DOS names (which are derived from the long names).
+fn file_names<T>(file: &NtfsFile, fs: &mut T) -> Result<Vec<NtfsFileName>>
+where
+    T: Read + Seek,
+{
+    let mut names = Vec::new();
+    let mut iter = file.names();
+
+    while let Some(name) = iter.next(fs) {
+        let name = name?;
+
+        if name.namespace() != Some(NtfsFileNamespace::Dos) {
+            names.push(name);
+        }
+    }
+
+    Ok(names)
+}
+
+/// Returns the contiguous extents of the non-resident value of `attribute` on the filesystem,
+/// or `None` if the value cannot be overwritten in place.
+fn non_resident_extents<T>(
+    attribute: &NtfsAttribute,
+    cluster_size: u64,
+    fs: &mut T,
+) -> Result<Option<Vec<(u64, u64)>>>
+where
+    T: Read + Seek,
+{
+    let flags = attribute.flags();
+    if flags.intersects(
+        NtfsAttributeFlags::COMPRESSED | NtfsAttributeFlags::ENCRYPTED | NtfsAttributeFlags::SPARSE,
+    ) || attribute.non_resident_value_initialized_size() < attribute.value_length()
+    {
+        return Ok(None);
+    }
+
+    let length = attribute.value_length();
+    let mut value = attribute.value(fs)?;
+    let mut extents = Vec::<(u64, u64)>::new();
+    let mut offset = 0;
+
+    // Consecutive clusters of the value may be in different Data Runs, so look up every cluster.
+    while offset < length {
+        let chunk_length = (cluster_size - offset % cluster_size).min(length - offset);
+
+        value.seek(fs, SeekFrom::Start(offset))?;
+        let position = match value.data_position().value() {
+            Some(position) => position.get(),
+            None => return Ok(None),
+        };
+
+        match extents.last_mut() {
+            Some((last_position, last_length)) if *last_position + *last_length == position => {
+                *last_length += chunk_length;
+            }
+            _ => extents.push((position, chunk_length)),
+        }
+
+        offset += chunk_length;
+    }
+
+    Ok(Some(extents))
+}
+
+#[cfg(test)]
+mod tests {
+    use binrw::io::Cursor;
+
+    use super::*;
+    use crate::ntfs::Ntfs;
+    use crate::time::NtfsTime;
+    use crate::upcase_table::NtfsCaseSensitivity;
+
+    fn read_data(ntfs: &Ntfs, fs: &mut Cursor<Vec<u8>>, path: &str) -> Vec<u8> {
+        let file = ntfs
+            .file_by_path(fs, path, NtfsCaseSensitivity::Insensitive)
+            .unwrap()
+            .unwrap();
+        let data_item = file.data(fs, "").unwrap().unwrap();
+        let data_attribute = data_item.to_attribute().unwrap();
+        let mut data_value = data_attribute.value(fs).unwrap();
+
+        let mut data = vec![0u8; data_value.len() as usize];
+        data_value.read_exact(fs, &mut data).unwrap();
+        data
+    }
+
+    #[test]
+    fn test_sync() {
+        let mut src_fs = crate::helpers::tests::testfs1();
+        let mut src_ntfs = Ntfs::new(&mut src_fs).unwrap();
+        src_ntfs.read_upcase_table(&mut src_fs).unwrap();
+
+        let mut dst_fs = crate::helpers::tests::testfs1();
+        let mut dst_ntfs = Ntfs::new(&mut dst_fs).unwrap();
+        dst_ntfs.read_upcase_table(&mut dst_fs).unwrap();
+
+        // Scramble the destination file, then synchronize the original onto it.
+        let mut destination = dst_ntfs
+            .file_by_path(
+                &mut dst_fs,
+                "1000-bytes-file",
+                NtfsCaseSensitivity::Insensitive,
+            )
+            .unwrap()
+            .unwrap();
+        let mut info = destination.info().unwrap();
+        info.set_modification_time(NtfsTime::from_unix_timestamp(0).unwrap());
+        destination
+            .write_standard_information(&mut dst_fs, &info)
+            .unwrap();
+
+        let data_position = {
+            let data_item = destination.data(&mut dst_fs, "").unwrap().unwrap();
+            let data_attribute = data_item.to_attribute().unwrap();
+            let data_value = data_attribute.value(&mut dst_fs).unwrap();
+            data_value.data_position().value().unwrap().get()
+        };
+        dst_fs.get_mut()[data_position as usize..][..1000].fill(b'x');
+        assert_eq!(
+            read_data(&dst_ntfs, &mut dst_fs, "1000-bytes-file"),
+            b"x".repeat(1000)
+        );
+
+        let source = src_ntfs
+            .file_by_path(
+                &mut src_fs,
+                "1000-bytes-file",
+                NtfsCaseSensitivity::Insensitive,
+            )
+            .unwrap()
+            .unwrap();
+        let report = source
+            .sync_to(&mut src_fs, &mut destination, &mut dst_fs)
+            .unwrap();
+        assert!(report.is_complete());
+        assert!(report.standard_information_copied());
+        assert_eq!(report.streams_copied(), 1);
+        assert_eq!(report.bytes_copied(), 1000);
+
+        assert_eq!(
+            read_data(&dst_ntfs, &mut dst_fs, "1000-bytes-file"),
+            b"12345".repeat(200)
+        );
+        let destination = dst_ntfs
+            .file_by_path(
+                &mut dst_fs,
+                "1000-bytes-file",
+                NtfsCaseSensitivity::Insensitive,
+            )
+            .unwrap()
+            .unwrap();
+        let modification_time = source.info().unwrap().modification_time();
+        assert_eq!(
+            destination.info().unwrap().modification_time(),
+            modification_time
+        );
+
+        // Synchronizing onto a different file only copies what fits, and leaves the file times alone.
+        let source = src_ntfs
+            .file_by_path(
+                &mut src_fs,
+                "file-with-12345",
+                NtfsCaseSensitivity::Insensitive,
+            )
+            .unwrap()
+            .unwrap();
+        let report = source
+            .sync_to(&mut src_fs, &mut destination.clone(), &mut dst_fs)
+            .unwrap();
+        assert!(!report.is_complete());
+        assert!(!report.standard_information_copied());
+        assert_eq!(report.streams_copied(), 0);
+        assert_eq!(
+            report.skipped(),
+            [
+                NtfsSyncSkip::MissingName {
+                    name: String::from("file-with-12345")
+                },
+                NtfsSyncSkip::StreamSizeMismatch {
+                    name: String::new(),
+                    expected: 5,
+                    actual: 1000,
+                },
+            ]
+        );
+        assert_eq!(
+            read_data(&dst_ntfs, &mut dst_fs, "1000-bytes-file"),
+            b"12345".repeat(200)
+        );
+        let destination = dst_ntfs
+            .file_by_path(
+                &mut dst_fs,
+                "1000-bytes-file",
+                NtfsCaseSensitivity::Insensitive,
+            )
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            destination.info().unwrap().modification_time(),
+            modification_time
+        );
+    }
+
+    #[test]
+    fn test_sync_attribute_list() {
+        let mut src_fs = crate::helpers::tests::testfs3();
+        let mut src_ntfs = Ntfs::new(&mut src_fs).unwrap();
+        src_ntfs.read_upcase_table(&mut src_fs).unwrap();
+
+        let mut dst_fs = crate::helpers::tests::testfs3();
+        let mut dst_ntfs = Ntfs::new(&mut dst_fs).unwrap();
+        dst_ntfs.read_upcase_table(&mut dst_fs).unwrap();
+
+        let stream_data = |ntfs: &Ntfs, fs: &mut Cursor<Vec<u8>>, name: &str| {
+            let file = ntfs
+                .file_by_path(fs, "many-streams-file", NtfsCaseSensitivity::Insensitive)
+                .unwrap()
+                .unwrap();
+            let data_item = file.data(fs, name).unwrap().unwrap();
+            let data_attribute = data_item.to_attribute().unwrap();
+            let mut data_value = data_attribute.value(fs).unwrap();
+
+            let mut data = vec![0u8; data_value.len() as usize];
+            data_value.read_exact(fs, &mut data).unwrap();
+            data
+        };
+
+        // Scramble all resident streams of the destination file, wherever they are stored.
+        let mut destination = dst_ntfs
+            .file_by_path(
+                &mut dst_fs,
+                "many-streams-file",
+                NtfsCaseSensitivity::Insensitive,
+            )
+            .unwrap()
+            .unwrap();
+        let mut extension_streams = Vec::new();
+
+        for (item, name) in data_streams(&destination, &mut dst_fs).unwrap() {
+            let attribute = item.to_attribute().unwrap();
+            if !attribute.is_resident() {
+                continue;
+            }
+
+            let file_record_number = attribute.file().file_record_number();
+            if file_record_number != destination.file_record_number() {
+                extension_streams.push(name);
+            }
+
+            let range = attribute.resident_value_range().unwrap();
+            let mut file = dst_ntfs.file(&mut dst_fs, file_record_number).unwrap();
+            file.record_data_mut()[range].fill(b'x');
+            file.write_record(&mut dst_fs).unwrap();
+        }
+
+        assert!(!extension_streams.is_empty());
+        assert_eq!(
+            stream_data(&dst_ntfs, &mut dst_fs, &extension_streams[0]),
+            b"x".repeat(200)
+        );
+
+        // Reload the destination file to pick up the scrambled resident streams of its base File Record.
+        destination = dst_ntfs
+            .file(&mut dst_fs, destination.file_record_number())
+            .unwrap();
+        let source = src_ntfs
+            .file_by_path(
+                &mut src_fs,
+                "many-streams-file",
+                NtfsCaseSensitivity::Insensitive,
+            )
+            .unwrap()
+            .unwrap();
+        let report = source
+            .sync_to(&mut src_fs, &mut destination, &mut dst_fs)
+            .unwrap();
+        assert!(report.is_complete());
+        assert_eq!(report.streams_copied(), 13);
+
+        // Every stream has been restored, and the base File Record is still intact.
+        for i in 1..=12 {
+            let name = alloc::format!("stream{i}");
+            assert_eq!(
+                stream_data(&dst_ntfs, &mut dst_fs, &name),
+                alloc::format!("{i:0200}").as_bytes()
+            );
+        }
+
+        let destination = dst_ntfs
+            .file(&mut dst_fs, destination.file_record_number())
+            .unwrap();
+        let file_name = destination.name(&mut dst_fs, None, None).unwrap().unwrap();
+        assert_eq!(file_name.name(), "many-streams-file");
+        assert_eq!(
+            destination.info().unwrap().modification_time(),
+            source.info().unwrap().modification_time()
+        );
+    }
+}
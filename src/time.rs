@@ -4,20 +4,29 @@
 use binrw::BinRead;
 use derive_more::From;
 
+use crate::error::{NtfsError, Result};
+
+#[cfg(feature = "chrono")]
+use chrono::{DateTime, Utc};
+
 #[cfg(feature = "time")]
-use {crate::error::NtfsError, time::OffsetDateTime};
+use time::OffsetDateTime;
+
+#[cfg(feature = "serde")]
+use serde::{Serialize, Serializer};
 
 #[cfg(feature = "std")]
-use std::time::{SystemTime, SystemTimeError};
+use std::time::{Duration, SystemTime, SystemTimeError};
 
 /// Difference in 100-nanosecond intervals between the Windows/NTFS epoch (1601-01-01) and the Unix epoch (1970-01-01).
-#[cfg(any(feature = "time", feature = "std"))]
 const EPOCH_DIFFERENCE_IN_INTERVALS: u64 = 116_444_736_000_000_000;
 
 /// Number of 100-nanosecond intervals in a second.
-#[cfg(any(feature = "time", feature = "std"))]
 const INTERVALS_PER_SECOND: u64 = 10_000_000;
 
+/// Number of nanoseconds in a 100-nanosecond interval.
+const NANOS_PER_INTERVAL: i128 = 100;
+
 /// An NTFS timestamp, used for expressing file times.
 ///
 /// NTFS (and the Windows NT line of operating systems) represent time as an unsigned 64-bit integer
@@ -26,10 +35,82 @@ const INTERVALS_PER_SECOND: u64 = 10_000_000;
 pub struct NtfsTime(u64);
 
 impl NtfsTime {
+    /// Creates an [`NtfsTime`] from the number of seconds since the Unix epoch (January 1, 1970).
+    ///
+    /// Negative values denote times before the Unix epoch.
+    /// Returns [`NtfsError::InvalidTime`] if the time lies outside the range representable by an [`NtfsTime`].
+    pub fn from_unix_timestamp(seconds: i64) -> Result<Self> {
+        Self::from_unix_timestamp_nanos(seconds as i128 * 1_000_000_000)
+    }
+
+    /// Creates an [`NtfsTime`] from the number of nanoseconds since the Unix epoch (January 1, 1970).
+    ///
+    /// Negative values denote times before the Unix epoch.
+    /// As NTFS only stores 100-nanosecond intervals, the time is truncated to a full interval towards the
+    /// Unix epoch.
+    /// Returns [`NtfsError::InvalidTime`] if the time lies outside the range representable by an [`NtfsTime`].
+    pub fn from_unix_timestamp_nanos(nanos: i128) -> Result<Self> {
+        let intervals_since_unix_epoch = nanos / NANOS_PER_INTERVAL;
+        let intervals_since_windows_epoch =
+            intervals_since_unix_epoch + EPOCH_DIFFERENCE_IN_INTERVALS as i128;
+        let nt_timestamp =
+            u64::try_from(intervals_since_windows_epoch).map_err(|_| NtfsError::InvalidTime)?;
+
+        Ok(Self(nt_timestamp))
+    }
+
     /// Returns the stored NT timestamp (number of 100-nanosecond intervals since January 1, 1601).
     pub fn nt_timestamp(&self) -> u64 {
         self.0
     }
+
+    /// Returns the number of nanoseconds within the second of this time (between 0 and 999,999,900).
+    ///
+    /// Together with [`NtfsTime::unix_timestamp`], this fully describes the time.
+    pub fn subsec_nanos(&self) -> u32 {
+        // The Windows and Unix epochs are both aligned to full seconds.
+        (self.0 % INTERVALS_PER_SECOND) as u32 * NANOS_PER_INTERVAL as u32
+    }
+
+    /// Returns the number of full seconds since the Unix epoch (January 1, 1970).
+    ///
+    /// Times before the Unix epoch return negative values, rounded down to the previous full second.
+    /// Use [`NtfsTime::subsec_nanos`] to get the remaining nanoseconds.
+    pub fn unix_timestamp(&self) -> i64 {
+        let seconds_since_windows_epoch = (self.0 / INTERVALS_PER_SECOND) as i64;
+        let epoch_difference_in_seconds =
+            (EPOCH_DIFFERENCE_IN_INTERVALS / INTERVALS_PER_SECOND) as i64;
+
+        seconds_since_windows_epoch - epoch_difference_in_seconds
+    }
+
+    /// Returns the number of nanoseconds since the Unix epoch (January 1, 1970).
+    ///
+    /// Times before the Unix epoch return negative values.
+    pub fn unix_timestamp_nanos(&self) -> i128 {
+        let intervals_since_unix_epoch = self.0 as i128 - EPOCH_DIFFERENCE_IN_INTERVALS as i128;
+        intervals_since_unix_epoch * NANOS_PER_INTERVAL
+    }
+}
+
+#[cfg(feature = "chrono")]
+#[cfg_attr(docsrs, doc(cfg(feature = "chrono")))]
+impl TryFrom<DateTime<Utc>> for NtfsTime {
+    type Error = NtfsError;
+
+    fn try_from(dt: DateTime<Utc>) -> Result<Self> {
+        let nanos = dt.timestamp() as i128 * 1_000_000_000 + dt.timestamp_subsec_nanos() as i128;
+        Self::from_unix_timestamp_nanos(nanos)
+    }
+}
+
+#[cfg(feature = "chrono")]
+#[cfg_attr(docsrs, doc(cfg(feature = "chrono")))]
+impl From<NtfsTime> for DateTime<Utc> {
+    fn from(nt: NtfsTime) -> DateTime<Utc> {
+        // This unwrap is safe, because chrono covers all possible NTFS timestamps.
+        DateTime::from_timestamp(nt.unix_timestamp(), nt.subsec_nanos()).unwrap()
+    }
 }
 
 #[cfg(feature = "time")]
@@ -37,15 +118,8 @@ impl NtfsTime {
 impl TryFrom<OffsetDateTime> for NtfsTime {
     type Error = NtfsError;
 
-    fn try_from(dt: OffsetDateTime) -> Result<Self, Self::Error> {
-        let nanos_since_unix_epoch = dt.unix_timestamp_nanos();
-        let intervals_since_unix_epoch = nanos_since_unix_epoch / 100;
-        let intervals_since_windows_epoch =
-            intervals_since_unix_epoch + EPOCH_DIFFERENCE_IN_INTERVALS as i128;
-        let nt_timestamp =
-            u64::try_from(intervals_since_windows_epoch).map_err(|_| NtfsError::InvalidTime)?;
-
-        Ok(Self(nt_timestamp))
+    fn try_from(dt: OffsetDateTime) -> Result<Self> {
+        Self::from_unix_timestamp_nanos(dt.unix_timestamp_nanos())
     }
 }
 
@@ -53,12 +127,26 @@ impl TryFrom<OffsetDateTime> for NtfsTime {
 #[cfg_attr(docsrs, doc(cfg(feature = "time")))]
 impl From<NtfsTime> for OffsetDateTime {
     fn from(nt: NtfsTime) -> OffsetDateTime {
-        let intervals_since_windows_epoch = nt.nt_timestamp() as i128;
-        let intervals_since_unix_epoch =
-            intervals_since_windows_epoch - EPOCH_DIFFERENCE_IN_INTERVALS as i128;
-        let nanos_since_unix_epoch = intervals_since_unix_epoch * 100;
+        // This unwrap is safe, because the "large-dates" feature covers all possible NTFS timestamps.
+        OffsetDateTime::from_unix_timestamp_nanos(nt.unix_timestamp_nanos()).unwrap()
+    }
+}
 
-        OffsetDateTime::from_unix_timestamp_nanos(nanos_since_unix_epoch).unwrap()
+#[cfg(feature = "std")]
+#[cfg_attr(docsrs, doc(cfg(feature = "std")))]
+impl From<NtfsTime> for SystemTime {
+    fn from(nt: NtfsTime) -> SystemTime {
+        let intervals_since_windows_epoch = nt.nt_timestamp();
+
+        if intervals_since_windows_epoch >= EPOCH_DIFFERENCE_IN_INTERVALS {
+            let intervals_since_unix_epoch =
+                intervals_since_windows_epoch - EPOCH_DIFFERENCE_IN_INTERVALS;
+            SystemTime::UNIX_EPOCH + intervals_to_duration(intervals_since_unix_epoch)
+        } else {
+            let intervals_before_unix_epoch =
+                EPOCH_DIFFERENCE_IN_INTERVALS - intervals_since_windows_epoch;
+            SystemTime::UNIX_EPOCH - intervals_to_duration(intervals_before_unix_epoch)
+        }
     }
 }
 
@@ -67,7 +155,7 @@ impl From<NtfsTime> for OffsetDateTime {
 impl TryFrom<SystemTime> for NtfsTime {
     type Error = SystemTimeError;
 
-    fn try_from(st: SystemTime) -> Result<Self, Self::Error> {
+    fn try_from(st: SystemTime) -> core::result::Result<Self, Self::Error> {
         let duration_since_unix_epoch = st.duration_since(SystemTime::UNIX_EPOCH)?;
         let intervals_since_unix_epoch = duration_since_unix_epoch.as_secs() * INTERVALS_PER_SECOND
             + duration_since_unix_epoch.subsec_nanos() as u64 / 100;
@@ -78,6 +166,25 @@ impl TryFrom<SystemTime> for NtfsTime {
     }
 }
 
+/// Serializes the raw NT timestamp (see [`NtfsTime::nt_timestamp`]).
+#[cfg(feature = "serde")]
+#[cfg_attr(docsrs, doc(cfg(feature = "serde")))]
+impl Serialize for NtfsTime {
+    fn serialize<S>(&self, serializer: S) -> core::result::Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_newtype_struct("NtfsTime", &self.0)
+    }
+}
+
+#[cfg(feature = "std")]
+fn intervals_to_duration(intervals: u64) -> Duration {
+    let seconds = intervals / INTERVALS_PER_SECOND;
+    let nanos = (intervals % INTERVALS_PER_SECOND) as u32 * NANOS_PER_INTERVAL as u32;
+    Duration::new(seconds, nanos)
+}
+
 #[cfg(test)]
 pub(crate) mod tests {
     use super::*;
@@ -104,6 +211,10 @@ pub(crate) mod tests {
         let dt = datetime!(1600-12-31 23:59:59 UTC);
         assert!(NtfsTime::try_from(dt).is_err());
 
+        let dt = datetime!(1969-12-31 23:59:59.999_999_999 UTC);
+        let nt = NtfsTime::try_from(dt).unwrap();
+        assert_eq!(nt.nt_timestamp(), EPOCH_DIFFERENCE_IN_INTERVALS);
+
         let dt = datetime!(+60056-05-28 0:00 UTC);
         assert!(NtfsTime::try_from(dt).is_ok());
 
@@ -111,11 +222,96 @@ pub(crate) mod tests {
         assert!(NtfsTime::try_from(dt).is_err());
     }
 
+    #[cfg(feature = "chrono")]
+    #[test]
+    fn test_chrono() {
+        let dt = DateTime::parse_from_rfc3339("2013-01-05T18:15:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        let nt = NtfsTime::try_from(dt).unwrap();
+        assert_eq!(nt.nt_timestamp(), 130018833000000000u64);
+        assert_eq!(DateTime::<Utc>::from(nt), dt);
+
+        let nt = NtfsTime::from(0);
+        let dt = DateTime::<Utc>::from(nt);
+        assert_eq!(dt.to_rfc3339(), "1601-01-01T00:00:00+00:00");
+        assert_eq!(NtfsTime::try_from(dt).unwrap(), nt);
+        assert!(NtfsTime::try_from(dt - chrono::Duration::seconds(1)).is_err());
+
+        // Sub-interval nanoseconds are truncated towards the Unix epoch, just like for `OffsetDateTime`.
+        let dt = DateTime::from_timestamp(-1, 999_999_999).unwrap();
+        assert_eq!(
+            NtfsTime::try_from(dt).unwrap(),
+            NtfsTime::from(EPOCH_DIFFERENCE_IN_INTERVALS)
+        );
+
+        let nt = NtfsTime::from(u64::MAX);
+        assert_eq!(NtfsTime::try_from(DateTime::<Utc>::from(nt)).unwrap(), nt);
+    }
+
     #[cfg(feature = "std")]
     #[test]
     fn test_systemtime() {
         let st = SystemTime::now();
         let nt = NtfsTime::try_from(st).unwrap();
         assert!(nt.nt_timestamp() > NT_TIMESTAMP_2021_01_01);
+
+        let st = SystemTime::UNIX_EPOCH + Duration::new(1609459200, 123456700);
+        let nt = NtfsTime::try_from(st).unwrap();
+        assert_eq!(SystemTime::from(nt), st);
+
+        let st = SystemTime::from(NtfsTime::from(0));
+        assert_eq!(
+            SystemTime::UNIX_EPOCH.duration_since(st).unwrap(),
+            Duration::from_secs(11644473600)
+        );
+    }
+
+    #[test]
+    fn test_unix_timestamp() {
+        let nt = NtfsTime::from(NT_TIMESTAMP_2021_01_01 + 1234567);
+        assert_eq!(nt.unix_timestamp(), 1609459200);
+        assert_eq!(nt.subsec_nanos(), 123456700);
+        assert_eq!(nt.unix_timestamp_nanos(), 1609459200123456700);
+        assert_eq!(
+            NtfsTime::from_unix_timestamp_nanos(1609459200123456789).unwrap(),
+            nt
+        );
+        assert_eq!(
+            NtfsTime::from_unix_timestamp(1609459200)
+                .unwrap()
+                .nt_timestamp(),
+            NT_TIMESTAMP_2021_01_01
+        );
+
+        // Seconds of times before the Unix epoch are rounded down, while nanoseconds passed in are
+        // truncated towards the Unix epoch.
+        let nt = NtfsTime::from(EPOCH_DIFFERENCE_IN_INTERVALS - 1);
+        assert_eq!(nt.unix_timestamp(), -1);
+        assert_eq!(nt.subsec_nanos(), 999999900);
+        assert_eq!(nt.unix_timestamp_nanos(), -100);
+        assert_eq!(NtfsTime::from_unix_timestamp_nanos(-199).unwrap(), nt);
+        assert_eq!(
+            NtfsTime::from_unix_timestamp_nanos(-1)
+                .unwrap()
+                .nt_timestamp(),
+            EPOCH_DIFFERENCE_IN_INTERVALS
+        );
+
+        // The Windows epoch is the earliest and u64::MAX the latest representable time.
+        let nt = NtfsTime::from(0);
+        assert_eq!(nt.unix_timestamp(), -11644473600);
+        assert_eq!(NtfsTime::from_unix_timestamp(-11644473600).unwrap(), nt);
+        assert!(matches!(
+            NtfsTime::from_unix_timestamp(-11644473601),
+            Err(NtfsError::InvalidTime)
+        ));
+
+        let nt = NtfsTime::from(u64::MAX);
+        assert_eq!(
+            NtfsTime::from_unix_timestamp_nanos(nt.unix_timestamp_nanos()).unwrap(),
+            nt
+        );
+        assert!(NtfsTime::from_unix_timestamp_nanos(nt.unix_timestamp_nanos() + 100).is_err());
     }
 }
@@ -0,0 +1,259 @@
+// Copyright 2021-2023 Colin Finck <colin@reactos.org>
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+use core::fmt::Write as _;
+
+use alloc::string::String;
+use alloc::vec;
+use binrw::io::{Read, Seek, Write};
+
+use crate::error::Result;
+use crate::file::NtfsFile;
+use crate::helpers::write_json_string;
+use crate::ntfs::Ntfs;
+use crate::progress::{report_progress, NtfsNoProgress, NtfsProgressSink, NtfsProgressUnit};
+use crate::structured_values::{NtfsFileName, NtfsFileNamespace};
+
+/// Exporter of a directory tree as JSON Lines (one JSON object per line), for feeding UIs and databases.
+///
+/// Every file and directory below the start directory is written as a single line with a pointer to
+/// its parent directory:
+///
+/// ```text
+/// {"file_record_number":5,"parent":null,"depth":0,"name":"","is_directory":true}
+/// {"file_record_number":64,"parent":5,"depth":1,"name":"file.txt","is_directory":false,"data_size":1000,"creation_time":132000000000000000,"modification_time":...,"mft_record_modification_time":...,"access_time":...}
+/// ```
+///
+/// Times are NT timestamps (see [`NtfsTime::nt_timestamp`]).
+/// Names are written as they are stored, with unpaired UTF-16 surrogates as `\uXXXX` escapes.
+/// All information is taken from the directory indexes, just like a directory listing on Windows,
+/// so no File Record needs to be read for regular files.
+/// The directory indexes are walked depth-first, and only the subdirectories of the directories on the
+/// current path are kept in memory.
+///
+/// MS-DOS names are left out, so every file is only written once per directory.
+/// A file with multiple hard links appears once for every directory it is linked from.
+///
+/// This writes JSON directly, because the `serde` feature doesn't pull in any serializer.
+/// If you need another format, serialize the entries of [`NtfsFile::directory_index`] with the `serde`
+/// feature instead.
+///
+/// This is a builder: Start with [`NtfsJsonTreeExporter::new`] and chain calls to configure it.
+///
+/// [`NtfsFile::directory_index`]: crate::NtfsFile::directory_index
+/// [`NtfsTime::nt_timestamp`]: crate::NtfsTime::nt_timestamp
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub struct NtfsJsonTreeExporter {
+    max_depth: Option<u32>,
+}
+
+impl NtfsJsonTreeExporter {
+    /// Creates a new [`NtfsJsonTreeExporter`] that exports the entire tree.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Writes the tree below `directory` to `w` and returns the number of lines written.
+    ///
+    /// The first line describes `directory` itself (without a parent).
+    pub fn export<T, W>(
+        &self,
+        ntfs: &Ntfs,
+        directory: &NtfsFile,
+        fs: &mut T,
+        w: &mut W,
+    ) -> Result<u64>
+    where
+        T: Read + Seek,
+        W: Write,
+    {
+        self.export_with_progress(ntfs, directory, fs, w, &mut NtfsNoProgress)
+    }
+
+    /// Variant of [`NtfsJsonTreeExporter::export`] that reports the number of lines written to an
+    /// [`NtfsProgressSink`] (in [`NtfsProgressUnit::Lines`]), which may also cancel the export.
+    pub fn export_with_progress<T, W, P>(
+        &self,
+        ntfs: &Ntfs,
+        directory: &NtfsFile,
+        fs: &mut T,
+        w: &mut W,
+        progress: &mut P,
+    ) -> Result<u64>
+    where
+        T: Read + Seek,
+        W: Write,
+        P: NtfsProgressSink + ?Sized,
+    {
+        let mut line = String::new();
+        let root_file_record_number = directory.file_record_number();
+        let _ = write!(
+            line,
+            "{{\"file_record_number\":{},\"parent\":null,\"depth\":0,\"name\":\"\",\"is_directory\":true}}",
+            root_file_record_number
+        );
+        line.push('\n');
+        w.write_all(line.as_bytes())?;
+        let mut lines_written = 1;
+
+        // Every directory is only walked once, even on a corrupted filesystem with cyclic references.
+        let file_record_count = ntfs.file_record_count(fs)?;
+        let mut visited = vec![0u8; (file_record_count as usize + 7) / 8];
+        mark_visited(&mut visited, root_file_record_number);
+
+        // Subdirectories that still need to be walked, along with their depth.
+        let mut pending = vec![(root_file_record_number, 0u32)];
+
+        while let Some((parent, parent_depth)) = pending.pop() {
+            let depth = parent_depth + 1;
+            let directory = ntfs.file(fs, parent)?;
+            let index = directory.directory_index(fs)?;
+            let mut entries = index.entries();
+
+            while let Some(entry) = entries.next(fs) {
+                let entry = entry?;
+                let file_name = match entry.key() {
+                    Some(file_name) => file_name?,
+                    None => continue,
+                };
+
+                let file_record_number = entry.file_reference().file_record_number();
+                if file_name.namespace() == Some(NtfsFileNamespace::Dos)
+                    || file_record_number == parent
+                {
+                    continue;
+                }
+
+                report_progress(progress, NtfsProgressUnit::Lines, lines_written, None)?;
+
+                line.clear();
+                write_line(&mut line, &file_name, file_record_number, parent, depth);
+                w.write_all(line.as_bytes())?;
+                lines_written += 1;
+
+                let descend = self.max_depth.map_or(true, |max_depth| depth < max_depth);
+                if descend
+                    && file_name.is_directory()
+                    && mark_visited(&mut visited, file_record_number)
+                {
+                    pending.push((file_record_number, depth));
+                }
+            }
+        }
+
+        Ok(lines_written)
+    }
+
+    /// Returns the maximum depth of the export (if any).
+    pub fn max_depth(&self) -> Option<u32> {
+        self.max_depth
+    }
+
+    /// Limits the export to the given depth below the start directory.
+    ///
+    /// A depth of 1 only exports the contents of the start directory itself.
+    pub fn with_max_depth(mut self, max_depth: u32) -> Self {
+        self.max_depth = Some(max_depth);
+        self
+    }
+}
+
+/// Marks the given File Record Number as visited and returns `true` if it hasn't been visited before.
+fn mark_visited(visited: &mut [u8], file_record_number: u64) -> bool {
+    let (byte, bit) = ((file_record_number / 8) as usize, file_record_number % 8);
+
+    match visited.get_mut(byte) {
+        Some(byte) if *byte & (1 << bit) == 0 => {
+            *byte |= 1 << bit;
+            true
+        }
+        _ => false,
+    }
+}
+
+fn write_line(
+    line: &mut String,
+    file_name: &NtfsFileName,
+    file_record_number: u64,
+    parent: u64,
+    depth: u32,
+) {
+    let _ = write!(
+        line,
+        "{{\"file_record_number\":{},\"parent\":{},\"depth\":{},\"name\":",
+        file_record_number, parent, depth
+    );
+    write_json_string(line, file_name.name().u16_iter());
+    let _ = write!(
+        line,
+        ",\"is_directory\":{},\"data_size\":{},\"creation_time\":{},\"modification_time\":{},\"mft_record_modification_time\":{},\"access_time\":{}}}",
+        file_name.is_directory(),
+        file_name.data_size(),
+        file_name.creation_time().nt_timestamp(),
+        file_name.modification_time().nt_timestamp(),
+        file_name.mft_record_modification_time().nt_timestamp(),
+        file_name.access_time().nt_timestamp()
+    );
+    line.push('\n');
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::string::ToString;
+    use alloc::vec::Vec;
+
+    use super::*;
+
+    #[test]
+    fn test_json_tree_exporter() {
+        let mut testfs1 = crate::helpers::tests::testfs1();
+        let ntfs = Ntfs::new(&mut testfs1).unwrap();
+        let root_dir = ntfs.root_directory(&mut testfs1).unwrap();
+
+        let mut output = Vec::new();
+        let lines_written = NtfsJsonTreeExporter::new()
+            .export(&ntfs, &root_dir, &mut testfs1, &mut output)
+            .unwrap();
+        let output = String::from_utf8(output).unwrap();
+        let lines = output.lines().collect::<Vec<_>>();
+        assert_eq!(lines.len() as u64, lines_written);
+        assert_eq!(
+            lines[0],
+            "{\"file_record_number\":5,\"parent\":null,\"depth\":0,\"name\":\"\",\"is_directory\":true}"
+        );
+
+        // Files of the root directory, including the NTFS metadata files, are at depth 1.
+        assert!(lines.iter().any(|line| line.starts_with(
+            "{\"file_record_number\":0,\"parent\":5,\"depth\":1,\"name\":\"$MFT\",\"is_directory\":false,"
+        )));
+        let data_size = lines
+            .iter()
+            .find(|line| line.contains("\"name\":\"1000-bytes-file\""))
+            .unwrap();
+        assert!(data_size.contains("\"parent\":5,\"depth\":1,"));
+        assert!(data_size.contains("\"data_size\":1000,"));
+
+        // The contents of many_subdirs are deeper down.
+        assert!(lines
+            .iter()
+            .any(|line| line.contains("\"depth\":2,\"name\":\"123\"")));
+
+        // A maximum depth of 1 leaves them out.
+        let mut output = Vec::new();
+        let lines_written_depth_1 = NtfsJsonTreeExporter::new()
+            .with_max_depth(1)
+            .export(&ntfs, &root_dir, &mut testfs1, &mut output)
+            .unwrap();
+        assert!(lines_written_depth_1 < lines_written);
+        assert!(!String::from_utf8(output).unwrap().contains("\"depth\":2"));
+
+        let mut escaped = String::new();
+        write_json_string(&mut escaped, "a\"b\\c\u{1}".encode_utf16());
+        assert_eq!(escaped, "\"a\\\"b\\\\c\\u0001\"".to_string());
+
+        // Unpaired surrogates are escaped instead of being replaced.
+        let mut escaped = String::new();
+        write_json_string(&mut escaped, [0x61, 0xd800, 0x62]);
+        assert_eq!(escaped, "\"a\\ud800b\"".to_string());
+    }
+}
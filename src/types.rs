@@ -13,6 +13,9 @@ use derive_more::{Binary, Display, From, LowerHex, Octal, UpperHex};
 use crate::error::{NtfsError, Result};
 use crate::ntfs::Ntfs;
 
+#[cfg(feature = "serde")]
+use serde::{Serialize, Serializer};
+
 /// An absolute nonzero byte position on the NTFS filesystem.
 /// Can be used to seek, but even more often in [`NtfsError`] variants to assist with debugging.
 ///
@@ -137,6 +140,18 @@ impl From<NonZeroU64> for NtfsPosition {
     }
 }
 
+/// Serializes the position as a number or, if there is no valid position, as `None`.
+#[cfg(feature = "serde")]
+#[cfg_attr(docsrs, doc(cfg(feature = "serde")))]
+impl Serialize for NtfsPosition {
+    fn serialize<S>(&self, serializer: S) -> core::result::Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_newtype_struct("NtfsPosition", &self.0)
+    }
+}
+
 /// A Logical Cluster Number (LCN).
 ///
 /// NTFS divides a filesystem into clusters of a given size (power of two), see [`Ntfs::cluster_size`].
@@ -172,7 +187,14 @@ impl Lcn {
     }
 
     /// Returns the absolute byte position of this LCN within the filesystem.
+    ///
+    /// This fails with [`NtfsError::ClusterDataUnavailable`] if only a standalone $MFT file has been
+    /// opened via [`Ntfs::from_mft`].
     pub fn position(&self, ntfs: &Ntfs) -> Result<NtfsPosition> {
+        if ntfs.is_standalone_mft() {
+            return Err(NtfsError::ClusterDataUnavailable { lcn: *self });
+        }
+
         let value = self
             .0
             .checked_mul(ntfs.cluster_size() as u64)
@@ -186,6 +208,17 @@ impl Lcn {
     }
 }
 
+#[cfg(feature = "serde")]
+#[cfg_attr(docsrs, doc(cfg(feature = "serde")))]
+impl Serialize for Lcn {
+    fn serialize<S>(&self, serializer: S) -> core::result::Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_newtype_struct("Lcn", &self.0)
+    }
+}
+
 /// A Virtual Cluster Number (VCN).
 ///
 /// NTFS divides a filesystem into clusters of a given size (power of two), see [`Ntfs::cluster_size`].
@@ -222,3 +255,14 @@ impl Vcn {
         self.0
     }
 }
+
+#[cfg(feature = "serde")]
+#[cfg_attr(docsrs, doc(cfg(feature = "serde")))]
+impl Serialize for Vcn {
+    fn serialize<S>(&self, serializer: S) -> core::result::Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_newtype_struct("Vcn", &self.0)
+    }
+}
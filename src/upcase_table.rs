@@ -13,6 +13,7 @@ use crate::attribute::NtfsAttributeType;
 use crate::error::{NtfsError, Result};
 use crate::file::KnownNtfsFileRecordNumber;
 use crate::ntfs::Ntfs;
+use crate::string_buf::NtfsStringBuf;
 use crate::traits::NtfsReadSeek;
 
 /// The Upcase Table contains an uppercase character for each Unicode character of the Basic Multilingual Plane.
@@ -81,6 +82,67 @@ impl UpcaseTable {
     }
 }
 
+/// Selects how file names are compared when looking up files, e.g. via
+/// [`NtfsFileNameIndex::find_with_case_sensitivity`] or [`Ntfs::file_by_path`].
+///
+/// [`NtfsFileNameIndex::find_with_case_sensitivity`]: crate::indexes::NtfsFileNameIndex::find_with_case_sensitivity
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum NtfsCaseSensitivity {
+    /// Names are compared case-insensitively based on the filesystem's $UpCase table.
+    /// This is the Win32 behavior.
+    Insensitive,
+    /// Names are compared according to the case sensitivity flag of the directory that is searched
+    /// (see [`NtfsFile::case_sensitivity`]).
+    /// This is the default and matches the behavior of Windows 10 and later.
+    ///
+    /// Functions that only get an index and not its directory
+    /// (like [`NtfsFileNameIndex::find_with_case_sensitivity`]) treat this like [`NtfsCaseSensitivity::Insensitive`].
+    ///
+    /// [`NtfsFile::case_sensitivity`]: crate::NtfsFile::case_sensitivity
+    /// [`NtfsFileNameIndex::find_with_case_sensitivity`]: crate::indexes::NtfsFileNameIndex::find_with_case_sensitivity
+    PerDirectory,
+    /// Names are compared code unit by code unit (POSIX semantics).
+    ///
+    /// Use this for volumes with files whose names only differ by case (e.g. created by WSL).
+    Sensitive,
+}
+
+impl Default for NtfsCaseSensitivity {
+    fn default() -> Self {
+        Self::PerDirectory
+    }
+}
+
+/// Compares a file name to search for with a file name from a filename index.
+///
+/// Filename indexes are sorted case-insensitively, and names that only differ by case are sorted
+/// by their binary representation.
+/// Hence, a case-sensitive comparison needs to order by the uppercase variants first to remain
+/// compatible with the index order.
+///
+/// # Panics
+///
+/// Panics if [`read_upcase_table`][Ntfs::read_upcase_table] had not been called on the passed [`Ntfs`] object.
+///
+/// `name` is an iterator over the UTF-16 code units of the name to search for, which lets callers
+/// pass both UTF-8 strings (via [`str::encode_utf16`]) and UTF-16 slices without allocating.
+pub(crate) fn file_name_cmp<I>(
+    ntfs: &Ntfs,
+    name: I,
+    other: &U16StrLe,
+    case_sensitivity: NtfsCaseSensitivity,
+) -> Ordering
+where
+    I: Iterator<Item = u16> + Clone,
+{
+    let ordering = upcase_cmp_iter(name.clone(), other.u16_iter(), ntfs);
+
+    match case_sensitivity {
+        NtfsCaseSensitivity::Insensitive | NtfsCaseSensitivity::PerDirectory => ordering,
+        NtfsCaseSensitivity::Sensitive => ordering.then_with(|| name.cmp(other.u16_iter())),
+    }
+}
+
 /// Trait for a case-insensitive ordering with respect to the $UpCase table read from the filesystem.
 pub trait UpcaseOrd<Rhs> {
     /// Performs a case-insensitive ordering based on the $UpCase table read from the filesystem.
@@ -109,6 +171,30 @@ impl<'a> UpcaseOrd<U16StrLe<'a>> for &str {
     }
 }
 
+impl<'a> UpcaseOrd<&[u16]> for U16StrLe<'a> {
+    fn upcase_cmp(&self, ntfs: &Ntfs, other: &&[u16]) -> Ordering {
+        upcase_cmp_iter(self.u16_iter(), other.iter().copied(), ntfs)
+    }
+}
+
+impl<'a> UpcaseOrd<U16StrLe<'a>> for &[u16] {
+    fn upcase_cmp(&self, ntfs: &Ntfs, other: &U16StrLe<'a>) -> Ordering {
+        upcase_cmp_iter(self.iter().copied(), other.u16_iter(), ntfs)
+    }
+}
+
+impl<'a> UpcaseOrd<U16StrLe<'a>> for NtfsStringBuf {
+    fn upcase_cmp(&self, ntfs: &Ntfs, other: &U16StrLe<'a>) -> Ordering {
+        upcase_cmp_iter(self.u16_iter(), other.u16_iter(), ntfs)
+    }
+}
+
+impl UpcaseOrd<&str> for NtfsStringBuf {
+    fn upcase_cmp(&self, ntfs: &Ntfs, other: &&str) -> Ordering {
+        upcase_cmp_iter(self.u16_iter(), other.encode_utf16(), ntfs)
+    }
+}
+
 fn upcase_cmp_iter<TI, OI>(mut this_iter: TI, mut other_iter: OI, ntfs: &Ntfs) -> Ordering
 where
     TI: Iterator<Item = u16>,
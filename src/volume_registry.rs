@@ -0,0 +1,298 @@
+// Copyright 2021-2023 Colin Finck <colin@reactos.org>
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+use alloc::vec::Vec;
+use binrw::io::{Read, Seek};
+
+use crate::error::Result;
+use crate::file::NtfsFile;
+use crate::guid::NtfsGuid;
+use crate::ntfs::Ntfs;
+use crate::upcase_table::NtfsCaseSensitivity;
+
+/// A single NTFS volume of an [`NtfsVolumeRegistry`], consisting of the [`Ntfs`] object and the
+/// filesystem reader for its partition.
+#[derive(Debug)]
+pub struct NtfsRegisteredVolume<T> {
+    ntfs: Ntfs,
+    fs: T,
+    guid: Option<NtfsGuid>,
+}
+
+impl<T> NtfsRegisteredVolume<T> {
+    /// Returns a mutable reference to the filesystem reader of this volume.
+    pub fn fs_mut(&mut self) -> &mut T {
+        &mut self.fs
+    }
+
+    /// Returns the GUID this volume has been registered with (if any).
+    pub fn guid(&self) -> Option<&NtfsGuid> {
+        self.guid.as_ref()
+    }
+
+    /// Returns the [`Ntfs`] object of this volume.
+    pub fn ntfs(&self) -> &Ntfs {
+        &self.ntfs
+    }
+
+    /// Returns the [`Ntfs`] object and the filesystem reader of this volume at the same time,
+    /// which is what every function of this crate needs.
+    pub fn parts_mut(&mut self) -> (&Ntfs, &mut T) {
+        (&self.ntfs, &mut self.fs)
+    }
+
+    /// Returns the 64-bit serial number of this volume (see [`Ntfs::serial_number`]).
+    pub fn serial_number(&self) -> u64 {
+        self.ntfs.serial_number()
+    }
+}
+
+/// Manager for multiple opened NTFS volumes, like all NTFS partitions of a full disk image.
+///
+/// Volumes are identified by their 64-bit serial number and, optionally, by a GUID.
+/// The GUID can be passed explicitly (e.g. the unique partition GUID from a GPT partition table)
+/// or taken from the Object ID of the volume (see [`NtfsVolumeRegistry::add_with_object_id`]).
+///
+/// Paths of the form `{GUID}\path` or `{serial number}\path` are routed to the right volume by
+/// [`NtfsVolumeRegistry::file_by_path`].
+/// A GUID is given in its usual string form (like `{67C8770B-44F1-410A-AB9A-F9B5446F13EE}`), a serial
+/// number in hexadecimal (like `{0x1234ABCD5678EF90}`, the `0x` prefix is optional).
+/// If several volumes share a serial number or GUID, the first registered one is used.
+#[derive(Debug)]
+pub struct NtfsVolumeRegistry<T> {
+    volumes: Vec<NtfsRegisteredVolume<T>>,
+}
+
+impl<T> NtfsVolumeRegistry<T>
+where
+    T: Read + Seek,
+{
+    /// Creates a new [`NtfsVolumeRegistry`] without any volumes.
+    pub fn new() -> Self {
+        Self {
+            volumes: Vec::new(),
+        }
+    }
+
+    /// Adds a volume, optionally along with a GUID, and returns its [`NtfsRegisteredVolume`].
+    ///
+    /// Call [`Ntfs::read_upcase_table`] before if you want to look up paths on this volume.
+    pub fn add(
+        &mut self,
+        ntfs: Ntfs,
+        fs: T,
+        guid: Option<NtfsGuid>,
+    ) -> &mut NtfsRegisteredVolume<T> {
+        self.volumes.push(NtfsRegisteredVolume { ntfs, fs, guid });
+        self.volumes.last_mut().unwrap()
+    }
+
+    /// Adds a volume with the GUID from its Object ID (see [`Ntfs::volume_object_id`]) and returns
+    /// its [`NtfsRegisteredVolume`].
+    ///
+    /// A volume without an Object ID is added without a GUID.
+    pub fn add_with_object_id(
+        &mut self,
+        ntfs: Ntfs,
+        mut fs: T,
+    ) -> Result<&mut NtfsRegisteredVolume<T>> {
+        let guid = match ntfs.volume_object_id(&mut fs) {
+            Some(object_id) => Some(object_id?.object_id().clone()),
+            None => None,
+        };
+
+        Ok(self.add(ntfs, fs, guid))
+    }
+
+    /// Splits a path of the form `{GUID}\path` or `{serial number}\path`, and returns the
+    /// [`NtfsRegisteredVolume`] it refers to along with the remaining path.
+    ///
+    /// This is `None` if the path doesn't start with a known volume.
+    pub fn resolve<'p>(
+        &mut self,
+        path: &'p str,
+    ) -> Option<(&mut NtfsRegisteredVolume<T>, &'p str)> {
+        let path = path.strip_prefix('{')?;
+        let (key, path) = path.split_once('}')?;
+
+        let volume = if let Some(guid) = parse_guid(key) {
+            self.volumes
+                .iter_mut()
+                .find(|volume| volume.guid.as_ref() == Some(&guid))
+        } else {
+            let serial_number = parse_serial_number(key)?;
+            self.volumes
+                .iter_mut()
+                .find(|volume| volume.serial_number() == serial_number)
+        }?;
+
+        Some((volume, path))
+    }
+
+    /// Looks up a path of the form `{GUID}\path` or `{serial number}\path` on the volume it refers to,
+    /// and returns the [`NtfsFile`] along with the filesystem reader of that volume.
+    ///
+    /// This is `None` if the path doesn't start with a known volume or if the file doesn't exist.
+    /// Paths on the volume are resolved like in [`Ntfs::file_by_path`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if [`read_upcase_table`][Ntfs::read_upcase_table] had not been called on the volume.
+    pub fn file_by_path(
+        &mut self,
+        path: &str,
+        case_sensitivity: NtfsCaseSensitivity,
+    ) -> Option<Result<(NtfsFile<'_>, &mut T)>> {
+        let (volume, path) = self.resolve(path)?;
+        let (ntfs, fs) = volume.parts_mut();
+        let file = iter_try!(ntfs.file_by_path(fs, path, case_sensitivity)?);
+
+        Some(Ok((file, fs)))
+    }
+
+    /// Returns the first volume registered with the given GUID.
+    pub fn volume_by_guid(&self, guid: &NtfsGuid) -> Option<&NtfsRegisteredVolume<T>> {
+        self.volumes
+            .iter()
+            .find(|volume| volume.guid.as_ref() == Some(guid))
+    }
+
+    /// Returns the first volume with the given serial number.
+    pub fn volume_by_serial_number(&self, serial_number: u64) -> Option<&NtfsRegisteredVolume<T>> {
+        self.volumes
+            .iter()
+            .find(|volume| volume.serial_number() == serial_number)
+    }
+
+    /// Returns all volumes in the order they have been added.
+    pub fn volumes(&self) -> &[NtfsRegisteredVolume<T>] {
+        &self.volumes
+    }
+}
+
+impl<T> Default for NtfsVolumeRegistry<T>
+where
+    T: Read + Seek,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Parses a GUID in the form `67C8770B-44F1-410A-AB9A-F9B5446F13EE` (case-insensitively).
+fn parse_guid(s: &str) -> Option<NtfsGuid> {
+    let mut groups = s.split('-');
+    let mut next_group = |len: usize| {
+        let group = groups.next().filter(|group| group.len() == len)?;
+        u64::from_str_radix(group, 16).ok()
+    };
+
+    let data1 = next_group(8)? as u32;
+    let data2 = next_group(4)? as u16;
+    let data3 = next_group(4)? as u16;
+    let data4_high = next_group(4)? as u16;
+    let data4_low = next_group(12)?;
+    if groups.next().is_some() {
+        return None;
+    }
+
+    let mut data4 = [0u8; 8];
+    data4[..2].copy_from_slice(&data4_high.to_be_bytes());
+    data4[2..].copy_from_slice(&data4_low.to_be_bytes()[2..]);
+
+    Some(NtfsGuid {
+        data1,
+        data2,
+        data3,
+        data4,
+    })
+}
+
+/// Parses a serial number in hexadecimal, with an optional `0x` prefix.
+fn parse_serial_number(s: &str) -> Option<u64> {
+    let s = s
+        .strip_prefix("0x")
+        .or_else(|| s.strip_prefix("0X"))
+        .unwrap_or(s);
+    u64::from_str_radix(s, 16).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::format;
+    use binrw::io::Cursor;
+
+    use super::*;
+    use crate::traits::NtfsReadSeek;
+
+    #[test]
+    fn test_volume_registry() {
+        let guid = parse_guid("67c8770b-44f1-410a-ab9a-f9b5446f13ee").unwrap();
+        assert_eq!(guid.to_string(), "67C8770B-44F1-410A-AB9A-F9B5446F13EE");
+        assert!(parse_guid("67C8770B-44F1-410A-AB9A").is_none());
+        assert!(parse_guid("67C8770B-44F1-410A-AB9A-F9B5446F13EE-00").is_none());
+
+        let mut registry = NtfsVolumeRegistry::<Cursor<Vec<u8>>>::new();
+
+        let mut testfs1 = crate::helpers::tests::testfs1();
+        let mut ntfs = Ntfs::new(&mut testfs1).unwrap();
+        ntfs.read_upcase_table(&mut testfs1).unwrap();
+        let serial_number = ntfs.serial_number();
+        let volume = registry.add_with_object_id(ntfs, testfs1).unwrap();
+        assert_eq!(volume.serial_number(), serial_number);
+
+        let mut testfs1 = crate::helpers::tests::testfs1();
+        let mut ntfs = Ntfs::new(&mut testfs1).unwrap();
+        ntfs.read_upcase_table(&mut testfs1).unwrap();
+        registry.add(ntfs, testfs1, Some(guid.clone()));
+        assert_eq!(registry.volumes().len(), 2);
+
+        // Route by GUID.
+        let (file, fs) = registry
+            .file_by_path(
+                "{67C8770B-44F1-410A-AB9A-F9B5446F13EE}\\file-with-12345",
+                NtfsCaseSensitivity::Insensitive,
+            )
+            .unwrap()
+            .unwrap();
+        let data_item = file.data(fs, "").unwrap().unwrap();
+        let data_attribute = data_item.to_attribute().unwrap();
+        let mut data_value = data_attribute.value(fs).unwrap();
+        let mut buf = [0u8; 5];
+        data_value.read_exact(fs, &mut buf).unwrap();
+        assert_eq!(&buf, b"12345");
+
+        // Route by serial number, which picks the first volume.
+        let path = format!("{{{:#x}}}/many_subdirs/123", serial_number);
+        let (volume, remaining_path) = registry.resolve(&path).unwrap();
+        assert!(volume.guid().is_none());
+        assert_eq!(remaining_path, "/many_subdirs/123");
+        assert!(registry
+            .file_by_path(&path, NtfsCaseSensitivity::Insensitive)
+            .unwrap()
+            .is_ok());
+
+        // Unknown volumes and files aren't found.
+        assert!(registry
+            .file_by_path(
+                "{00000000-0000-0000-0000-000000000000}\\file-with-12345",
+                NtfsCaseSensitivity::Insensitive,
+            )
+            .is_none());
+        assert!(registry
+            .file_by_path(
+                "{67C8770B-44F1-410A-AB9A-F9B5446F13EE}\\no-such-file",
+                NtfsCaseSensitivity::Insensitive,
+            )
+            .is_none());
+        assert!(registry
+            .file_by_path("file-with-12345", NtfsCaseSensitivity::Insensitive)
+            .is_none());
+
+        assert_eq!(
+            registry.volume_by_guid(&guid).unwrap().serial_number(),
+            serial_number
+        );
+        assert!(registry.volume_by_serial_number(serial_number).is_some());
+    }
+}
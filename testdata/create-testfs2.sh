@@ -0,0 +1,26 @@
+#!/bin/bash
+set -eu
+
+# testfs2 is formatted for a 4K-native disk (4096-byte sectors).
+# Contrary to testfs1, it is populated via ntfscp, so it doesn't need to be mounted.
+dd if=/dev/zero of=testfs2 bs=1k count=2048
+mkntfs -s 4096 -c 4096 -L mylabel -F testfs2
+
+# Create a 10000-bytes file with non-resident data spanning multiple clusters.
+for i in {1..2000}; do
+    echo -n 12345 >> 10000-bytes-file
+done
+ntfscp testfs2 10000-bytes-file 10000-bytes-file
+rm 10000-bytes-file
+
+# Create a small "pagefile.sys" to be found as a memory file.
+head -c 20000 /dev/zero | tr '\0' 'P' > pagefile.sys
+ntfscp testfs2 pagefile.sys pagefile.sys
+rm pagefile.sys
+
+# Create so many files that the root directory needs an INDEX_ROOT and INDEX_ALLOCATION.
+echo -n 12345 > file-with-12345
+for i in {1..100}; do
+    ntfscp testfs2 file-with-12345 $i
+done
+rm file-with-12345
@@ -0,0 +1,63 @@
+#!/bin/bash
+set -eu
+
+if [ "`whoami`" != "root" ]; then
+    echo Needs to be run as root!
+    exit 1
+fi
+
+# testfs3 has the same geometry as testfs1, but simulates files left behind by Windows.
+dd if=/dev/zero of=testfs3 bs=1k count=2048
+mkntfs -c 512 -L mylabel -F testfs3
+
+# The "permissions" option makes ntfs-3g write NTFS 3.x file information like Windows does.
+mkdir mnt
+mount -t ntfs-3g -o loop,streams_interface=windows,permissions testfs3 mnt
+cd mnt
+
+# Create a Recycle Bin directory for a single user.
+mkdir '$Recycle.Bin'
+cd '$Recycle.Bin'
+mkdir S-1-5-21-1004336348-1177238915-682003330-1001
+cd S-1-5-21-1004336348-1177238915-682003330-1001
+echo -n '[.ShellClassInfo]' > desktop.ini
+
+# Create a version 2 $I file (as written by Windows 10) for a deleted 5000-bytes file,
+# together with its $R file holding the data.
+{
+    printf '\x02\0\0\0\0\0\0\0'
+    printf '\x88\x13\0\0\0\0\0\0'
+    printf '\0\0\x5a\xf6\x4c\xf5\xd4\x01'
+    printf '\x20\0\0\0'
+    echo -n 'C:\Users\user\Desktop\notes.txt' | iconv -f utf-8 -t utf-16le
+    printf '\0\0'
+} > '$IAB12CD.txt'
+head -c 5000 /dev/zero | tr '\0' 'R' > '$RAB12CD.txt'
+
+# Create a version 1 $I file (as written by Windows Vista to 8.1) with a fixed-size path field,
+# whose $R file has already been removed.
+{
+    printf '\x01\0\0\0\0\0\0\0'
+    printf '\x39\x30\0\0\0\0\0\0'
+    printf '\0\x80\x93\x51\xce\x67\xd1\x01'
+    echo -n 'C:\Users\user\Documents\old.doc' | iconv -f utf-8 -t utf-16le
+    head -c $((520 - 62)) /dev/zero
+} > '$IEF34GH.doc'
+cd ../..
+
+# Create a file with so many Alternate Data Streams that it needs an $ATTRIBUTE_LIST and
+# stores some of its resident streams in an extension File Record.
+touch many-streams-file
+for i in {1..12}; do
+    printf '%0200d' $i > many-streams-file:stream$i
+done
+
+# Create a directory with two files whose names only differ in case, as in a directory marked
+# case-sensitive by Windows 10 or WSL.
+mkdir case-sensitive-dir
+echo -n 12345 > case-sensitive-dir/FILE
+echo -n 1234567890 > case-sensitive-dir/file
+
+cd ..
+umount mnt
+rmdir mnt